@@ -0,0 +1,58 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+use chip8::{bench, palette, Chip8};
+
+const STEPS_PER_ITER: u64 = 1000;
+
+fn loaded_cpu(rom: &[u8]) -> Chip8 {
+    let mut cpu = Chip8::new();
+    cpu.load_rom(rom).unwrap();
+    cpu
+}
+
+fn opcode_dispatch(c: &mut Criterion) {
+    c.bench_function("opcode_dispatch", |b| {
+        b.iter_batched_ref(
+            || loaded_cpu(&bench::OPCODE_DISPATCH_ROM),
+            |cpu| bench::run_cpu(cpu, STEPS_PER_ITER).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn drw_heavy(c: &mut Criterion) {
+    c.bench_function("drw_heavy", |b| {
+        b.iter_batched_ref(
+            || loaded_cpu(&bench::DRW_HEAVY_ROM),
+            |cpu| bench::run_cpu(cpu, STEPS_PER_ITER).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn display_conversion(c: &mut Criterion) {
+    let mut cpu = loaded_cpu(&bench::DRW_HEAVY_ROM);
+    bench::run_cpu(&mut cpu, STEPS_PER_ITER).unwrap();
+
+    let mut buffer = vec![0; cpu.display.len()];
+    let colors = palette::builtin(0);
+
+    c.bench_function("display_conversion", |b| {
+        b.iter(|| palette::convert_display(black_box(&cpu.display), &mut buffer, colors))
+    });
+}
+
+fn emulator_session(c: &mut Criterion) {
+    c.bench_function("emulator_session", |b| {
+        b.iter(|| bench::run_session(&bench::DRW_HEAVY_ROM, STEPS_PER_ITER).unwrap())
+    });
+}
+
+criterion_group!(
+    benches,
+    opcode_dispatch,
+    drw_heavy,
+    display_conversion,
+    emulator_session
+);
+criterion_main!(benches);
@@ -0,0 +1,162 @@
+#ifndef CHIP8_H
+#define CHIP8_H
+
+/* Generated by cbindgen from src/chip8_ffi.rs. Do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/* Opaque interpreter handle */
+typedef struct Chip8 Chip8;
+
+/*
+ Result code returned by the `chip8_*` functions
+ */
+typedef enum Chip8Status {
+  CHIP8_STATUS_OK = 0,
+  CHIP8_STATUS_STACK_UNDERFLOW = 1,
+  CHIP8_STATUS_STACK_OVERFLOW = 2,
+  CHIP8_STATUS_UNKNOWN_OPCODE = 3,
+  /*
+   The ROM does not fit in memory
+   */
+  CHIP8_STATUS_ROM_TOO_LARGE = 4,
+  /*
+   A required pointer argument was null
+   */
+  CHIP8_STATUS_NULL_POINTER = 5,
+  /*
+   The opcode is a SCHIP or XO-CHIP instruction, which is not enabled
+   */
+  CHIP8_STATUS_EXTENSION_OPCODE = 6,
+} Chip8Status;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Create an interpreter, reset and ready to load a ROM
+
+ Free it with `chip8_destroy`.
+ */
+Chip8 *chip8_create(void);
+
+/*
+ Free an interpreter created by `chip8_create`
+
+ # Safety
+
+ `chip8` must be null or a pointer returned by `chip8_create` that has not
+ been destroyed yet.
+ */
+void chip8_destroy(Chip8 *chip8);
+
+/*
+ Reset the machine, clearing memory, registers, and the display
+
+ # Safety
+
+ `chip8` must be null or a live pointer returned by `chip8_create`.
+ */
+enum Chip8Status chip8_reset(Chip8 *chip8);
+
+/*
+ Copy `len` bytes of ROM to 0x200
+
+ # Safety
+
+ `chip8` must be null or a live pointer returned by `chip8_create`, and
+ `rom` must be null or point to `len` readable bytes.
+ */
+enum Chip8Status chip8_load_rom(Chip8 *chip8, const uint8_t *rom, size_t len);
+
+/*
+ Execute one instruction
+
+ Keys set with `chip8_set_key` are released afterwards.
+
+ # Safety
+
+ `chip8` must be null or a live pointer returned by `chip8_create`.
+ */
+enum Chip8Status chip8_step(Chip8 *chip8);
+
+/*
+ Decrement the delay and sound timers; call at 60 Hz
+
+ # Safety
+
+ `chip8` must be null or a live pointer returned by `chip8_create`.
+ */
+enum Chip8Status chip8_timer_tick(Chip8 *chip8);
+
+/*
+ Hold keypad `key` (0x0 ~ 0xF) down for the next `chip8_step`
+
+ # Safety
+
+ `chip8` must be null or a live pointer returned by `chip8_create`.
+ */
+enum Chip8Status chip8_set_key(Chip8 *chip8, uint8_t key);
+
+/*
+ Whether the buzzer should sound, i.e. the sound timer is non-zero
+
+ # Safety
+
+ `chip8` must be null or a live pointer returned by `chip8_create`.
+ */
+bool chip8_sound_active(const Chip8 *chip8);
+
+/*
+ Display width in pixels, or 0 if `chip8` is null
+
+ # Safety
+
+ `chip8` must be null or a live pointer returned by `chip8_create`.
+ */
+size_t chip8_display_width(const Chip8 *chip8);
+
+/*
+ Display height in pixels, or 0 if `chip8` is null
+
+ # Safety
+
+ `chip8` must be null or a live pointer returned by `chip8_create`.
+ */
+size_t chip8_display_height(const Chip8 *chip8);
+
+/*
+ Pointer to the display, `width * height` bytes row by row, each the mask
+ of the bit planes lit there (0 = unlit)
+
+ Valid until the interpreter is destroyed: a hi-res ROM loaded later
+ resizes the display in place, so read `chip8_display_width() *
+ chip8_display_height()` bytes each time. Returns null if `chip8` is null.
+
+ # Safety
+
+ `chip8` must be null or a live pointer returned by `chip8_create`.
+ */
+const uint8_t *chip8_get_display_ptr(const Chip8 *chip8);
+
+/*
+ Rows of the display changed since the last call, bit n set for row n, or 0
+ if `chip8` is null
+
+ Redrawing only these rows from `chip8_get_display_ptr` keeps a frontend up
+ to date.
+
+ # Safety
+
+ `chip8` must be null or a live pointer returned by `chip8_create`.
+ */
+uint64_t chip8_take_changed_rows(Chip8 *chip8);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CHIP8_H */
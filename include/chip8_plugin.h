@@ -0,0 +1,56 @@
+/* Plugin interface for the chip8 emulator, loaded with --plugin.
+ *
+ * Build a shared library exporting chip8_plugin_init, which fills in the
+ * table and returns true. Every callback is optional and receives `user`.
+ * The layout matches src/plugin.rs; bump CHIP8_PLUGIN_ABI_VERSION with it. */
+
+#ifndef CHIP8_PLUGIN_H
+#define CHIP8_PLUGIN_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#define CHIP8_PLUGIN_ABI_VERSION 1
+
+/* Machine state passed to the step hook */
+typedef struct Chip8PluginState {
+    /* Instructions executed since reset */
+    uint64_t cycle;
+    uint16_t pc;
+    uint16_t i;
+    /* Opcode just executed */
+    uint16_t opcode;
+    uint8_t v[16];
+    uint8_t dt;
+    uint8_t st;
+    uint8_t sp;
+} Chip8PluginState;
+
+typedef struct Chip8Plugin {
+    /* Set to CHIP8_PLUGIN_ABI_VERSION */
+    uint32_t abi_version;
+    /* Name shown in messages; must outlive the plugin */
+    const char *name;
+    void *user;
+    /* Display filter: change width * height 0RGB pixels in place */
+    void (*filter)(void *user, uint32_t *pixels, size_t width, size_t height);
+    /* Audio sink: the buzzer turned on or off */
+    void (*set_beeping)(void *user, bool beeping);
+    /* Step hook: called after every executed instruction */
+    void (*on_step)(void *user, const Chip8PluginState *state);
+    /* Called once before the library is unloaded */
+    void (*destroy)(void *user);
+} Chip8Plugin;
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+bool chip8_plugin_init(Chip8Plugin *plugin);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CHIP8_PLUGIN_H */
@@ -0,0 +1,156 @@
+use alloc::vec::Vec;
+
+use crate::chip8::{Chip8, Chip8Panic, OutOfMemory, StepOutcome, RNG_SEED};
+
+/// Instructions per 60 Hz timer tick, as in headless runs
+pub const DEFAULT_INSTRUCTIONS_PER_TICK: u32 = 16;
+
+/// Machine state after a step of an `Env`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Observation {
+    /// One byte per pixel, row by row: the mask of planes lit, 0 when off
+    pub display: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+    pub v: [u8; 0x10],
+    pub i: u16,
+    pub pc: u16,
+    pub sp: u8,
+    pub dt: u8,
+    pub st: u8,
+    /// Instructions executed since reset
+    pub cycle: u64,
+    /// The episode is over: the program exited or crashed
+    pub done: bool,
+    /// The crash that ended the episode, if it crashed
+    pub error: Option<Chip8Panic>,
+}
+
+/// Headless environment for bots and reinforcement learning, stepped a frame
+/// at a time with the keys to hold
+///
+/// Runs at full speed with no window or wall-clock timing: each step runs
+/// `frames_per_step` frames of `instructions_per_tick` instructions, ticking
+/// the timers after each. The same ROM, seed, and keys give the same
+/// observations every time. Memory, e.g. for computing a reward, is read from
+/// `cpu`.
+///
+/// ```
+/// use chip8::agent::Env;
+///
+/// // LD V1, K; ADD V0, 1; JP 200
+/// let rom = [0xF1, 0x0A, 0x70, 0x01, 0x12, 0x00];
+/// let mut env = Env::new(&rom)?;
+///
+/// let idle = env.step_with_keys(0);
+/// assert_eq!(idle.v[0], 0, "waits for a key");
+///
+/// let pressed = env.step_with_keys(1 << 0x5);
+/// assert_eq!(pressed.v[1], 0x5);
+/// assert!(pressed.v[0] > 0);
+///
+/// let restarted = env.reset();
+/// assert_eq!(restarted.cycle, 0);
+/// # Ok::<(), chip8::OutOfMemory>(())
+/// ```
+pub struct Env {
+    pub cpu: Chip8,
+    /// RND seed, applied at reset
+    pub seed: [u8; 32],
+    pub instructions_per_tick: u32,
+    /// Frames each step runs with the same keys held
+    pub frames_per_step: u32,
+    rom: Vec<u8>,
+    cycle: u64,
+    error: Option<Chip8Panic>,
+    exited: bool,
+}
+
+impl Env {
+    /// Load `rom` on a machine with the default quirks
+    ///
+    /// Set `cpu.quirks` or `seed` and call `reset` to start over with others.
+    pub fn new(rom: &[u8]) -> Result<Self, OutOfMemory> {
+        let mut env = Env {
+            cpu: Chip8::new(),
+            seed: RNG_SEED,
+            instructions_per_tick: DEFAULT_INSTRUCTIONS_PER_TICK,
+            frames_per_step: 1,
+            rom: rom.to_vec(),
+            cycle: 0,
+            error: None,
+            exited: false,
+        };
+        env.cpu.load_rom(rom)?;
+
+        Ok(env)
+    }
+
+    /// Restart the ROM, keeping the quirks
+    pub fn reset(&mut self) -> Observation {
+        self.cpu.reset();
+        self.cpu.seed_rng(self.seed);
+        self.cpu
+            .load_rom(&self.rom)
+            .expect("ROM fit in memory when loaded");
+
+        self.cycle = 0;
+        self.error = None;
+        self.exited = false;
+
+        self.observe()
+    }
+
+    /// Hold `keys` (bit n = key n) and run one step, unless the episode is
+    /// over
+    pub fn step_with_keys(&mut self, keys: u16) -> Observation {
+        let ipt = self.instructions_per_tick.max(1);
+
+        for _ in 0..self.frames_per_step.max(1) {
+            for _ in 0..ipt {
+                if self.is_done() {
+                    return self.observe();
+                }
+
+                for key in 0..0x10 {
+                    self.cpu.keys[key] = keys & (1 << key) != 0;
+                }
+
+                match self.cpu.step() {
+                    // Breakpoints set on `cpu` are stepped over
+                    Ok(StepOutcome::Breakpoint(_)) => {}
+                    Ok(outcome) => {
+                        self.exited = outcome == StepOutcome::Exited;
+                        self.cycle += 1;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+
+            self.cpu.timer_tick();
+        }
+
+        self.observe()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.exited || self.error.is_some()
+    }
+
+    pub fn observe(&self) -> Observation {
+        Observation {
+            display: self.cpu.display.clone(),
+            width: self.cpu.display_width(),
+            height: self.cpu.display_height(),
+            v: self.cpu.v,
+            i: self.cpu.i,
+            pc: self.cpu.pc,
+            sp: self.cpu.sp,
+            dt: self.cpu.dt,
+            st: self.cpu.st,
+            cycle: self.cycle,
+            done: self.is_done(),
+            error: self.error,
+        }
+    }
+}
@@ -0,0 +1,1008 @@
+use anyhow::anyhow;
+use rand::{rngs::OsRng, RngCore};
+use std::{
+    fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
+
+use chip8::{
+    bus,
+    cheat::Cheats,
+    chip8::{is_hires_rom, plane_color, RAM_SIZE, XO_RAM_SIZE},
+    disasm, emu,
+    filter::{self, FilterSpec, Pipeline},
+    frames::FrameDumper,
+    gdb::GdbServer,
+    instruction::{decode, Instruction},
+    keys::{Action, Binding},
+    kiosk::{self, Kiosk},
+    locale::Locale,
+    metadata::Metadata,
+    movie::{BuzzerEvent, Movie},
+    netplay::{NetGuest, NetHost},
+    palette, png,
+    practice::Practice,
+    profile::CallProfile,
+    reload::RomWatcher,
+    rng,
+    rom::{self, RomSource},
+    romdb::RomDb,
+    stats::Stats,
+    symbols::Symbols,
+    term::TermFrontend,
+    throttle::RingBuffer,
+    trigger::Triggers,
+    Chip8, Emulator, Frontend, HeadlessFrontend, Quirks,
+};
+
+use crate::{
+    config::{self, Config, Profiles},
+    debugger::Debugger,
+    monitor::Monitor,
+    picker::Picker,
+    storage, Backend, ListingFormat, Opt,
+};
+
+/// Exit code for a headless run stopped by --input-timeout
+const EXIT_WAITING_FOR_INPUT: i32 = 3;
+
+/// Run the ROM `opt` names, or the picker's choice, in the emulator or disassembler
+pub fn run(opt: &Opt, locale: &Locale) -> anyhow::Result<()> {
+    let picked = match opt.file {
+        None if can_pick_rom(opt) => {
+            match Picker::new(storage(opt), PathBuf::from("."), locale.clone())?.run()? {
+                Some(path) => Some(path.to_string_lossy().into_owned()),
+                None if accepts_dropped_rom(opt) => None,
+                None => return Ok(()),
+            }
+        }
+        _ => None,
+    };
+
+    if opt.monitor && opt.file.as_deref() == Some("-") {
+        return Err(anyhow!(
+            "--monitor reads commands from stdin, so the ROM cannot be read from it"
+        ));
+    }
+
+    let (source, (program_rom, metadata)) = match opt.file.as_deref().or(picked.as_deref()) {
+        Some(arg) => {
+            let source = rom::from_arg(arg, &storage(opt));
+            let loaded = rom::load_with_metadata(source.as_ref())?;
+            (source, loaded)
+        }
+        None if accepts_dropped_rom(opt) => {
+            let source: Box<dyn RomSource> = Box::new(rom::NoRom);
+            (source, (Vec::new(), None))
+        }
+        None => return Err(anyhow!("no ROM file given")),
+    };
+    let metadata = with_romdb(opt, &program_rom, metadata)?;
+
+    let marks_path = opt.marks.clone().or_else(|| {
+        source
+            .local_path()
+            .map(|path| path.with_extension("marks"))
+            .filter(|path| path.exists())
+    });
+    let marks = match (&marks_path, opt.disassemble) {
+        (Some(path), true) => load_marks(path)?,
+        _ => disasm::Marks::default(),
+    };
+
+    if opt.disassemble && opt.format == ListingFormat::Json {
+        if opt.octo {
+            return Err(anyhow!("--octo and --format json cannot be combined"));
+        }
+
+        let instructions: Vec<String> = disasm::instructions_marked(&program_rom, 0x200, &marks)
+            .iter()
+            .map(|ins| format!("  {}", ins.to_json()))
+            .collect();
+
+        println!("[\n{}\n]", instructions.join(",\n"));
+    } else if opt.disassemble {
+        let listing = if opt.octo {
+            disasm::disassemble_octo_marked(&program_rom, 0x200, &marks)
+        } else {
+            disasm::disassemble_marked(&program_rom, 0x200, &marks)
+        };
+
+        for line in listing {
+            println!("{}", line);
+        }
+    } else {
+        let mut config = Config::find(opt.config.as_deref())?;
+
+        let profiles = Profiles::load(&storage(opt).profiles_path())?;
+        if let Some(profile) = profiles.get(&program_rom) {
+            config.apply_profile(profile);
+        }
+
+        let scale = if opt.kiosk {
+            minifb::Scale::FitScreen
+        } else {
+            config.window_scale()?.unwrap_or(emu::DEFAULT_WINDOW_SCALE)
+        };
+
+        let mut filters = if opt.filters.is_empty() {
+            config.filter_specs()?
+        } else {
+            opt.filters.clone()
+        };
+
+        if let Some(decay) = opt.phosphor {
+            // Fade the pixels themselves, before the palette colors them
+            let decay = decay.unwrap_or(filter::DEFAULT_PHOSPHOR_DECAY);
+            filters.retain(|spec| !matches!(spec, FilterSpec::Phosphor(_)));
+            filters.insert(0, FilterSpec::Phosphor(decay));
+        }
+
+        if opt.headless {
+            if opt.cycles.is_none() && !opt.until_halt {
+                return Err(anyhow!("--headless needs --cycles or --until-halt"));
+            }
+
+            let mut frontend = HeadlessFrontend::new();
+            frontend.frame_limit = Some(1);
+
+            let mut emu = Emulator::with_frontend(new_cpu(opt, &program_rom), frontend)?;
+            emu.filters = Pipeline::from_specs(&filters);
+            return run_emulator(opt, &config, source.as_ref(), program_rom, &metadata, emu);
+        }
+
+        match opt.backend {
+            Backend::Minifb => {
+                let cpu = new_cpu(opt, &program_rom);
+                let emu = Emulator::new(cpu, scale, Pipeline::from_specs(&filters))?;
+                run_emulator(opt, &config, source.as_ref(), program_rom, &metadata, emu)?;
+            }
+            Backend::Term => {
+                if opt.debug {
+                    return Err(anyhow!(
+                        "--debug uses the terminal, so it cannot be combined with --backend term"
+                    ));
+                }
+
+                if opt.monitor {
+                    return Err(anyhow!(
+                        "--monitor reads stdin, so it cannot be combined with --backend term"
+                    ));
+                }
+
+                let frontend = TermFrontend::new()?;
+                let remote = bus::spawn_ui(move || frontend, Duration::from_millis(4));
+
+                let mut emu = Emulator::with_frontend(new_cpu(opt, &program_rom), remote)?;
+                emu.filters = Pipeline::from_specs(&filters);
+                run_emulator(opt, &config, source.as_ref(), program_rom, &metadata, emu)?;
+            }
+            #[cfg(feature = "sdl")]
+            Backend::Sdl => {
+                run_sdl(
+                    opt,
+                    &config,
+                    source.as_ref(),
+                    program_rom,
+                    &metadata,
+                    scale,
+                    &filters,
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Title shown for the ROM from `source`: the one its metadata or the ROM
+/// database gives, or else its file name
+fn rom_title(source: &dyn RomSource, title: Option<String>) -> Option<String> {
+    title.or_else(|| {
+        let path = source.local_path()?;
+        Some(path.file_name()?.to_string_lossy().into_owned())
+    })
+}
+
+/// `metadata` from the ROM itself, with unset fields filled in from the ROM
+/// database entry for `program`
+fn with_romdb(opt: &Opt, program: &[u8], metadata: Option<Metadata>) -> anyhow::Result<Metadata> {
+    let known = load_romdb(opt)?.get(program).cloned().unwrap_or_default();
+
+    Ok(metadata.unwrap_or_default().or(known))
+}
+
+/// The built-in ROM database with the user's entries added
+pub fn load_romdb(opt: &Opt) -> anyhow::Result<RomDb> {
+    let mut romdb = RomDb::builtin();
+    romdb.extend(RomDb::load(&storage(opt).romdb_path())?);
+
+    Ok(romdb)
+}
+
+/// Whether to show the ROM picker when no ROM is given: for the emulator,
+/// run from a terminal
+fn can_pick_rom(opt: &Opt) -> bool {
+    !opt.headless && !opt.disassemble && io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Add `source` to the recent ROMs, if it is a file
+fn remember_rom(opt: &Opt, source: &dyn RomSource, locale: &Locale) {
+    if let Some(path) = source.local_path().filter(|path| path.is_file()) {
+        if let Err(e) = storage(opt).add_recent(&path) {
+            let message = locale.format("error.recent_roms", &[("message", &format!("{:#}", e))]);
+            eprintln!(
+                "{}",
+                locale.format("error.warning", &[("message", &message)])
+            );
+        }
+    }
+}
+
+/// Whether the emulator can start without a ROM, waiting for one to be
+/// dropped onto the window
+fn accepts_dropped_rom(opt: &Opt) -> bool {
+    // Only the SDL window reports dropped files
+    #[cfg(feature = "sdl")]
+    let droppable = opt.backend == Backend::Sdl;
+    #[cfg(not(feature = "sdl"))]
+    let droppable = false;
+
+    droppable && !opt.headless && !opt.disassemble
+}
+
+/// Restart `emu` with the contents of `source`, announced with the message
+/// `status_key`, or keep the old program if they do not load
+///
+/// Returns whether the new program was loaded.
+fn load_new_rom<F: Frontend>(
+    opt: &Opt,
+    source: &dyn RomSource,
+    status_key: &str,
+    emu: &mut Emulator<F>,
+) -> anyhow::Result<bool> {
+    match rom::load_with_metadata(source) {
+        Ok((program, metadata)) => {
+            let rom_storage = storage(opt).rom(&source.name(), &program);
+            emu.savestate_path = Some(rom_storage.state_path());
+            emu.screenshot_dir = Some(rom_storage.screenshot_dir());
+            emu.flags_path = Some(rom_storage.flags_path()).filter(|_| !opt.no_persist);
+            emu.load_flags()?;
+
+            // Settings stay as started, but the display and RAM follow the new
+            // ROM, as does the title
+            let title = with_romdb(opt, &program, metadata)?.title;
+            emu.set_rom_title(rom_title(source, title));
+            set_modes(opt, &mut emu.cpu, &program);
+            emu.rom = program;
+            emu.reset()?;
+            emu.unpause();
+            println!(
+                "{}",
+                emu.locale.format(status_key, &[("rom", &source.name())])
+            );
+
+            Ok(true)
+        }
+        Err(e) => {
+            eprintln!(
+                "{}",
+                emu.locale
+                    .format("error.prefix", &[("message", &format!("{:#}", e))])
+            );
+
+            Ok(false)
+        }
+    }
+}
+
+/// Interpreter for `program_rom`, in the modes `set_modes` picks
+///
+/// The mode has to be known before the window opens, so it is sized to match.
+fn new_cpu(opt: &Opt, program_rom: &[u8]) -> Chip8 {
+    let mut cpu = Chip8::new();
+    set_modes(opt, &mut cpu, program_rom);
+    cpu
+}
+
+/// Put `cpu` in hi-res mode if `program_rom` or `--hires` asks for it, give
+/// it XO-CHIP RAM if `--xo-ram` does, or the original RAM otherwise, and
+/// enable SCHIP if `--schip` does
+///
+/// Loading the ROM still switches to XO-CHIP RAM if it does not fit.
+fn set_modes(opt: &Opt, cpu: &mut Chip8, program_rom: &[u8]) {
+    cpu.set_hires(opt.hires || is_hires_rom(program_rom));
+    cpu.set_ram_size(if opt.xo_ram { XO_RAM_SIZE } else { RAM_SIZE });
+    cpu.set_schip(opt.schip);
+}
+
+/// Run the emulator on its own thread, with the SDL window on this one
+///
+/// SDL has to stay on the thread that created it, and window operations such
+/// as moving or resizing can block that thread.
+#[cfg(feature = "sdl")]
+fn run_sdl(
+    opt: &Opt,
+    config: &Config,
+    source: &dyn RomSource,
+    program_rom: Vec<u8>,
+    metadata: &Metadata,
+    scale: minifb::Scale,
+    filters: &[FilterSpec],
+) -> anyhow::Result<()> {
+    let cpu = new_cpu(opt, &program_rom);
+    let pipeline = Pipeline::from_specs(filters);
+    let (width, height) = pipeline.output_size(cpu.display_width(), cpu.display_height());
+    let scale = emu::window_scale(
+        scale,
+        pipeline.upscale(cpu.display_width(), cpu.display_height()),
+    );
+
+    let frontend = chip8::sdl::SdlFrontend::new(emu::TITLE, width, height, scale)?;
+    let (remote, ui) = bus::connect();
+
+    std::thread::scope(|scope| {
+        let emu_thread = scope.spawn(move || {
+            let mut emu = Emulator::with_frontend(cpu, remote)?;
+            // Filters are not Send, so build them on the emulator thread
+            emu.filters = Pipeline::from_specs(filters);
+            run_emulator(opt, config, source, program_rom, metadata, emu)
+        });
+
+        ui.run(frontend, Duration::from_millis(4));
+
+        emu_thread.join().unwrap()
+    })
+}
+
+fn run_emulator<F: Frontend>(
+    opt: &Opt,
+    config: &Config,
+    source: &dyn RomSource,
+    program_rom: Vec<u8>,
+    metadata: &Metadata,
+    mut emu: Emulator<F>,
+) -> anyhow::Result<()> {
+    emu.debug_print = opt.verbose;
+
+    if let Some(language) = opt.language.as_deref().or(config.language()) {
+        emu.set_locale(Locale::find(language)?);
+    }
+
+    // Headless runs are checks, not something the user opened
+    if !opt.headless {
+        remember_rom(opt, source, &emu.locale);
+    }
+
+    emu.trace_buffer = opt.trace_buffer.map(RingBuffer::new);
+    load_plugins(opt, &mut emu)?;
+    load_scripts(opt, &mut emu)?;
+
+    for spec in opt.samples.iter() {
+        emu.throttles.set(*spec);
+    }
+    // ROM metadata supplies defaults that the config and options override
+    emu.cpu.quirks = match metadata.quirks {
+        Some(quirks) if config.quirks == Quirks::default() => quirks,
+        _ => config.quirks,
+    };
+    emu.cpu.breakpoints.extend(opt.breakpoints.iter().copied());
+
+    for watchpoint in opt.watchpoints.iter() {
+        emu.cpu.watchpoints.add(*watchpoint);
+    }
+
+    let bindings = config.key_bindings()?.into_iter().chain(
+        opt.key_mappings
+            .iter()
+            .map(|mapping| (mapping.key, mapping.binding)),
+    );
+
+    for (key, binding) in bindings {
+        let previous = emu.bind(key, binding);
+
+        // Remapping a keypad key is expected; losing one to a hotkey, or a
+        // hotkey to the keypad, is worth pointing out
+        match (previous, binding) {
+            (Some(previous @ Binding::Keypad(_)), Binding::Action(_))
+            | (Some(previous @ Binding::Action(_)), Binding::Keypad(_)) => {
+                let message = emu.locale.format(
+                    "error.rebound",
+                    &[
+                        ("key", &key),
+                        ("previous", &previous),
+                        ("binding", &binding),
+                    ],
+                );
+                eprintln!(
+                    "{}",
+                    emu.locale.format("error.warning", &[("message", &message)])
+                );
+            }
+            _ => {}
+        }
+    }
+
+    if opt.kiosk {
+        let exit = match opt.kiosk_exit {
+            Some(exit) => exit,
+            None => kiosk::DEFAULT_EXIT_CHORD.parse()?,
+        };
+        let idle_reset = match opt.idle_reset {
+            Some(secs) if !secs.is_finite() || secs < 0. => {
+                return Err(anyhow!(
+                    "--idle-reset must be 0 or more seconds, got {}",
+                    secs
+                ));
+            }
+            Some(0.) => None,
+            Some(secs) => Some(Duration::from_secs_f64(secs)),
+            None => Some(kiosk::DEFAULT_IDLE_RESET),
+        };
+
+        // Visitors get the keypad only
+        emu.control_map.clear();
+        emu.bind(exit, Binding::Action(Action::Quit));
+        emu.kiosk = Some(Kiosk::new(idle_reset));
+    }
+
+    let builtin_palette = opt.palette.or_else(|| {
+        config
+            .palette()
+            .and_then(config::PaletteConfig::builtin_index)
+    });
+
+    if let Some(p) = builtin_palette {
+        emu.palette_index = p;
+        emu.set_palette(palette::builtin(p));
+    } else if let Some(p) = config.palette() {
+        emu.set_palette(p.colors());
+    }
+    emu.palette_config = config.path.clone();
+
+    if let Some(clock) = opt.clock.or(config.clock()) {
+        emu.clock_period = if clock > 0. {
+            Some(Duration::from_secs_f64(1. / clock))
+        } else {
+            // Use native clock
+            None
+        };
+    } else if let (Some(tickrate), None) = (metadata.tickrate, opt.instructions_per_tick) {
+        // Instructions per 60 Hz frame, run on the clock so the timers stay at
+        // 60 Hz
+        emu.clock_period = Some(Duration::from_secs_f64(1. / (60. * f64::from(tickrate))));
+    }
+
+    if let Some(refresh) = opt.refresh.or(config.refresh()) {
+        if !refresh.is_finite() || refresh <= 0. {
+            return Err(anyhow!("refresh must be more than 0 Hz, got {}", refresh));
+        }
+
+        emu.refresh_period = Some(Duration::from_secs_f64(1. / refresh));
+    }
+
+    if let Some(policy) = opt.paused_timers.or(config.paused_timers()) {
+        emu.paused_timers = policy;
+    }
+
+    #[cfg(feature = "gamepad")]
+    match chip8::gamepad::Gamepad::new() {
+        Ok(mut gamepad) => {
+            gamepad.button_map.extend(config.gamepad_bindings()?);
+            emu.gamepad = Some(gamepad);
+        }
+        Err(e) => eprintln!(
+            "{}",
+            emu.locale
+                .format("error.gamepad_disabled", &[("message", &e)])
+        ),
+    }
+
+    emu.single_key_keypad = opt.single_key;
+    emu.keypad = opt.keypad;
+    emu.instructions_per_tick = opt.instructions_per_tick;
+    emu.practice = opt.practice.map(Practice::new);
+    if !program_rom.is_empty() {
+        let rom_storage = storage(opt).rom(&source.name(), &program_rom);
+        emu.savestate_path = Some(rom_storage.state_path());
+        emu.screenshot_dir = Some(rom_storage.screenshot_dir());
+        emu.flags_path = Some(rom_storage.flags_path()).filter(|_| !opt.no_persist);
+        emu.load_flags()?;
+    }
+    emu.screenshot_scale =
+        emu::scale_factor(config.window_scale()?.unwrap_or(emu::DEFAULT_WINDOW_SCALE));
+
+    let triggers_path = opt.triggers.clone().or_else(|| {
+        source
+            .local_path()
+            .map(|path| path.with_extension("triggers.toml"))
+            .filter(|path| path.exists())
+    });
+    if let Some(path) = &triggers_path {
+        emu.triggers = Some(Triggers::load(path)?);
+    }
+
+    let cheats_path = opt.cheats.clone().or_else(|| {
+        source
+            .local_path()
+            .map(|path| path.with_extension("cheats"))
+            .filter(|path| path.exists())
+    });
+    if let Some(path) = &cheats_path {
+        emu.cheats = Some(Cheats::load(path)?);
+    }
+
+    if opt.stats {
+        emu.stats = Some(Stats::new(emu.cpu.ram.len()));
+    }
+
+    if opt.profile {
+        emu.profile = Some(CallProfile::new(emu.cpu.pc));
+    }
+
+    let seed = match (opt.seed, opt.random) {
+        (Some(seed), _) => Some(seed),
+        (None, true) => {
+            let seed = OsRng.next_u64();
+            eprintln!(
+                "{}",
+                emu.locale.format("status.rng_seed", &[("seed", &seed)])
+            );
+            Some(seed)
+        }
+        (None, false) => None,
+    };
+    if let Some(seed) = seed {
+        emu.rng_seed = rng::seed_from_u64(seed);
+    }
+
+    let played = match &opt.play {
+        Some(path) => {
+            let movie = Movie::load(path)?;
+            emu.instructions_per_tick = Some(movie.instructions_per_tick);
+            emu.rng_seed = movie.seed;
+            emu.key_schedule = movie.schedule();
+            Some(movie)
+        }
+        None => None,
+    };
+
+    if let Some(path) = &opt.record {
+        let ipt = *emu
+            .instructions_per_tick
+            .get_or_insert(emu::DEFAULT_INSTRUCTIONS_PER_TICK);
+
+        let mut movie = Movie::new(ipt);
+        movie.seed = emu.rng_seed;
+        emu.recording = Some(movie);
+        emu.recording_path = Some(path.clone());
+    }
+
+    let mut net_host = match opt.host {
+        Some(port) => {
+            emu.instructions_per_tick
+                .get_or_insert(emu::DEFAULT_INSTRUCTIONS_PER_TICK);
+
+            let host = NetHost::bind(("0.0.0.0", port))?;
+            let addr = host.local_addr()?;
+            println!(
+                "{}",
+                emu.locale.format("status.sharing", &[("addr", &addr)])
+            );
+            Some(host)
+        }
+        None => None,
+    };
+
+    let mut net_guest = match &opt.join {
+        Some(addr) => {
+            let guest = NetGuest::connect(addr.as_str())?;
+
+            // Only the host's input runs, so the session stays the same
+            emu.key_map.clear();
+            #[cfg(feature = "gamepad")]
+            {
+                emu.gamepad = None;
+            }
+
+            Some(guest)
+        }
+        None => None,
+    };
+
+    if opt.headless {
+        // Run flat out, with timers in emulated time so runs are repeatable
+        emu.clock_period = None;
+        emu.instructions_per_tick
+            .get_or_insert(emu::DEFAULT_INSTRUCTIONS_PER_TICK);
+    }
+
+    emu.set_rom_title(rom_title(source, metadata.title.clone()));
+    emu.rom = program_rom;
+    emu.reset()?;
+
+    let mut debugger = if opt.debug {
+        let symbols_path = opt.symbols.clone().or_else(|| {
+            source
+                .local_path()
+                .map(|path| path.with_extension("sym"))
+                .filter(|path| path.exists())
+        });
+        let symbols = match symbols_path {
+            Some(path) => Symbols::load(&path)?,
+            None => Symbols::default(),
+        };
+
+        let mut debugger = Debugger::new(&emu.locale)?;
+        debugger.symbols = symbols;
+        Some(debugger)
+    } else {
+        None
+    };
+
+    let mut monitor = opt.monitor.then(Monitor::new);
+
+    let mut gdb = match opt.gdb {
+        Some(port) => {
+            let server = GdbServer::bind(("127.0.0.1", port))?;
+            let addr = server.local_addr()?;
+            println!(
+                "{}",
+                emu.locale
+                    .format("status.gdb_listening", &[("addr", &addr)])
+            );
+            Some(server)
+        }
+        None => None,
+    };
+
+    let mut frame_dumper = match &opt.dump_frames {
+        Some(dir) => Some(FrameDumper::new(dir)?),
+        None => None,
+    };
+
+    let mut rom_watcher = match (opt.reload, source.local_path()) {
+        (false, _) => None,
+        (true, Some(path)) if path.is_file() => Some(RomWatcher::new(&path)?),
+        // Watched once one is dropped onto the window
+        (true, None) if emu.rom.is_empty() => None,
+        (true, _) => return Err(anyhow!("--reload needs a ROM file")),
+    };
+
+    // ROM dropped onto the window, replacing `source`
+    let mut dropped: Option<Box<dyn RomSource>> = None;
+
+    let mut stopped = None;
+    let mut exit_code = None;
+    // Instruction count when the program started waiting for a key
+    let mut waiting_since = None;
+
+    while !emu.closing {
+        let (pc, cycle) = (emu.cpu.pc, emu.cycle);
+
+        emu.step()?;
+
+        if let Some(path) = emu.frontend.take_dropped_file() {
+            let rom = rom::from_arg(&path.to_string_lossy(), &storage(opt));
+
+            if load_new_rom(opt, rom.as_ref(), "status.loaded_rom", &mut emu)? {
+                remember_rom(opt, rom.as_ref(), &emu.locale);
+
+                if opt.reload {
+                    rom_watcher = match rom.local_path().filter(|path| path.is_file()) {
+                        Some(path) => Some(RomWatcher::new(&path)?),
+                        None => None,
+                    };
+                }
+
+                dropped = Some(rom);
+            }
+        }
+
+        if let Some(watcher) = &mut rom_watcher {
+            if watcher.changed() {
+                let current = dropped.as_deref().unwrap_or(source);
+                load_new_rom(opt, current, "status.reloaded_rom", &mut emu)?;
+            }
+        }
+
+        if let Some(dumper) = &mut frame_dumper {
+            dumper.update(&emu)?;
+        }
+
+        if let Some(debugger) = &mut debugger {
+            debugger.update(&mut emu)?;
+        }
+
+        if let Some(gdb) = &mut gdb {
+            gdb.update(&mut emu)?;
+        }
+
+        if let Some(monitor) = &mut monitor {
+            monitor.update(&mut emu);
+        }
+
+        if let Some(host) = &mut net_host {
+            host.update(&mut emu)?;
+        }
+
+        if let Some(guest) = &mut net_guest {
+            guest.update(&mut emu)?;
+        }
+
+        // Nothing can resume a headless run paused on a breakpoint or error
+        let attached = debugger.is_some()
+            || gdb.is_some()
+            || monitor.as_ref().is_some_and(Monitor::is_open)
+            || net_guest.as_ref().is_some_and(NetGuest::is_connected);
+
+        stopped = stop_reason(opt, &emu, pc, cycle, attached);
+        if stopped.is_some() {
+            break;
+        }
+
+        if opt.headless && waiting_for_key(&emu, pc, cycle) {
+            let since = *waiting_since.get_or_insert_with(|| {
+                // One line per wait, for scripts watching stderr
+                eprintln!(
+                    "{{\"event\":\"waiting_for_input\",\"pc\":{},\"cycle\":{}}}",
+                    pc, emu.cycle
+                );
+                emu.cycle
+            });
+
+            if opt
+                .input_timeout
+                .is_some_and(|timeout| emu.cycle - since >= timeout)
+            {
+                stopped = Some(emu.locale.format(
+                    "status.input_timeout",
+                    &[("pc", &format!("{:04X}", pc)), ("cycles", &emu.cycle)],
+                ));
+                exit_code = Some(EXIT_WAITING_FOR_INPUT);
+                break;
+            }
+        } else if emu.cpu.pc != pc {
+            waiting_since = None;
+        }
+    }
+
+    drop(debugger);
+    emu.flush_trace();
+
+    if let Some(host) = net_host {
+        host.finish(&mut emu);
+    }
+
+    let dump_path = opt
+        .dump
+        .clone()
+        .or_else(|| opt.headless.then(|| PathBuf::from("-")));
+    let dump = match &dump_path {
+        Some(path) => Some(render_dump(path, &emu)?),
+        None => None,
+    };
+
+    if opt.screenshot_on_exit {
+        let path = emu.save_screenshot()?;
+        println!(
+            "{}",
+            emu.locale
+                .format("status.saved_screenshot", &[("path", &path.display())])
+        );
+    }
+
+    // Close first so a terminal frontend has restored the screen before printing
+    let stats = emu.stats.take();
+    let profile = emu.profile.take();
+    let buzzer_mismatch = played
+        .as_ref()
+        .and_then(|movie| buzzer_mismatch(movie, &emu.buzzer_events, emu.cycle));
+
+    emu.save_recording()?;
+    let locale = emu.locale.clone();
+    emu.close();
+
+    if let Some(reason) = &stopped {
+        eprintln!("{}", reason);
+    }
+
+    if let Some(cycle) = buzzer_mismatch {
+        let message = locale.format("error.buzzer_mismatch", &[("cycle", &cycle)]);
+        eprintln!(
+            "{}",
+            locale.format("error.warning", &[("message", &message)])
+        );
+    }
+
+    if let (Some(path), Some(dump)) = (&dump_path, &dump) {
+        if path == Path::new("-") {
+            io::stdout().write_all(dump)?;
+        } else {
+            fs::write(path, dump)?;
+        }
+    }
+
+    if let (Some(dir), Some(dumper)) = (&opt.dump_frames, &frame_dumper) {
+        println!(
+            "{}",
+            locale.format(
+                "status.wrote_frames",
+                &[("count", &dumper.count()), ("path", &dir.display())]
+            )
+        );
+    }
+
+    if let Some(stats) = &stats {
+        let rom = dropped.as_deref().unwrap_or(source).name();
+        println!("{}", locale.format("status.statistics", &[("rom", &rom)]));
+        print!("{}", stats.summary());
+    }
+
+    if let Some(profile) = &profile {
+        print!("{}", profile.summary());
+    }
+
+    if let Some(code) = exit_code {
+        process::exit(code);
+    }
+
+    Ok(())
+}
+
+/// Whether the instruction just run from `pc` at instruction `cycle` was an
+/// FX0A still waiting, with no scheduled input to end the wait
+fn waiting_for_key<F: Frontend>(emu: &Emulator<F>, pc: u16, cycle: u64) -> bool {
+    let opcode = emu.cpu.mem_read_opcode(pc);
+
+    emu.cycle > cycle
+        && emu.cpu.pc == pc
+        && matches!(decode(opcode), Instruction::LdVxK(_))
+        && emu.key_schedule.is_empty()
+}
+
+/// First instruction at which `events` stop matching the buzzer logged in
+/// `movie`, up to `cycle` or the end of the movie; movies without a log match
+fn buzzer_mismatch(movie: &Movie, events: &[BuzzerEvent], cycle: u64) -> Option<u64> {
+    if movie.buzzer.is_empty() {
+        return None;
+    }
+
+    let end = cycle.min(movie.end_step());
+    let before_end = |events: &[BuzzerEvent]| -> Vec<BuzzerEvent> {
+        events.iter().filter(|e| e.cycle < end).copied().collect()
+    };
+    let (played, recorded) = (before_end(events), before_end(&movie.buzzer));
+
+    (0..played.len().max(recorded.len())).find_map(|i| match (played.get(i), recorded.get(i)) {
+        (Some(a), Some(b)) if a == b => None,
+        (a, b) => a.iter().chain(b.iter()).map(|e| e.cycle).min(),
+    })
+}
+
+#[cfg(feature = "plugins")]
+fn load_plugins<F: Frontend>(opt: &Opt, emu: &mut Emulator<F>) -> anyhow::Result<()> {
+    use chip8::plugin::{Plugin, PluginFilter};
+    use std::rc::Rc;
+
+    for path in &opt.plugins {
+        let plugin = Rc::new(Plugin::load(path)?);
+
+        // Plugin filters keep the frame size, so the window needs no resizing
+        if plugin.has_filter() {
+            emu.filters.push(Box::new(PluginFilter(Rc::clone(&plugin))));
+        }
+
+        println!(
+            "{}",
+            emu.locale
+                .format("status.loaded_plugin", &[("name", &plugin.name)])
+        );
+        emu.plugins.push(plugin);
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "plugins"))]
+fn load_plugins<F: Frontend>(opt: &Opt, _emu: &mut Emulator<F>) -> anyhow::Result<()> {
+    if opt.plugins.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("--plugin needs the `plugins` feature"))
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn load_scripts<F: Frontend>(opt: &Opt, emu: &mut Emulator<F>) -> anyhow::Result<()> {
+    use chip8::script::Script;
+
+    for path in &opt.scripts {
+        emu.scripts.push(Script::load(path)?);
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "scripting"))]
+fn load_scripts<F: Frontend>(opt: &Opt, _emu: &mut Emulator<F>) -> anyhow::Result<()> {
+    if opt.scripts.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("--script needs the `scripting` feature"))
+    }
+}
+
+/// Why a run should stop after stepping from `pc` at instruction `cycle`, if it should
+fn stop_reason<F: Frontend>(
+    opt: &Opt,
+    emu: &Emulator<F>,
+    pc: u16,
+    cycle: u64,
+    attached: bool,
+) -> Option<String> {
+    let locale = &emu.locale;
+
+    if opt.headless && emu.paused && !attached {
+        let reason = emu
+            .break_reason
+            .as_deref()
+            .unwrap_or(locale.get("status.cpu_error"));
+
+        return Some(locale.format(
+            "status.stopped",
+            &[
+                ("pc", &format!("{:04X}", emu.cpu.pc)),
+                ("cycles", &emu.cycle),
+                ("reason", &reason),
+            ],
+        ));
+    }
+
+    if opt.cycles.is_some_and(|cycles| emu.cycle >= cycles) {
+        return Some(locale.format("status.ran", &[("cycles", &emu.cycle)]));
+    }
+
+    // An instruction ran without moving PC: a jump to itself, or FX0A with no key
+    let halted = emu.cycle > cycle && emu.cpu.pc == pc && !emu.paused;
+    if opt.until_halt && halted && emu.key_schedule.is_empty() {
+        return Some(locale.format(
+            "status.halted",
+            &[("pc", &format!("{:04X}", pc)), ("cycles", &emu.cycle)],
+        ));
+    }
+
+    None
+}
+
+/// Display contents for `--dump`: a PNG image for .png paths, otherwise ASCII art
+fn render_dump<F: Frontend>(path: &Path, emu: &Emulator<F>) -> anyhow::Result<Vec<u8>> {
+    let is_png = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+
+    if is_png {
+        let pixels: Vec<u32> = emu
+            .cpu
+            .display
+            .iter()
+            .map(|&px| plane_color(px, (emu.color_off, emu.color_on)))
+            .collect();
+
+        png::encode(&pixels, emu.cpu.display_width(), emu.cpu.display_height())
+    } else {
+        let mut text = emu.cpu.display_to_ascii();
+        text.push('\n');
+        Ok(text.into_bytes())
+    }
+}
+
+fn load_marks(path: &Path) -> anyhow::Result<disasm::Marks> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read marks {}: {}", path.display(), e))?;
+
+    disasm::Marks::parse(&text).map_err(|e| anyhow!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,330 @@
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{convert::TryFrom, fmt};
+
+use crate::metadata::Metadata;
+
+/// Address the first assembled byte is loaded at
+const ORIGIN: u16 = 0x200;
+
+/// Directives setting a `Metadata` field, followed by its value
+const METADATA_DIRECTIVES: [&str; 4] = ["title", "author", "tickrate", "quirks"];
+
+/// Problem on one line of assembly source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// 1-based line
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.line, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AsmError {}
+
+/// Assemble source in the mnemonic syntax `disasm` emits into a ROM loaded at 200
+///
+/// One instruction per line, e.g. `LD V0, 05` or `DRW V0, V1, 5`. Numbers are
+/// hex, optionally prefixed with `0x` or `#`. `name:` defines a label at the
+/// next address, usable wherever an address is expected. `DB` and `DW` emit
+/// bytes and big-endian words. `;` starts a comment. Mnemonics and registers
+/// are case-insensitive; labels are not.
+///
+/// `TITLE`, `AUTHOR`, `TICKRATE`, and `QUIRKS` lines take the rest of the line
+/// as a value and append a metadata block to the ROM (see `Metadata`), e.g.
+/// `TITLE Pong` or `QUIRKS shift_vy, jump_vx`.
+///
+/// Returns every error found, in line order.
+pub fn assemble(source: &str) -> Result<Vec<u8>, Vec<AsmError>> {
+    let mut errors = Vec::new();
+    let mut labels = BTreeMap::new();
+    let mut statements = Vec::new();
+    let mut metadata = Metadata::default();
+    let mut addr = ORIGIN;
+
+    // First pass: find labels and where each statement goes
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let mut error = |message: String| {
+            errors.push(AsmError {
+                line: line_number,
+                message,
+            })
+        };
+
+        let mut text = line.split(';').next().unwrap_or("").trim();
+
+        while let Some((label, rest)) = split_label(text) {
+            if labels.insert(label, addr).is_some() {
+                error(format!("label '{}' is already defined", label));
+            }
+            text = rest;
+        }
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operands) = match text.split_once(char::is_whitespace) {
+            Some((mnemonic, rest)) => (mnemonic, split_operands(rest)),
+            None => (text, Vec::new()),
+        };
+        let mnemonic = mnemonic.to_ascii_uppercase();
+
+        if let Some(key) = METADATA_DIRECTIVES
+            .iter()
+            .find(|key| key.eq_ignore_ascii_case(&mnemonic))
+        {
+            let value = text.split_once(char::is_whitespace).map_or("", |(_, v)| v);
+
+            if let Err(e) = metadata.set(key, value) {
+                error(e.message);
+            }
+            continue;
+        }
+
+        let size = match mnemonic.as_str() {
+            "DB" => operands.len(),
+            "DW" => operands.len() * 2,
+            _ => 2,
+        };
+
+        statements.push((line_number, mnemonic, operands));
+
+        match u16::try_from(size)
+            .ok()
+            .and_then(|size| addr.checked_add(size))
+            .filter(|end| *end <= 0x1000)
+        {
+            Some(end) => addr = end,
+            None => {
+                error("program does not fit in memory".to_string());
+                break;
+            }
+        }
+    }
+
+    // Second pass: encode, now that every label is known
+    let mut rom = Vec::new();
+
+    for (line, mnemonic, operands) in statements {
+        let encoder = Encoder {
+            labels: &labels,
+            operands: &operands,
+        };
+
+        match encoder.encode(&mnemonic) {
+            Ok(bytes) => rom.extend(bytes),
+            Err(message) => errors.push(AsmError { line, message }),
+        }
+    }
+
+    if !metadata.is_empty() {
+        match metadata.append_to(&rom) {
+            Ok(tagged) => rom = tagged,
+            Err(e) => errors.push(AsmError {
+                line: source.lines().count(),
+                message: e.message,
+            }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(rom)
+    } else {
+        errors.sort_by_key(|e| e.line);
+        Err(errors)
+    }
+}
+
+/// Split `name: rest` into a label and the rest of the line
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let (label, rest) = text.split_once(':')?;
+    let label = label.trim();
+
+    let is_identifier = label
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if is_identifier {
+        Some((label, rest.trim()))
+    } else {
+        None
+    }
+}
+
+/// Comma-separated operands, accepting `SHR V0 {, V1}` as written by `disasm`
+fn split_operands(text: &str) -> Vec<&str> {
+    text.split(',')
+        .map(|op| op.trim_matches(|c: char| c.is_whitespace() || c == '{' || c == '}'))
+        .filter(|op| !op.is_empty())
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand<'a> {
+    V(u8),
+    I,
+    /// `[I]`
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    B,
+    Value(&'a str),
+}
+
+fn operand(text: &str) -> Operand<'_> {
+    let upper = text.to_ascii_uppercase();
+
+    match upper.as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "DT" => Operand::Dt,
+        "ST" => Operand::St,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "B" => Operand::B,
+        _ => match upper.strip_prefix('V') {
+            Some(reg) if reg.len() == 1 => match u8::from_str_radix(reg, 16) {
+                Ok(x) => Operand::V(x),
+                Err(_) => Operand::Value(text),
+            },
+            _ => Operand::Value(text),
+        },
+    }
+}
+
+struct Encoder<'a> {
+    labels: &'a BTreeMap<&'a str, u16>,
+    operands: &'a [&'a str],
+}
+
+impl Encoder<'_> {
+    fn encode(&self, mnemonic: &str) -> Result<Vec<u8>, String> {
+        use Operand::*;
+
+        match mnemonic {
+            "DB" => {
+                return self
+                    .operands
+                    .iter()
+                    .map(|op| self.value(op, 0xFF).map(|b| b as u8))
+                    .collect()
+            }
+            "DW" => {
+                return Ok(self
+                    .operands
+                    .iter()
+                    .map(|op| self.value(op, 0xFFFF))
+                    .collect::<Result<Vec<u16>, String>>()?
+                    .into_iter()
+                    .flat_map(u16::to_be_bytes)
+                    .collect())
+            }
+            _ => {}
+        }
+
+        let ops: Vec<Operand> = self.operands.iter().map(|op| operand(op)).collect();
+        let xy = |x: u8, y: u8, n: u16| 0x8000 | u16::from(x) << 8 | u16::from(y) << 4 | n;
+        let fx = |x: u8, kk: u16| 0xF000 | u16::from(x) << 8 | kk;
+
+        let opcode = match (mnemonic, ops.as_slice()) {
+            ("CLS", []) => 0x00E0,
+            ("RET", []) => 0x00EE,
+            ("SYS", [Value(a)]) => self.value(a, 0xFFF)?,
+            ("JP", [Value(a)]) => 0x1000 | self.value(a, 0xFFF)?,
+            ("JP", [V(0), Value(a)]) => 0xB000 | self.value(a, 0xFFF)?,
+            ("CALL", [Value(a)]) => 0x2000 | self.value(a, 0xFFF)?,
+            ("SE", [V(x), V(y)]) => 0x5000 | u16::from(*x) << 8 | u16::from(*y) << 4,
+            ("SE", [V(x), Value(kk)]) => 0x3000 | u16::from(*x) << 8 | self.value(kk, 0xFF)?,
+            ("SNE", [V(x), V(y)]) => 0x9000 | u16::from(*x) << 8 | u16::from(*y) << 4,
+            ("SNE", [V(x), Value(kk)]) => 0x4000 | u16::from(*x) << 8 | self.value(kk, 0xFF)?,
+            ("LD", [V(x), V(y)]) => xy(*x, *y, 0x0),
+            ("LD", [V(x), Value(kk)]) => 0x6000 | u16::from(*x) << 8 | self.value(kk, 0xFF)?,
+            ("LD", [I, Value(a)]) => 0xA000 | self.value(a, 0xFFF)?,
+            ("LD", [V(x), Dt]) => fx(*x, 0x07),
+            ("LD", [V(x), K]) => fx(*x, 0x0A),
+            ("LD", [Dt, V(x)]) => fx(*x, 0x15),
+            ("LD", [St, V(x)]) => fx(*x, 0x18),
+            ("LD", [F, V(x)]) => fx(*x, 0x29),
+            ("LD", [B, V(x)]) => fx(*x, 0x33),
+            ("LD", [IndirectI, V(x)]) => fx(*x, 0x55),
+            ("LD", [V(x), IndirectI]) => fx(*x, 0x65),
+            ("ADD", [V(x), V(y)]) => xy(*x, *y, 0x4),
+            ("ADD", [V(x), Value(kk)]) => 0x7000 | u16::from(*x) << 8 | self.value(kk, 0xFF)?,
+            ("ADD", [I, V(x)]) => fx(*x, 0x1E),
+            ("OR", [V(x), V(y)]) => xy(*x, *y, 0x1),
+            ("AND", [V(x), V(y)]) => xy(*x, *y, 0x2),
+            ("XOR", [V(x), V(y)]) => xy(*x, *y, 0x3),
+            ("SUB", [V(x), V(y)]) => xy(*x, *y, 0x5),
+            ("SHR", [V(x)]) => xy(*x, *x, 0x6),
+            ("SHR", [V(x), V(y)]) => xy(*x, *y, 0x6),
+            ("SUBN", [V(x), V(y)]) => xy(*x, *y, 0x7),
+            ("SHL", [V(x)]) => xy(*x, *x, 0xE),
+            ("SHL", [V(x), V(y)]) => xy(*x, *y, 0xE),
+            ("RND", [V(x), Value(kk)]) => 0xC000 | u16::from(*x) << 8 | self.value(kk, 0xFF)?,
+            // A sprite height of B or F reads as a register name
+            ("DRW", [V(x), V(y), n @ (Value(_) | B | F)]) => {
+                let n = match n {
+                    Value(n) => self.value(n, 0xF)?,
+                    B => 0xB,
+                    _ => 0xF,
+                };
+
+                0xD000 | u16::from(*x) << 8 | u16::from(*y) << 4 | n
+            }
+            ("SKP", [V(x)]) => 0xE09E | u16::from(*x) << 8,
+            ("SKNP", [V(x)]) => 0xE0A1 | u16::from(*x) << 8,
+            (
+                "CLS" | "RET" | "SYS" | "JP" | "CALL" | "SE" | "SNE" | "LD" | "ADD" | "OR" | "AND"
+                | "XOR" | "SUB" | "SHR" | "SUBN" | "SHL" | "RND" | "DRW" | "SKP" | "SKNP",
+                _,
+            ) => {
+                return Err(format!(
+                    "invalid operands for {}: '{}'",
+                    mnemonic,
+                    self.operands.join(", ")
+                ))
+            }
+            _ => return Err(format!("unknown instruction '{}'", mnemonic)),
+        };
+
+        Ok(opcode.to_be_bytes().to_vec())
+    }
+
+    /// A label's address, or a hex number no greater than `max`
+    fn value(&self, text: &str, max: u16) -> Result<u16, String> {
+        let value = match self.labels.get(text) {
+            Some(addr) => *addr,
+            None => {
+                let digits = text
+                    .strip_prefix("0x")
+                    .or_else(|| text.strip_prefix("0X"))
+                    .or_else(|| text.strip_prefix('#'))
+                    .unwrap_or(text);
+
+                u16::from_str_radix(digits, 16)
+                    .map_err(|_| format!("'{}' is not a hex number or known label", text))?
+            }
+        };
+
+        if value > max {
+            return Err(format!("{} does not fit in {:X}", text, max));
+        }
+
+        Ok(value)
+    }
+}
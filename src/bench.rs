@@ -0,0 +1,151 @@
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use minifb::Key;
+
+use crate::{
+    chip8::Chip8,
+    emu::{Emulator, DEFAULT_INSTRUCTIONS_PER_TICK},
+    frontend::HeadlessFrontend,
+    palette,
+};
+
+/// Tight ALU/branch loop exercising opcode dispatch
+pub const OPCODE_DISPATCH_ROM: [u8; 20] = [
+    0x60, 0x00, // 0200: LD V0, 00
+    0x61, 0x01, // 0202: LD V1, 01
+    0x80, 0x14, // 0204: ADD V0, V1
+    0x81, 0x02, // 0206: AND V1, V0
+    0x71, 0x01, // 0208: ADD V1, 01
+    0x30, 0x00, // 020A: SE V0, 00
+    0x80, 0x13, // 020C: XOR V0, V1
+    0xA3, 0x00, // 020E: LD I, 300
+    0xF0, 0x1E, // 0210: ADD I, V0
+    0x12, 0x04, // 0212: JP 204
+];
+
+/// Loop drawing a full-height sprite across the screen
+pub const DRW_HEAVY_ROM: [u8; 29] = [
+    0x60, 0x00, // 0200: LD V0, 00
+    0x61, 0x00, // 0202: LD V1, 00
+    0xA2, 0x0E, // 0204: LD I, 20E
+    0xD0, 0x1F, // 0206: DRW V0, V1, F
+    0x70, 0x08, // 0208: ADD V0, 08
+    0x71, 0x03, // 020A: ADD V1, 03
+    0x12, 0x06, // 020C: JP 206
+    // 020E: sprite
+    0xFF, 0x81, 0xBD, 0xA5, 0xA5, 0xBD, 0x81, 0xFF, 0x18, 0x3C, 0x7E, 0xFF, 0x7E, 0x3C, 0x18,
+];
+
+#[derive(Debug, Clone, Copy)]
+pub enum Workload {
+    Rom(&'static str, &'static [u8]),
+    DisplayConversion,
+    EmulatorSession,
+}
+
+pub const SUITE: [Workload; 4] = [
+    Workload::Rom("opcode_dispatch", &OPCODE_DISPATCH_ROM),
+    Workload::Rom("drw_heavy", &DRW_HEAVY_ROM),
+    Workload::DisplayConversion,
+    Workload::EmulatorSession,
+];
+
+impl Workload {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Workload::Rom(name, _) => name,
+            Workload::DisplayConversion => "display_conversion",
+            Workload::EmulatorSession => "emulator_session",
+        }
+    }
+
+    /// Run `iterations` of the workload, returning the elapsed time
+    pub fn run(&self, iterations: u64) -> anyhow::Result<Duration> {
+        match self {
+            Workload::Rom(_, rom) => run_rom(rom, iterations),
+            Workload::DisplayConversion => {
+                let mut cpu = Chip8::new();
+                cpu.load_rom(&DRW_HEAVY_ROM)?;
+                run_cpu(&mut cpu, 1000)?;
+
+                let mut buffer = vec![0; cpu.display.len()];
+                let colors = palette::builtin(0);
+
+                let start = Instant::now();
+                for _ in 0..iterations {
+                    palette::convert_display(
+                        black_box(&cpu.display),
+                        black_box(&mut buffer),
+                        colors,
+                    );
+                }
+                Ok(start.elapsed())
+            }
+            Workload::EmulatorSession => run_session(&DRW_HEAVY_ROM, iterations),
+        }
+    }
+}
+
+/// Run a full headless `Emulator` session (input, timers, frame presentation)
+/// for `steps` steps, returning the elapsed time
+pub fn run_session(rom: &[u8], steps: u64) -> anyhow::Result<Duration> {
+    let mut frontend = HeadlessFrontend::new();
+    frontend.close_at = Some(steps);
+    frontend.frame_limit = Some(1);
+    frontend.hold_key(Key::X, 0, steps / 2);
+
+    let mut emu = Emulator::with_frontend(Chip8::new(), frontend)?;
+    emu.clock_period = None;
+    emu.instructions_per_tick = Some(DEFAULT_INSTRUCTIONS_PER_TICK);
+    emu.rom = rom.to_vec();
+    emu.reset()?;
+
+    let start = Instant::now();
+    while !emu.closing {
+        emu.step()?;
+    }
+    let elapsed = start.elapsed();
+
+    emu.close();
+
+    Ok(elapsed)
+}
+
+/// Create a CPU with `rom` loaded and run it for `cycles` steps, returning the elapsed time
+pub fn run_rom(rom: &[u8], cycles: u64) -> anyhow::Result<Duration> {
+    let mut cpu = Chip8::new();
+    cpu.load_rom(rom)?;
+
+    let start = Instant::now();
+    run_cpu(&mut cpu, cycles)?;
+    Ok(start.elapsed())
+}
+
+/// Step `cpu` for `cycles` steps
+pub fn run_cpu(cpu: &mut Chip8, cycles: u64) -> anyhow::Result<()> {
+    for _ in 0..cycles {
+        cpu.step()?;
+    }
+    Ok(())
+}
+
+/// Print a results line for a benchmark run
+pub fn report(name: &str, iterations: u64, elapsed: Duration) {
+    let secs = elapsed.as_secs_f64();
+    let rate = if secs > 0. {
+        iterations as f64 / secs
+    } else {
+        f64::INFINITY
+    };
+
+    println!(
+        "{:<20} {:>12} iters {:>10.3} ms {:>14.0} iters/s",
+        name,
+        iterations,
+        secs * 1000.,
+        rate
+    );
+}
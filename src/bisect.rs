@@ -0,0 +1,87 @@
+use anyhow::anyhow;
+
+use crate::{
+    chip8::{Chip8, Quirks},
+    movie::{Crash, Movie},
+};
+
+/// Minimal reproduction of a crash found in a movie
+#[derive(Debug, Clone)]
+pub struct Repro {
+    pub crash: Crash,
+    /// Machine state just before instruction `snapshot_step`
+    pub snapshot: Chip8,
+    pub snapshot_step: u64,
+    /// Recorded input from `snapshot_step` up to and including the crash
+    pub inputs: Movie,
+    /// Number of replays run during the search
+    pub replays: u32,
+}
+
+/// Find the earliest point in `movie` after which input no longer matters for its crash
+///
+/// Replays the movie from reset to find the crash, then binary-searches the
+/// instruction count for the earliest snapshot from which the same crash
+/// (same error at the same PC) still happens with the keypad left idle for
+/// the rest of the movie. The search assumes that once input stops mattering,
+/// it keeps not mattering.
+pub fn bisect(rom: &[u8], quirks: Quirks, movie: &Movie) -> anyhow::Result<Repro> {
+    if movie.start_step != 0 {
+        return Err(anyhow!(
+            "movie starts at instruction {}, expected a recording from reset",
+            movie.start_step
+        ));
+    }
+
+    let boot = |steps: u64| -> anyhow::Result<Chip8> {
+        let mut cpu = Chip8::new();
+        cpu.quirks = quirks;
+        cpu.seed_rng(movie.seed);
+        cpu.load_rom(rom)?;
+
+        if movie.replay(&mut cpu, 0, steps, false).is_some() {
+            return Err(anyhow!("crashed before instruction {}", steps));
+        }
+
+        Ok(cpu)
+    };
+
+    let crash = {
+        let mut cpu = boot(0)?;
+        movie
+            .replay(&mut cpu, 0, movie.end_step(), false)
+            .ok_or_else(|| anyhow!("movie does not end in a crash"))?
+    };
+
+    let mut replays = 1;
+
+    let mut reproduces = |step: u64| -> anyhow::Result<bool> {
+        replays += 1;
+
+        let mut cpu = boot(step)?;
+        let idle_crash = movie.replay(&mut cpu, step, crash.step + 1, true);
+
+        Ok(matches!(idle_crash, Some(c) if c.pc == crash.pc && c.error == crash.error))
+    };
+
+    // The failing instruction itself never depends on input, so `hi` always reproduces
+    let (mut lo, mut hi) = (0, crash.step);
+
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+
+        if reproduces(mid)? {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+
+    Ok(Repro {
+        crash,
+        snapshot: boot(hi)?,
+        snapshot_step: hi,
+        inputs: movie.slice(hi, crash.step + 1),
+        replays,
+    })
+}
@@ -0,0 +1,331 @@
+use std::{
+    collections::HashSet,
+    ops::Range,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use minifb::Key;
+
+use crate::{
+    chip8::AudioPattern,
+    frontend::{Beeper, Display, Frontend, Input},
+};
+
+/// Request from the emulator thread to the UI thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UiCommand {
+    /// Show a frame; sent again only once the UI reports it `FrameShown`
+    Present(Frame),
+    SetTitle(String),
+    SetBeeping(bool),
+    SetAudioPattern(Option<AudioPattern>),
+    /// The emulator is shutting down; close the frontend
+    Close,
+}
+
+/// Notification from the UI thread to the emulator thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UiEvent {
+    /// Keys held down changed
+    Keys(Option<Vec<Key>>),
+    /// Keys pressed since the last event
+    KeysPressed(HashSet<Key>),
+    /// A file was dropped onto the window
+    FileDropped(PathBuf),
+    /// Points held down on the window changed
+    Pointers(Vec<(usize, usize)>),
+    /// The window was resized, to this width and height in host pixels
+    Resized(usize, usize),
+    /// The last `Present` is on screen; its frame is handed back for reuse
+    FrameShown(Frame),
+    /// The user closed the window
+    Closed,
+}
+
+/// Frame of 0RGB pixels on its way to the UI
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    pub buffer: Vec<u32>,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Emulator-side end of the bus, used as the `Emulator`'s frontend
+///
+/// Never blocks: commands are queued, and input is whatever the UI last
+/// reported. A fast emulator presents far more often than the UI refreshes,
+/// so at most one frame is in flight; frames presented meanwhile replace each
+/// other until the UI has shown it. If the UI thread stalls, emulation and
+/// sound timing carry on; pausing only happens through an explicit pause
+/// action.
+#[derive(Debug)]
+pub struct RemoteFrontend {
+    commands: Sender<UiCommand>,
+    events: Receiver<UiEvent>,
+    /// Sent, and not yet reported shown
+    frame_in_flight: bool,
+    /// Newest frame, waiting for the one in flight to be shown
+    pending_frame: Option<Frame>,
+    /// Buffer handed back by the UI, to fill with the next frame
+    spare_frame: Option<Frame>,
+    keys: Option<Vec<Key>>,
+    keys_pressed: Option<HashSet<Key>>,
+    dropped_file: Option<PathBuf>,
+    pointers: Vec<(usize, usize)>,
+    window_size: Option<(usize, usize)>,
+    closing: bool,
+    ui_thread: Option<JoinHandle<()>>,
+}
+
+/// UI-side end of the bus, driving a real frontend with `run`
+#[derive(Debug)]
+pub struct UiEnd {
+    commands: Receiver<UiCommand>,
+    events: Sender<UiEvent>,
+}
+
+/// Create both ends of a bus
+pub fn connect() -> (RemoteFrontend, UiEnd) {
+    let (command_tx, command_rx) = mpsc::channel();
+    let (event_tx, event_rx) = mpsc::channel();
+
+    let remote = RemoteFrontend {
+        commands: command_tx,
+        events: event_rx,
+        frame_in_flight: false,
+        pending_frame: None,
+        spare_frame: None,
+        keys: None,
+        keys_pressed: None,
+        dropped_file: None,
+        pointers: Vec::new(),
+        window_size: None,
+        closing: false,
+        ui_thread: None,
+    };
+
+    let ui = UiEnd {
+        commands: command_rx,
+        events: event_tx,
+    };
+
+    (remote, ui)
+}
+
+/// Run the frontend built by `make_frontend` on a new UI thread
+///
+/// For frontends that can live off the main thread. Closing the returned
+/// frontend waits for the UI thread to finish.
+pub fn spawn_ui<F, M>(make_frontend: M, refresh_period: Duration) -> RemoteFrontend
+where
+    F: Frontend,
+    M: FnOnce() -> F + Send + 'static,
+{
+    let (mut remote, ui) = connect();
+
+    remote.ui_thread = Some(thread::spawn(move || {
+        ui.run(make_frontend(), refresh_period)
+    }));
+
+    remote
+}
+
+impl Display for RemoteFrontend {
+    fn update(&mut self) {
+        loop {
+            match self.events.try_recv() {
+                Ok(UiEvent::Keys(keys)) => self.keys = keys,
+                Ok(UiEvent::KeysPressed(keys)) => self
+                    .keys_pressed
+                    .get_or_insert_with(HashSet::new)
+                    .extend(keys),
+                Ok(UiEvent::FileDropped(path)) => self.dropped_file = Some(path),
+                Ok(UiEvent::Pointers(pointers)) => self.pointers = pointers,
+                Ok(UiEvent::Resized(width, height)) => self.window_size = Some((width, height)),
+                Ok(UiEvent::FrameShown(frame)) => {
+                    self.frame_in_flight = false;
+                    self.spare_frame = Some(frame);
+                }
+                Ok(UiEvent::Closed) | Err(TryRecvError::Disconnected) => {
+                    self.closing = true;
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        self.send_frame();
+    }
+
+    fn is_closing(&self) -> bool {
+        self.closing
+    }
+
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) {
+        let mut frame = self
+            .pending_frame
+            .take()
+            .or_else(|| self.spare_frame.take())
+            .unwrap_or_default();
+
+        frame.buffer.clear();
+        frame.buffer.extend_from_slice(buffer);
+        frame.width = width;
+        frame.height = height;
+
+        self.pending_frame = Some(frame);
+        self.send_frame();
+    }
+
+    /// A frame still waiting only needs the changed rows copied in
+    fn present_rows(&mut self, buffer: &[u32], width: usize, height: usize, rows: Range<usize>) {
+        match &mut self.pending_frame {
+            Some(frame)
+                if (frame.width, frame.height, frame.buffer.len())
+                    == (width, height, buffer.len()) =>
+            {
+                let changed = rows.start * width..(rows.end * width).min(buffer.len());
+                frame.buffer[changed.clone()].copy_from_slice(&buffer[changed]);
+            }
+            _ => self.present(buffer, width, height),
+        }
+    }
+
+    fn window_size(&self) -> Option<(usize, usize)> {
+        self.window_size
+    }
+
+    fn set_title(&mut self, title: String) {
+        let _ = self.commands.send(UiCommand::SetTitle(title));
+    }
+
+    fn close(self) {
+        let _ = self.commands.send(UiCommand::Close);
+
+        // A panicking UI thread has already reported itself
+        if let Some(ui_thread) = self.ui_thread {
+            let _ = ui_thread.join();
+        }
+    }
+}
+
+impl RemoteFrontend {
+    /// Send the pending frame, unless the UI has yet to show the last one
+    fn send_frame(&mut self) {
+        if self.frame_in_flight {
+            return;
+        }
+
+        if let Some(frame) = self.pending_frame.take() {
+            self.frame_in_flight = self.commands.send(UiCommand::Present(frame)).is_ok();
+        }
+    }
+}
+
+impl Input for RemoteFrontend {
+    fn get_keys(&self) -> Option<Vec<Key>> {
+        self.keys.clone()
+    }
+
+    fn get_keys_pressed(&mut self) -> Option<HashSet<Key>> {
+        self.keys_pressed.take()
+    }
+
+    fn take_dropped_file(&mut self) -> Option<PathBuf> {
+        self.dropped_file.take()
+    }
+
+    fn get_pointers(&self) -> Vec<(usize, usize)> {
+        self.pointers.clone()
+    }
+}
+
+impl Beeper for RemoteFrontend {
+    fn set_beeping(&mut self, beeping: bool) {
+        let _ = self.commands.send(UiCommand::SetBeeping(beeping));
+    }
+
+    fn set_audio_pattern(&mut self, pattern: Option<AudioPattern>) {
+        let _ = self.commands.send(UiCommand::SetAudioPattern(pattern));
+    }
+}
+
+impl UiEnd {
+    /// Drive `frontend` on the current thread, polling every `refresh_period`
+    ///
+    /// Returns when the emulator sends `Close` or goes away, or when the user
+    /// closes the frontend.
+    pub fn run<F: Frontend>(self, mut frontend: F, refresh_period: Duration) {
+        let mut keys = None;
+        let mut pointers = Vec::new();
+        let mut window_size = None;
+
+        loop {
+            frontend.update();
+
+            if frontend.is_closing() {
+                let _ = self.events.send(UiEvent::Closed);
+                break;
+            }
+
+            let new_keys = frontend.get_keys();
+            if new_keys != keys {
+                keys = new_keys;
+                let _ = self.events.send(UiEvent::Keys(keys.clone()));
+            }
+
+            if let Some(pressed) = frontend.get_keys_pressed() {
+                let _ = self.events.send(UiEvent::KeysPressed(pressed));
+            }
+
+            let new_pointers = frontend.get_pointers();
+            if new_pointers != pointers {
+                pointers = new_pointers;
+                let _ = self.events.send(UiEvent::Pointers(pointers.clone()));
+            }
+
+            if let Some(path) = frontend.take_dropped_file() {
+                let _ = self.events.send(UiEvent::FileDropped(path));
+            }
+
+            let new_size = frontend.window_size();
+            if new_size != window_size {
+                window_size = new_size;
+
+                if let Some((width, height)) = window_size {
+                    let _ = self.events.send(UiEvent::Resized(width, height));
+                }
+            }
+
+            let mut closed = false;
+
+            loop {
+                match self.commands.try_recv() {
+                    Ok(UiCommand::Present(frame)) => {
+                        frontend.present(&frame.buffer, frame.width, frame.height);
+                        let _ = self.events.send(UiEvent::FrameShown(frame));
+                    }
+                    Ok(UiCommand::SetTitle(title)) => frontend.set_title(title),
+                    Ok(UiCommand::SetBeeping(beeping)) => frontend.set_beeping(beeping),
+                    Ok(UiCommand::SetAudioPattern(pattern)) => frontend.set_audio_pattern(pattern),
+                    Ok(UiCommand::Close) | Err(TryRecvError::Disconnected) => {
+                        closed = true;
+                        break;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                }
+            }
+
+            if closed {
+                break;
+            }
+
+            thread::sleep(refresh_period);
+        }
+
+        frontend.close();
+    }
+}
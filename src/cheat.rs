@@ -0,0 +1,116 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Context};
+
+use crate::chip8::Chip8;
+
+/// A byte of memory held at a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cheat {
+    pub addr: u16,
+    pub value: u8,
+    /// Written when the cheats are enabled or the ROM reset, instead of on
+    /// every frame
+    pub once: bool,
+}
+
+/// Per-ROM memory patches, for infinite lives or trying out ROM internals
+///
+/// Loaded from text, conventionally `<rom>.cheats` next to the ROM, with one
+/// hex `ADDR=VALUE` per line, followed by `once` for a patch written only when
+/// the cheats are turned on:
+///
+/// ```text
+/// # infinite lives
+/// 2F0=03
+/// # start on level 5
+/// 2F1=05 once
+/// ```
+///
+/// The other patches are written on every frame (timer tick) while enabled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cheats {
+    pub cheats: Vec<Cheat>,
+    enabled: bool,
+    /// `once` patches wait to be written
+    armed: bool,
+}
+
+impl Cheats {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read cheats {}", path.display()))?;
+
+        Self::parse(&text).with_context(|| format!("invalid cheats in {}", path.display()))
+    }
+
+    /// Parse cheats, enabled
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut cheats = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (patch, once) = match line.strip_suffix("once") {
+                Some(patch) => (patch.trim(), true),
+                None => (line, false),
+            };
+            let (addr, value) = patch
+                .split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected ADDR=VALUE, got '{}'", i + 1, line))?;
+
+            let hex = |text: &str| text.trim().trim_start_matches("0x").to_string();
+            let addr = u16::from_str_radix(&hex(addr), 16)
+                .map_err(|_| anyhow!("line {}: '{}' is not a hex address", i + 1, addr.trim()))?;
+            let value = u8::from_str_radix(&hex(value), 16)
+                .map_err(|_| anyhow!("line {}: '{}' is not a hex byte", i + 1, value.trim()))?;
+
+            cheats.push(Cheat { addr, value, once });
+        }
+
+        Ok(Cheats {
+            cheats,
+            enabled: true,
+            armed: true,
+        })
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turn the cheats on or off; turning them on writes the `once` patches
+    /// again
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.armed |= enabled && !self.enabled;
+        self.enabled = enabled;
+    }
+
+    /// Write the `once` patches again on the next frame, e.g. after a reset
+    pub fn rearm(&mut self) {
+        self.armed = true;
+    }
+
+    /// Write the patches due this frame to `cpu`'s memory
+    ///
+    /// Patches past the end of memory are skipped.
+    pub fn apply(&mut self, cpu: &mut Chip8) {
+        if !self.enabled {
+            return;
+        }
+
+        for cheat in &self.cheats {
+            if !cheat.once || self.armed {
+                if let Some(byte) = cpu.ram.get_mut(usize::from(cheat.addr)) {
+                    *byte = cheat.value;
+                }
+            }
+        }
+
+        self.armed = false;
+    }
+}
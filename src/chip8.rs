@@ -1,19 +1,65 @@
-use anyhow::anyhow;
-use rand::{rngs::StdRng, RngCore, SeedableRng};
-use thiserror::Error;
+use alloc::{collections::BTreeSet, format, string::String, vec, vec::Vec};
+use core::{convert::TryFrom, fmt, ops::Range};
+
+use rand_chacha::ChaCha12Rng;
+#[cfg(feature = "std")]
+use serde::Deserialize;
+
+pub use crate::instruction::{Extension, ExtensionOp};
+use crate::{
+    instruction::{decode, Instruction},
+    rng::Chip8Rng,
+    watch::{Access, WatchHit, Watchpoints},
+};
 
 const DISPLAY_WIDTH: usize = 64;
 const DISPLAY_HEIGHT: usize = 32;
-const DISPLAY_BUFFER_LENGTH: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT;
+const HIRES_DISPLAY_HEIGHT: usize = 64;
+const SCHIP_DISPLAY_WIDTH: usize = 128;
+const SCHIP_DISPLAY_HEIGHT: usize = 64;
+
+/// Pixels in the largest display, which the display buffer has room for from
+/// the start so changing modes never moves it
+const MAX_DISPLAY_SIZE: usize = SCHIP_DISPLAY_WIDTH * SCHIP_DISPLAY_HEIGHT;
 
-// Arbitrary bytes
-const RNG_SEED: [u8; 32] = [
+/// Seed the RNG is reset with; arbitrary bytes
+pub const RNG_SEED: [u8; 32] = [
     0xBA, 0xD5, 0xEE, 0xD5, 0xBA, 0xD5, 0xEE, 0xD5, 0xBA, 0xD5, 0xEE, 0xD5, 0xBA, 0xD5, 0xEE, 0xD5,
     0xBA, 0xD5, 0xEE, 0xD5, 0xBA, 0xD5, 0xEE, 0xD5, 0xBA, 0xD5, 0xEE, 0xD5, 0xBA, 0xD5, 0xEE, 0xD5,
 ];
 
 const ADDR_PROGRAM: u16 = 0x200;
 
+/// RAM of the original interpreter
+pub const RAM_SIZE: usize = 0x1000;
+
+/// RAM of XO-CHIP, the whole 16-bit address space
+pub const XO_RAM_SIZE: usize = 0x10000;
+
+/// SCHIP RPL user flags, the HP-48 registers programs keep data in between
+/// runs
+pub const RPL_FLAGS: usize = 8;
+
+/// Display bit planes, as in XO-CHIP
+pub const PLANES: usize = 2;
+
+/// Brightness of each plane mask from the off color (0) to the on color
+/// (255): the first plane fully on, and the second and both planes between
+const PLANE_LEVELS: [u32; 1 << PLANES] = [0, 255, 85, 170];
+
+/// First instruction of a hi-res ROM, a jump over the interpreter patch that
+/// the original two-page display needed
+const HIRES_ENTRY: u16 = 0x1260;
+
+/// Where hi-res programs start, after the patch
+const ADDR_HIRES_PROGRAM: u16 = 0x2C0;
+
+/// Clears the display in hi-res mode, in place of CLS
+const HIRES_CLS: u16 = 0x0230;
+
+/// Ends the program (SCHIP)
+const EXIT: u16 = 0x00FD;
+
 const ADDR_CHARACTER: u16 = 0;
 const SIZE_CHARACTER: u16 = 5;
 const CHARACTER_ROM: [u8; 80] = [
@@ -35,22 +81,192 @@ const CHARACTER_ROM: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // 'F'
 ];
 
-#[derive(Debug, Error)]
+/// SCHIP's 8x10 digits, after the small ones, with XO-CHIP's A to F
+const ADDR_BIG_CHARACTER: u16 = ADDR_CHARACTER + CHARACTER_ROM.len() as u16;
+const SIZE_BIG_CHARACTER: u16 = 10;
+const BIG_CHARACTER_ROM: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // '0'
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // '1'
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // '2'
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // '3'
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // '4'
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // '5'
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // '6'
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // '7'
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // '8'
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // '9'
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // 'A'
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // 'B'
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // 'C'
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // 'D'
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 'E'
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // 'F'
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Chip8Panic {
-    #[error("attempted to return while stack pointer is 0")]
     StackUnderflow,
-
-    #[error("attempted to push to full return stack")]
     StackOverflow,
-
-    #[error("unknown opcode")]
     UnknownOpCode,
+    /// The opcode belongs to an instruction set extension that is not enabled
+    ExtensionOpCode(ExtensionOp),
+}
+
+impl fmt::Display for Chip8Panic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Panic::StackUnderflow => {
+                f.write_str("attempted to return while stack pointer is 0")
+            }
+            Chip8Panic::StackOverflow => f.write_str("attempted to push to full return stack"),
+            Chip8Panic::UnknownOpCode => f.write_str("unknown opcode"),
+            Chip8Panic::ExtensionOpCode(op) => write!(
+                f,
+                "{} needs {}; run with {} to enable it",
+                op.mnemonic,
+                op.extension,
+                op.extension.flag()
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Chip8Panic {}
+
+/// Data written to memory does not fit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfMemory {
+    pub addr: u16,
+    pub len: usize,
+}
+
+impl fmt::Display for OutOfMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes at {:04X} do not fit in memory",
+            self.len, self.addr
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for OutOfMemory {}
+
+/// Behavior differences between CHIP-8 implementations
+///
+/// The default matches Cowgod's reference, except that SYS stops the program
+/// rather than being ignored, so one that runs into empty memory (0000) is
+/// caught.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "std",
+    derive(Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct Quirks {
+    /// SHR/SHL shift Vy into Vx instead of shifting Vx in place
+    pub shift_vy: bool,
+
+    /// LD [I], Vx and LD Vx, [I] leave I incremented past the last register
+    pub load_store_increment_i: bool,
+
+    /// JP V0, addr uses Vx (x = highest nibble of addr) instead of V0
+    pub jump_vx: bool,
+
+    /// OR/AND/XOR reset VF to 0
+    pub logic_vf_reset: bool,
+
+    /// What SYS addr (0nnn) does
+    pub sys: SysPolicy,
+}
+
+/// Handling of SYS addr (0nnn), which called the host computer's machine code
+/// in the original interpreter
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Deserialize), serde(rename_all = "lowercase"))]
+pub enum SysPolicy {
+    /// Stop with `Chip8Panic::UnknownOpCode`
+    #[default]
+    Trap,
+    /// Continue with the next instruction, as most modern interpreters do
+    Ignore,
+    /// Continue, after running `Chip8::sys_handler` if one is set
+    Callback,
+}
+
+/// Pitch an XO-CHIP audio pattern plays at until PITCH sets another, for a
+/// rate of 4000 bits per second
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// XO-CHIP sound: a 1-bit waveform played in a loop while ST is non-zero, in
+/// place of the buzzer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioPattern {
+    /// 128 samples, most significant bit of the first byte first
+    pub bits: [u8; 16],
+    /// Playback rate as `4000 * 2^((pitch - 64) / 48)` samples per second
+    pub pitch: u8,
 }
 
+#[cfg(feature = "std")]
+impl AudioPattern {
+    /// Samples played per second
+    pub fn rate(&self) -> f32 {
+        4000. * 2f32.powf((f32::from(self.pitch) - 64.) / 48.)
+    }
+}
+
+/// Result of a successful `Chip8::step`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Executed,
+
+    /// PC reached a breakpoint; the instruction there runs on the next step
+    Breakpoint(u16),
+
+    /// The instruction just executed accessed watched memory
+    Watchpoint(WatchHit),
+
+    /// The program ended with EXIT; PC stays on it, so every later step ends
+    /// the same way until a reset
+    Exited,
+}
+
+/// Where execution continues after an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    /// The following instruction
+    Next,
+    /// The one after it
+    Skip,
+    Jump(u16),
+    /// The same instruction again
+    Stay,
+}
+
+impl Flow {
+    fn skip_if(condition: bool) -> Self {
+        if condition {
+            Flow::Skip
+        } else {
+            Flow::Next
+        }
+    }
+}
+
+/// CHIP-8 interpreter
+///
+/// Generic over the RND source; the default `ChaCha12Rng` is the same
+/// algorithm as `StdRng`, but its seed and stream position can be captured.
 #[derive(Debug, Clone)]
-pub struct Chip8 {
+pub struct Chip8<R: Chip8Rng = ChaCha12Rng> {
     /// Deterministic Random Number Generator
-    pub rng: StdRng,
+    pub rng: R,
+
+    /// Seed the RNG was last reset with
+    pub rng_seed: [u8; 32],
 
     /// General Purpose Registers
     ///
@@ -90,26 +306,96 @@ pub struct Chip8 {
     pub stack: [u16; 0x10],
 
     /// RAM
-    pub ram: [u8; 0x1000],
+    ///
+    /// `RAM_SIZE` bytes, or `XO_RAM_SIZE` for XO-CHIP programs.
+    pub ram: Vec<u8>,
 
     /// Display 1-bit Buffer
-    pub display: [bool; DISPLAY_BUFFER_LENGTH],
+    ///
+    /// `display_width() * display_height()` pixels, row by row. Each is a
+    /// mask of the bit planes lit there: bit 0 for the first plane, and so
+    /// on up to `PLANES`.
+    pub display: Vec<u8>,
 
     /// Input keys
     ///
     /// Hex input keys '0' to 'F'
     pub keys: [bool; 0x10],
 
+    /// RPL user flags
+    ///
+    /// Kept by `reset`, like the calculator's, and left out of save states.
+    pub rpl: [u8; RPL_FLAGS],
+
     /// Display dirty flag
     ///
     /// Set when the display buffer has changed.
     pub display_dirty: bool,
+
+    /// Rows of the display changed since `take_changed_rows` or
+    /// `take_dirty_rows`, bit n for row n, so frontends can copy only those
+    changed_rows: u64,
+
+    /// Compatibility quirks
+    pub quirks: Quirks,
+
+    /// 64x64 hi-res mode, for two-page display ROMs
+    pub(crate) hires: bool,
+
+    /// SCHIP instructions enabled; XO-CHIP RAM enables them too
+    schip: bool,
+
+    /// SCHIP's 128x64 high resolution, selected by HIGH and left by LOW
+    pub(crate) schip_hires: bool,
+
+    /// Mask of the bit planes CLS and DRW act on
+    pub(crate) plane: u8,
+
+    /// Waveform loaded by AUDIO, if any
+    pub(crate) audio_bits: Option<[u8; 16]>,
+    pub(crate) pitch: u8,
+
+    /// Breakpoint addresses
+    ///
+    /// Not part of the machine state: kept by `restore`.
+    pub breakpoints: BTreeSet<u16>,
+
+    /// Memory watchpoints, kept by `restore` like breakpoints
+    ///
+    /// Execute watchpoints behave like breakpoints over a range.
+    pub watchpoints: Watchpoints,
+
+    /// Breakpoint already reported at this address, to be stepped over
+    break_resume: Option<u16>,
+
+    /// First watchpoint hit by the current instruction
+    watch_hit: Option<WatchHit>,
+
+    /// Run with the address of each SYS under `SysPolicy::Callback`, with PC
+    /// already on the next instruction
+    ///
+    /// Not part of the machine state, like breakpoints.
+    pub sys_handler: Option<fn(&mut Chip8<R>, u16)>,
 }
 
 impl Chip8 {
     pub fn new() -> Self {
+        Self::with_rng()
+    }
+}
+
+impl<R: Chip8Rng> Default for Chip8<R> {
+    fn default() -> Self {
+        Self::with_rng()
+    }
+}
+
+impl<R: Chip8Rng> Chip8<R> {
+    /// Create an interpreter using `R` as the RND source
+    pub fn with_rng() -> Self {
         let mut chip8 = Chip8 {
-            rng: StdRng::from_entropy(),
+            rng: R::from_seed(RNG_SEED),
+            rng_seed: RNG_SEED,
             v: [0; 0x10],
             i: 0,
             dt: 0,
@@ -117,10 +403,24 @@ impl Chip8 {
             pc: 0,
             sp: 0,
             stack: [0; 0x10],
-            ram: [0; 0x1000],
-            display: [false; DISPLAY_BUFFER_LENGTH],
+            ram: vec![0; RAM_SIZE],
+            display: display_buffer(DISPLAY_WIDTH * DISPLAY_HEIGHT),
             keys: [false; 0x10],
+            rpl: [0; RPL_FLAGS],
             display_dirty: false,
+            changed_rows: 0,
+            quirks: Quirks::default(),
+            hires: false,
+            schip: false,
+            schip_hires: false,
+            plane: 1,
+            audio_bits: None,
+            pitch: DEFAULT_PITCH,
+            breakpoints: BTreeSet::new(),
+            watchpoints: Watchpoints::default(),
+            break_resume: None,
+            watch_hit: None,
+            sys_handler: None,
         };
 
         chip8.reset();
@@ -148,32 +448,194 @@ impl Chip8 {
     }
 
     pub fn display_width(&self) -> usize {
-        DISPLAY_WIDTH
+        if self.schip_hires {
+            SCHIP_DISPLAY_WIDTH
+        } else {
+            DISPLAY_WIDTH
+        }
     }
 
     pub fn display_height(&self) -> usize {
-        DISPLAY_HEIGHT
+        if self.schip_hires {
+            SCHIP_DISPLAY_HEIGHT
+        } else if self.hires {
+            HIRES_DISPLAY_HEIGHT
+        } else {
+            DISPLAY_HEIGHT
+        }
+    }
+
+    pub fn hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Switch between the 64x32 display and the 64x64 hi-res one, clearing it
+    ///
+    /// `load_rom` switches to hi-res by itself for ROMs that start the hi-res
+    /// way, so this is only needed to force a mode.
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.resize_display();
+        self.redraw();
+    }
+
+    /// Whether SCHIP instructions are enabled, by `set_schip` or along with
+    /// the XO-CHIP ones
+    pub fn schip(&self) -> bool {
+        self.schip || self.xo_chip()
+    }
+
+    /// Enable or disable the SCHIP instructions
+    ///
+    /// XO-CHIP RAM enables them regardless, XO-CHIP being a superset of SCHIP.
+    pub fn set_schip(&mut self, schip: bool) {
+        self.schip = schip;
+    }
+
+    /// Whether the display is in SCHIP's 128x64 high resolution
+    pub fn schip_hires(&self) -> bool {
+        self.schip_hires
+    }
+
+    /// Whether XO-CHIP instructions are enabled, which they are with XO-CHIP
+    /// RAM
+    pub fn xo_chip(&self) -> bool {
+        self.ram.len() == XO_RAM_SIZE
+    }
+
+    pub fn ram_size(&self) -> usize {
+        self.ram.len()
+    }
+
+    /// Resize RAM to `size` bytes, `RAM_SIZE` or `XO_RAM_SIZE`, keeping what
+    /// fits
+    ///
+    /// XO-CHIP RAM also enables the XO-CHIP instructions. `load_rom` switches
+    /// to it by itself for ROMs too large for the original, so this is only
+    /// needed to force a size.
+    pub fn set_ram_size(&mut self, size: usize) {
+        self.ram.resize(size, 0);
+        self.pc = (usize::from(self.pc) % size) as u16;
+    }
+
+    /// Mask of the bit planes that drawing acts on, the first one unless an
+    /// XO-CHIP program selects others
+    pub fn plane(&self) -> u8 {
+        self.plane
+    }
+
+    /// Sound to play while ST is non-zero, if the program loaded one; the
+    /// buzzer otherwise
+    pub fn audio_pattern(&self) -> Option<AudioPattern> {
+        self.audio_bits.map(|bits| AudioPattern {
+            bits,
+            pitch: self.pitch,
+        })
+    }
+
+    /// Mark the whole display as changed
+    pub fn redraw(&mut self) {
+        self.display_dirty = true;
+        self.changed_rows = u64::MAX >> (64 - self.display_height());
+    }
+
+    /// Rows changed since the last call, as a range that may include some
+    /// unchanged rows; empty if none were
+    pub fn take_dirty_rows(&mut self) -> Range<usize> {
+        match self.take_changed_rows() {
+            0 => 0..0,
+            rows => rows.trailing_zeros() as usize..64 - rows.leading_zeros() as usize,
+        }
+    }
+
+    /// Rows changed since the last call, bit n set for row n, so a frontend
+    /// can redraw exactly those
+    ///
+    /// ```
+    /// use chip8::Chip8;
+    ///
+    /// // LD F, V0; DRW V0, V0, 5: the digit 0 at the top left
+    /// let mut cpu = Chip8::new();
+    /// cpu.load_rom(&[0xF0, 0x29, 0xD0, 0x05])?;
+    /// cpu.take_changed_rows();
+    ///
+    /// cpu.step()?;
+    /// cpu.step()?;
+    /// assert_eq!(cpu.take_changed_rows(), 0b11111);
+    /// assert_eq!(cpu.take_changed_rows(), 0);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// Shares its record with `take_dirty_rows`: either one clears it.
+    pub fn take_changed_rows(&mut self) -> u64 {
+        core::mem::take(&mut self.changed_rows)
+    }
+
+    /// The display as ASCII art, one line per row: `#` for lit pixels and `.`
+    /// for unlit ones
+    pub fn display_to_ascii(&self) -> String {
+        self.display
+            .chunks(self.display_width())
+            .map(|row| {
+                row.iter()
+                    .map(|px| if *px != 0 { '#' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Stable 64-bit hash of the display, to check a run's output by a short
+    /// value
+    ///
+    /// FNV-1a over the width, height, and the plane mask of each pixel, so the value is
+    /// the same on every platform and build. Printed as 16 hex digits by
+    /// `chip8 test`.
+    pub fn display_hash(&self) -> u64 {
+        let size = [self.display_width() as u8, self.display_height() as u8];
+        let pixels = self.display.iter().copied();
+
+        size.iter()
+            .copied()
+            .chain(pixels)
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    /// Restart the RNG from `seed`, until the next `reset` restores `RNG_SEED`
+    pub fn seed_rng(&mut self, seed: [u8; 32]) {
+        self.rng_seed = seed;
+        self.rng = R::from_seed(seed);
     }
 
     pub fn reset(&mut self) {
-        self.rng = SeedableRng::from_seed(RNG_SEED);
+        self.rng_seed = RNG_SEED;
+        self.rng = R::from_seed(self.rng_seed);
 
         self.i = 0;
         self.dt = 0;
         self.st = 0;
         self.pc = ADDR_PROGRAM;
         self.sp = 0;
+        self.break_resume = None;
 
         fill_array(&mut self.v, 0);
         fill_array(&mut self.stack, 0);
-        fill_array(&mut self.display, false);
         fill_array(&mut self.keys, false);
+        self.schip_hires = false;
+        self.resize_display();
+        self.plane = 1;
+        self.audio_bits = None;
+        self.pitch = DEFAULT_PITCH;
 
         fill_array(&mut self.ram, 0);
         self.mem_write_slice(ADDR_CHARACTER, &CHARACTER_ROM)
             .unwrap();
+        self.mem_write_slice(ADDR_BIG_CHARACTER, &BIG_CHARACTER_ROM)
+            .unwrap();
 
-        self.display_dirty = true;
+        self.redraw();
     }
 
     pub fn set_key(&mut self, key: u8) {
@@ -181,440 +643,621 @@ impl Chip8 {
         self.keys[key] = true;
     }
 
-    pub fn step(&mut self) -> Result<(), Chip8Panic> {
+    pub fn step(&mut self) -> Result<StepOutcome, Chip8Panic> {
+        if let Some(addr) = self.check_breakpoint() {
+            return Ok(StepOutcome::Breakpoint(addr));
+        }
+
+        self.break_resume = None;
+        self.watch_hit = None;
+
         let opcode = self.mem_read_opcode(self.pc);
 
         self.execute_opcode(opcode)?;
 
         fill_array(&mut self.keys, false);
 
-        Ok(())
-    }
+        if opcode == EXIT {
+            return Ok(StepOutcome::Exited);
+        }
 
-    pub fn timer_tick(&mut self) {
-        if self.dt > 0 {
-            self.dt -= 1;
+        match self.watch_hit.take() {
+            Some(hit) => Ok(StepOutcome::Watchpoint(hit)),
+            None => Ok(StepOutcome::Executed),
         }
+    }
+
+    /// Return addresses of the subroutine calls in progress, oldest first
+    ///
+    /// Each is the address of the CALL, which RET returns past.
+    pub fn call_stack(&self) -> &[u16] {
+        &self.stack[1..=usize::from(self.sp).min(self.stack.len() - 1)]
+    }
 
-        if self.st > 0 {
-            self.st -= 1;
+    /// Return PC if it is at a breakpoint that has not been reported yet
+    ///
+    /// Each hit is reported once; the following `step` executes the instruction.
+    pub fn check_breakpoint(&mut self) -> Option<u16> {
+        let hit =
+            self.breakpoints.contains(&self.pc) || self.watchpoints.check(self.pc, Access::Execute);
+
+        if hit && self.break_resume != Some(self.pc) {
+            self.break_resume = Some(self.pc);
+            Some(self.pc)
+        } else {
+            None
         }
     }
 
+    /// Replace the machine state with `snapshot`, keeping breakpoints and watchpoints
+    pub fn restore(&mut self, snapshot: &Self) {
+        let breakpoints = core::mem::take(&mut self.breakpoints);
+        let watchpoints = core::mem::take(&mut self.watchpoints);
+
+        *self = snapshot.clone();
+        self.breakpoints = breakpoints;
+        self.watchpoints = watchpoints;
+        self.break_resume = None;
+        self.redraw();
+    }
+
+    pub fn timer_tick(&mut self) {
+        self.timer_ticks(1);
+    }
+
+    /// Apply `ticks` timer ticks at once, stopping both timers at 0
+    ///
+    /// ```
+    /// use chip8::Chip8;
+    ///
+    /// let mut cpu = Chip8::new();
+    /// cpu.dt = 200;
+    /// cpu.st = 3;
+    ///
+    /// cpu.timer_ticks(2);
+    /// assert_eq!((cpu.dt, cpu.st), (198, 1));
+    ///
+    /// // More ticks than fit in a timer still stop it, rather than wrapping
+    /// cpu.timer_ticks(0x1_0000_0001);
+    /// assert_eq!((cpu.dt, cpu.st), (0, 0));
+    /// ```
+    pub fn timer_ticks(&mut self, ticks: u64) {
+        let ticks = u8::try_from(ticks).unwrap_or(u8::MAX);
+
+        self.dt = self.dt.saturating_sub(ticks);
+        self.st = self.st.saturating_sub(ticks);
+    }
+
     fn v<T: Into<usize>>(&mut self, x: T) -> &mut u8 {
         &mut self.v[x.into()]
     }
 
-    fn execute_opcode(&mut self, opcode: u16) -> Result<(), Chip8Panic> {
-        let nnn = opcode & 0x0fff;
-        let kk = (opcode & 0x00ff) as u8;
+    pub(crate) fn execute_opcode(&mut self, opcode: u16) -> Result<(), Chip8Panic> {
+        let result = self.execute(opcode, decode(opcode));
 
-        match split_opcode(opcode) {
-            (0x0, 0x0, 0xE, 0x0) => {
-                // CLS: Clear the display
+        match result {
+            Ok(Flow::Next) => self.pc = self.pc.wrapping_add(2),
+            Ok(Flow::Skip) => self.skip(),
+            Ok(Flow::Jump(addr)) => self.pc = addr,
+            // Errors leave PC on the faulting instruction
+            Ok(Flow::Stay) | Err(_) => {}
+        }
 
-                fill_array(&mut self.display, false);
-                self.display_dirty = true;
+        // Jumps and skips past the end of RAM wrap around, as with a 12-bit PC
+        // (or 16-bit with XO-CHIP RAM)
+        self.pc = (usize::from(self.pc) % self.ram.len()) as u16;
 
-                self.pc += 2;
+        result.map(|_| ())
+    }
 
-                Ok(())
-            }
-            (0x0, 0x0, 0xE, 0xE) => {
-                // RET: Return from a subroutine
+    /// Run `instruction`, decoded from `opcode`, and say where execution
+    /// continues; PC is still the instruction's address
+    fn execute(&mut self, opcode: u16, instruction: Instruction) -> Result<Flow, Chip8Panic> {
+        let flow = match instruction {
+            Instruction::Cls => {
+                // Clear the selected planes of the display
+                let plane = self.plane;
+                for px in self.display.iter_mut() {
+                    *px &= !plane;
+                }
+                self.redraw();
 
+                Flow::Next
+            }
+            Instruction::HiresCls if self.hires => return self.execute(opcode, Instruction::Cls),
+            // Outside of hi-res mode, 0230 is an ordinary SYS
+            Instruction::HiresCls => return self.execute(opcode, Instruction::Sys(HIRES_CLS)),
+            Instruction::Ret => {
                 if self.sp == 0 {
-                    Err(Chip8Panic::StackUnderflow)
-                } else {
-                    self.pc = self.stack[usize::from(self.sp)];
-                    self.sp -= 1;
-                    self.pc += 2;
-
-                    Ok(())
+                    return Err(Chip8Panic::StackUnderflow);
                 }
-            }
-            (0x1, _x, _y, _z) => {
-                // JP addr: Jump to address
 
-                self.pc = nnn;
+                // Return past the CALL
+                let call = self.stack[usize::from(self.sp)];
+                self.sp -= 1;
 
-                Ok(())
+                Flow::Jump(call.wrapping_add(2))
             }
-            (0x2, _x, _y, _z) => {
-                // CALL addr: Call subroutine at address
-
-                if self.sp >= 0xff {
-                    Err(Chip8Panic::StackOverflow)
-                } else {
-                    self.sp += 1;
-                    self.stack[usize::from(self.sp)] = self.pc;
-                    self.pc = nnn;
-
-                    Ok(())
-                }
+            Instruction::Scd(n) if self.schip() => {
+                self.scroll(0, isize::from(n));
+                Flow::Next
+            }
+            Instruction::Scu(n) if self.xo_chip() => {
+                self.scroll(0, -isize::from(n));
+                Flow::Next
             }
-            (0x3, x, _y, _z) => {
-                // SE Vx, kk: Skip next instruction if Vx = kk
+            // PC stays on EXIT
+            Instruction::Exit if self.schip() => Flow::Stay,
+            Instruction::Low if self.schip() => {
+                self.schip_hires = false;
+                self.resize_display();
+                self.redraw();
+                Flow::Next
+            }
+            Instruction::High if self.schip() => {
+                self.schip_hires = true;
+                self.resize_display();
+                self.redraw();
+                Flow::Next
+            }
+            Instruction::Scr if self.schip() => {
+                self.scroll(4, 0);
+                Flow::Next
+            }
+            Instruction::Scl if self.schip() => {
+                self.scroll(-4, 0);
+                Flow::Next
+            }
+            Instruction::Sys(nnn) if self.quirks.sys != SysPolicy::Trap => {
+                // Jump to a machine code routine at nnn, which only the
+                // original computer could run. The handler sees PC past it.
+                if self.quirks.sys == SysPolicy::Callback {
+                    self.pc = self.pc.wrapping_add(2);
 
-                if *self.v(x) == kk {
-                    self.pc += 4;
-                } else {
-                    self.pc += 2;
+                    if let Some(handler) = self.sys_handler {
+                        handler(self, nnn);
+                    }
+
+                    return Ok(Flow::Stay);
                 }
 
-                Ok(())
+                Flow::Next
             }
-            (0x4, x, _y, _z) => {
-                // SNE Vx, kk: Skip next instruction if Vx != kk
-
-                if *self.v(x) != kk {
-                    self.pc += 4;
+            Instruction::Jp(nnn) => {
+                if self.hires && opcode == HIRES_ENTRY && self.pc == ADDR_PROGRAM {
+                    // Skip the patch, which is machine code for the original interpreter
+                    Flow::Jump(ADDR_HIRES_PROGRAM)
                 } else {
-                    self.pc += 2;
+                    Flow::Jump(nnn)
                 }
-
-                Ok(())
             }
-            (0x5, x, y, 0x0) => {
-                // SE Vx, Vy: Skip next instruction if Vx == Vy
-                if *self.v(x) == *self.v(y) {
-                    self.pc += 4;
-                } else {
-                    self.pc += 2;
+            Instruction::Call(nnn) => {
+                if usize::from(self.sp) + 1 >= self.stack.len() {
+                    return Err(Chip8Panic::StackOverflow);
                 }
 
-                Ok(())
-            }
-            (0x6, x, _y, _z) => {
-                // LD Vx, kk: Vx = kk
+                self.sp += 1;
+                self.stack[usize::from(self.sp)] = self.pc;
 
+                Flow::Jump(nnn)
+            }
+            Instruction::SeByte(x, kk) => Flow::skip_if(*self.v(x) == kk),
+            Instruction::SneByte(x, kk) => Flow::skip_if(*self.v(x) != kk),
+            Instruction::SeReg(x, y) => Flow::skip_if(*self.v(x) == *self.v(y)),
+            Instruction::LdByte(x, kk) => {
                 *self.v(x) = kk;
-                self.pc += 2;
-
-                Ok(())
+                Flow::Next
             }
-            (0x7, x, _y, _z) => {
-                // ADD Vx, kk: Vx = Vx + kk
-
+            Instruction::AddByte(x, kk) => {
                 *self.v(x) = (*self.v(x)).wrapping_add(kk);
-                self.pc += 2;
-
-                Ok(())
+                Flow::Next
             }
-            (0x8, x, y, 0x0) => {
-                // LD Vx, Vy: Set Vx = Vy
+            Instruction::LdReg(x, y) => {
                 *self.v(x) = *self.v(y);
-                self.pc += 2;
-                Ok(())
+                Flow::Next
             }
-            (0x8, x, y, 0x1) => {
-                // OR Vx, Vy: Set Vx = Vx OR Vy
+            Instruction::Or(x, y) => {
                 *self.v(x) = *self.v(x) | *self.v(y);
-                self.pc += 2;
-                Ok(())
+                self.logic_vf_reset();
+                Flow::Next
             }
-            (0x8, x, y, 0x2) => {
-                // AND Vx, Vy: Set Vx = Vx AND Vy
+            Instruction::And(x, y) => {
                 *self.v(x) = *self.v(x) & *self.v(y);
-                self.pc += 2;
-                Ok(())
+                self.logic_vf_reset();
+                Flow::Next
             }
-            (0x8, x, y, 0x3) => {
-                // XOR Vx, Vy: Set Vx = Vx XOR Vy
+            Instruction::Xor(x, y) => {
                 *self.v(x) = *self.v(x) ^ *self.v(y);
-                self.pc += 2;
-                Ok(())
+                self.logic_vf_reset();
+                Flow::Next
             }
-            (0x8, x, y, 0x4) => {
-                // ADD Vx, Vy: Set Vx = Vx + Vy, set VF = carry
+            Instruction::AddReg(x, y) => {
+                // VF = carry
                 let (sum, ovf) = (*self.v(x)).overflowing_add(*self.v(y));
                 self.v[0xf] = if ovf { 1 } else { 0 };
                 *self.v(x) = sum;
 
-                self.pc += 2;
-                Ok(())
+                Flow::Next
             }
-            (0x8, x, y, 0x5) => {
-                // SUB Vx, Vy: Set Vx = Vx - Vy, set VF = NOT borrow
+            Instruction::Sub(x, y) => {
+                // VF = NOT borrow
 
                 // Note: VF is assigned first, so it could change Vx - Vy if x or y is F
                 // If this doesn't matter, then we could use overflowing_sub() instead
                 self.v[0xf] = if *self.v(x) > *self.v(y) { 1 } else { 0 };
                 *self.v(x) = (*self.v(x)).wrapping_sub(*self.v(y));
 
-                self.pc += 2;
-                Ok(())
+                Flow::Next
             }
-            (0x8, x, _y, 0x6) => {
-                // SHR Vx|Vy: Set Vx = Vx >> 1, set VF = shifted-out bit
+            Instruction::Shr(x, y) => {
+                // VF = shifted-out bit
+
+                // Compatibility note: Some machines use Vx = Vy >> 1 (shift_vy quirk)
 
-                // Compatibility note: Some machines may use Vx = Vy >> 1
+                let src = if self.quirks.shift_vy { y } else { x };
+                let val = *self.v(src);
 
-                self.v[0xf] = *self.v(x) & 1;
-                *self.v(x) = *self.v(x) >> 1;
+                self.v[0xf] = val & 1;
+                *self.v(x) = val >> 1;
 
-                self.pc += 2;
-                Ok(())
+                Flow::Next
             }
-            (0x8, x, y, 0x7) => {
-                // SUBN Vx, Vy: Set Vx = Vy - Vx, set VF = NOT borrow
+            Instruction::Subn(x, y) => {
+                // Vx = Vy - Vx, VF = NOT borrow
 
                 // Note: VF is assigned first, so it could change Vx - Vy if x or y is F
                 // If this doesn't matter, then we could use overflowing_sub() instead
                 self.v[0xf] = if *self.v(y) > *self.v(x) { 1 } else { 0 };
                 *self.v(x) = (*self.v(y)).wrapping_sub(*self.v(x));
 
-                self.pc += 2;
-                Ok(())
+                Flow::Next
             }
-            (0x8, x, _y, 0xE) => {
-                // SHL Vx|Vy: Set Vx = Vx << 1, set VF = shifted-out bit
+            Instruction::Shl(x, y) => {
+                // VF = shifted-out bit
 
-                // Compatibility note: Some machines may use Vx = Vy << 1
+                // Compatibility note: Some machines use Vx = Vy << 1 (shift_vy quirk)
 
-                self.v[0xf] = if *self.v(x) & 0x80 == 0 { 0 } else { 1 };
-                *self.v(x) = *self.v(x) << 1;
+                let src = if self.quirks.shift_vy { y } else { x };
+                let val = *self.v(src);
 
-                self.pc += 2;
-                Ok(())
-            }
-            (0x9, x, y, 0x0) => {
-                // SNE Vx, Vy: Skip next instruction if Vx != Vy
+                self.v[0xf] = if val & 0x80 == 0 { 0 } else { 1 };
+                *self.v(x) = val << 1;
 
-                if *self.v(x) != *self.v(y) {
-                    self.pc += 4;
-                } else {
-                    self.pc += 2;
-                }
-
-                Ok(())
+                Flow::Next
             }
-            (0xA, _x, _y, _z) => {
-                // LD I, addr: Set I = nnn
-
+            Instruction::SneReg(x, y) => Flow::skip_if(*self.v(x) != *self.v(y)),
+            Instruction::LdI(nnn) => {
                 self.i = nnn;
-                self.pc += 2;
-
-                Ok(())
+                Flow::Next
             }
-            (0xB, _x, _y, _z) => {
-                // JP V0, addr: Jump to location nnn + V0
+            Instruction::JpV0(x, nnn) => {
+                // Compatibility note: Some machines use nnn + Vx (jump_vx quirk)
 
-                self.pc = u16::from(self.v[0]) + nnn;
+                let offset = if self.quirks.jump_vx {
+                    *self.v(x)
+                } else {
+                    self.v[0]
+                };
 
-                Ok(())
+                Flow::Jump(u16::from(offset) + nnn)
             }
-            (0xC, x, _y, _z) => {
-                // RND Vx, kk: Random byte AND kk
-
-                *self.v(x) = kk & ((self.rng.next_u32() & 0xff) as u8);
-                self.pc += 2;
-
-                Ok(())
+            Instruction::Rnd(x, kk) => {
+                *self.v(x) = kk & self.rng.next_byte();
+                Flow::Next
             }
-            (0xD, x, y, z) => {
-                // DRW Vx, Vy, nibble:
-                // Display n-byte sprite starting at memory location I at (Vx, Vy),
-                // set VF = collision.
+            Instruction::Drw(x, y, n) => {
+                // Display an n-byte sprite from I at (Vx, Vy), VF = collision.
+                //
+                // With several planes selected, each gets its own sprite, one
+                // after another in memory. With SCHIP, n = 0 draws a 16x16
+                // sprite, two bytes a row.
 
                 let vx = usize::from(*self.v(x));
                 let vy = usize::from(*self.v(y));
-                let i = usize::from(self.i);
+                let mut i = usize::from(self.i);
+                let (rows, row_bytes) = match n {
+                    0 if self.schip() => (16, 2),
+                    n => (usize::from(n), 1),
+                };
 
                 self.v[0xf] = 0;
 
-                for dy in 0..z {
-                    let dy = usize::from(dy);
+                for plane in (0..PLANES).map(|p| 1 << p) {
+                    if self.plane & plane == 0 {
+                        continue;
+                    }
 
-                    self.disp_toggle_sprite_row(vx, vy + dy, self.ram[i + dy]);
-                }
-
-                self.pc += 2;
-
-                Ok(())
-            }
-            (0xE, x, 0x9, 0xE) => {
-                // SKP Vx: Skip next instruction if key with value of Vx is pressed
-                let key_idx = usize::from(*self.v(x) & 0xf);
-                let key_pressed = self.keys[key_idx];
+                    for dy in 0..rows {
+                        for byte in 0..row_bytes {
+                            let row = self.mem_read(i + dy * row_bytes + byte);
+                            self.disp_toggle_sprite_row(vx + 8 * byte, vy + dy, row, plane);
+                        }
+                    }
 
-                if key_pressed {
-                    self.pc += 4;
-                } else {
-                    self.pc += 2;
+                    i += rows * row_bytes;
                 }
 
-                Ok(())
+                Flow::Next
+            }
+            Instruction::Skp(x) => Flow::skip_if(self.keys[usize::from(*self.v(x) & 0xf)]),
+            Instruction::Sknp(x) => Flow::skip_if(!self.keys[usize::from(*self.v(x) & 0xf)]),
+            Instruction::LdILong if self.xo_chip() => {
+                // The address is the next two bytes, which are skipped
+                self.i = self.mem_read_opcode(self.pc.wrapping_add(2));
+                Flow::Jump(self.pc.wrapping_add(4))
             }
-            (0xE, x, 0xA, 0x1) => {
-                // SKNP Vx: Skip next instruction if key with value of Vx is not pressed
-                let key_idx = usize::from(*self.v(x) & 0xf);
-                let key_pressed = self.keys[key_idx];
+            Instruction::Plane(n) if self.xo_chip() => {
+                self.plane = n & ((1 << PLANES) - 1);
+                Flow::Next
+            }
+            Instruction::Audio if self.xo_chip() => {
+                // Load the 16-byte audio pattern at I
 
-                if !key_pressed {
-                    self.pc += 4;
-                } else {
-                    self.pc += 2;
+                let i = usize::from(self.i);
+                let mut bits = [0; 16];
+                for (offset, byte) in bits.iter_mut().enumerate() {
+                    *byte = self.mem_read(i + offset);
                 }
 
-                Ok(())
+                self.audio_bits = Some(bits);
+                Flow::Next
             }
-            (0xF, x, 0x0, 0x7) => {
-                // LD Vx, DT: set Vx = DT
-
+            Instruction::LdVxDt(x) => {
                 *self.v(x) = self.dt;
-
-                self.pc += 2;
-
-                Ok(())
+                Flow::Next
             }
-            (0xF, x, 0x0, 0xA) => {
-                // LD Vx, K: Wait for a key press, store value of key in Vx
+            Instruction::LdVxK(x) => {
+                // Wait for a key press, staying on the instruction until then
 
-                let key_pressed = self
-                    .keys
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, is_pressed)| **is_pressed)
-                    .map(|(i, _)| i)
-                    .next();
+                let key_pressed = self.keys.iter().position(|is_pressed| *is_pressed);
 
-                if let Some(key_pressed) = key_pressed {
-                    *self.v(x) = key_pressed as u8;
-                    self.pc += 2;
+                match key_pressed {
+                    Some(key) => {
+                        *self.v(x) = key as u8;
+                        Flow::Next
+                    }
+                    None => Flow::Stay,
                 }
-
-                Ok(())
             }
-            (0xF, x, 0x1, 0x5) => {
-                // LD DT, Vx: Set DT = Vx
-
+            Instruction::LdDtVx(x) => {
                 self.dt = *self.v(x);
-
-                self.pc += 2;
-
-                Ok(())
+                Flow::Next
             }
-            (0xF, x, 0x1, 0x8) => {
-                // LD ST, Vx: Set ST = Vx
-
+            Instruction::LdStVx(x) => {
                 self.st = *self.v(x);
-
-                self.pc += 2;
-
-                Ok(())
+                Flow::Next
             }
-            (0xF, x, 0x1, 0xE) => {
-                // ADD I, Vx: Set I = I + Vx
-
-                self.i += u16::from(*self.v(x));
+            Instruction::AddI(x) => {
+                self.i = self.i.wrapping_add(u16::from(*self.v(x)));
+                Flow::Next
+            }
+            Instruction::LdF(x) => {
+                // I = location of the sprite for digit Vx
 
-                self.pc += 2;
+                let char = u16::from(*self.v(x) & 0x0f);
+                self.i = ADDR_CHARACTER + SIZE_CHARACTER * char;
 
-                Ok(())
+                Flow::Next
             }
-            (0xF, x, 0x2, 0x9) => {
-                // LD F, Vx: Set I = location of sprite for digit Vx
+            Instruction::LdHf(x) if self.schip() => {
+                // I = location of the big sprite for digit Vx
 
                 let char = u16::from(*self.v(x) & 0x0f);
+                self.i = ADDR_BIG_CHARACTER + SIZE_BIG_CHARACTER * char;
 
-                self.i = ADDR_CHARACTER + SIZE_CHARACTER * char;
-
-                self.pc += 2;
+                Flow::Next
+            }
+            Instruction::Pitch(x) if self.xo_chip() => {
+                // Set the audio pattern playback rate
 
-                Ok(())
+                self.pitch = *self.v(x);
+                Flow::Next
             }
-            (0xF, x, 0x3, 0x3) => {
-                // LD B, Vx: Store BCD repr of Vx in mem locations I, I+1, I+2
+            Instruction::LdB(x) => {
+                // Store the BCD representation of Vx at I, I+1, I+2
 
                 let i = usize::from(self.i);
                 let vx = *self.v(x);
 
                 let hundreds = vx / 100 % 10;
                 let tens = vx / 10 % 10;
-                let ones = vx / 1 % 10;
-
-                self.ram[i] = hundreds;
-                self.ram[i + 1] = tens;
-                self.ram[i + 2] = ones;
+                let ones = vx % 10;
 
-                self.pc += 2;
+                self.mem_write(i, hundreds);
+                self.mem_write(i + 1, tens);
+                self.mem_write(i + 2, ones);
 
-                Ok(())
+                Flow::Next
             }
-            (0xF, x, 0x5, 0x5) => {
-                // LD [I], Vx: Store registers V0 through Vx in memory starting at I
+            Instruction::Store(x) => {
+                // Store registers V0 through Vx in memory starting at I
 
                 for di in 0_usize..=usize::from(x) {
-                    let addr = (usize::from(self.i) + di) % self.ram.len();
-                    self.ram[addr] = self.v[di];
+                    self.mem_write(usize::from(self.i) + di, self.v[di]);
                 }
 
-                self.pc += 2;
+                if self.quirks.load_store_increment_i {
+                    self.i = self.i.wrapping_add(u16::from(x) + 1);
+                }
 
-                Ok(())
+                Flow::Next
             }
-            (0xF, x, 0x6, 0x5) => {
-                // LD Vx, [I]: Read registers V0 through Vx from memory starting at I
+            Instruction::Load(x) => {
+                // Read registers V0 through Vx from memory starting at I
 
                 for di in 0_usize..=usize::from(x) {
-                    let addr = (usize::from(self.i) + di) % self.ram.len();
-                    self.v[di] = self.ram[addr];
+                    self.v[di] = self.mem_read(usize::from(self.i) + di);
                 }
 
-                self.pc += 2;
+                if self.quirks.load_store_increment_i {
+                    self.i = self.i.wrapping_add(u16::from(x) + 1);
+                }
 
-                Ok(())
+                Flow::Next
             }
-            _ => Err(Chip8Panic::UnknownOpCode),
+            Instruction::SaveRange(x, y) if self.xo_chip() => {
+                // Store Vx through Vy at I, in reverse if x > y, leaving I
+
+                let i = usize::from(self.i);
+                for (offset, reg) in register_range(x, y).enumerate() {
+                    self.mem_write(i + offset, self.v[reg]);
+                }
+
+                Flow::Next
+            }
+            Instruction::LoadRange(x, y) if self.xo_chip() => {
+                let i = usize::from(self.i);
+                for (offset, reg) in register_range(x, y).enumerate() {
+                    self.v[reg] = self.mem_read(i + offset);
+                }
+
+                Flow::Next
+            }
+            Instruction::StoreRpl(x) if self.schip() => {
+                // Only the first 8 registers have flags to go in
+
+                let n = (usize::from(x) + 1).min(RPL_FLAGS);
+                self.rpl[..n].copy_from_slice(&self.v[..n]);
+
+                Flow::Next
+            }
+            Instruction::LoadRpl(x) if self.schip() => {
+                let n = (usize::from(x) + 1).min(RPL_FLAGS);
+                self.v[..n].copy_from_slice(&self.rpl[..n]);
+
+                Flow::Next
+            }
+            // Unknown opcodes, and extension instructions outside of their mode
+            _ => {
+                return Err(match instruction.extension_op() {
+                    Some(op) => Chip8Panic::ExtensionOpCode(op),
+                    None => Chip8Panic::UnknownOpCode,
+                })
+            }
+        };
+
+        Ok(flow)
+    }
+
+    /// Skip the next instruction, which with XO-CHIP RAM may be the
+    /// four-byte LD I, nnnn
+    fn skip(&mut self) {
+        let next = self.pc.wrapping_add(2);
+        let len = if self.xo_chip() && self.mem_read_opcode(next) == 0xF000 {
+            4
+        } else {
+            2
+        };
+
+        self.pc = next.wrapping_add(len);
+    }
+
+    fn logic_vf_reset(&mut self) {
+        if self.quirks.logic_vf_reset {
+            self.v[0xf] = 0;
         }
     }
 
-    fn disp_toggle_sprite_row(&mut self, x: usize, y: usize, s: u8) {
+    /// Move the selected planes `dx` pixels right and `dy` down (left and up
+    /// if negative), in display pixels in either mode; pixels moved in from
+    /// the edges are unlit
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (
+            self.display_width() as isize,
+            self.display_height() as isize,
+        );
+        let plane = self.plane;
+
+        // Shift in place, visiting pixels in the order that reads each one
+        // before it is overwritten
+        let backward = dy * width + dx > 0;
+
+        for n in 0..width * height {
+            let idx = if backward { width * height - 1 - n } else { n };
+            let (sx, sy) = (idx % width - dx, idx / width - dy);
+
+            let moved = if (0..width).contains(&sx) && (0..height).contains(&sy) {
+                self.display[(sy * width + sx) as usize] & plane
+            } else {
+                0
+            };
+
+            let px = &mut self.display[idx as usize];
+            *px = (*px & !plane) | moved;
+        }
+
+        self.redraw();
+    }
+
+    /// Clear the display and resize it to the current mode, in place within
+    /// the room it was made with
+    pub(crate) fn resize_display(&mut self) {
+        self.display.clear();
+        self.display
+            .resize(self.display_width() * self.display_height(), 0);
+    }
+
+    fn disp_toggle_sprite_row(&mut self, x: usize, y: usize, s: u8, plane: u8) {
         for i in (0..8).rev() {
             if (s >> i) & 1 == 1 {
-                self.disp_toggle_coord(x + 7 - i, y);
+                self.disp_toggle_coord(x + 7 - i, y, plane);
             }
         }
     }
 
-    fn disp_toggle_coord(&mut self, x: usize, y: usize) {
+    fn disp_toggle_coord(&mut self, x: usize, y: usize, plane: u8) {
         let idx = self.disp_coord_to_index(x, y);
 
-        if self.display[idx] {
+        if self.display[idx] & plane != 0 {
             self.v[0xf] = 1;
         }
 
-        self.display[idx] = !self.display[idx];
+        self.display[idx] ^= plane;
 
+        self.changed_rows |= 1 << (idx / self.display_width());
         self.display_dirty = true;
     }
 
     fn disp_coord_to_index(&self, mut x: usize, mut y: usize) -> usize {
-        x = x % self.display_width();
-        y = y % self.display_height();
+        x %= self.display_width();
+        y %= self.display_height();
 
         y * self.display_width() + x
     }
 
     pub fn mem_read_opcode(&self, addr: u16) -> u16 {
         let msb: u16 = self.mem_read_byte(addr).into();
-        let lsb: u16 = self.mem_read_byte(addr + 1).into();
+        let lsb: u16 = self.mem_read_byte(addr.wrapping_add(1)).into();
 
         (msb << 8) | lsb
     }
 
-    pub fn load_rom(&mut self, data: &[u8]) -> anyhow::Result<()> {
+    /// Copy `data` into RAM at 0x200, switching to hi-res mode if it is a
+    /// hi-res ROM, and to XO-CHIP RAM if it does not fit in the original's
+    ///
+    /// Other ROMs leave the mode and RAM size as they were.
+    pub fn load_rom(&mut self, data: &[u8]) -> Result<(), OutOfMemory> {
+        if usize::from(ADDR_PROGRAM) + data.len() >= self.ram.len() {
+            self.set_ram_size(XO_RAM_SIZE);
+        }
+
         self.mem_write_slice(ADDR_PROGRAM, data)?;
 
+        if is_hires_rom(data) && !self.hires {
+            self.set_hires(true);
+        }
+
         Ok(())
     }
 
-    fn mem_write_slice(&mut self, start: u16, slice: &[u8]) -> anyhow::Result<()> {
-        let start = usize::from(start);
+    fn mem_write_slice(&mut self, addr: u16, slice: &[u8]) -> Result<(), OutOfMemory> {
+        let start = usize::from(addr);
 
         if start + slice.len() >= self.ram.len() {
-            return Err(anyhow!("mem_insert out-of-bounds"));
+            return Err(OutOfMemory {
+                addr,
+                len: slice.len(),
+            });
         }
 
         for (offset, val) in slice.iter().enumerate() {
@@ -633,6 +1276,67 @@ impl Chip8 {
         let addr = usize::from(addr) % self.ram.len();
         self.ram[addr]
     }
+
+    /// Read a byte on behalf of the running program, checking watchpoints
+    fn mem_read(&mut self, addr: usize) -> u8 {
+        let addr = addr % self.ram.len();
+        self.watch(addr, Access::Read);
+        self.ram[addr]
+    }
+
+    /// Write a byte on behalf of the running program, checking watchpoints
+    fn mem_write(&mut self, addr: usize, val: u8) {
+        let addr = addr % self.ram.len();
+        self.watch(addr, Access::Write);
+        self.ram[addr] = val;
+    }
+
+    fn watch(&mut self, addr: usize, access: Access) {
+        let addr = addr as u16;
+
+        if self.watch_hit.is_none() && self.watchpoints.check(addr, access) {
+            self.watch_hit = Some(WatchHit {
+                addr,
+                access,
+                pc: self.pc,
+            });
+        }
+    }
+}
+
+/// Whether `rom` is for the 64x64 hi-res mode: its first instruction jumps
+/// over the patch that the original interpreter needed
+pub fn is_hires_rom(rom: &[u8]) -> bool {
+    rom.starts_with(&HIRES_ENTRY.to_be_bytes())
+}
+
+/// Color of a pixel lit in the planes of mask `px`, blended from the (off, on)
+/// `colors`
+pub fn plane_color(px: u8, colors: (u32, u32)) -> u32 {
+    let (off, on) = colors;
+
+    match PLANE_LEVELS[usize::from(px) % PLANE_LEVELS.len()] {
+        0 => off,
+        255 => on,
+        level => [16, 8, 0].iter().fold(0, |acc, &shift| {
+            let (lo, hi) = ((off >> shift) & 0xff, (on >> shift) & 0xff);
+            acc | ((lo * (255 - level) + hi * level) / 255) << shift
+        }),
+    }
+}
+
+/// Cleared display of `size` pixels with room for the largest one
+fn display_buffer(size: usize) -> Vec<u8> {
+    let mut display = Vec::with_capacity(MAX_DISPLAY_SIZE);
+    display.resize(size, 0);
+    display
+}
+
+/// Registers x through y, counting down if y is below x
+fn register_range(x: u8, y: u8) -> impl Iterator<Item = usize> {
+    let (x, y) = (usize::from(x), usize::from(y));
+
+    (0..=x.abs_diff(y)).map(move |n| if x <= y { x + n } else { x - n })
 }
 
 fn fill_array<T: Copy>(a: &mut [T], val: T) {
@@ -0,0 +1,200 @@
+use alloc::boxed::Box;
+use core::{ptr, slice};
+
+use crate::chip8::{Chip8, Chip8Panic};
+
+/// Result code returned by the `chip8_*` functions
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Status {
+    Ok = 0,
+    StackUnderflow = 1,
+    StackOverflow = 2,
+    UnknownOpcode = 3,
+    /// The ROM does not fit in memory
+    RomTooLarge = 4,
+    /// A required pointer argument was null
+    NullPointer = 5,
+    /// The opcode is a SCHIP or XO-CHIP instruction, which is not enabled
+    ExtensionOpcode = 6,
+}
+
+impl From<Chip8Panic> for Chip8Status {
+    fn from(e: Chip8Panic) -> Self {
+        match e {
+            Chip8Panic::StackUnderflow => Chip8Status::StackUnderflow,
+            Chip8Panic::StackOverflow => Chip8Status::StackOverflow,
+            Chip8Panic::UnknownOpCode => Chip8Status::UnknownOpcode,
+            Chip8Panic::ExtensionOpCode(_) => Chip8Status::ExtensionOpcode,
+        }
+    }
+}
+
+/// Create an interpreter, reset and ready to load a ROM
+///
+/// Free it with `chip8_destroy`.
+#[no_mangle]
+pub extern "C" fn chip8_create() -> *mut Chip8 {
+    Box::into_raw(Box::new(Chip8::new()))
+}
+
+/// Free an interpreter created by `chip8_create`
+///
+/// # Safety
+///
+/// `chip8` must be null or a pointer returned by `chip8_create` that has not
+/// been destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_destroy(chip8: *mut Chip8) {
+    if !chip8.is_null() {
+        drop(Box::from_raw(chip8));
+    }
+}
+
+/// Reset the machine, clearing memory, registers, and the display
+///
+/// # Safety
+///
+/// `chip8` must be null or a live pointer returned by `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_reset(chip8: *mut Chip8) -> Chip8Status {
+    match chip8.as_mut() {
+        Some(chip8) => {
+            chip8.reset();
+            Chip8Status::Ok
+        }
+        None => Chip8Status::NullPointer,
+    }
+}
+
+/// Copy `len` bytes of ROM to 0x200
+///
+/// # Safety
+///
+/// `chip8` must be null or a live pointer returned by `chip8_create`, and
+/// `rom` must be null or point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(
+    chip8: *mut Chip8,
+    rom: *const u8,
+    len: usize,
+) -> Chip8Status {
+    match (chip8.as_mut(), rom.is_null()) {
+        (Some(chip8), false) => match chip8.load_rom(slice::from_raw_parts(rom, len)) {
+            Ok(()) => Chip8Status::Ok,
+            Err(_) => Chip8Status::RomTooLarge,
+        },
+        _ => Chip8Status::NullPointer,
+    }
+}
+
+/// Execute one instruction
+///
+/// Keys set with `chip8_set_key` are released afterwards.
+///
+/// # Safety
+///
+/// `chip8` must be null or a live pointer returned by `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_step(chip8: *mut Chip8) -> Chip8Status {
+    match chip8.as_mut() {
+        Some(chip8) => match chip8.step() {
+            Ok(_) => Chip8Status::Ok,
+            Err(e) => e.into(),
+        },
+        None => Chip8Status::NullPointer,
+    }
+}
+
+/// Decrement the delay and sound timers; call at 60 Hz
+///
+/// # Safety
+///
+/// `chip8` must be null or a live pointer returned by `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_timer_tick(chip8: *mut Chip8) -> Chip8Status {
+    match chip8.as_mut() {
+        Some(chip8) => {
+            chip8.timer_tick();
+            Chip8Status::Ok
+        }
+        None => Chip8Status::NullPointer,
+    }
+}
+
+/// Hold keypad `key` (0x0 ~ 0xF) down for the next `chip8_step`
+///
+/// # Safety
+///
+/// `chip8` must be null or a live pointer returned by `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(chip8: *mut Chip8, key: u8) -> Chip8Status {
+    match chip8.as_mut() {
+        Some(chip8) => {
+            chip8.set_key(key);
+            Chip8Status::Ok
+        }
+        None => Chip8Status::NullPointer,
+    }
+}
+
+/// Whether the buzzer should sound, i.e. the sound timer is non-zero
+///
+/// # Safety
+///
+/// `chip8` must be null or a live pointer returned by `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_sound_active(chip8: *const Chip8) -> bool {
+    chip8.as_ref().is_some_and(|chip8| chip8.st > 0)
+}
+
+/// Display width in pixels, or 0 if `chip8` is null
+///
+/// # Safety
+///
+/// `chip8` must be null or a live pointer returned by `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_display_width(chip8: *const Chip8) -> usize {
+    chip8.as_ref().map_or(0, |chip8| chip8.display_width())
+}
+
+/// Display height in pixels, or 0 if `chip8` is null
+///
+/// # Safety
+///
+/// `chip8` must be null or a live pointer returned by `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_display_height(chip8: *const Chip8) -> usize {
+    chip8.as_ref().map_or(0, |chip8| chip8.display_height())
+}
+
+/// Pointer to the display, `width * height` bytes row by row, each the mask
+/// of the bit planes lit there (0 = unlit)
+///
+/// Valid until the interpreter is destroyed: a hi-res ROM loaded later
+/// resizes the display in place, so read `chip8_display_width() *
+/// chip8_display_height()` bytes each time. Returns null if `chip8` is null.
+///
+/// # Safety
+///
+/// `chip8` must be null or a live pointer returned by `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_get_display_ptr(chip8: *const Chip8) -> *const u8 {
+    chip8
+        .as_ref()
+        .map_or(ptr::null(), |chip8| chip8.display.as_ptr())
+}
+
+/// Rows of the display changed since the last call, bit n set for row n, or 0
+/// if `chip8` is null
+///
+/// Redrawing only these rows from `chip8_get_display_ptr` keeps a frontend up
+/// to date.
+///
+/// # Safety
+///
+/// `chip8` must be null or a live pointer returned by `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_take_changed_rows(chip8: *mut Chip8) -> u64 {
+    chip8.as_mut().map_or(0, |chip8| chip8.take_changed_rows())
+}
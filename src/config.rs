@@ -0,0 +1,757 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use minifb::Scale;
+use serde::Deserialize;
+use toml::Spanned;
+
+use chip8::{
+    emu::PausedTimers,
+    filter::FilterSpec,
+    keys::{self, Action, Binding, Hotkey},
+    locale::{self, Locale},
+    palette, storage, Quirks,
+};
+
+/// Config file looked up in the working directory when `--config` is not given
+pub const DEFAULT_CONFIG_FILE: &str = "chip8.toml";
+
+/// Commented default config written by `chip8 config init`
+pub const DEFAULT_CONFIG_TEMPLATE: &str = r##"# chip8 configuration
+#
+# Command-line options override these settings. Everything is commented out,
+# so the built-in defaults apply until a line is uncommented.
+
+# Clock speed in Hz, or 0 to run as fast as possible
+#clock = 1000.0
+
+# Emulated display refresh rate in Hz; by default every change is shown
+#refresh = 60.0
+
+# Delay and sound timers while paused or stopped on an error: "freeze" stops
+# them with the program, "run" keeps counting down in real time
+#paused_timers = "freeze"
+
+# Built-in palette (0 ~ 9 or a name such as "amber"), or custom colors as
+# RRGGBB hex
+#palette = 0
+#palette = "amber"
+#palette = { off = "000000", on = "ffffff" }
+
+# Window scale factor: 1, 2, 4, 8, 16, or 32
+#scale = 16
+
+# Language of window titles and messages: en, es, or a language file path
+#language = "en"
+
+# Display filter stages, applied in order:
+#   palette, fade[=MS], hue[=DEG_PER_S], phosphor[=DECAY],
+#   scanlines[=BRIGHTNESS], grid[=SIZE], crt[=SIZE], rotate=DEGREES
+#filters = ["phosphor=0.7", "palette", "scanlines"]
+
+# Host keys bound to keypad keys (0x0 ~ 0xF), added to the default layout:
+#
+#   1 2 3 4        1 2 3 C
+#   Q W E R   ->   4 5 6 D
+#   A S D F        7 8 9 E
+#   Z X C V        A 0 B F
+[keys]
+#Up = 0x5
+#Down = 0x8
+
+# Host keys bound to emulator controls (defaults shown). Chords such as
+# "Ctrl+R" or "Ctrl+Shift+S" leave the plain key free for the keypad.
+[controls]
+#quit = "Escape"
+#reset = "F1"
+#debug = "F2"
+#overlay = "F4"
+#palette = "F3"
+#save = "F5"
+#checkpoint = "F6"
+#load = "F7"
+#pause = "Space"
+#step = "Enter"
+#rewind = "Backspace"
+#screenshot = "F12"
+#turbo = "Tab"
+#cheats = "F8"
+#rates = "F9"
+#menu = "F10"
+
+# Gamepad buttons bound to keypad keys; requires the `gamepad` feature
+#[gamepad]
+#South = 0x5
+#DPadUp = 0x2
+
+# Compatibility quirks for ROMs written for other interpreters
+[quirks]
+#shift_vy = false
+#load_store_increment_i = false
+#jump_vx = false
+#logic_vf_reset = false
+# SYS (0nnn): "trap" stops the program, "ignore" skips it
+#sys = "trap"
+"##;
+
+/// Settings loaded from a TOML config file
+///
+/// ```toml
+/// clock = 700.0
+/// refresh = 60.0
+/// palette = 4                                  # or { off = "000000", on = "ffffff" }
+/// scale = 8
+/// language = "es"                             # or "path/to/messages.toml"
+/// filters = ["phosphor=0.7", "palette", "scanlines"]
+///
+/// [keys]
+/// Q = 0x4
+/// Up = 0x5
+///
+/// [controls]
+/// pause = "P"
+/// reset = "Ctrl+R"
+///
+/// [gamepad]                                  # requires the `gamepad` feature
+/// South = 0x5
+/// DPadUp = 0x2
+///
+/// [quirks]
+/// shift_vy = true
+/// ```
+///
+/// Values keep their position in the file so `validate` can point at them.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Clock speed (Hz), 0 for native speed
+    pub clock: Option<Spanned<f64>>,
+
+    /// Emulated display refresh rate (Hz)
+    pub refresh: Option<Spanned<f64>>,
+
+    /// Whether the timers run while paused
+    pub paused_timers: Option<Spanned<PausedTimers>>,
+
+    pub palette: Option<Spanned<PaletteConfig>>,
+
+    /// Window scale factor (1, 2, 4, 8, 16, or 32)
+    pub scale: Option<Spanned<usize>>,
+
+    /// Built-in language code or language file path (see `Locale::find`)
+    pub language: Option<Spanned<String>>,
+
+    /// Display filter stages, in order (see `FilterSpec`)
+    pub filters: Vec<Spanned<String>>,
+
+    /// File the config was loaded from, if any, where cycling the palette
+    /// saves it
+    ///
+    /// Cleared when a profile sets the palette, since the profile would
+    /// override a saved choice.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+
+    /// Host key name to CHIP-8 key (0x0 ~ 0xF), added to the default key map
+    pub keys: HashMap<String, Spanned<u8>>,
+
+    /// Emulator action name to host key or chord (`Ctrl+R`)
+    pub controls: HashMap<String, Spanned<String>>,
+
+    /// Gamepad button name to CHIP-8 key (0x0 ~ 0xF), added to the default button map
+    ///
+    /// Only used with the `gamepad` feature, but always accepted so `validate`
+    /// can say why it is ignored.
+    pub gamepad: HashMap<String, Spanned<u8>>,
+
+    pub quirks: Quirks,
+}
+
+/// Config overrides for single ROMs, keyed by ROM hash (`storage::rom_hash`)
+///
+/// Each profile is a table with the same settings as the config file:
+///
+/// ```toml
+/// [4c3f2a1b]
+/// clock = 500.0
+/// palette = "amber"
+///
+/// [4c3f2a1b.quirks]
+/// shift_vy = true
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Profiles {
+    profiles: HashMap<String, Config>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PaletteConfig {
+    Builtin(usize),
+    Named(String),
+    Custom { off: String, on: String },
+}
+
+/// Problem found in a config file
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    /// 1-based line, if known
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+
+        let mut config = Self::parse(&text).map_err(|errors| {
+            let lines: Vec<String> = errors
+                .iter()
+                .map(|e| format!("  {}:{}", path.display(), e))
+                .collect();
+
+            anyhow!("invalid config:\n{}", lines.join("\n"))
+        })?;
+        config.path = Some(path.to_path_buf());
+
+        Ok(config)
+    }
+
+    /// Parse and validate config text, returning every problem found
+    pub fn parse(text: &str) -> Result<Self, Vec<ConfigError>> {
+        let config: Self = toml::from_str(text).map_err(|e| vec![toml_error(&e)])?;
+
+        let errors = config.validate(text);
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Load `path` if given, otherwise `chip8.toml` if it exists, otherwise defaults
+    pub fn find(path: Option<&Path>) -> anyhow::Result<Self> {
+        match path {
+            Some(path) => Self::load(path),
+            None => {
+                let default_path = Path::new(DEFAULT_CONFIG_FILE);
+
+                if default_path.exists() {
+                    Self::load(default_path)
+                } else {
+                    Ok(Self::default())
+                }
+            }
+        }
+    }
+
+    /// Override these settings with the ones `profile` sets
+    ///
+    /// Key, control, and gamepad bindings are added to, and quirks replaced
+    /// if the profile enables any.
+    pub fn apply_profile(&mut self, profile: &Config) {
+        let profile = profile.clone();
+
+        self.clock = profile.clock.or(self.clock.take());
+        self.refresh = profile.refresh.or(self.refresh.take());
+        self.paused_timers = profile.paused_timers.or(self.paused_timers.take());
+        self.scale = profile.scale.or(self.scale.take());
+        self.language = profile.language.or(self.language.take());
+
+        if profile.palette.is_some() {
+            self.palette = profile.palette;
+            self.path = None;
+        }
+
+        if !profile.filters.is_empty() {
+            self.filters = profile.filters;
+        }
+
+        self.keys.extend(profile.keys);
+        self.controls.extend(profile.controls);
+        self.gamepad.extend(profile.gamepad);
+
+        if profile.quirks != Quirks::default() {
+            self.quirks = profile.quirks;
+        }
+    }
+
+    /// Check values that parse as TOML but make no sense, sorted by line
+    ///
+    /// `text` is the source the config was parsed from, used to turn value
+    /// positions into line numbers.
+    pub fn validate(&self, text: &str) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        let mut error = |spanned_start: usize, message: String| {
+            errors.push(ConfigError {
+                line: Some(line_number(text, spanned_start)),
+                message,
+            })
+        };
+
+        if let Some(clock) = &self.clock {
+            if !clock.get_ref().is_finite() || *clock.get_ref() < 0. {
+                error(
+                    clock.start(),
+                    format!("clock must be 0 or more Hz, got {}", clock.get_ref()),
+                );
+            }
+        }
+
+        if let Some(refresh) = &self.refresh {
+            if !refresh.get_ref().is_finite() || *refresh.get_ref() <= 0. {
+                error(
+                    refresh.start(),
+                    format!("refresh must be more than 0 Hz, got {}", refresh.get_ref()),
+                );
+            }
+        }
+
+        if let Some(palette) = &self.palette {
+            match palette.get_ref() {
+                PaletteConfig::Builtin(index) if *index >= palette::BUILTIN_PALETTES.len() => {
+                    error(
+                        palette.start(),
+                        format!(
+                            "palette {} is not a built-in palette, expected 0 ~ {}",
+                            index,
+                            palette::BUILTIN_PALETTES.len() - 1
+                        ),
+                    );
+                }
+                PaletteConfig::Named(name) if palette::find(name).is_none() => {
+                    error(
+                        palette.start(),
+                        format!(
+                            "unknown palette \"{}\", expected one of {}",
+                            name,
+                            palette::names()
+                        ),
+                    );
+                }
+                PaletteConfig::Custom { off, on } => {
+                    for (field, color) in [("off", off), ("on", on)] {
+                        if palette::parse_hex(color).is_none() {
+                            error(
+                                palette.start(),
+                                format!(
+                                    "palette color {} = \"{}\" is not an RRGGBB hex color",
+                                    field, color
+                                ),
+                            );
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(scale) = &self.scale {
+            if window_scale(*scale.get_ref()).is_none() {
+                error(
+                    scale.start(),
+                    format!(
+                        "scale must be 1, 2, 4, 8, 16, or 32, got {}",
+                        scale.get_ref()
+                    ),
+                );
+            }
+        }
+
+        if let Some(language) = &self.language {
+            let name = language.get_ref();
+
+            if Locale::builtin(name).is_none() && !locale::is_path(name) {
+                error(
+                    language.start(),
+                    format!(
+                        "unknown language '{}', expected {} or a path to a language file",
+                        name,
+                        locale::codes()
+                    ),
+                );
+            }
+        }
+
+        for filter in self.filters.iter() {
+            if let Err(e) = filter.get_ref().parse::<FilterSpec>() {
+                error(filter.start(), format!("in filters: {}", e));
+            }
+        }
+
+        // Host key -> (what it is bound to, line), for reporting conflicts
+        let mut bound: HashMap<Hotkey, (String, usize)> = HashMap::new();
+
+        for (name, code) in sorted_by_position(&self.keys) {
+            let line = line_number(text, code.start());
+
+            if *code.get_ref() > 0xf {
+                error(
+                    code.start(),
+                    format!(
+                        "[keys] {} = {:#X} is not a keypad key, expected 0x0 ~ 0xF",
+                        name,
+                        code.get_ref()
+                    ),
+                );
+            }
+
+            match keys::parse_key(name) {
+                None => error(code.start(), format!("[keys] unknown key '{}'", name)),
+                Some(key) => {
+                    let binding = format!("keypad key {:#X}", code.get_ref());
+
+                    if let Some((other, other_line)) = bound.get(&Hotkey::plain(key)) {
+                        error(
+                            code.start(),
+                            format!(
+                                "[keys] '{}' is already bound to {} on line {}",
+                                name, other, other_line
+                            ),
+                        );
+                    } else {
+                        bound.insert(Hotkey::plain(key), (binding, line));
+                    }
+                }
+            }
+        }
+
+        for (action, name) in sorted_by_position(&self.controls) {
+            let line = line_number(text, name.start());
+
+            if action.parse::<Action>().is_err() {
+                let names: Vec<&str> = Action::ALL.iter().map(|a| a.name()).collect();
+
+                error(
+                    name.start(),
+                    format!(
+                        "[controls] unknown action '{}', expected one of: {}",
+                        action,
+                        names.join(", ")
+                    ),
+                );
+            }
+
+            match name.get_ref().parse::<Hotkey>() {
+                Err(e) => error(name.start(), format!("[controls] {} = {}", action, e)),
+                Ok(key) => {
+                    let binding = format!("action '{}'", action);
+
+                    if let Some((other, other_line)) = bound.get(&key) {
+                        error(
+                            name.start(),
+                            format!(
+                                "[controls] {} = '{}' conflicts with {} on line {}",
+                                action,
+                                name.get_ref(),
+                                other,
+                                other_line
+                            ),
+                        );
+                    } else {
+                        bound.insert(key, (binding, line));
+                    }
+                }
+            }
+        }
+
+        self.validate_gamepad(&mut error);
+
+        errors.sort_by_key(|e| e.line);
+        errors
+    }
+
+    #[cfg(feature = "gamepad")]
+    fn validate_gamepad(&self, error: &mut impl FnMut(usize, String)) {
+        for (name, code) in sorted_by_position(&self.gamepad) {
+            if *code.get_ref() > 0xf {
+                error(
+                    code.start(),
+                    format!(
+                        "[gamepad] {} = {:#X} is not a keypad key, expected 0x0 ~ 0xF",
+                        name,
+                        code.get_ref()
+                    ),
+                );
+            }
+
+            if chip8::gamepad::parse_button(name).is_none() {
+                error(
+                    code.start(),
+                    format!("[gamepad] unknown gamepad button '{}'", name),
+                );
+            }
+        }
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    fn validate_gamepad(&self, error: &mut impl FnMut(usize, String)) {
+        if let Some((_, code)) = sorted_by_position(&self.gamepad).first() {
+            error(
+                code.start(),
+                "[gamepad] needs the `gamepad` feature; these bindings are ignored".to_string(),
+            );
+        }
+    }
+
+    pub fn clock(&self) -> Option<f64> {
+        self.clock.as_ref().map(|clock| *clock.get_ref())
+    }
+
+    pub fn refresh(&self) -> Option<f64> {
+        self.refresh.as_ref().map(|refresh| *refresh.get_ref())
+    }
+
+    pub fn paused_timers(&self) -> Option<PausedTimers> {
+        self.paused_timers.as_ref().map(|policy| *policy.get_ref())
+    }
+
+    pub fn palette(&self) -> Option<&PaletteConfig> {
+        self.palette.as_ref().map(Spanned::get_ref)
+    }
+
+    pub fn window_scale(&self) -> anyhow::Result<Option<Scale>> {
+        self.scale
+            .as_ref()
+            .map(|scale| {
+                window_scale(*scale.get_ref()).ok_or_else(|| {
+                    anyhow!(
+                        "scale must be 1, 2, 4, 8, 16, or 32, got {}",
+                        scale.get_ref()
+                    )
+                })
+            })
+            .transpose()
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.language
+            .as_ref()
+            .map(|language| language.get_ref().as_str())
+    }
+
+    pub fn filter_specs(&self) -> anyhow::Result<Vec<FilterSpec>> {
+        self.filters.iter().map(|s| s.get_ref().parse()).collect()
+    }
+
+    /// Keypad bindings followed by control bindings
+    pub fn key_bindings(&self) -> anyhow::Result<Vec<(Hotkey, Binding)>> {
+        let parse_key =
+            |name: &str| keys::parse_key(name).ok_or_else(|| anyhow!("unknown key '{}'", name));
+
+        let keypad = self.keys.iter().map(|(name, code)| {
+            let code = *code.get_ref();
+
+            if code > 0xf {
+                return Err(anyhow!(
+                    "key '{}' maps to {:#X}, expected 0x0 ~ 0xF",
+                    name,
+                    code
+                ));
+            }
+
+            Ok((Hotkey::plain(parse_key(name)?), Binding::Keypad(code)))
+        });
+
+        let controls = self
+            .controls
+            .iter()
+            .map(|(action, name)| Ok((name.get_ref().parse()?, Binding::Action(action.parse()?))));
+
+        keypad.chain(controls).collect()
+    }
+
+    #[cfg(feature = "gamepad")]
+    pub fn gamepad_bindings(&self) -> anyhow::Result<Vec<(gilrs::Button, u8)>> {
+        self.gamepad
+            .iter()
+            .map(|(name, code)| {
+                let code = *code.get_ref();
+
+                if code > 0xf {
+                    return Err(anyhow!(
+                        "button '{}' maps to {:#X}, expected 0x0 ~ 0xF",
+                        name,
+                        code
+                    ));
+                }
+
+                let button = chip8::gamepad::parse_button(name)
+                    .ok_or_else(|| anyhow!("unknown gamepad button '{}'", name))?;
+
+                Ok((button, code))
+            })
+            .collect()
+    }
+}
+
+impl Profiles {
+    /// Load `path`, or no profiles if it does not exist
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read profiles {}", path.display()))?;
+
+        Self::parse(&text).map_err(|errors| {
+            let lines: Vec<String> = errors
+                .iter()
+                .map(|e| format!("  {}:{}", path.display(), e))
+                .collect();
+
+            anyhow!("invalid profiles:\n{}", lines.join("\n"))
+        })
+    }
+
+    /// Parse and validate profiles text, returning every problem found
+    pub fn parse(text: &str) -> Result<Self, Vec<ConfigError>> {
+        let profiles: HashMap<String, Config> =
+            toml::from_str(text).map_err(|e| vec![toml_error(&e)])?;
+
+        // Spans are positions in the whole file, so every profile checks against it
+        let mut errors: Vec<ConfigError> = profiles
+            .values()
+            .flat_map(|profile| profile.validate(text))
+            .collect();
+        errors.sort_by_key(|e| e.line);
+
+        if errors.is_empty() {
+            Ok(Profiles { profiles })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Profile for the ROM with contents `rom`
+    pub fn get(&self, rom: &[u8]) -> Option<&Config> {
+        self.profiles.get(&storage::rom_hash(rom))
+    }
+}
+
+impl PaletteConfig {
+    /// (off, on) colors
+    pub fn colors(&self) -> (u32, u32) {
+        match self.builtin_index() {
+            Some(index) => palette::builtin(index),
+            None => match self {
+                PaletteConfig::Custom { off, on } => {
+                    (palette::from_hex(off), palette::from_hex(on))
+                }
+                _ => palette::builtin(0),
+            },
+        }
+    }
+
+    /// Index into `palette::BUILTIN_PALETTES`, unless the colors are custom
+    pub fn builtin_index(&self) -> Option<usize> {
+        match self {
+            PaletteConfig::Builtin(index) => Some(*index),
+            PaletteConfig::Named(name) => palette::find(name),
+            PaletteConfig::Custom { .. } => None,
+        }
+    }
+}
+
+/// Check the config file `file`, or the default one, printing any problems
+pub fn check(file: Option<&Path>, locale: &Locale) -> anyhow::Result<()> {
+    let path = file.unwrap_or_else(|| Path::new(DEFAULT_CONFIG_FILE));
+    let text = fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read config {}: {}", path.display(), e))?;
+
+    match Config::parse(&text) {
+        Ok(_) => {
+            println!(
+                "{}",
+                locale.format("tools.config_ok", &[("path", &path.display())])
+            );
+            Ok(())
+        }
+        Err(errors) => {
+            for e in errors.iter() {
+                eprintln!("{}:{}", path.display(), e);
+            }
+
+            Err(anyhow!(locale.format(
+                "tools.config_problems",
+                &[("count", &errors.len()), ("path", &path.display())]
+            )))
+        }
+    }
+}
+
+/// Write the commented default config to `file`, or the default config file
+///
+/// An existing file is only replaced if `force` is set.
+pub fn init(file: Option<&Path>, force: bool, locale: &Locale) -> anyhow::Result<()> {
+    let path = file.unwrap_or_else(|| Path::new(DEFAULT_CONFIG_FILE));
+
+    if path.exists() && !force {
+        return Err(anyhow!(
+            locale.format("tools.config_exists", &[("path", &path.display())])
+        ));
+    }
+
+    fs::write(path, DEFAULT_CONFIG_TEMPLATE)?;
+    println!(
+        "{}",
+        locale.format("tools.wrote", &[("path", &path.display())])
+    );
+
+    Ok(())
+}
+
+fn window_scale(scale: usize) -> Option<Scale> {
+    match scale {
+        1 => Some(Scale::X1),
+        2 => Some(Scale::X2),
+        4 => Some(Scale::X4),
+        8 => Some(Scale::X8),
+        16 => Some(Scale::X16),
+        32 => Some(Scale::X32),
+        _ => None,
+    }
+}
+
+/// 1-based line containing byte `offset` of `text`
+fn line_number(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+/// Table entries in file order, so reports and conflicts are deterministic
+fn sorted_by_position<T>(table: &HashMap<String, Spanned<T>>) -> Vec<(&String, &Spanned<T>)> {
+    let mut entries: Vec<_> = table.iter().collect();
+    entries.sort_by_key(|(_, value)| value.start());
+    entries
+}
+
+/// Syntax and schema errors from the TOML parser, with the position split out
+fn toml_error(e: &toml::de::Error) -> ConfigError {
+    let message = e.to_string();
+
+    // The message ends with " at line L column C" when the position is known
+    let message = match (e.line_col(), message.rfind(" at line ")) {
+        (Some(_), Some(i)) => message[..i].to_string(),
+        _ => message,
+    };
+
+    ConfigError {
+        line: e.line_col().map(|(line, _)| line + 1),
+        message,
+    }
+}
@@ -0,0 +1,559 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryFrom,
+    io::{self, Stdout, Write},
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Print, PrintStyledContent, Stylize},
+    terminal::{self, ClearType},
+};
+
+use chip8::{
+    chip8::Chip8,
+    disasm,
+    emu::Emulator,
+    frontend::Frontend,
+    instruction::{decode, Instruction},
+    locale::Locale,
+    symbols::Symbols,
+    watch::Watchpoint,
+};
+
+const REFRESH_PERIOD_S: f64 = 1. / 30.;
+
+/// Instructions shown before and after PC
+const DISASM_CONTEXT: u16 = 8;
+
+const MEMORY_ROWS: usize = 8;
+const MEMORY_COLUMNS: usize = 16;
+
+/// How long a written byte stays highlighted in the memory pane
+const RECENT_WRITE_S: f64 = 1.;
+
+const DISASM_COLUMN: u16 = 36;
+
+/// Most snapshots kept for `undo`
+const UNDO_DEPTH: usize = 64;
+
+/// Interactive terminal debugger shown alongside the emulator window
+///
+/// Puts the terminal in raw mode on an alternate screen until dropped.
+pub struct Debugger {
+    out: Stdout,
+    /// First address of the memory pane
+    pub memory_addr: u16,
+    /// Register the memory pane follows, if any
+    memory_follow: Option<Follow>,
+    /// RAM as of the last draw, to find the bytes written since
+    last_ram: Vec<u8>,
+    /// When each recently written byte changed
+    written: HashMap<usize, Instant>,
+    command: String,
+    last_command: String,
+    message: String,
+    last_draw: Option<Instant>,
+    was_paused: bool,
+    /// Machine state before each change made from the console, newest last
+    undo: VecDeque<(String, Chip8)>,
+    /// Names shown for call targets in the stack pane
+    pub symbols: Symbols,
+}
+
+impl Debugger {
+    /// Start with the prompt in `locale`; later messages use the emulator's
+    pub fn new(locale: &Locale) -> anyhow::Result<Self> {
+        let mut out = io::stdout();
+
+        terminal::enable_raw_mode()?;
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        Ok(Debugger {
+            out,
+            memory_addr: 0x200,
+            memory_follow: None,
+            last_ram: Vec::new(),
+            written: HashMap::new(),
+            command: String::new(),
+            last_command: String::new(),
+            message: locale.get("debugger.prompt").to_string(),
+            last_draw: None,
+            was_paused: false,
+            undo: VecDeque::new(),
+            symbols: Symbols::default(),
+        })
+    }
+
+    /// Handle pending terminal input, then redraw if needed
+    ///
+    /// Call after every `Emulator::step`.
+    pub fn update<F: Frontend>(&mut self, emu: &mut Emulator<F>) -> anyhow::Result<()> {
+        let mut redraw = emu.paused != self.was_paused;
+
+        if let (true, false, Some(reason)) = (emu.paused, self.was_paused, &emu.break_reason) {
+            self.message = reason.clone();
+        }
+
+        self.was_paused = emu.paused;
+
+        while event::poll(Duration::from_secs(0))? {
+            if let Event::Key(key) = event::read()? {
+                self.handle_key(key, emu);
+                redraw = true;
+            }
+        }
+
+        let stale = self.last_draw.is_none_or(|t| {
+            !emu.paused && t.elapsed() >= Duration::from_secs_f64(REFRESH_PERIOD_S)
+        });
+
+        if redraw || stale {
+            self.draw(emu)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_key<F: Frontend>(&mut self, key: KeyEvent, emu: &mut Emulator<F>) {
+        if key.kind == KeyEventKind::Release {
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => emu.quit(),
+            KeyCode::Char(c) => self.command.push(c),
+            KeyCode::Backspace => {
+                self.command.pop();
+            }
+            KeyCode::Esc => self.command.clear(),
+            KeyCode::Enter => {
+                // An empty command repeats the previous one
+                let command = match self.command.trim() {
+                    "" => self.last_command.clone(),
+                    command => command.to_string(),
+                };
+
+                self.message = match self.run_command(&command, emu) {
+                    Ok(message) => message,
+                    Err(e) => emu.locale.format("error.prefix", &[("message", &e)]),
+                };
+
+                self.last_command = command;
+                self.command.clear();
+            }
+            _ => {}
+        }
+    }
+
+    fn run_command<F: Frontend>(
+        &mut self,
+        command: &str,
+        emu: &mut Emulator<F>,
+    ) -> anyhow::Result<String> {
+        let mut words = command.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let arg = words.next();
+        let locale = emu.locale.clone();
+        let hex = |addr: u16| format!("{:04X}", addr);
+
+        let addr = || -> anyhow::Result<u16> {
+            let arg =
+                arg.ok_or_else(|| anyhow!(locale.get("debugger.expected_address").to_string()))?;
+            let arg = arg.trim_start_matches("0x").trim_start_matches("0X");
+            Ok(u16::from_str_radix(arg, 16)?)
+        };
+
+        match name {
+            "s" | "step" => {
+                let n = arg.map_or(Ok(1), str::parse)?;
+
+                if !emu.paused {
+                    emu.pause();
+                }
+                emu.step += n;
+
+                Ok(locale.format("debugger.stepping", &[("count", &n)]))
+            }
+            "c" | "continue" => {
+                emu.unpause();
+                Ok(locale.get("debugger.running").into())
+            }
+            "p" | "pause" => {
+                emu.pause();
+                Ok(locale.get("debugger.paused").into())
+            }
+            "b" | "break" => {
+                let addr = addr()?;
+
+                if emu.cpu.breakpoints.insert(addr) {
+                    Ok(locale.format("debugger.breakpoint_set", &[("addr", &hex(addr))]))
+                } else {
+                    emu.cpu.breakpoints.remove(&addr);
+                    Ok(locale.format("debugger.breakpoint_removed", &[("addr", &hex(addr))]))
+                }
+            }
+            "d" | "delete" => {
+                if arg.is_none() {
+                    emu.cpu.breakpoints.clear();
+                    Ok(locale.get("debugger.breakpoints_cleared").into())
+                } else if emu.cpu.breakpoints.remove(&addr()?) {
+                    Ok(locale.format("debugger.breakpoint_removed", &[("addr", &hex(addr()?))]))
+                } else {
+                    Err(anyhow!(locale.format(
+                        "debugger.no_breakpoint",
+                        &[("addr", &hex(addr()?))]
+                    )))
+                }
+            }
+            "w" | "watch" => {
+                let watchpoint: Watchpoint = arg
+                    .ok_or_else(|| anyhow!(locale.get("debugger.expected_range").to_string()))?
+                    .parse()?;
+
+                emu.cpu.watchpoints.add(watchpoint);
+                Ok(locale.format("debugger.watching", &[("watchpoint", &watchpoint)]))
+            }
+            "u" | "unwatch" => match arg {
+                None => {
+                    emu.cpu.watchpoints.clear();
+                    Ok(locale.get("debugger.watchpoints_cleared").into())
+                }
+                Some(n) => {
+                    let watchpoint = emu.cpu.watchpoints.remove(n.parse()?).ok_or_else(|| {
+                        anyhow!(locale.format("debugger.no_watchpoint", &[("index", &n)]))
+                    })?;
+                    Ok(locale.format("debugger.unwatched", &[("watchpoint", &watchpoint)]))
+                }
+            },
+            "m" | "mem" => match arg.map(str::to_ascii_lowercase).as_deref() {
+                Some("i") => {
+                    self.memory_follow = Some(Follow::I);
+                    Ok(locale.format("debugger.memory_follows", &[("register", &"I")]))
+                }
+                Some("pc") => {
+                    self.memory_follow = Some(Follow::Pc);
+                    Ok(locale.format("debugger.memory_follows", &[("register", &"PC")]))
+                }
+                _ => {
+                    self.memory_follow = None;
+                    self.memory_addr = addr()? & 0xff0;
+                    Ok(locale.format("debugger.memory_at", &[("addr", &hex(self.memory_addr))]))
+                }
+            },
+            "poke" => {
+                let addr = addr()?;
+                let bytes = words
+                    .map(|b| u8::from_str_radix(b.trim_start_matches("0x"), 16))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if bytes.is_empty() {
+                    return Err(anyhow!(locale.get("debugger.expected_bytes").to_string()));
+                }
+
+                let end = usize::from(addr) + bytes.len();
+                if end > emu.cpu.ram.len() {
+                    return Err(anyhow!(locale.format(
+                        "debugger.past_memory",
+                        &[("count", &bytes.len()), ("addr", &hex(addr))]
+                    )));
+                }
+
+                self.snapshot(command, &emu.cpu);
+                emu.cpu.ram[usize::from(addr)..end].copy_from_slice(&bytes);
+                emu.cpu.display_dirty = true;
+
+                Ok(locale.format(
+                    "debugger.poked",
+                    &[("count", &bytes.len()), ("addr", &hex(addr))],
+                ))
+            }
+            "set" => {
+                let reg = arg
+                    .ok_or_else(|| anyhow!(locale.get("debugger.expected_register").to_string()))?;
+                let value = words
+                    .next()
+                    .ok_or_else(|| anyhow!(locale.get("debugger.expected_value").to_string()))?;
+                let value = u16::from_str_radix(value.trim_start_matches("0x"), 16)?;
+                let byte = || {
+                    u8::try_from(value).map_err(|_| {
+                        anyhow!(locale.format(
+                            "debugger.byte_register",
+                            &[("register", &reg), ("value", &format!("{:X}", value))]
+                        ))
+                    })
+                };
+
+                let mut cpu = emu.cpu.clone();
+                match reg.to_ascii_uppercase().as_str() {
+                    "I" => cpu.i = value,
+                    "PC" => cpu.pc = value,
+                    "SP" => match byte()? {
+                        sp if usize::from(sp) < cpu.stack.len() => cpu.sp = sp,
+                        sp => {
+                            return Err(anyhow!(locale.format(
+                                "debugger.sp_range",
+                                &[
+                                    ("max", &format!("{:X}", cpu.stack.len() - 1)),
+                                    ("value", &format!("{:X}", sp)),
+                                ]
+                            )))
+                        }
+                    },
+                    "DT" => cpu.dt = byte()?,
+                    "ST" => cpu.st = byte()?,
+                    r => match r.strip_prefix('V').map(|x| usize::from_str_radix(x, 16)) {
+                        Some(Ok(x)) if x < cpu.v.len() => cpu.v[x] = byte()?,
+                        _ => {
+                            return Err(anyhow!(
+                                locale.format("debugger.unknown_register", &[("register", &reg)])
+                            ))
+                        }
+                    },
+                }
+
+                let previous = std::mem::replace(&mut emu.cpu, cpu);
+                self.snapshot(command, &previous);
+
+                Ok(format!("{} = {:X}", reg.to_ascii_uppercase(), value))
+            }
+            "undo" => {
+                let (command, snapshot) = self
+                    .undo
+                    .pop_back()
+                    .ok_or_else(|| anyhow!(locale.get("debugger.nothing_to_undo").to_string()))?;
+
+                emu.cpu.restore(&snapshot);
+
+                Ok(locale.format(
+                    "debugger.undid",
+                    &[("command", &command), ("count", &self.undo.len())],
+                ))
+            }
+            "r" | "reset" => {
+                self.snapshot(command, &emu.cpu);
+                emu.reset()?;
+                Ok(locale.get("debugger.reset").into())
+            }
+            "q" | "quit" => {
+                emu.quit();
+                Ok(locale.get("debugger.quitting").into())
+            }
+            "" => Ok(String::new()),
+            _ => Err(anyhow!(
+                locale.format("debugger.unknown_command", &[("command", &name)])
+            )),
+        }
+    }
+
+    /// Keep the state before `command` changes it, so `undo` can restore it
+    fn snapshot(&mut self, command: &str, cpu: &Chip8) {
+        if self.undo.len() >= UNDO_DEPTH {
+            self.undo.pop_front();
+        }
+
+        self.undo.push_back((command.to_string(), cpu.clone()));
+    }
+
+    /// Note the bytes that changed since the last draw, and forget old writes
+    fn track_writes(&mut self, ram: &[u8]) {
+        let now = Instant::now();
+
+        if self.last_ram.len() == ram.len() {
+            for (addr, (old, new)) in self.last_ram.iter().zip(ram).enumerate() {
+                if old != new {
+                    self.written.insert(addr, now);
+                }
+            }
+        }
+
+        self.written
+            .retain(|_, t| now.duration_since(*t).as_secs_f64() < RECENT_WRITE_S);
+        self.last_ram = ram.to_vec();
+    }
+
+    fn draw<F: Frontend>(&mut self, emu: &Emulator<F>) -> anyhow::Result<()> {
+        let cpu = &emu.cpu;
+        let locale = &emu.locale;
+
+        self.track_writes(&cpu.ram);
+
+        let follow = match self.memory_follow {
+            Some(Follow::I) => Some(cpu.i),
+            Some(Follow::Pc) => Some(cpu.pc),
+            None => None,
+        };
+        if let Some(addr) = follow {
+            let last_row = cpu.ram.len() - MEMORY_ROWS * MEMORY_COLUMNS;
+            self.memory_addr = (usize::from(addr & 0xff0)).min(last_row) as u16;
+        }
+
+        // Registers
+        let mut left = vec![
+            format!("PC {:04X}   I {:04X}   SP {:X}", cpu.pc, cpu.i, cpu.sp),
+            format!("DT {:02X}     ST {:02X}", cpu.dt, cpu.st),
+            String::new(),
+        ];
+
+        left.extend(cpu.v.chunks(4).enumerate().map(|(row, regs)| {
+            regs.iter()
+                .enumerate()
+                .map(|(i, v)| format!("V{:X} {:02X}", row * 4 + i, v))
+                .collect::<Vec<_>>()
+                .join("  ")
+        }));
+
+        left.push(String::new());
+        left.push(locale.get("debugger.calls").to_string());
+        // Newest first, each as the CALL and the subroutine it entered
+        left.extend(cpu.call_stack().iter().rev().map(|&addr| {
+            match decode(cpu.mem_read_opcode(addr)) {
+                Instruction::Call(target) => match self.symbols.get(target) {
+                    Some(name) => format!("  {:04X} -> {:04X} {}", addr, target, name),
+                    None => format!("  {:04X} -> {:04X}", addr, target),
+                },
+                _ => format!("  {:04X} {}", addr, locale.get("debugger.not_call")),
+            }
+        }));
+
+        // Disassembly around PC
+        let start = cpu.pc.saturating_sub(DISASM_CONTEXT * 2);
+        let right: Vec<String> = (0..=DISASM_CONTEXT * 2)
+            .map(|i| start + i * 2)
+            .filter(|addr| usize::from(*addr) + 1 < cpu.ram.len())
+            .map(|addr| {
+                let (hi, lo) = (cpu.ram[usize::from(addr)], cpu.ram[usize::from(addr) + 1]);
+                let marker = match (addr == cpu.pc, cpu.breakpoints.contains(&addr)) {
+                    (true, true) => "*>",
+                    (true, false) => " >",
+                    (false, true) => "* ",
+                    (false, false) => "  ",
+                };
+
+                format!(
+                    "{} {:04X}  {:02X}{:02X}  {}",
+                    marker,
+                    addr,
+                    hi,
+                    lo,
+                    disasm::mnemonic(hi, lo)
+                )
+            })
+            .collect();
+
+        let out = &mut self.out;
+        queue!(out, terminal::Clear(ClearType::All))?;
+
+        for (row, line) in left.iter().enumerate() {
+            queue!(out, cursor::MoveTo(0, row as u16), Print(line))?;
+        }
+
+        for (row, line) in right.iter().enumerate() {
+            queue!(out, cursor::MoveTo(DISASM_COLUMN, row as u16), Print(line))?;
+        }
+
+        // Memory
+        let mut row = left.len().max(right.len()) as u16 + 1;
+
+        queue!(
+            out,
+            cursor::MoveTo(0, row),
+            Print(format!("{}  ", locale.get("debugger.memory"))),
+            PrintStyledContent("PC".reverse()),
+            Print("  "),
+            PrintStyledContent("I".underlined()),
+            Print("  "),
+            PrintStyledContent(locale.get("debugger.written").yellow()),
+        )?;
+        row += 1;
+
+        for r in 0..MEMORY_ROWS {
+            let addr = usize::from(self.memory_addr) + r * MEMORY_COLUMNS;
+
+            if addr + MEMORY_COLUMNS > cpu.ram.len() {
+                break;
+            }
+
+            queue!(out, cursor::MoveTo(0, row), Print(format!("{:04X}:", addr)))?;
+
+            for (a, byte) in (addr..addr + MEMORY_COLUMNS).zip(&cpu.ram[addr..]) {
+                let mut text = format!("{:02X}", byte).stylize();
+
+                if a == usize::from(cpu.pc) || a == usize::from(cpu.pc) + 1 {
+                    text = text.reverse();
+                }
+                if a == usize::from(cpu.i) {
+                    text = text.underlined();
+                }
+                if self.written.contains_key(&a) {
+                    text = text.yellow();
+                }
+
+                queue!(out, Print(" "), PrintStyledContent(text))?;
+            }
+
+            row += 1;
+        }
+
+        let state = match emu.paused {
+            true => locale.get("debugger.state_paused"),
+            false => locale.get("debugger.state_running"),
+        };
+        let breakpoints = cpu
+            .breakpoints
+            .iter()
+            .map(|addr| format!("{:04X}", addr))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let watchpoints = cpu
+            .watchpoints
+            .list()
+            .iter()
+            .enumerate()
+            .map(|(i, w)| format!("#{} {}", i, w))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        queue!(
+            out,
+            cursor::MoveTo(0, row + 1),
+            Print(locale.format(
+                "debugger.points",
+                &[
+                    ("state", &state),
+                    ("breakpoints", &breakpoints),
+                    ("watchpoints", &watchpoints),
+                ]
+            )),
+            cursor::MoveTo(0, row + 2),
+            Print(&self.message),
+            cursor::MoveTo(0, row + 3),
+            Print(locale.get("debugger.help")),
+            cursor::MoveTo(0, row + 4),
+            Print(format!("> {}", self.command)),
+        )?;
+
+        out.flush()?;
+        self.last_draw = Some(Instant::now());
+
+        Ok(())
+    }
+}
+
+/// Register whose address the memory pane keeps in view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Follow {
+    I,
+    Pc,
+}
+
+impl Drop for Debugger {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
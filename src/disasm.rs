@@ -1,61 +1,556 @@
-use crate::chip8::split_opcode2;
+use alloc::{collections::BTreeSet, format, string::String, vec, vec::Vec};
+
+use crate::instruction::{decode, Instruction as Op};
 
 // Hex bytes
 
+/// Listing of `rom`, loaded and entered at `offset`, one instruction per line
+///
+/// Bytes no path from `offset` reaches are shown as data (`DB F0, 90`) rather
+/// than decoded, and JP, CALL, and `LD I` targets get labels (`L0234:`) that
+/// the instructions refer to. See `analyze`.
 pub fn disassemble(rom: &[u8], offset: u16) -> Vec<String> {
-    rom.chunks(2)
-        .enumerate()
-        .map(|(i, opcode)| {
-            let op_hex = opcode
-                .iter()
-                .map(|b| format!("{:02X}", b))
-                .collect::<Vec<_>>()
-                .join(" ");
-
-            let mnemonic = if opcode.len() == 2 {
-                match split_opcode2(opcode[0], opcode[1]) {
-                    (0x0, 0x0, 0xE, 0x0) => format!("CLS"),
-                    (0x0, 0x0, 0xE, 0xE) => format!("RET"),
-                    (0x0, x, y, z) => format!("SYS {:X}{:X}{:X}", x, y, z),
-                    (0x1, x, y, z) => format!("JP {:X}{:X}{:X}", x, y, z),
-                    (0x2, x, y, z) => format!("CALL {:X}{:X}{:X}", x, y, z),
-                    (0x3, x, y, z) => format!("SE V{:X}, {:X}{:X}", x, y, z),
-                    (0x4, x, y, z) => format!("SNE V{:X}, {:X}{:X}", x, y, z),
-                    (0x5, x, y, 0x0) => format!("SE V{:X}, V{:X}", x, y),
-                    (0x6, x, y, z) => format!("LD V{:X}, {:X}{:X}", x, y, z),
-                    (0x7, x, y, z) => format!("ADD V{:X}, {:X}{:X}", x, y, z),
-                    (0x8, x, y, 0x0) => format!("LD V{:X}, V{:X}", x, y),
-                    (0x8, x, y, 0x1) => format!("OR V{:X}, V{:X}", x, y),
-                    (0x8, x, y, 0x2) => format!("AND V{:X}, V{:X}", x, y),
-                    (0x8, x, y, 0x3) => format!("XOR V{:X}, V{:X}", x, y),
-                    (0x8, x, y, 0x4) => format!("ADD V{:X}, V{:X}", x, y),
-                    (0x8, x, y, 0x5) => format!("SUB V{:X}, V{:X}", x, y),
-                    (0x8, x, y, 0x6) => format!("SHR V{:X} {{, V{:X}}}", x, y),
-                    (0x8, x, y, 0x7) => format!("SUBN V{:X}, V{:X}", x, y),
-                    (0x8, x, y, 0xE) => format!("SHL V{:X} {{, V{:X}}}", x, y),
-                    (0x9, x, y, 0x0) => format!("SNE V{:X}, V{:X}", x, y),
-                    (0xA, x, y, z) => format!("LD I, {:X}{:X}{:X}", x, y, z),
-                    (0xB, x, y, z) => format!("JP V0, {:X}{:X}{:X}", x, y, z),
-                    (0xC, x, y, z) => format!("RND V{:X}, {:X}{:X}", x, y, z),
-                    (0xD, x, y, z) => format!("DRW V{:X}, V{:X}, {:X}", x, y, z),
-                    (0xE, x, 0x9, 0xE) => format!("SKP V{:X}", x),
-                    (0xE, x, 0xA, 0x1) => format!("SKNP V{:X}", x),
-                    (0xF, x, 0x0, 0x7) => format!("LD V{:X}, DT", x),
-                    (0xF, x, 0x0, 0xA) => format!("LD V{:X}, K", x),
-                    (0xF, x, 0x1, 0x5) => format!("LD DT, V{:X}", x),
-                    (0xF, x, 0x1, 0x8) => format!("LD ST, V{:X}", x),
-                    (0xF, x, 0x1, 0xE) => format!("ADD I, V{:X}", x),
-                    (0xF, x, 0x2, 0x9) => format!("LD F, V{:X}", x),
-                    (0xF, x, 0x3, 0x3) => format!("LD B, V{:X}", x),
-                    (0xF, x, 0x5, 0x5) => format!("LD [I], V{:X}", x),
-                    (0xF, x, 0x6, 0x5) => format!("LD V{:X}, [I]", x),
-                    _ => format!(""),
-                }
-            } else {
-                String::from("")
+    listing(rom, offset, Syntax::Mnemonic)
+}
+
+/// Like `disassemble`, with instructions in Octo syntax
+pub fn disassemble_octo(rom: &[u8], offset: u16) -> Vec<String> {
+    listing(rom, offset, Syntax::Octo)
+}
+
+/// Like `disassemble`, decoding code and data as `marks` says where set
+pub fn disassemble_marked(rom: &[u8], offset: u16, marks: &Marks) -> Vec<String> {
+    listing_marked(rom, offset, marks, Syntax::Mnemonic)
+}
+
+/// Like `disassemble_octo`, decoding code and data as `marks` says where set
+pub fn disassemble_octo_marked(rom: &[u8], offset: u16, marks: &Marks) -> Vec<String> {
+    listing_marked(rom, offset, marks, Syntax::Octo)
+}
+
+/// What a marked stretch of a ROM holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkKind {
+    Code,
+    Data,
+}
+
+impl MarkKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            MarkKind::Code => "code",
+            MarkKind::Data => "data",
+        }
+    }
+}
+
+/// Addresses `start..end` marked as code or data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mark {
+    pub start: u16,
+    pub end: u16,
+    pub kind: MarkKind,
+}
+
+/// Regions marked by hand as code or data, overriding what `analyze` finds
+///
+/// Code marks are decoded from their start two bytes at a time and followed
+/// from there, which reaches code only used through `JP V0` tables. Data
+/// marks are never decoded, even when a path leads into them.
+///
+/// The text form, as `chip8 hexview` saves it, has one mark per line with
+/// hex addresses and an exclusive end, like watchpoints:
+///
+/// ```text
+/// code 0200..0248
+/// data 0248..0260
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Marks {
+    /// Non-overlapping, sorted by address
+    marks: Vec<Mark>,
+}
+
+impl Marks {
+    pub fn iter(&self) -> impl Iterator<Item = &Mark> {
+        self.marks.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.marks.is_empty()
+    }
+
+    /// How `addr` is marked, if at all
+    pub fn kind(&self, addr: u16) -> Option<MarkKind> {
+        self.marks
+            .iter()
+            .find(|m| (m.start..m.end).contains(&addr))
+            .map(|m| m.kind)
+    }
+
+    /// Mark `start..end` as `kind`, or clear it with `None`, replacing
+    /// whatever it overlaps; neighbors of the same kind are merged
+    pub fn set(&mut self, start: u16, end: u16, kind: Option<MarkKind>) {
+        if start >= end {
+            return;
+        }
+
+        let mut marks = Vec::new();
+
+        for m in self.marks.drain(..) {
+            if m.start < start {
+                marks.push(Mark {
+                    end: m.end.min(start),
+                    ..m
+                });
+            }
+            if m.end > end {
+                marks.push(Mark {
+                    start: m.start.max(end),
+                    ..m
+                });
+            }
+        }
+
+        if let Some(kind) = kind {
+            marks.push(Mark { start, end, kind });
+        }
+
+        marks.sort_by_key(|m| m.start);
+
+        for m in marks {
+            match self.marks.last_mut() {
+                Some(last) if last.end == m.start && last.kind == m.kind => last.end = m.end,
+                _ => self.marks.push(m),
+            }
+        }
+    }
+
+    /// Read the text form; `#` starts a comment
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut marks = Marks::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = |message: String| format!("line {}: {}", i + 1, message);
+            let addr = |a: &str| {
+                u16::from_str_radix(a.trim().trim_start_matches("0x"), 16)
+                    .map_err(|_| error(format!("invalid address '{}'", a)))
             };
 
-            format!("{:04X}: {}  {}", i + usize::from(offset), op_hex, mnemonic)
-        })
-        .collect()
+            let (kind, range) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let kind = match kind {
+                "code" => MarkKind::Code,
+                "data" => MarkKind::Data,
+                _ => return Err(error(format!("expected code or data, got '{}'", kind))),
+            };
+            let (start, end) = range.split_once("..").ok_or_else(|| {
+                error(format!("expected a range like 0200..0248, got '{}'", range))
+            })?;
+            let (start, end) = (addr(start)?, addr(end)?);
+
+            if start >= end {
+                return Err(error(format!("empty range {:04X}..{:04X}", start, end)));
+            }
+
+            marks.set(start, end, Some(kind));
+        }
+
+        Ok(marks)
+    }
+
+    pub fn to_text(&self) -> String {
+        self.marks
+            .iter()
+            .map(|m| format!("{} {:04X}..{:04X}\n", m.kind.name(), m.start, m.end))
+            .collect()
+    }
+}
+
+/// Control flow found by following every path from the entry point
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Analysis {
+    /// Addresses of reachable instructions
+    pub reachable: BTreeSet<u16>,
+    /// JP, CALL, `JP V0`, and `LD I` targets within the ROM
+    pub labels: BTreeSet<u16>,
+}
+
+/// Follow execution from `offset`, where `rom` is loaded
+///
+/// Paths end at RET, SYS, and unknown opcodes, and at `JP V0, nnn`, which
+/// depends on V0 at run time; its base address is still labeled. Code only
+/// reached through a `JP V0` table is therefore reported as unreachable.
+/// Everything not reachable is taken to be data, such as sprites.
+pub fn analyze(rom: &[u8], offset: u16) -> Analysis {
+    analyze_marked(rom, offset, &Marks::default())
+}
+
+/// Like `analyze`, also starting from every instruction in code marks and
+/// never entering data marks
+pub fn analyze_marked(rom: &[u8], offset: u16, marks: &Marks) -> Analysis {
+    let start = usize::from(offset);
+    let end = start + rom.len();
+    let in_rom = |addr: u16| (start..end).contains(&usize::from(addr));
+    let is_data = |addr: u16| marks.kind(addr) == Some(MarkKind::Data);
+
+    let mut analysis = Analysis::default();
+    let mut pending = vec![offset];
+
+    for m in marks.iter().filter(|m| m.kind == MarkKind::Code) {
+        pending.extend((m.start..m.end).step_by(2));
+    }
+
+    while let Some(addr) = pending.pop() {
+        let i = usize::from(addr);
+        if i < start
+            || i + 2 > end
+            || is_data(addr)
+            || is_data(addr.wrapping_add(1))
+            || !analysis.reachable.insert(addr)
+        {
+            continue;
+        }
+
+        let (hi, lo) = (rom[i - start], rom[i - start + 1]);
+        let op = decode(u16::from_be_bytes([hi, lo]));
+        let next = addr.wrapping_add(2);
+
+        if mnemonic(hi, lo).is_empty() {
+            continue;
+        }
+
+        if let Some(target) = op.target().filter(|t| in_rom(*t)) {
+            analysis.labels.insert(target);
+        }
+
+        match op {
+            Op::Cls => pending.push(next),
+            Op::Jp(nnn) => pending.push(nnn),
+            Op::Call(nnn) => pending.extend([nnn, next]),
+            Op::JpV0(..) => {}
+            _ if hi >> 4 == 0x0 => {}
+            _ if op.is_skip() => pending.extend([next, next.wrapping_add(2)]),
+            _ => pending.push(next),
+        }
+    }
+
+    analysis
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    Mnemonic,
+    Octo,
+}
+
+impl Syntax {
+    fn instruction(self, hi: u8, lo: u8) -> String {
+        match self {
+            Syntax::Mnemonic => mnemonic(hi, lo),
+            Syntax::Octo => octo_mnemonic(hi, lo),
+        }
+    }
+
+    fn label(self, addr: u16) -> String {
+        match self {
+            Syntax::Mnemonic => format!("{}:", label_name(addr)),
+            Syntax::Octo => format!(": {}", label_name(addr)),
+        }
+    }
+
+    fn data(self, bytes: &[u8]) -> String {
+        let hex: Vec<String> = match self {
+            Syntax::Mnemonic => bytes.iter().map(|b| format!("{:02X}", b)).collect(),
+            Syntax::Octo => bytes.iter().map(|b| format!("0x{:02X}", b)).collect(),
+        };
+
+        match self {
+            Syntax::Mnemonic => format!("DB {}", hex.join(", ")),
+            Syntax::Octo => hex.join(" "),
+        }
+    }
+}
+
+fn label_name(addr: u16) -> String {
+    format!("L{:04X}", addr)
+}
+
+/// Most data bytes shown on one line
+const DATA_PER_LINE: usize = 8;
+
+/// One line of a disassembly: an instruction, or a run of data bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub addr: u16,
+    /// The opcode, or `None` for data
+    pub opcode: Option<u16>,
+    /// The two opcode bytes, or the data
+    pub bytes: Vec<u8>,
+    /// `LD`, `DB` for data, or empty for an unknown opcode
+    pub mnemonic: String,
+    /// Operands as in the listing, e.g. `["V0", "05"]`, with labeled targets
+    /// written as their label
+    pub operands: Vec<String>,
+    /// Address a JP, CALL, `JP V0`, or `LD I` refers to
+    pub target: Option<u16>,
+    /// Label defined at `addr`, e.g. `L0234`
+    pub label: Option<String>,
+}
+
+impl Instruction {
+    /// JSON object with the same fields; addresses, opcodes, and bytes as numbers
+    pub fn to_json(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "null".into());
+        let strings = |items: &[String]| {
+            let quoted: Vec<String> = items.iter().map(|s| json_string(s)).collect();
+            format!("[{}]", quoted.join(","))
+        };
+        let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{}", b)).collect();
+
+        format!(
+            "{{\"addr\":{},\"opcode\":{},\"bytes\":[{}],\"mnemonic\":{},\"operands\":{},\"target\":{},\"label\":{}}}",
+            self.addr,
+            optional(self.opcode.map(|op| format!("{}", op))),
+            bytes.join(","),
+            json_string(&self.mnemonic),
+            strings(&self.operands),
+            optional(self.target.map(|addr| format!("{}", addr))),
+            optional(self.label.as_deref().map(json_string)),
+        )
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+/// Decoded `rom`, loaded and entered at `offset`, as `disassemble` lists it
+pub fn instructions(rom: &[u8], offset: u16) -> Vec<Instruction> {
+    instructions_marked(rom, offset, &Marks::default())
+}
+
+/// Like `instructions`, decoding code and data as `marks` says where set
+pub fn instructions_marked(rom: &[u8], offset: u16, marks: &Marks) -> Vec<Instruction> {
+    let analysis = analyze_marked(rom, offset, marks);
+    let mut instructions = Vec::new();
+    let mut i = 0;
+
+    while i < rom.len() {
+        let addr = offset.wrapping_add(i as u16);
+        let label = analysis.labels.contains(&addr).then(|| label_name(addr));
+
+        let instruction = match &rom[i..rom.len().min(i + 2)] {
+            [hi, lo] if analysis.reachable.contains(&addr) => {
+                let opcode = u16::from_be_bytes([*hi, *lo]);
+                let target = decode(opcode).target();
+
+                let text = mnemonic(*hi, *lo);
+                let (name, operands) = text.split_once(' ').unwrap_or((&text, ""));
+                let mut operands: Vec<String> = operands
+                    .split(',')
+                    .map(|op| op.trim_matches(|c: char| c.is_whitespace() || c == '{' || c == '}'))
+                    .filter(|op| !op.is_empty())
+                    .map(String::from)
+                    .collect();
+
+                // Target addresses are the last operand
+                if let Some(target) = target.filter(|t| analysis.labels.contains(t)) {
+                    if let Some(last) = operands.last_mut() {
+                        *last = label_name(target);
+                    }
+                }
+
+                Instruction {
+                    addr,
+                    opcode: Some(opcode),
+                    bytes: vec![*hi, *lo],
+                    mnemonic: name.into(),
+                    operands,
+                    target,
+                    label,
+                }
+            }
+            _ => {
+                // Data runs up to the next instruction or label
+                let len = (1..DATA_PER_LINE.min(rom.len() - i))
+                    .find(|n| {
+                        let next = addr.wrapping_add(*n as u16);
+                        analysis.reachable.contains(&next) || analysis.labels.contains(&next)
+                    })
+                    .unwrap_or(DATA_PER_LINE.min(rom.len() - i));
+                let bytes = rom[i..i + len].to_vec();
+
+                Instruction {
+                    addr,
+                    opcode: None,
+                    mnemonic: "DB".into(),
+                    operands: bytes.iter().map(|b| format!("{:02X}", b)).collect(),
+                    bytes,
+                    target: None,
+                    label,
+                }
+            }
+        };
+
+        i += instruction.bytes.len();
+        instructions.push(instruction);
+    }
+
+    instructions
+}
+
+fn listing(rom: &[u8], offset: u16, syntax: Syntax) -> Vec<String> {
+    listing_marked(rom, offset, &Marks::default(), syntax)
+}
+
+fn listing_marked(rom: &[u8], offset: u16, marks: &Marks, syntax: Syntax) -> Vec<String> {
+    let instructions = instructions_marked(rom, offset, marks);
+    let labels: BTreeSet<u16> = instructions
+        .iter()
+        .filter(|ins| ins.label.is_some())
+        .map(|ins| ins.addr)
+        .collect();
+
+    let mut lines = Vec::new();
+
+    for ins in instructions.iter() {
+        if ins.label.is_some() {
+            lines.push(syntax.label(ins.addr));
+        }
+
+        let text = match ins.opcode {
+            None => syntax.data(&ins.bytes),
+            Some(_) => {
+                let mut text = syntax.instruction(ins.bytes[0], ins.bytes[1]);
+
+                // Target addresses are the last operand in either syntax
+                if let Some(target) = ins.target.filter(|t| labels.contains(t)) {
+                    if let Some((head, _)) = text.rsplit_once(' ') {
+                        text = format!("{} {}", head, label_name(target));
+                    }
+                }
+
+                text
+            }
+        };
+
+        let op_hex = ins
+            .bytes
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        lines.push(format!("{:04X}: {}  {}", ins.addr, op_hex, text));
+    }
+
+    lines
+}
+
+/// Mnemonic for a single instruction, empty if the opcode is unknown
+///
+/// Only CHIP-8 instructions are decoded; other 0nnn opcodes are listed as SYS.
+pub fn mnemonic(hi: u8, lo: u8) -> String {
+    let opcode = u16::from_be_bytes([hi, lo]);
+
+    match decode(opcode) {
+        Op::Cls => "CLS".into(),
+        Op::Ret => "RET".into(),
+        _ if hi >> 4 == 0x0 => format!("SYS {:03X}", opcode & 0x0FFF),
+        Op::Jp(nnn) => format!("JP {:03X}", nnn),
+        Op::Call(nnn) => format!("CALL {:03X}", nnn),
+        Op::SeByte(x, kk) => format!("SE V{:X}, {:02X}", x, kk),
+        Op::SneByte(x, kk) => format!("SNE V{:X}, {:02X}", x, kk),
+        Op::SeReg(x, y) => format!("SE V{:X}, V{:X}", x, y),
+        Op::LdByte(x, kk) => format!("LD V{:X}, {:02X}", x, kk),
+        Op::AddByte(x, kk) => format!("ADD V{:X}, {:02X}", x, kk),
+        Op::LdReg(x, y) => format!("LD V{:X}, V{:X}", x, y),
+        Op::Or(x, y) => format!("OR V{:X}, V{:X}", x, y),
+        Op::And(x, y) => format!("AND V{:X}, V{:X}", x, y),
+        Op::Xor(x, y) => format!("XOR V{:X}, V{:X}", x, y),
+        Op::AddReg(x, y) => format!("ADD V{:X}, V{:X}", x, y),
+        Op::Sub(x, y) => format!("SUB V{:X}, V{:X}", x, y),
+        Op::Shr(x, y) => format!("SHR V{:X} {{, V{:X}}}", x, y),
+        Op::Subn(x, y) => format!("SUBN V{:X}, V{:X}", x, y),
+        Op::Shl(x, y) => format!("SHL V{:X} {{, V{:X}}}", x, y),
+        Op::SneReg(x, y) => format!("SNE V{:X}, V{:X}", x, y),
+        Op::LdI(nnn) => format!("LD I, {:03X}", nnn),
+        Op::JpV0(_, nnn) => format!("JP V0, {:03X}", nnn),
+        Op::Rnd(x, kk) => format!("RND V{:X}, {:02X}", x, kk),
+        Op::Drw(x, y, n) => format!("DRW V{:X}, V{:X}, {:X}", x, y, n),
+        Op::Skp(x) => format!("SKP V{:X}", x),
+        Op::Sknp(x) => format!("SKNP V{:X}", x),
+        Op::LdVxDt(x) => format!("LD V{:X}, DT", x),
+        Op::LdVxK(x) => format!("LD V{:X}, K", x),
+        Op::LdDtVx(x) => format!("LD DT, V{:X}", x),
+        Op::LdStVx(x) => format!("LD ST, V{:X}", x),
+        Op::AddI(x) => format!("ADD I, V{:X}", x),
+        Op::LdF(x) => format!("LD F, V{:X}", x),
+        Op::LdB(x) => format!("LD B, V{:X}", x),
+        Op::Store(x) => format!("LD [I], V{:X}", x),
+        Op::Load(x) => format!("LD V{:X}, [I]", x),
+        _ => String::new(),
+    }
+}
+
+/// Instruction in Octo syntax, empty if Octo has no form for the opcode
+///
+/// Skips are written as the `if ... then` that assembles to them, so
+/// `SE V0, 05` is `if v0 != 0x05 then`: the next instruction runs when the
+/// comparison holds.
+pub fn octo_mnemonic(hi: u8, lo: u8) -> String {
+    match decode(u16::from_be_bytes([hi, lo])) {
+        Op::Cls => "clear".into(),
+        Op::Ret => "return".into(),
+        Op::Jp(nnn) => format!("jump 0x{:03X}", nnn),
+        Op::Call(nnn) => format!(":call 0x{:03X}", nnn),
+        Op::SeByte(x, kk) => format!("if v{:x} != 0x{:02X} then", x, kk),
+        Op::SneByte(x, kk) => format!("if v{:x} == 0x{:02X} then", x, kk),
+        Op::SeReg(x, y) => format!("if v{:x} != v{:x} then", x, y),
+        Op::LdByte(x, kk) => format!("v{:x} := 0x{:02X}", x, kk),
+        Op::AddByte(x, kk) => format!("v{:x} += 0x{:02X}", x, kk),
+        Op::LdReg(x, y) => format!("v{:x} := v{:x}", x, y),
+        Op::Or(x, y) => format!("v{:x} |= v{:x}", x, y),
+        Op::And(x, y) => format!("v{:x} &= v{:x}", x, y),
+        Op::Xor(x, y) => format!("v{:x} ^= v{:x}", x, y),
+        Op::AddReg(x, y) => format!("v{:x} += v{:x}", x, y),
+        Op::Sub(x, y) => format!("v{:x} -= v{:x}", x, y),
+        Op::Shr(x, y) => format!("v{:x} >>= v{:x}", x, y),
+        Op::Subn(x, y) => format!("v{:x} =- v{:x}", x, y),
+        Op::Shl(x, y) => format!("v{:x} <<= v{:x}", x, y),
+        Op::SneReg(x, y) => format!("if v{:x} == v{:x} then", x, y),
+        Op::LdI(nnn) => format!("i := 0x{:03X}", nnn),
+        Op::JpV0(_, nnn) => format!("jump0 0x{:03X}", nnn),
+        Op::Rnd(x, kk) => format!("v{:x} := random 0x{:02X}", x, kk),
+        Op::Drw(x, y, n) => format!("sprite v{:x} v{:x} {}", x, y, n),
+        Op::Skp(x) => format!("if v{:x} -key then", x),
+        Op::Sknp(x) => format!("if v{:x} key then", x),
+        Op::LdVxDt(x) => format!("v{:x} := delay", x),
+        Op::LdVxK(x) => format!("v{:x} := key", x),
+        Op::LdDtVx(x) => format!("delay := v{:x}", x),
+        Op::LdStVx(x) => format!("buzzer := v{:x}", x),
+        Op::AddI(x) => format!("i += v{:x}", x),
+        Op::LdF(x) => format!("i := hex v{:x}", x),
+        Op::LdB(x) => format!("bcd v{:x}", x),
+        Op::Store(x) => format!("save v{:x}", x),
+        Op::Load(x) => format!("load v{:x}", x),
+        _ => String::new(),
+    }
 }
@@ -1,70 +1,498 @@
 use std::{
     collections::HashMap,
+    fs, mem,
+    path::PathBuf,
+    str::FromStr,
     thread,
-    time::{Duration, SystemTime},
+    time::{Duration, Instant, SystemTime},
 };
 
+#[cfg(feature = "plugins")]
+use std::rc::Rc;
+
+#[cfg(feature = "gamepad")]
+use crate::gamepad::Gamepad;
+#[cfg(feature = "plugins")]
+use crate::plugin::Plugin;
+#[cfg(feature = "scripting")]
+use crate::script::{Hook, Script, ScriptRequest};
 use crate::{
-    chip8::{self, Chip8},
-    palette,
+    cheat::Cheats,
+    chip8::{self, plane_color, AudioPattern, Chip8, StepOutcome},
+    filter::Pipeline,
+    frontend::{Beeper, Display, Frontend, Input, Split},
+    keypad,
+    keys::{Action, Binding, Hotkey, Modifiers},
+    kiosk::Kiosk,
+    locale::Locale,
+    menu::{MenuItem, MenuPress, PauseMenu},
+    movie::{BuzzerEvent, Movie},
+    overlay, palette, png,
+    practice::Practice,
+    profile::CallProfile,
+    rewind::Rewind,
+    savestate,
+    schedule::KeySchedule,
+    stats::{RateMeter, Stats},
+    storage,
+    throttle::{RingBuffer, Throttles},
+    trigger::Triggers,
     window::{self, WindowHandle},
 };
-use minifb::Key;
+use anyhow::{anyhow, Context};
+use minifb::{Key, Scale};
+use serde::Deserialize;
 
-const TITLE: &str = "Rust CHIP-8 Emulator";
+pub const TITLE: &str = "Rust CHIP-8 Emulator";
 
 const DEFAULT_COLOR_ON: u32 = u32::MAX;
 const DEFAULT_COLOR_OFF: u32 = 0;
 
+pub const DEFAULT_WINDOW_SCALE: Scale = Scale::X16;
+
 const DEFAULT_CLOCK_PERIOD_S: f64 = 1. / 1000.;
 const DEFAULT_TIMER_PERIOD_S: f64 = 1. / 60.;
 
-pub struct Emulator {
+/// Rewind history: one snapshot every 16 steps, 600 snapshots (~10 s at the default clock)
+const REWIND_CAPACITY: usize = 600;
+const REWIND_INTERVAL: u32 = 16;
+
+/// Most time the clock and timers catch up on in one step, e.g. after the
+/// window was dragged; the rest is dropped
+const MAX_CLOCK_LAG_S: f64 = 1. / 10.;
+
+/// Shortest wait for the clock, so a fast one runs in batches rather than
+/// waking for every instruction
+const MIN_CLOCK_SLEEP: Duration = Duration::from_millis(1);
+
+/// Seconds between overlay redraws while the display is unchanged
+const OVERLAY_PERIOD_S: f64 = 1. / 30.;
+
+/// Instructions per timer tick matching the default clock and timer rates
+pub const DEFAULT_INSTRUCTIONS_PER_TICK: u32 = 16;
+
+/// Something that happened while running, passed to `Emulator::on_event`
+/// callbacks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// A frame was presented to the frontend
+    FramePresented,
+    /// The delay and sound timers ticked, 60 times a second of emulated time
+    TimerTick,
+    /// The sound timer was set, starting the buzzer
+    SoundStarted,
+    /// The sound timer ran out
+    SoundStopped,
+    /// The program crashed; the emulator is paused, or reset in kiosk mode
+    Crashed(chip8::Chip8Panic),
+}
+
+/// What the delay and sound timers do while the emulator is paused, including
+/// after a crash
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PausedTimers {
+    /// Stop with the program, so stepping through it sees them as it would
+    /// running
+    #[default]
+    Freeze,
+    /// Count down in real time, as on hardware whose timers run on their own
+    Run,
+}
+
+impl FromStr for PausedTimers {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "freeze" => Ok(PausedTimers::Freeze),
+            "run" => Ok(PausedTimers::Run),
+            _ => Err(anyhow!(
+                "unknown timer policy '{}', expected freeze or run",
+                s
+            )),
+        }
+    }
+}
+
+/// Callback registered with `Emulator::on_event`
+type EventCallback<F> = Box<dyn FnMut(&mut Emulator<F>, Event)>;
+
+pub struct Emulator<F: Frontend = WindowHandle> {
     pub cpu: Chip8,
-    pub window_handle: WindowHandle,
+    pub frontend: F,
     pub key_map: HashMap<Key, u8>,
+    pub control_map: HashMap<Hotkey, Action>,
+    /// Only report the lowest-numbered pressed key, like the original hex keypad
+    pub single_key_keypad: bool,
     pub clock_period: Option<Duration>,
+    /// Real time the clock has not run instructions for yet
+    clock_acc: Duration,
+    /// When `clock_acc` was last brought up to date
+    clock_time: Instant,
     pub timer_period: Duration,
     pub timer_acc: Duration,
+    pub paused_timers: PausedTimers,
+    /// When set, run exactly this many instructions per timer tick, independent of wall-clock time
+    pub instructions_per_tick: Option<u32>,
+    pub tick_instructions: u32,
+    /// Instructions executed since reset
+    pub cycle: u64,
+    /// Keypad input scheduled by cycle, pressed alongside host input
+    pub key_schedule: KeySchedule,
+    /// Instruction count to wait at for more input, when mirroring a netplay host
+    pub input_limit: Option<u64>,
     pub sys_time: SystemTime,
     pub paused: bool,
+    /// Why execution last stopped at a breakpoint or watchpoint, cleared on unpause
+    pub break_reason: Option<String>,
     pub step: usize,
     pub closing: bool,
     pub debug_print: bool,
+    /// Language of window titles and messages
+    pub locale: Locale,
+    /// Present the display at most once per this much emulated time; `None`
+    /// presents every change
+    pub refresh_period: Option<Duration>,
+    /// Emulated frame last presented, counted in `refresh_period`s
+    presented_frame: Option<u128>,
+    /// The display changed since it was last presented
+    frame_pending: bool,
+    /// Wall-clock time of the last reset, for emulated time when neither the
+    /// clock nor instructions per tick are fixed
+    reset_time: Instant,
+    /// Factor screenshots are enlarged by, normally the window scale
+    pub screenshot_scale: usize,
+    /// Where the screenshot control saves images
+    pub screenshot_dir: Option<PathBuf>,
+    /// Draw registers and the current instruction over the display
+    pub overlay: bool,
+    overlay_drawn: Option<Instant>,
+    /// Menu drawn over the display, open while paused with the menu control
+    pub pause_menu: Option<PauseMenu>,
+    /// Draw a hex keypad below the display that can be clicked to press keys
+    pub keypad: bool,
+    /// Size of the frame last presented with the keypad, to find the keys
+    /// clicked in it
+    keypad_frame: (usize, usize),
+    /// Keypad keys held at the last read, lit on the keypad
+    keypad_held: u16,
+    /// Program run on reset; while empty, the emulator idles waiting for one
     pub rom: Vec<u8>,
+    /// Title of the program, shown in the window title
+    rom_title: Option<String>,
+    /// Measures frame and instruction rates while they are shown in the
+    /// window title
+    pub rate_meter: Option<RateMeter>,
+    /// What a trigger last announced, shown in the window title until the
+    /// next reset or unpause
+    announcement: Option<String>,
     pub color_on: u32,
     pub color_off: u32,
     pub palette_index: usize,
+    /// Config file that cycling the palette saves the choice to
+    pub palette_config: Option<PathBuf>,
+    pub practice: Option<Practice>,
+    /// Unattended mode: reset when idle, restart instead of stopping on errors
+    pub kiosk: Option<Kiosk>,
+    /// Conditions that announce themselves when met
+    pub triggers: Option<Triggers>,
+    /// Memory patches, toggled with the `cheats` control
+    pub cheats: Option<Cheats>,
+    pub savestate_path: Option<PathBuf>,
+    /// File the RPL flags are kept in between runs, if they persist
+    pub flags_path: Option<PathBuf>,
+    /// RPL flags as last loaded or saved
+    saved_flags: [u8; chip8::RPL_FLAGS],
+    pub rewind: Rewind,
+    pub rewinding: bool,
+    /// Skip the `clock_period` sleep while the turbo control is held
+    ///
+    /// Timers still follow the emulated clock, so a sound lasts as many
+    /// instructions in turbo as at normal speed, and a backlog of timer time
+    /// runs the timers down without wrapping them.
+    ///
+    /// ```
+    /// use std::{cell::Cell, rc::Rc, time::Duration};
+    ///
+    /// use chip8::{emu::Event, Chip8, Emulator, HeadlessFrontend};
+    ///
+    /// // LD V0, 3C; LD ST, V0; JP 204
+    /// let rom = [0x60, 0x3C, 0xF0, 0x18, 0x12, 0x04];
+    ///
+    /// for &turbo in &[false, true] {
+    ///     let mut emu = Emulator::with_frontend(Chip8::new(), HeadlessFrontend::new())?;
+    ///     emu.timer_period = Duration::from_millis(1);
+    ///     emu.clock_period = Some(Duration::from_micros(100));
+    ///     emu.rom = rom.to_vec();
+    ///     emu.reset()?;
+    ///     emu.turbo = turbo;
+    ///     emu.timer_acc = emu.timer_period * 1000;
+    ///
+    ///     let stopped_at = Rc::new(Cell::new(0));
+    ///     let stopped = Rc::clone(&stopped_at);
+    ///     emu.on_event(move |emu, event| {
+    ///         if let Event::SoundStopped = event {
+    ///             stopped.set(emu.cycle);
+    ///             emu.quit();
+    ///         }
+    ///     });
+    ///
+    ///     while !emu.closing {
+    ///         emu.step()?;
+    ///     }
+    ///
+    ///     // Started after the second instruction; 60 ticks of 10 instructions
+    ///     // later it stops, at the same point either way
+    ///     assert_eq!(stopped_at.get(), 601);
+    ///     assert!(!emu.beeping);
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub turbo: bool,
+    pub beeping: bool,
+    /// XO-CHIP audio pattern last passed to the frontend
+    audio_pattern: Option<AudioPattern>,
+    /// Buzzer transitions since reset, timed in instructions
+    pub buzzer_events: Vec<BuzzerEvent>,
+    #[cfg(feature = "gamepad")]
+    pub gamepad: Option<Gamepad>,
+    pub filters: Pipeline,
+    /// RNG seed applied on every reset
+    pub rng_seed: [u8; 32],
+    /// Input movie being recorded, saved to `recording_path`
+    pub recording: Option<Movie>,
+    pub recording_path: Option<PathBuf>,
+    /// Execution statistics, collected when set
+    pub stats: Option<Stats>,
+    /// Subroutine profile, collected when set
+    pub profile: Option<CallProfile>,
+    /// Which steps the trace, triggers, and practice check run on
+    pub throttles: Throttles,
+    /// When set, trace lines are held here and printed when execution stops
+    pub trace_buffer: Option<RingBuffer>,
+    /// Loaded plugins, told about the buzzer and every step
+    #[cfg(feature = "plugins")]
+    pub plugins: Vec<Rc<Plugin>>,
+    /// Loaded scripts, run on the events they hook
+    #[cfg(feature = "scripting")]
+    pub scripts: Vec<Script>,
+    callbacks: Vec<EventCallback<F>>,
 }
 
-impl Emulator {
-    pub fn new() -> anyhow::Result<Self> {
-        let cpu = Chip8::new();
+impl Emulator<WindowHandle> {
+    /// Run `cpu` in a new minifb window, sized for its display
+    pub fn new(cpu: Chip8, scale: Scale, filters: Pipeline) -> anyhow::Result<Self> {
+        let (width, height) = filters.output_size(cpu.display_width(), cpu.display_height());
+        let scale = window_scale(
+            scale,
+            filters.upscale(cpu.display_width(), cpu.display_height()),
+        );
+
+        let window_handle = window::spawn(TITLE.into(), width, height, scale);
+
+        let mut emu = Self::with_frontend(cpu, window_handle)?;
+        emu.filters = filters;
 
-        let width = cpu.display_width();
-        let height = cpu.display_height();
+        Ok(emu)
+    }
+}
 
-        let window_handle = window::spawn(TITLE.into(), width, height);
+impl<D: Display, I: Input, B: Beeper> Emulator<Split<D, I, B>> {
+    /// Drive separate display, input, and sound backends
+    pub fn with_parts(cpu: Chip8, display: D, input: I, beeper: B) -> anyhow::Result<Self> {
+        Self::with_frontend(
+            cpu,
+            Split {
+                display,
+                input,
+                beeper,
+            },
+        )
+    }
+}
 
+impl<F: Frontend> Emulator<F> {
+    pub fn with_frontend(cpu: Chip8, frontend: F) -> anyhow::Result<Self> {
         Ok(Emulator {
             cpu,
-            window_handle,
+            frontend,
             key_map: default_key_map(),
+            control_map: default_control_map(),
+            single_key_keypad: false,
             clock_period: Some(Duration::from_secs_f64(DEFAULT_CLOCK_PERIOD_S)),
+            clock_acc: Duration::ZERO,
+            clock_time: Instant::now(),
             timer_period: Duration::from_secs_f64(DEFAULT_TIMER_PERIOD_S),
             timer_acc: Duration::from_secs(0),
+            paused_timers: PausedTimers::default(),
+            instructions_per_tick: None,
+            tick_instructions: 0,
+            cycle: 0,
+            key_schedule: KeySchedule::new(),
+            input_limit: None,
             sys_time: SystemTime::now(),
             paused: false,
+            break_reason: None,
             step: 0,
             closing: false,
             debug_print: false,
+            locale: Locale::english(),
+            refresh_period: None,
+            presented_frame: None,
+            frame_pending: false,
+            reset_time: Instant::now(),
+            screenshot_scale: 1,
+            screenshot_dir: None,
+            overlay: false,
+            overlay_drawn: None,
+            pause_menu: None,
+            keypad: false,
+            keypad_frame: (0, 0),
+            keypad_held: 0,
             rom: Vec::new(),
+            rom_title: None,
+            announcement: None,
+            rate_meter: None,
             color_on: DEFAULT_COLOR_ON,
             color_off: DEFAULT_COLOR_OFF,
             palette_index: 0,
+            palette_config: None,
+            practice: None,
+            kiosk: None,
+            triggers: None,
+            cheats: None,
+            savestate_path: None,
+            flags_path: None,
+            saved_flags: [0; chip8::RPL_FLAGS],
+            rewind: Rewind::new(REWIND_CAPACITY, REWIND_INTERVAL),
+            rewinding: false,
+            turbo: false,
+            beeping: false,
+            audio_pattern: None,
+            buzzer_events: Vec::new(),
+            #[cfg(feature = "gamepad")]
+            gamepad: None,
+            filters: Pipeline::default(),
+            rng_seed: chip8::RNG_SEED,
+            recording: None,
+            recording_path: None,
+            stats: None,
+            profile: None,
+            throttles: Throttles::default(),
+            trace_buffer: None,
+            #[cfg(feature = "plugins")]
+            plugins: Vec::new(),
+            #[cfg(feature = "scripting")]
+            scripts: Vec::new(),
+            callbacks: Vec::new(),
         })
     }
 
+    /// Call `callback` on every `Event`, with the emulator to inspect or
+    /// change
+    ///
+    /// Callbacks run in the order registered, in the middle of `step`.
+    ///
+    /// ```
+    /// use std::{cell::Cell, rc::Rc};
+    ///
+    /// use chip8::{emu::Event, Chip8, Emulator, HeadlessFrontend};
+    ///
+    /// // LD V0, 3C; LD ST, V0; JP 204
+    /// let rom = [0x60, 0x3C, 0xF0, 0x18, 0x12, 0x04];
+    ///
+    /// let mut emu = Emulator::with_frontend(Chip8::new(), HeadlessFrontend::new())?;
+    /// emu.clock_period = None;
+    /// emu.instructions_per_tick = Some(16);
+    /// emu.rom = rom.to_vec();
+    /// emu.reset()?;
+    ///
+    /// let ticks = Rc::new(Cell::new(0));
+    /// let counter = Rc::clone(&ticks);
+    /// emu.on_event(move |emu, event| match event {
+    ///     Event::TimerTick => counter.set(counter.get() + 1),
+    ///     Event::SoundStopped => emu.quit(),
+    ///     _ => {}
+    /// });
+    ///
+    /// while !emu.closing {
+    ///     emu.step()?;
+    /// }
+    ///
+    /// assert_eq!(ticks.get(), 0x3C);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn on_event(&mut self, callback: impl FnMut(&mut Emulator<F>, Event) + 'static) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    fn emit(&mut self, event: Event) {
+        // Taken out while they run, as they get the emulator
+        let mut callbacks = mem::take(&mut self.callbacks);
+
+        for callback in &mut callbacks {
+            callback(self, event);
+        }
+
+        // Keeping any registered by the callbacks
+        callbacks.append(&mut self.callbacks);
+        self.callbacks = callbacks;
+    }
+
+    /// Bind a host key or chord, replacing any existing binding of it
+    ///
+    /// Binding an action moves it off whichever key it was previously on.
+    /// Keypad keys ignore modifiers. Returns what the key was bound to before,
+    /// if that was something else, so callers can report collisions between
+    /// hotkeys and the keypad layout.
+    pub fn bind(&mut self, hotkey: Hotkey, binding: Binding) -> Option<Binding> {
+        let previous = match binding {
+            Binding::Keypad(code) => {
+                let key = hotkey.key;
+                let action = self.control_map.remove(&Hotkey::plain(key));
+                let code = self.key_map.insert(key, code);
+
+                action.map(Binding::Action).or(code.map(Binding::Keypad))
+            }
+            Binding::Action(action) => {
+                let code = if hotkey.modifiers.is_empty() {
+                    self.key_map.remove(&hotkey.key)
+                } else {
+                    None
+                };
+                self.control_map.retain(|_, a| *a != action);
+                let other = self.control_map.insert(hotkey, action);
+
+                other.map(Binding::Action).or(code.map(Binding::Keypad))
+            }
+        };
+
+        previous.filter(|previous| *previous != binding)
+    }
+
+    /// Hold keypad `key` from cycle `press_at_cycle` until (not including) `release_at_cycle`
+    ///
+    /// Cycles count instructions executed since reset.
+    pub fn schedule_key(&mut self, key: u8, press_at_cycle: u64, release_at_cycle: u64) {
+        self.key_schedule
+            .schedule(key, press_at_cycle, release_at_cycle);
+    }
+
+    /// Switch the language of titles and messages, retitling the window
+    pub fn set_locale(&mut self, locale: Locale) {
+        self.locale = locale;
+        self.update_title();
+    }
+
+    /// Show the running ROM's title in the window title, or stop showing one
+    ///
+    /// Also brings the clock speed shown there up to date.
+    pub fn set_rom_title(&mut self, rom_title: Option<String>) {
+        self.rom_title = rom_title;
+        self.update_title();
+    }
+
     pub fn set_palette(&mut self, p: (u32, u32)) {
         let (off, on) = p;
         self.color_off = off;
@@ -79,167 +507,1028 @@ impl Emulator {
 
     pub fn reset(&mut self) -> anyhow::Result<()> {
         self.cpu.reset();
+        self.cpu.seed_rng(self.rng_seed);
         self.cpu.load_rom(&self.rom)?;
+
+        if let Some(practice) = &mut self.practice {
+            practice.set_checkpoint(&self.cpu);
+        }
+
+        if let Some(triggers) = &mut self.triggers {
+            triggers.rearm();
+        }
+
+        if let Some(cheats) = &mut self.cheats {
+            cheats.rearm();
+        }
+
+        self.rewind.clear();
         self.timer_acc = Duration::from_secs(0);
+        self.tick_instructions = 0;
+        self.cycle = 0;
         self.sys_time = SystemTime::now();
+        self.reset_time = Instant::now();
+        self.presented_frame = None;
+
+        self.buzzer_events.clear();
+
+        if let Some(movie) = &mut self.recording {
+            movie.inputs.clear();
+            movie.buzzer.clear();
+        }
+
+        self.announcement = None;
+        self.update_title();
+
+        Ok(())
+    }
+
+    pub fn save_state(&mut self) -> anyhow::Result<()> {
+        if let Some(path) = &self.savestate_path {
+            savestate::save(path, &self.cpu)?;
+            println!(
+                "{}",
+                self.locale
+                    .format("status.saved_state", &[("path", &path.display())])
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn load_state(&mut self) -> anyhow::Result<()> {
+        if let Some(path) = &self.savestate_path {
+            savestate::load(path, &mut self.cpu)?;
+            println!(
+                "{}",
+                self.locale
+                    .format("status.loaded_state", &[("path", &path.display())])
+            );
+            self.save_recording()?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the RPL flags from `flags_path`, or clear them if there is no file
+    pub fn load_flags(&mut self) -> anyhow::Result<()> {
+        let mut flags = [0; chip8::RPL_FLAGS];
+
+        if let Some(path) = self.flags_path.as_ref().filter(|path| path.exists()) {
+            let data = fs::read(path)
+                .with_context(|| format!("failed to read RPL flags {}", path.display()))?;
+            let n = data.len().min(flags.len());
+            flags[..n].copy_from_slice(&data[..n]);
+        }
+
+        self.cpu.rpl = flags;
+        self.saved_flags = flags;
+
+        Ok(())
+    }
+
+    /// Write the RPL flags to `flags_path` if the program changed them
+    ///
+    /// A failed write is reported without stopping the program.
+    fn save_flags(&mut self) {
+        if self.cpu.rpl == self.saved_flags {
+            return;
+        }
+
+        self.saved_flags = self.cpu.rpl;
+
+        if let Some(path) = &self.flags_path {
+            let saved = storage::create_parent(path).and_then(|()| {
+                fs::write(path, self.cpu.rpl)
+                    .with_context(|| format!("failed to write RPL flags {}", path.display()))
+            });
+
+            if let Err(e) = saved {
+                eprintln!(
+                    "{}",
+                    self.locale
+                        .format("error.prefix", &[("message", &format!("{:#}", e))])
+                );
+            }
+        }
+    }
+
+    /// The display in the active palette, enlarged by `screenshot_scale`, as
+    /// 0RGB pixels with the width and height
+    pub fn screenshot(&self) -> (Vec<u32>, usize, usize) {
+        let scale = self.screenshot_scale.max(1);
+        let (width, height) = (self.cpu.display_width(), self.cpu.display_height());
+        let mut pixels = Vec::with_capacity(width * scale * height * scale);
+
+        for row in self.cpu.display.chunks(width).take(height) {
+            let line: Vec<u32> = row
+                .iter()
+                .map(|&px| plane_color(px, (self.color_off, self.color_on)))
+                .flat_map(|px| std::iter::repeat_n(px, scale))
+                .collect();
+
+            for _ in 0..scale {
+                pixels.extend_from_slice(&line);
+            }
+        }
+
+        (pixels, width * scale, height * scale)
+    }
+
+    /// Save a screenshot to the next free `screenshot-NNNN.png` in
+    /// `screenshot_dir`, returning its path
+    pub fn save_screenshot(&self) -> anyhow::Result<PathBuf> {
+        let dir = self
+            .screenshot_dir
+            .as_ref()
+            .ok_or_else(|| anyhow!("{}", self.locale.get("error.no_screenshot_dir")))?;
+        fs::create_dir_all(dir)?;
+
+        let path = (1..)
+            .map(|n| dir.join(format!("screenshot-{:04}.png", n)))
+            .find(|path| !path.exists())
+            .expect("unbounded range");
+
+        let (pixels, width, height) = self.screenshot();
+        png::save(&path, &pixels, width, height)?;
+
+        Ok(path)
+    }
+
+    /// Write the recorded movie (if any) and stop recording
+    ///
+    /// Called once the recording can no longer be replayed from reset, e.g.
+    /// after rewinding or loading a state.
+    pub fn save_recording(&mut self) -> anyhow::Result<()> {
+        if let (Some(movie), Some(path)) = (self.recording.take(), &self.recording_path) {
+            movie.save(path)?;
+            println!(
+                "{}",
+                self.locale.format(
+                    "status.saved_recording",
+                    &[("count", &movie.inputs.len()), ("path", &path.display())]
+                )
+            );
+        }
 
         Ok(())
     }
 
     pub fn step(&mut self) -> anyhow::Result<()> {
-        if self.window_handle.is_closing() {
+        self.frontend.update();
+
+        if self.frontend.is_closing() {
             self.quit();
         }
 
         if !self.closing {
             self.read_inputs()?;
+
+            #[cfg(feature = "scripting")]
+            self.run_scripts(Hook::Key);
+        }
+
+        if self.kiosk.as_mut().is_some_and(Kiosk::check_idle) {
+            println!("{}", self.locale.get("status.idle_reset"));
+            self.reset()?;
         }
 
-        if (!self.paused || self.step > 0) && !self.closing {
-            if self.step > 0 {
-                self.step -= 1;
+        if self.rewinding && !self.closing {
+            if let Some(snapshot) = self.rewind.step_back() {
+                self.cpu.restore(&snapshot);
+                self.save_recording()?;
             }
 
-            if self.debug_print {
-                println!("{}", self.cpu.status());
+            if let Some(clock_period) = self.clock_period {
+                spin_sleep::sleep(clock_period);
             }
+        } else {
+            let due = self.instructions_due();
+            let mut ran = 0;
 
-            while self.timer_acc > self.timer_period {
-                self.timer_acc -= self.timer_period;
-                self.cpu.timer_tick();
+            while ran < due && self.can_execute() {
+                self.execute()?;
+                ran += 1;
             }
 
-            self.cpu_step()?;
+            if let Some(meter) = &mut self.rate_meter {
+                meter.record_instructions(ran);
+            }
 
             match self.clock_period {
-                Some(clock_period) => {
-                    self.timer_acc += clock_period;
-                    spin_sleep::sleep(clock_period);
+                // Wait for the next instruction to come due
+                Some(period) if due == 0 => {
+                    spin_sleep::sleep(period.saturating_sub(self.clock_acc).max(MIN_CLOCK_SLEEP))
                 }
+                _ if ran == 0 => thread::sleep(Duration::from_micros(1)),
+                _ => {}
+            }
 
-                None => {
-                    self.timer_acc += self.sys_time.elapsed()?;
-                }
+            if self.paused && ran == 0 && self.paused_timers == PausedTimers::Run {
+                self.timer_acc += self.real_time_elapsed();
+                self.catch_up_timers();
             }
-        } else {
-            thread::sleep(Duration::from_micros(1));
         }
 
         self.sys_time = SystemTime::now();
 
-        if self.cpu.display_dirty {
+        // Registers change between draws, so the overlay refreshes on its own
+        let overlay_due = self.overlay
+            && self
+                .overlay_drawn
+                .is_none_or(|t| t.elapsed() >= Duration::from_secs_f64(OVERLAY_PERIOD_S));
+
+        if self.cpu.display_dirty || self.filters.needs_redraw() || overlay_due {
             self.cpu.display_dirty = false;
+            self.frame_pending = true;
+        }
+
+        if self.frame_pending && self.frame_due() {
+            self.frame_pending = false;
 
             self.update_window();
+            self.emit(Event::FramePresented);
+
+            if let Some(meter) = &mut self.rate_meter {
+                meter.record_frame();
+            }
+
+            #[cfg(feature = "scripting")]
+            self.run_scripts(Hook::Draw);
+        }
+
+        if self.rate_meter.as_mut().is_some_and(RateMeter::update) {
+            self.update_title();
+        }
+
+        Ok(())
+    }
+
+    /// Instructions this step should run: as many as the clock period has
+    /// come due in the real time since the last step, or one when running
+    /// flat out, in turbo, or single-stepping
+    fn instructions_due(&mut self) -> u64 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(mem::replace(&mut self.clock_time, now));
+
+        match self.clock_period {
+            Some(period) if !self.paused && !self.turbo => {
+                let lag = Duration::from_secs_f64(MAX_CLOCK_LAG_S);
+                self.clock_acc = (self.clock_acc + elapsed).min(lag);
+
+                let period = period.as_nanos().max(1);
+                let due = self.clock_acc.as_nanos() / period;
+                self.clock_acc = Duration::from_nanos((self.clock_acc.as_nanos() % period) as u64);
+
+                due as u64
+            }
+            _ => {
+                self.clock_acc = Duration::ZERO;
+                1
+            }
+        }
+    }
+
+    /// Whether the next instruction may run: not paused (or single-stepping),
+    /// its input has arrived, and no breakpoint or script stops it
+    fn can_execute(&mut self) -> bool {
+        (!self.paused || self.step > 0)
+            && !self.closing
+            && !self.rom.is_empty()
+            && self.input_limit.is_none_or(|limit| self.cycle < limit)
+            && !self.hit_breakpoint()
+            && !self.script_break()
+    }
+
+    /// Run one instruction, with the timers, recording, and checks around it
+    fn execute(&mut self) -> anyhow::Result<()> {
+        if self.step > 0 {
+            self.step -= 1;
+        }
+
+        let opcode = self.cpu.mem_read_opcode(self.cpu.pc);
+
+        if self.debug_print && self.throttles.trace.sample(opcode) {
+            let status = self.cpu.status();
+
+            match &mut self.trace_buffer {
+                Some(buffer) => buffer.push(status),
+                None => println!("{}", status),
+            }
+        }
+
+        match self.instructions_per_tick {
+            Some(k) => {
+                if self.tick_instructions >= k.max(1) {
+                    self.tick_instructions = 0;
+                    self.cpu.timer_tick();
+                    self.apply_cheats();
+                    self.emit(Event::TimerTick);
+
+                    #[cfg(feature = "scripting")]
+                    self.run_scripts(Hook::Tick);
+                }
+
+                self.tick_instructions += 1;
+            }
+
+            None => self.catch_up_timers(),
+        }
+
+        if let Some(movie) = &mut self.recording {
+            movie.record(&self.cpu);
+        }
+
+        self.cpu_step()?;
+        self.cycle += 1;
+        self.rewind.record(&self.cpu);
+
+        #[cfg(feature = "plugins")]
+        for plugin in &self.plugins {
+            plugin.on_step(&self.cpu, opcode, self.cycle);
+        }
+
+        if let Some(practice) = &mut self.practice {
+            if self.throttles.practice.sample(opcode) && practice.check(&mut self.cpu) {
+                if self.debug_print {
+                    println!("{}", self.locale.get("status.practice_restored"));
+                }
+
+                self.save_recording()?;
+            }
+        }
+
+        self.check_triggers(opcode);
+        self.update_beeper();
+        self.save_flags();
+
+        match (self.clock_period, self.instructions_per_tick) {
+            // Ticks are counted in instructions instead, and time left here
+            // would come out as a burst of ticks when paused timers run
+            (_, Some(_)) => {}
+            // Timers follow the emulated clock, so they keep pace in turbo
+            (Some(clock_period), None) => self.timer_acc += clock_period,
+            (None, None) => self.timer_acc += self.real_time_elapsed(),
         }
 
         Ok(())
     }
 
+    /// Tick the timers for every `timer_period` in `timer_acc`
+    fn catch_up_timers(&mut self) {
+        let period = self.timer_period.as_nanos().max(1);
+        let elapsed = self.timer_acc.as_nanos();
+
+        let ticks = (elapsed / period) as u64;
+        self.cpu.timer_ticks(ticks);
+        self.timer_acc = Duration::from_nanos((elapsed % period) as u64);
+
+        if ticks > 0 {
+            self.apply_cheats();
+        }
+
+        for _ in 0..ticks {
+            self.emit(Event::TimerTick);
+        }
+
+        #[cfg(feature = "scripting")]
+        for _ in 0..ticks {
+            self.run_scripts(Hook::Tick);
+        }
+    }
+
+    /// Real time since the last step, at most `MAX_CLOCK_LAG_S`, so time
+    /// spent outside of stepping, e.g. at a debugger prompt, is not made up
+    fn real_time_elapsed(&self) -> Duration {
+        let elapsed = self.sys_time.elapsed().unwrap_or_default();
+        elapsed.min(Duration::from_secs_f64(MAX_CLOCK_LAG_S))
+    }
+
     fn read_inputs(&mut self) -> anyhow::Result<()> {
+        let held = self.frontend.get_keys();
+        let modifiers = held.as_deref().map(Modifiers::held).unwrap_or_default();
+
         // Read Key Presses
-        if let Some(keys_pressed) = self.window_handle.get_keys_pressed() {
+        if let Some(keys_pressed) = self.frontend.get_keys_pressed() {
             for key in keys_pressed {
-                match key {
-                    Key::Escape => {
+                // The open menu takes the keys it uses before the controls
+                if let Some(menu) = &mut self.pause_menu {
+                    match menu.press(key) {
+                        MenuPress::Ignored => {}
+                        MenuPress::Moved => {
+                            self.cpu.display_dirty = true;
+                            continue;
+                        }
+                        MenuPress::Chose(item) => {
+                            self.choose(item)?;
+                            continue;
+                        }
+                        MenuPress::Closed => {
+                            self.unpause();
+                            self.cpu.display_dirty = true;
+                            continue;
+                        }
+                    }
+                }
+
+                match self.control_map.get(&Hotkey { modifiers, key }) {
+                    Some(Action::Quit) => {
                         self.quit();
                     }
-                    Key::F1 => {
+                    Some(Action::Reset) => {
                         self.reset()?;
                     }
-                    Key::F2 => {
+                    Some(Action::ToggleDebug) => {
                         self.debug_print = !self.debug_print;
                     }
-                    Key::F3 => {
-                        self.rotate_palette();
+                    Some(Action::ToggleOverlay) => {
+                        self.overlay = !self.overlay;
+                        self.cpu.display_dirty = true;
+                    }
+                    Some(Action::CyclePalette) => self.cycle_palette(),
+                    Some(Action::Screenshot) => match self.save_screenshot() {
+                        Ok(path) => println!(
+                            "{}",
+                            self.locale
+                                .format("status.saved_screenshot", &[("path", &path.display())])
+                        ),
+                        Err(e) => self.print_error(&format!("{:#}", e)),
+                    },
+                    Some(Action::SaveState) => {
+                        if let Err(e) = self.save_state() {
+                            self.print_error(&format!("{:#}", e));
+                        }
+                    }
+                    Some(Action::LoadState) => {
+                        if let Err(e) = self.load_state() {
+                            self.print_error(&format!("{:#}", e));
+                        }
+                    }
+                    Some(Action::SetCheckpoint) => {
+                        if let Some(practice) = &mut self.practice {
+                            practice.set_checkpoint(&self.cpu);
+                        }
                     }
-                    Key::Space => {
+                    Some(Action::Pause) => {
                         if self.paused {
                             self.unpause();
                         } else {
                             self.pause();
                         }
                     }
-                    Key::Enter => {
+                    Some(Action::ToggleMenu) => {
+                        if self.pause_menu.is_some() {
+                            self.unpause();
+                        } else {
+                            self.pause();
+                            self.pause_menu = Some(PauseMenu::new());
+                            self.cpu.display_dirty = true;
+                        }
+                    }
+                    Some(Action::Step) => {
                         self.pause();
                         self.step += 1;
                     }
 
-                    _ => {}
+                    Some(Action::ToggleCheats) => {
+                        if let Some(cheats) = &mut self.cheats {
+                            let enabled = !cheats.enabled();
+                            cheats.set_enabled(enabled);
+
+                            let key = if enabled {
+                                "status.cheats_on"
+                            } else {
+                                "status.cheats_off"
+                            };
+                            println!("{}", self.locale.get(key));
+                        }
+                    }
+
+                    Some(Action::ToggleRates) => {
+                        self.rate_meter = match self.rate_meter {
+                            Some(_) => None,
+                            None => Some(RateMeter::new()),
+                        };
+                        self.update_title();
+                    }
+
+                    Some(Action::Rewind) | Some(Action::Turbo) | None => {}
                 }
             }
         }
 
         // Read Mapped Keys
-        if let Some(keys) = self.window_handle.get_keys() {
+        self.rewinding = false;
+        self.turbo = false;
+
+        let mut codes = Vec::new();
+
+        if let Some(keys) = held {
             for key in keys {
+                let chord = self.control_map.get(&Hotkey { modifiers, key });
+
+                match chord {
+                    Some(Action::Rewind) => self.rewinding = true,
+                    Some(Action::Turbo) => self.turbo = true,
+                    _ => {}
+                }
+
+                // A key pressed as part of a chord is not also a keypad press
+                if chord.is_some() && !modifiers.is_empty() {
+                    continue;
+                }
+
                 if let Some(code) = self.key_map.get(&key) {
-                    self.cpu.set_key(*code);
+                    codes.push(*code);
                 }
             }
         }
 
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepad) = &mut self.gamepad {
+            codes.extend(gamepad.poll());
+        }
+
+        if self.keypad {
+            let (width, height) = self.keypad_frame;
+
+            for (x, y) in self.frontend.get_pointers() {
+                codes.extend(keypad::key_at(width, height, x, y));
+            }
+
+            let held = codes.iter().fold(0, |held, code| held | 1 << (code & 0xF));
+            if held != self.keypad_held {
+                self.keypad_held = held;
+                self.cpu.display_dirty = true;
+            }
+        }
+
+        if let (Some(kiosk), false) = (&mut self.kiosk, codes.is_empty()) {
+            kiosk.input();
+        }
+
+        self.key_schedule.apply(&mut self.cpu, self.cycle);
+
+        if self.single_key_keypad {
+            // The original keypad only reports one key at a time, lowest index first
+            if let Some(code) = codes.iter().min() {
+                self.cpu.set_key(*code);
+            }
+        } else {
+            for code in codes {
+                self.cpu.set_key(code);
+            }
+        }
+
         Ok(())
     }
 
     fn cpu_step(&mut self) -> anyhow::Result<()> {
-        match self.cpu.step() {
-            Ok(()) => {}
+        let pc = self.cpu.pc;
+        let opcode = self.cpu.mem_read_opcode(pc);
+
+        let result = self.cpu.step();
+
+        if let Some(stats) = &mut self.stats {
+            stats.record_step(pc, opcode, self.cpu.pc, &result);
+        }
+
+        if let Some(profile) = &mut self.profile {
+            profile.record_step(pc, opcode, self.cpu.pc, &result);
+        }
+
+        match result {
+            Ok(StepOutcome::Watchpoint(hit)) => {
+                let reason = self.locale.format("status.watchpoint", &[("hit", &hit)]);
+                self.break_at(reason);
+            }
+            Ok(StepOutcome::Exited) => {
+                self.save_recording()?;
+
+                if let Some(kiosk) = &mut self.kiosk {
+                    kiosk.restarted();
+                    println!("{}", self.locale.get("status.crash_restart"));
+                    self.reset()?;
+                } else {
+                    let pc = format!("{:04X}", self.cpu.pc);
+                    let reason = self.locale.format("status.exited", &[("pc", &pc)]);
+                    self.break_at(reason);
+                }
+            }
+            Ok(_) => {}
             Err(e) => {
-                match e {
+                let pc = format!("{:04X}", self.cpu.pc);
+                let opcode = format!("{:04X}", self.cpu.mem_read_opcode(self.cpu.pc));
+
+                let message = match e {
                     chip8::Chip8Panic::StackUnderflow => {
-                        println!("Error: Stack Overflow at {:04X}", self.cpu.pc);
+                        self.locale.format("error.stack_underflow", &[("pc", &pc)])
                     }
                     chip8::Chip8Panic::StackOverflow => {
-                        println!("Error: Stack Overflow at {:04X}", self.cpu.pc);
+                        self.locale.format("error.stack_overflow", &[("pc", &pc)])
                     }
-                    chip8::Chip8Panic::UnknownOpCode => {
-                        println!(
-                            "Error: Unknown opcode at {:04X}: {:04X}",
-                            self.cpu.pc,
-                            self.cpu.mem_read_opcode(self.cpu.pc)
+                    chip8::Chip8Panic::UnknownOpCode => self
+                        .locale
+                        .format("error.unknown_opcode", &[("pc", &pc), ("opcode", &opcode)]),
+                    chip8::Chip8Panic::ExtensionOpCode(op) => {
+                        let message = self.locale.format(
+                            "error.extension_opcode",
+                            &[
+                                ("extension", &op.extension),
+                                ("pc", &pc),
+                                ("opcode", &opcode),
+                            ],
                         );
+                        format!("{}\n  {}", message, e)
                     }
+                };
+
+                self.print_error(&message);
+
+                self.save_recording()?;
+
+                if let Some(kiosk) = &mut self.kiosk {
+                    kiosk.restarted();
+                    println!("{}", self.locale.get("status.crash_restart"));
+                    self.reset()?;
+                } else {
+                    self.pause();
                 }
 
-                self.pause();
+                self.emit(Event::Crashed(e));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether a pending frame may be presented now, under `refresh_period`
+    ///
+    /// Changes within one emulated frame are dropped in favor of the last, and
+    /// a frame with no changes is not presented again; the window keeps
+    /// showing it. Paused, emulated time stands still, so every change shows.
+    fn frame_due(&mut self) -> bool {
+        let period = match self.refresh_period {
+            Some(period) if !self.paused && !self.rewinding => period,
+            _ => return true,
+        };
+
+        let frame = self.emulated_time().as_nanos() / period.as_nanos().max(1);
+
+        if self.presented_frame == Some(frame) {
+            false
+        } else {
+            self.presented_frame = Some(frame);
+            true
+        }
+    }
+
+    /// Time the program has run since reset, by the emulated clock
+    pub fn emulated_time(&self) -> Duration {
+        let nanos = match (self.clock_period, self.instructions_per_tick) {
+            (Some(period), _) => period.as_nanos() * u128::from(self.cycle),
+            (None, Some(k)) => {
+                self.timer_period.as_nanos() * u128::from(self.cycle) / u128::from(k.max(1))
             }
+            (None, None) => return self.reset_time.elapsed(),
+        };
+
+        Duration::from_nanos(nanos as u64)
+    }
+
+    /// Do what the pause menu's `item` does
+    fn choose(&mut self, item: MenuItem) -> anyhow::Result<()> {
+        match item {
+            MenuItem::Resume => self.unpause(),
+            MenuItem::Reset => {
+                self.reset()?;
+                self.unpause();
+            }
+            MenuItem::SaveState => {
+                if let Err(e) = self.save_state() {
+                    self.print_error(&format!("{:#}", e));
+                }
+                self.unpause();
+            }
+            MenuItem::LoadState => {
+                if let Err(e) = self.load_state() {
+                    self.print_error(&format!("{:#}", e));
+                }
+                self.unpause();
+            }
+            MenuItem::Palette => self.cycle_palette(),
+            MenuItem::Overlay => self.overlay = !self.overlay,
+            MenuItem::Quit => self.quit(),
         }
 
+        self.cpu.display_dirty = true;
         Ok(())
     }
 
+    /// Switch to the next palette, remembering it in the config file if one is
+    /// set
+    fn cycle_palette(&mut self) {
+        self.rotate_palette();
+
+        if let Some(path) = &self.palette_config {
+            if let Err(e) = palette::save_to_config(path, self.palette_index) {
+                self.print_error(&format!("{:#}", e));
+            }
+        }
+    }
+
     fn update_window(&mut self) {
-        for (i, b) in self
-            .window_handle
-            .get_display_buffer_mut()
-            .iter_mut()
-            .enumerate()
-        {
-            *b = match self.cpu.display[i] {
-                true => self.color_on,
-                false => self.color_off,
+        let (width, height) = (self.cpu.display_width(), self.cpu.display_height());
+        let colors = (self.color_off, self.color_on);
+        let rows = self.cpu.take_dirty_rows();
+
+        if self.overlay || self.pause_menu.is_some() || self.keypad {
+            let mut frame = self
+                .filters
+                .run(&self.cpu.display, width, height, colors)
+                .clone();
+
+            if self.overlay {
+                frame = overlay::draw(&frame, &self.cpu);
+                self.overlay_drawn = Some(Instant::now());
+            }
+
+            if let Some(menu) = &self.pause_menu {
+                let labels: Vec<&str> = MenuItem::ALL
+                    .iter()
+                    .map(|item| self.locale.get(item.label_key()))
+                    .collect();
+                let selected = MenuItem::ALL
+                    .iter()
+                    .position(|item| *item == menu.selected());
+
+                frame = overlay::draw_menu(&frame, &labels, selected.unwrap_or(0));
+            }
+
+            if self.keypad {
+                frame = keypad::draw(&frame, self.keypad_held);
+                self.keypad_frame = (frame.width, frame.height);
+            }
+
+            self.frontend
+                .present(&frame.pixels, frame.width, frame.height);
+        } else {
+            // Only the rows drawn to since the last frame are converted and copied
+            let (frame, rows) =
+                self.filters
+                    .run_rows(&self.cpu.display, width, height, colors, rows);
+            self.frontend
+                .present_rows(&frame.pixels, frame.width, frame.height, rows);
+        }
+
+        if let Some(stats) = &mut self.stats {
+            stats.record_frame();
+        }
+    }
+
+    /// Check triggers after executing `opcode`
+    fn check_triggers(&mut self, opcode: u16) {
+        let fired = match &mut self.triggers {
+            Some(triggers) if self.throttles.triggers.sample(opcode) => triggers.check(&self.cpu),
+            _ => return,
+        };
+
+        for trigger in fired {
+            println!(
+                "{}",
+                self.locale.format(
+                    "status.trigger",
+                    &[("cycle", &self.cycle), ("message", &trigger.message)]
+                )
+            );
+            self.announcement = Some(trigger.message.clone());
+            self.update_title();
+
+            if trigger.quit {
+                self.quit();
+            }
+        }
+    }
+
+    fn update_beeper(&mut self) {
+        let pattern = self.cpu.audio_pattern();
+
+        if pattern != self.audio_pattern {
+            self.audio_pattern = pattern;
+            self.frontend.set_audio_pattern(pattern);
+        }
+
+        let beeping = self.cpu.st > 0;
+
+        if beeping != self.beeping {
+            self.beeping = beeping;
+            self.frontend.set_beeping(beeping);
+
+            let event = BuzzerEvent {
+                cycle: self.cycle,
+                on: beeping,
             };
+            self.buzzer_events.push(event);
+
+            if let Some(movie) = &mut self.recording {
+                movie.buzzer.push(event);
+            }
+
+            #[cfg(feature = "plugins")]
+            for plugin in &self.plugins {
+                plugin.set_beeping(beeping);
+            }
+
+            self.emit(if beeping {
+                Event::SoundStarted
+            } else {
+                Event::SoundStopped
+            });
+        }
+    }
+
+    /// Pause if PC is at a breakpoint, before timers or input are applied
+    fn hit_breakpoint(&mut self) -> bool {
+        match self.cpu.check_breakpoint() {
+            Some(addr) => {
+                let addr = format!("{:04X}", addr);
+                let reason = self.locale.format("status.breakpoint", &[("addr", &addr)]);
+                self.break_at(reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Write the cheats due on this timer tick
+    fn apply_cheats(&mut self) {
+        if let Some(cheats) = &mut self.cheats {
+            cheats.apply(&mut self.cpu);
+        }
+    }
+
+    /// Run the scripts' `before_step` hooks, returning whether one paused or
+    /// quit, so the instruction is not run
+    fn script_break(&mut self) -> bool {
+        #[cfg(feature = "scripting")]
+        self.run_scripts(Hook::BeforeStep);
+
+        (self.paused && self.step == 0) || self.closing
+    }
+
+    /// Run `hook` in every script, pausing with the error if one fails
+    #[cfg(feature = "scripting")]
+    fn run_scripts(&mut self, hook: Hook) {
+        for i in 0..self.scripts.len() {
+            match self.scripts[i].run(hook, &mut self.cpu, self.cycle) {
+                Ok(None) => {}
+                Ok(Some(ScriptRequest::Pause(reason))) => self.break_at(reason),
+                Ok(Some(ScriptRequest::Quit)) => self.quit(),
+                Err(e) => {
+                    let message = format!("{:#}", e);
+                    self.print_error(&message);
+                    self.break_at(message);
+                }
+            }
         }
     }
 
+    fn break_at(&mut self, reason: String) {
+        self.step = 0;
+        self.break_reason = Some(reason);
+        self.pause();
+    }
+
     pub fn pause(&mut self) {
+        self.flush_trace();
         self.paused = true;
-        self.window_handle.set_title(format!("PAUSED - {}", TITLE));
+        self.update_title();
     }
 
     pub fn unpause(&mut self) {
+        // Time spent paused is not caught up on
+        self.sys_time = SystemTime::now();
+        self.clock_time = Instant::now();
         self.paused = false;
-        self.window_handle.set_title(TITLE.into());
+        self.pause_menu = None;
+        self.break_reason = None;
+        self.announcement = None;
+        self.update_title();
     }
 
     pub fn quit(&mut self) {
+        self.flush_trace();
         self.closing = true;
-        self.window_handle.set_title(format!("CLOSING - {}", TITLE));
+        self.update_title();
+    }
+
+    /// Show the window title for the emulator's current state
+    ///
+    /// The title is built from the inside out: the emulator's name, the clock
+    /// speed, the ROM's title, why it stopped (or what a trigger announced),
+    /// and then closing, paused, or waiting for a ROM, so no part hides
+    /// another.
+    fn update_title(&mut self) {
+        let mut title = self.locale.get("window.title").to_string();
+
+        if let Some(hz) = self.instructions_per_second() {
+            let clock = format!("{:.0}", hz);
+            title = self
+                .locale
+                .format("window.clock", &[("clock", &clock), ("title", &title)]);
+        }
+
+        if let Some(rom) = &self.rom_title {
+            title = self
+                .locale
+                .format("window.rom", &[("rom", rom), ("title", &title)]);
+        }
+
+        if let Some((fps, ips)) = self.rate_meter.as_ref().and_then(RateMeter::rates) {
+            let (fps, ips) = (format!("{:.0}", fps), format!("{:.0}", ips));
+            title = self.locale.format(
+                "window.rates",
+                &[("fps", &fps), ("ips", &ips), ("title", &title)],
+            );
+        }
+
+        if let Some(status) = self.break_reason.as_ref().or(self.announcement.as_ref()) {
+            title = self
+                .locale
+                .format("window.status", &[("status", status), ("title", &title)]);
+        }
+
+        let state = if self.closing {
+            Some("window.closing")
+        } else if self.paused {
+            Some("window.paused")
+        } else if self.rom.is_empty() {
+            Some("window.no_rom")
+        } else {
+            None
+        };
+
+        if let Some(key) = state {
+            title = self.locale.format(key, &[("title", &title)]);
+        }
+
+        self.frontend.set_title(title);
+    }
+
+    /// Instructions the clock runs per second, if it runs at a set speed
+    fn instructions_per_second(&self) -> Option<f64> {
+        match (self.clock_period, self.instructions_per_tick) {
+            (Some(period), _) => Some(1. / period.as_secs_f64()),
+            (None, Some(k)) => Some(f64::from(k) / self.timer_period.as_secs_f64()),
+            (None, None) => None,
+        }
+    }
+
+    fn print_error(&self, message: &str) {
+        println!(
+            "{}",
+            self.locale.format("error.prefix", &[("message", &message)])
+        );
+    }
+
+    /// Print the buffered trace leading up to now
+    pub fn flush_trace(&mut self) {
+        if let Some(buffer) = &mut self.trace_buffer {
+            buffer.flush();
+        }
     }
 
     pub fn close(self) {
-        self.window_handle.close();
+        self.frontend.close();
+    }
+}
+
+/// Window scale that keeps the window about as large as `scale` would when
+/// the filters already enlarge the display `upscale` times
+///
+/// Lets a filter such as `crt` draw at full size instead of having its output
+/// enlarged again by the window.
+pub fn window_scale(scale: Scale, upscale: usize) -> Scale {
+    match (scale, scale_factor(scale) / upscale.max(1)) {
+        (Scale::FitScreen, _) => Scale::FitScreen,
+        (_, 0 | 1) => Scale::X1,
+        (_, 2..=3) => Scale::X2,
+        (_, 4..=7) => Scale::X4,
+        (_, 8..=15) => Scale::X8,
+        (_, 16..=31) => Scale::X16,
+        _ => Scale::X32,
+    }
+}
+
+/// Pixels per display pixel at window scale `scale`
+pub fn scale_factor(scale: Scale) -> usize {
+    match scale {
+        Scale::X1 | Scale::FitScreen => 1,
+        Scale::X2 => 2,
+        Scale::X4 => 4,
+        Scale::X8 => 8,
+        Scale::X16 => 16,
+        Scale::X32 => 32,
     }
 }
 
@@ -276,3 +1565,26 @@ fn default_key_map() -> HashMap<Key, u8> {
 
     key_map
 }
+
+fn default_control_map() -> HashMap<Hotkey, Action> {
+    let mut control_map = HashMap::new();
+
+    control_map.insert(Hotkey::plain(Key::Escape), Action::Quit);
+    control_map.insert(Hotkey::plain(Key::F1), Action::Reset);
+    control_map.insert(Hotkey::plain(Key::F2), Action::ToggleDebug);
+    control_map.insert(Hotkey::plain(Key::F3), Action::CyclePalette);
+    control_map.insert(Hotkey::plain(Key::F4), Action::ToggleOverlay);
+    control_map.insert(Hotkey::plain(Key::F5), Action::SaveState);
+    control_map.insert(Hotkey::plain(Key::F6), Action::SetCheckpoint);
+    control_map.insert(Hotkey::plain(Key::F7), Action::LoadState);
+    control_map.insert(Hotkey::plain(Key::F8), Action::ToggleCheats);
+    control_map.insert(Hotkey::plain(Key::F9), Action::ToggleRates);
+    control_map.insert(Hotkey::plain(Key::F10), Action::ToggleMenu);
+    control_map.insert(Hotkey::plain(Key::Space), Action::Pause);
+    control_map.insert(Hotkey::plain(Key::Enter), Action::Step);
+    control_map.insert(Hotkey::plain(Key::Backspace), Action::Rewind);
+    control_map.insert(Hotkey::plain(Key::F12), Action::Screenshot);
+    control_map.insert(Hotkey::plain(Key::Tab), Action::Turbo);
+
+    control_map
+}
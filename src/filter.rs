@@ -0,0 +1,641 @@
+use std::{
+    ops::Range,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+
+use crate::palette;
+
+/// Minimum time between frames redrawn only for animated stages (60 Hz)
+const ANIMATION_PERIOD_S: f64 = 1. / 60.;
+
+/// Brightness a phosphor pixel keeps per frame unless given
+pub const DEFAULT_PHOSPHOR_DECAY: f32 = 0.75;
+
+/// Intermediate framebuffer passed between filter stages
+///
+/// Pixels start out as 0x000000 (off) and 0xFFFFFF (on), with shades of gray
+/// between for the other bit planes; stages may change the colors and the
+/// dimensions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+    /// (off, on) colors of the active palette
+    pub palette: (u32, u32),
+    /// Time since the pipeline was created, for animated stages
+    pub time: Duration,
+}
+
+/// One stage of the display post-processing pipeline
+pub trait DisplayFilter {
+    fn apply(&mut self, frame: &mut Frame);
+
+    /// Output dimensions for an input of `width` x `height`
+    fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        (width, height)
+    }
+
+    /// Whether the output changes over time even if the input does not
+    fn animating(&self) -> bool {
+        false
+    }
+
+    /// Whether each output pixel depends only on the same input pixel and the
+    /// palette, so part of a frame can be redone on its own
+    fn pointwise(&self) -> bool {
+        false
+    }
+}
+
+/// Map intensities onto the palette: black becomes the off color, white the on color
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PaletteMap;
+
+impl DisplayFilter for PaletteMap {
+    fn apply(&mut self, frame: &mut Frame) {
+        let (off, on) = frame.palette;
+
+        for px in frame.pixels.iter_mut() {
+            *px = map_channels(*px, |shift, value| {
+                let lo = (off >> shift) & 0xff;
+                let hi = (on >> shift) & 0xff;
+                (lo * (255 - value) + hi * value) / 255
+            });
+        }
+    }
+
+    fn pointwise(&self) -> bool {
+        true
+    }
+}
+
+/// Crossfade to a new palette instead of switching instantly
+///
+/// Only changes `Frame::palette`, so it must run before the palette map.
+#[derive(Debug, Clone, Default)]
+pub struct Crossfade {
+    pub duration: Duration,
+    /// Palette being faded from, and the fade's start time
+    from: (u32, u32),
+    start: Duration,
+    target: Option<(u32, u32)>,
+    current: (u32, u32),
+}
+
+impl Crossfade {
+    pub fn new(duration: Duration) -> Self {
+        Crossfade {
+            duration,
+            ..Default::default()
+        }
+    }
+}
+
+impl DisplayFilter for Crossfade {
+    fn apply(&mut self, frame: &mut Frame) {
+        match self.target {
+            Some(target) if target == frame.palette => {}
+            Some(_) => {
+                self.from = self.current;
+                self.start = frame.time;
+            }
+            None => {
+                self.from = frame.palette;
+                self.current = frame.palette;
+            }
+        }
+
+        self.target = Some(frame.palette);
+
+        let t = if self.duration.is_zero() {
+            1.
+        } else {
+            (frame.time.saturating_sub(self.start).as_secs_f32() / self.duration.as_secs_f32())
+                .min(1.)
+        };
+
+        let (off, on) = frame.palette;
+        self.current = (lerp(self.from.0, off, t), lerp(self.from.1, on, t));
+        frame.palette = self.current;
+    }
+
+    fn animating(&self) -> bool {
+        self.target != Some(self.current)
+    }
+}
+
+/// Continuously rotate the hue of the palette colors
+///
+/// Only changes `Frame::palette`, so it must run before the palette map.
+/// Grays, including black and white, have no hue and stay unchanged.
+#[derive(Debug, Clone, Copy)]
+pub struct HueCycle {
+    /// Rotation speed in degrees per second
+    pub speed: f32,
+}
+
+impl DisplayFilter for HueCycle {
+    fn apply(&mut self, frame: &mut Frame) {
+        let degrees = (frame.time.as_secs_f32() * self.speed) % 360.;
+        let (off, on) = frame.palette;
+        frame.palette = (rotate_hue(off, degrees), rotate_hue(on, degrees));
+    }
+
+    fn animating(&self) -> bool {
+        self.speed != 0.
+    }
+}
+
+/// Fade pixels out over several frames instead of switching them off instantly
+///
+/// The fade follows the frame time rather than the number of frames, so it
+/// looks the same whether the display is presented on every change or at a
+/// fixed refresh rate.
+#[derive(Debug, Clone, Default)]
+pub struct Phosphor {
+    /// Fraction of the brightness kept per 60 Hz frame (0.0 ~ 1.0)
+    pub decay: f32,
+    /// Brightness of each channel of the previous output, unrounded so slow
+    /// fades do not stall
+    prev: Vec<[f32; 3]>,
+    prev_time: Duration,
+    /// Some pixel is still fading out
+    fading: bool,
+}
+
+impl Phosphor {
+    pub fn new(decay: f32) -> Self {
+        Phosphor {
+            decay: decay.clamp(0., 1.),
+            ..Self::default()
+        }
+    }
+}
+
+impl DisplayFilter for Phosphor {
+    fn apply(&mut self, frame: &mut Frame) {
+        if self.prev.len() != frame.pixels.len() {
+            self.prev = vec![[0.; 3]; frame.pixels.len()];
+        }
+
+        let frames = frame.time.saturating_sub(self.prev_time).as_secs_f64() / ANIMATION_PERIOD_S;
+        let keep = self.decay.powf(frames as f32);
+
+        self.fading = false;
+
+        for (px, prev) in frame.pixels.iter_mut().zip(self.prev.iter_mut()) {
+            for (channel, shift) in prev.iter_mut().zip([16, 8, 0]) {
+                let value = ((*px >> shift) & 0xff) as f32;
+                let faded = *channel * keep;
+
+                // Below half a step, the rest of the fade would not show
+                if faded >= value + 0.5 {
+                    *channel = faded;
+                    self.fading = true;
+                } else {
+                    *channel = value;
+                }
+            }
+
+            *px = prev
+                .iter()
+                .zip([16, 8, 0])
+                .fold(0, |acc, (channel, shift)| {
+                    acc | ((channel.round() as u32) << shift)
+                });
+        }
+
+        self.prev_time = frame.time;
+    }
+
+    fn animating(&self) -> bool {
+        self.fading
+    }
+}
+
+/// Double the height, dimming every second row
+#[derive(Debug, Clone, Copy)]
+pub struct Scanlines {
+    /// Brightness of the dimmed rows (0.0 ~ 1.0)
+    pub brightness: f32,
+}
+
+impl DisplayFilter for Scanlines {
+    fn apply(&mut self, frame: &mut Frame) {
+        let brightness = (self.brightness.clamp(0., 1.) * 256.) as u32;
+        let mut pixels = Vec::with_capacity(frame.pixels.len() * 2);
+
+        for row in frame.pixels.chunks(frame.width.max(1)) {
+            pixels.extend_from_slice(row);
+            pixels.extend(
+                row.iter()
+                    .map(|px| map_channels(*px, |_, value| (value * brightness) >> 8)),
+            );
+        }
+
+        frame.height *= 2;
+        frame.pixels = pixels;
+    }
+
+    fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        (width, height * 2)
+    }
+}
+
+/// Upscale each pixel to a `size` x `size` cell with a one-pixel border
+#[derive(Debug, Clone, Copy)]
+pub struct Grid {
+    pub size: usize,
+    pub color: u32,
+}
+
+impl DisplayFilter for Grid {
+    fn apply(&mut self, frame: &mut Frame) {
+        let size = self.size.max(2);
+        let (width, height) = self.output_size(frame.width, frame.height);
+        let mut pixels = vec![self.color; width * height];
+
+        for y in 0..height {
+            if y % size == size - 1 {
+                continue;
+            }
+
+            for x in 0..width {
+                if x % size != size - 1 {
+                    pixels[y * width + x] = frame.pixels[(y / size) * frame.width + x / size];
+                }
+            }
+        }
+
+        frame.width = width;
+        frame.height = height;
+        frame.pixels = pixels;
+    }
+
+    fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        let size = self.size.max(2);
+        (width * size, height * size)
+    }
+}
+
+/// Upscale like a CRT: each pixel becomes a `size` x `size` cell with a
+/// slightly darker border, and every second row of the output is dimmed
+///
+/// Draws the enlarged picture itself, so the window scale is lowered to match.
+#[derive(Debug, Clone, Copy)]
+pub struct Crt {
+    pub size: usize,
+    /// Brightness of the dimmed rows (0.0 ~ 1.0)
+    pub scanline: f32,
+    /// Brightness of the last row and column of each cell (0.0 ~ 1.0)
+    pub border: f32,
+}
+
+impl DisplayFilter for Crt {
+    fn apply(&mut self, frame: &mut Frame) {
+        let size = self.size.max(2);
+        let (width, height) = self.output_size(frame.width, frame.height);
+        let scanline = (self.scanline.clamp(0., 1.) * 256.) as u32;
+        let border = (self.border.clamp(0., 1.) * 256.) as u32;
+        let mut pixels = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            let row = &frame.pixels[(y / size) * frame.width..][..frame.width];
+            let row_brightness = if y % 2 == 1 { scanline } else { 256 };
+            let row_border = y % size == size - 1;
+
+            pixels.extend((0..width).map(|x| {
+                let mut brightness = row_brightness;
+                if row_border || x % size == size - 1 {
+                    brightness = (brightness * border) >> 8;
+                }
+
+                map_channels(row[x / size], |_, value| (value * brightness) >> 8)
+            }));
+        }
+
+        frame.width = width;
+        frame.height = height;
+        frame.pixels = pixels;
+    }
+
+    fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        let size = self.size.max(2);
+        (width * size, height * size)
+    }
+}
+
+/// Rotate the display clockwise in 90 degree steps
+#[derive(Debug, Clone, Copy)]
+pub struct Rotate {
+    pub quarter_turns: u8,
+}
+
+impl DisplayFilter for Rotate {
+    fn apply(&mut self, frame: &mut Frame) {
+        let (w, h) = (frame.width, frame.height);
+        let turns = self.quarter_turns % 4;
+
+        if turns == 0 {
+            return;
+        }
+
+        let (out_w, out_h) = self.output_size(w, h);
+        let mut pixels = vec![0; frame.pixels.len()];
+
+        for y in 0..h {
+            for x in 0..w {
+                let (ox, oy) = match turns {
+                    1 => (h - 1 - y, x),
+                    2 => (w - 1 - x, h - 1 - y),
+                    _ => (y, w - 1 - x),
+                };
+                pixels[oy * out_w + ox] = frame.pixels[y * w + x];
+            }
+        }
+
+        frame.width = out_w;
+        frame.height = out_h;
+        frame.pixels = pixels;
+    }
+
+    fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        if self.quarter_turns % 2 == 1 {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+}
+
+/// Filter stage as given on the command line or in the config file
+///
+/// `palette`, `fade[=MILLISECONDS]`, `hue[=DEGREES_PER_SECOND]`,
+/// `phosphor[=DECAY]`, `scanlines[=BRIGHTNESS]`, `grid[=SIZE]`, `crt[=SIZE]`,
+/// `rotate=DEGREES`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterSpec {
+    Palette,
+    Fade(u64),
+    Hue(f32),
+    Phosphor(f32),
+    Scanlines(f32),
+    Grid(usize),
+    Crt(usize),
+    Rotate(u16),
+}
+
+impl FilterSpec {
+    /// Whether the stage only adjusts the palette, so it belongs before the palette map
+    pub fn is_palette_effect(&self) -> bool {
+        matches!(self, FilterSpec::Fade(_) | FilterSpec::Hue(_))
+    }
+
+    pub fn build(&self) -> Box<dyn DisplayFilter> {
+        match *self {
+            FilterSpec::Palette => Box::new(PaletteMap),
+            FilterSpec::Fade(ms) => Box::new(Crossfade::new(Duration::from_millis(ms))),
+            FilterSpec::Hue(speed) => Box::new(HueCycle { speed }),
+            FilterSpec::Phosphor(decay) => Box::new(Phosphor::new(decay)),
+            FilterSpec::Scanlines(brightness) => Box::new(Scanlines { brightness }),
+            FilterSpec::Grid(size) => Box::new(Grid {
+                size,
+                color: palette::from_u8_rgb(0x20, 0x20, 0x20),
+            }),
+            FilterSpec::Crt(size) => Box::new(Crt {
+                size,
+                scanline: 0.7,
+                border: 0.8,
+            }),
+            FilterSpec::Rotate(degrees) => Box::new(Rotate {
+                quarter_turns: (degrees / 90 % 4) as u8,
+            }),
+        }
+    }
+}
+
+impl FromStr for FilterSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, arg) = match s.split_once('=') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim())),
+            None => (s, None),
+        };
+
+        let spec = match (name.to_ascii_lowercase().as_str(), arg) {
+            ("palette", None) => FilterSpec::Palette,
+            ("fade", arg) => FilterSpec::Fade(arg.map_or(Ok(500), str::parse)?),
+            ("hue", arg) => FilterSpec::Hue(arg.map_or(Ok(60.), str::parse)?),
+            ("phosphor", arg) => {
+                FilterSpec::Phosphor(arg.map_or(Ok(DEFAULT_PHOSPHOR_DECAY), str::parse)?)
+            }
+            ("scanlines", arg) => FilterSpec::Scanlines(arg.map_or(Ok(0.5), str::parse)?),
+            ("grid", arg) => FilterSpec::Grid(arg.map_or(Ok(4), str::parse)?),
+            ("crt", arg) => FilterSpec::Crt(arg.map_or(Ok(8), str::parse)?),
+            ("rotate", Some(arg)) => {
+                let degrees: u16 = arg.parse()?;
+
+                if !degrees.is_multiple_of(90) {
+                    return Err(anyhow!(
+                        "rotation must be a multiple of 90, got {}",
+                        degrees
+                    ));
+                }
+
+                FilterSpec::Rotate(degrees)
+            }
+            _ => return Err(anyhow!("unknown display filter '{}'", s)),
+        };
+
+        Ok(spec)
+    }
+}
+
+/// Ordered chain of display filters turning the CHIP-8 display into a framebuffer
+pub struct Pipeline {
+    stages: Vec<Box<dyn DisplayFilter>>,
+    frame: Frame,
+    start: Instant,
+    last_run: Option<Instant>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::from_specs(&[])
+    }
+}
+
+impl Pipeline {
+    /// Build a pipeline from `specs`, in order
+    ///
+    /// If `specs` does not include the palette map, it is inserted after the
+    /// last palette effect (`fade`, `hue`), or first if there are none.
+    pub fn from_specs(specs: &[FilterSpec]) -> Self {
+        let mut stages: Vec<Box<dyn DisplayFilter>> = specs.iter().map(|s| s.build()).collect();
+
+        if !specs.contains(&FilterSpec::Palette) {
+            let index = specs
+                .iter()
+                .rposition(FilterSpec::is_palette_effect)
+                .map_or(0, |i| i + 1);
+            stages.insert(index, FilterSpec::Palette.build());
+        }
+
+        Pipeline {
+            stages,
+            frame: Frame::default(),
+            start: Instant::now(),
+            last_run: None,
+        }
+    }
+
+    /// Append `stage` after the others
+    pub fn push(&mut self, stage: Box<dyn DisplayFilter>) {
+        self.stages.push(stage);
+    }
+
+    pub fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        self.stages
+            .iter()
+            .fold((width, height), |(w, h), stage| stage.output_size(w, h))
+    }
+
+    /// How many times the stages enlarge a `width` x `height` display in both
+    /// directions, at least 1
+    pub fn upscale(&self, width: usize, height: usize) -> usize {
+        let (out_width, out_height) = self.output_size(width, height);
+        (out_width / width.max(1))
+            .min(out_height / height.max(1))
+            .max(1)
+    }
+
+    /// Whether an animated stage is due for a new frame although the display has not changed
+    pub fn needs_redraw(&self) -> bool {
+        self.stages.iter().any(|stage| stage.animating())
+            && self
+                .last_run
+                .is_none_or(|t| t.elapsed() >= Duration::from_secs_f64(ANIMATION_PERIOD_S))
+    }
+
+    /// Run every stage on `display`, returning the final frame
+    pub fn run(
+        &mut self,
+        display: &[u8],
+        width: usize,
+        height: usize,
+        colors: (u32, u32),
+    ) -> &Frame {
+        self.frame.width = width;
+        self.frame.height = height;
+        self.frame.palette = colors;
+        self.frame.time = self.start.elapsed();
+        self.frame.pixels.resize(display.len(), 0);
+        self.last_run = Some(Instant::now());
+
+        palette::convert_display(display, &mut self.frame.pixels, (0, 0xff_ffff));
+
+        for stage in self.stages.iter_mut() {
+            stage.apply(&mut self.frame);
+        }
+
+        &self.frame
+    }
+
+    /// Like `run`, but when only `rows` of `display` changed since the last
+    /// run, redo just those rows of the frame
+    ///
+    /// Falls back to a full run unless every stage is pointwise and the size
+    /// and palette are unchanged. Returns the frame and the rows of it that
+    /// changed.
+    pub fn run_rows(
+        &mut self,
+        display: &[u8],
+        width: usize,
+        height: usize,
+        colors: (u32, u32),
+        rows: Range<usize>,
+    ) -> (&Frame, Range<usize>) {
+        let partial = !rows.is_empty()
+            && rows.end <= height
+            && self.stages.iter().all(|stage| stage.pointwise())
+            && (self.frame.width, self.frame.height) == (width, height)
+            && self.frame.pixels.len() == display.len()
+            && self.frame.palette == colors;
+
+        if !partial {
+            let frame = self.run(display, width, height, colors);
+            return (frame, 0..frame.height);
+        }
+
+        let changed = rows.start * width..rows.end * width;
+        let mut part = Frame {
+            width,
+            height: rows.len(),
+            pixels: vec![0; changed.len()],
+            palette: colors,
+            time: self.start.elapsed(),
+        };
+
+        palette::convert_display(&display[changed.clone()], &mut part.pixels, (0, 0xff_ffff));
+
+        for stage in self.stages.iter_mut() {
+            stage.apply(&mut part);
+        }
+
+        self.frame.pixels[changed].copy_from_slice(&part.pixels);
+        self.frame.time = part.time;
+        self.last_run = Some(Instant::now());
+
+        (&self.frame, rows)
+    }
+}
+
+/// Apply `f(shift, value)` to each 8-bit channel of a 0RGB pixel
+fn map_channels(px: u32, f: impl Fn(u32, u32) -> u32) -> u32 {
+    [16, 8, 0].iter().fold(0, |acc, &shift| {
+        acc | (f(shift, (px >> shift) & 0xff).min(0xff) << shift)
+    })
+}
+
+/// Linear interpolation between two 0RGB colors, `t` in 0.0 ~ 1.0
+fn lerp(from: u32, to: u32, t: f32) -> u32 {
+    let t = (t.clamp(0., 1.) * 256.) as u32;
+
+    map_channels(0, |shift, _| {
+        let (a, b) = ((from >> shift) & 0xff, (to >> shift) & 0xff);
+        (a * (256 - t) + b * t) >> 8
+    })
+}
+
+/// Rotate the hue of a 0RGB color, keeping its luminance
+fn rotate_hue(color: u32, degrees: f32) -> u32 {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let [r, g, b] = [16, 8, 0].map(|shift| ((color >> shift) & 0xff) as f32);
+
+    let channel =
+        |(wr, wg, wb): (f32, f32, f32)| (wr * r + wg * g + wb * b).round().clamp(0., 255.) as u32;
+
+    let r2 = channel((
+        0.213 + cos * 0.787 - sin * 0.213,
+        0.715 - cos * 0.715 - sin * 0.715,
+        0.072 - cos * 0.072 + sin * 0.928,
+    ));
+    let g2 = channel((
+        0.213 - cos * 0.213 + sin * 0.143,
+        0.715 + cos * 0.285 + sin * 0.140,
+        0.072 - cos * 0.072 - sin * 0.283,
+    ));
+    let b2 = channel((
+        0.213 - cos * 0.213 - sin * 0.787,
+        0.715 - cos * 0.715 + sin * 0.715,
+        0.072 + cos * 0.928 + sin * 0.072,
+    ));
+
+    (r2 << 16) | (g2 << 8) | b2
+}
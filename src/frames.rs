@@ -0,0 +1,188 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+
+use crate::{chip8::plane_color, emu::Emulator, frontend::Frontend, png};
+
+/// XOR image color for pixels that differ
+const XOR_ON: u32 = 0xFFFFFF;
+/// XOR image color for pixels that match
+const XOR_OFF: u32 = 0x000000;
+
+/// Writes the display to numbered PNG files each time it changes
+///
+/// Frames are `frame-000000.png`, `frame-000001.png`, ... in the order shown,
+/// so two runs of the same input can be compared with `compare`.
+pub struct FrameDumper {
+    dir: PathBuf,
+    count: usize,
+    last: Vec<u8>,
+}
+
+impl FrameDumper {
+    /// Dump into `dir`, creating it if needed
+    pub fn new(dir: &Path) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir)?;
+
+        Ok(FrameDumper {
+            dir: dir.to_path_buf(),
+            count: 0,
+            last: Vec::new(),
+        })
+    }
+
+    /// Frames written so far
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Write the display if it changed since the last frame
+    ///
+    /// Call after every `Emulator::step`.
+    pub fn update<F: Frontend>(&mut self, emu: &Emulator<F>) -> anyhow::Result<()> {
+        if emu.cpu.display[..] == self.last[..] {
+            return Ok(());
+        }
+
+        let pixels: Vec<u32> = emu
+            .cpu
+            .display
+            .iter()
+            .map(|&px| plane_color(px, (emu.color_off, emu.color_on)))
+            .collect();
+
+        png::save(
+            &self.dir.join(frame_name(self.count)),
+            &pixels,
+            emu.cpu.display_width(),
+            emu.cpu.display_height(),
+        )?;
+
+        self.last = emu.cpu.display.to_vec();
+        self.count += 1;
+
+        Ok(())
+    }
+}
+
+fn frame_name(n: usize) -> String {
+    format!("frame-{:06}.png", n)
+}
+
+/// The first way two frame sequences differ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// A frame present in only one directory
+    Missing { name: String, in_first: bool },
+    /// Frames with different dimensions
+    Size {
+        name: String,
+        first: (usize, usize),
+        second: (usize, usize),
+    },
+    /// Frames whose pixels differ
+    Pixels {
+        name: String,
+        /// Number of differing pixels
+        count: usize,
+        /// White where the frames differ, black elsewhere
+        xor: Vec<u32>,
+        width: usize,
+        height: usize,
+    },
+}
+
+/// Result of comparing two frame directories
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison {
+    /// Frames that matched before the first difference (or in total)
+    pub matching: usize,
+    pub difference: Option<Difference>,
+}
+
+/// Compare the PNG frames of two dump directories in name order, stopping at
+/// the first difference
+pub fn compare(first: &Path, second: &Path) -> anyhow::Result<Comparison> {
+    let first_names = png_names(first)?;
+    let second_names = png_names(second)?;
+    let mut matching = 0;
+
+    for name in first_names.union(&second_names) {
+        let (in_first, in_second) = (first_names.contains(name), second_names.contains(name));
+
+        if !(in_first && in_second) {
+            return Ok(Comparison {
+                matching,
+                difference: Some(Difference::Missing {
+                    name: name.clone(),
+                    in_first,
+                }),
+            });
+        }
+
+        let (a, a_width, a_height) = png::load(&first.join(name))?;
+        let (b, b_width, b_height) = png::load(&second.join(name))?;
+
+        if (a_width, a_height) != (b_width, b_height) {
+            return Ok(Comparison {
+                matching,
+                difference: Some(Difference::Size {
+                    name: name.clone(),
+                    first: (a_width, a_height),
+                    second: (b_width, b_height),
+                }),
+            });
+        }
+
+        let count = a.iter().zip(&b).filter(|(a, b)| a != b).count();
+
+        if count > 0 {
+            let xor = a
+                .iter()
+                .zip(&b)
+                .map(|(a, b)| if a != b { XOR_ON } else { XOR_OFF })
+                .collect();
+
+            return Ok(Comparison {
+                matching,
+                difference: Some(Difference::Pixels {
+                    name: name.clone(),
+                    count,
+                    xor,
+                    width: a_width,
+                    height: a_height,
+                }),
+            });
+        }
+
+        matching += 1;
+    }
+
+    Ok(Comparison {
+        matching,
+        difference: None,
+    })
+}
+
+fn png_names(dir: &Path) -> anyhow::Result<BTreeSet<String>> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| anyhow!("failed to read {}: {}", dir.display(), e))?;
+    let mut names = BTreeSet::new();
+
+    for entry in entries {
+        let path = entry?.path();
+        let is_png = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+
+        if let (true, Some(name)) = (is_png, path.file_name().and_then(|n| n.to_str())) {
+            names.insert(name.to_string());
+        }
+    }
+
+    Ok(names)
+}
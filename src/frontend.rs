@@ -0,0 +1,289 @@
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    ops::Range,
+    path::PathBuf,
+};
+
+use minifb::Key;
+
+use crate::chip8::AudioPattern;
+
+/// Frame output, owning the window (or equivalent) lifecycle
+pub trait Display {
+    /// Called once at the start of every emulator step
+    fn update(&mut self) {}
+
+    fn is_closing(&self) -> bool;
+
+    /// Present a new `width` x `height` frame of 0RGB pixels
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize);
+
+    /// Present a frame that differs from the last one only in `rows`
+    ///
+    /// `buffer` holds the whole frame. Frontends that copy frames elsewhere
+    /// can copy just those rows; by default the frame is presented as usual.
+    fn present_rows(&mut self, buffer: &[u32], width: usize, height: usize, _rows: Range<usize>) {
+        self.present(buffer, width, height);
+    }
+
+    fn set_title(&mut self, title: String);
+
+    /// Size of the window in host pixels, if there is one
+    fn window_size(&self) -> Option<(usize, usize)> {
+        None
+    }
+
+    fn close(self);
+}
+
+/// Host key input
+pub trait Input {
+    /// Keys currently held down
+    fn get_keys(&self) -> Option<Vec<Key>>;
+
+    /// Keys pressed since the last call
+    fn get_keys_pressed(&mut self) -> Option<HashSet<Key>>;
+
+    /// File dropped onto the window since the last call; none by default
+    fn take_dropped_file(&mut self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Points held down on the window, in pixels of the last presented frame:
+    /// the mouse while its left button is held, and each finger touching it;
+    /// none by default
+    fn get_pointers(&self) -> Vec<(usize, usize)> {
+        Vec::new()
+    }
+}
+
+/// Position `pos` in a `window`-sized area in pixels of a `frame`-sized frame
+/// scaled to fit it, keeping its aspect ratio, and centered
+///
+/// None if `pos` is outside the frame.
+///
+/// ```
+/// use chip8::frontend::letterboxed;
+///
+/// // 64x32 in a 128x128 window: scaled by 2, with 32-pixel bars above and below
+/// assert_eq!(letterboxed((64, 32), (128., 128.), (127., 32.)), Some((63, 0)));
+/// assert_eq!(letterboxed((64, 32), (128., 128.), (64., 16.)), None);
+/// ```
+pub fn letterboxed(
+    frame: (usize, usize),
+    window: (f32, f32),
+    pos: (f32, f32),
+) -> Option<(usize, usize)> {
+    let (width, height) = (frame.0 as f32, frame.1 as f32);
+
+    let scale = (window.0 / width).min(window.1 / height);
+    if scale.is_nan() || scale <= 0. {
+        return None;
+    }
+
+    let x = (pos.0 - (window.0 - width * scale) / 2.) / scale;
+    let y = (pos.1 - (window.1 - height * scale) / 2.) / scale;
+
+    if (0. ..width).contains(&x) && (0. ..height).contains(&y) {
+        Some((x as usize, y as usize))
+    } else {
+        None
+    }
+}
+
+/// Buzzer output; silent by default
+pub trait Beeper {
+    /// Turn the buzzer on or off
+    fn set_beeping(&mut self, _beeping: bool) {}
+
+    /// Play `pattern` instead of the buzzer tone while beeping, or go back to
+    /// the tone if `None`
+    ///
+    /// Backends that cannot play patterns keep the tone.
+    fn set_audio_pattern(&mut self, _pattern: Option<AudioPattern>) {}
+}
+
+/// Backend driven by the `Emulator` for display output, input, and sound
+///
+/// Implemented for every type that is a `Display`, `Input`, and `Beeper`.
+/// Backends that only cover one of these can be combined with `Split`.
+pub trait Frontend: Display + Input + Beeper {}
+
+impl<T: Display + Input + Beeper> Frontend for T {}
+
+/// Frontend assembled from separate display, input, and sound backends
+///
+/// Closing closes the display; the input and beeper are dropped.
+#[derive(Debug, Default)]
+pub struct Split<D, I, B> {
+    pub display: D,
+    pub input: I,
+    pub beeper: B,
+}
+
+impl<D: Display, I, B> Display for Split<D, I, B> {
+    fn update(&mut self) {
+        self.display.update();
+    }
+
+    fn is_closing(&self) -> bool {
+        self.display.is_closing()
+    }
+
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) {
+        self.display.present(buffer, width, height);
+    }
+
+    fn present_rows(&mut self, buffer: &[u32], width: usize, height: usize, rows: Range<usize>) {
+        self.display.present_rows(buffer, width, height, rows);
+    }
+
+    fn set_title(&mut self, title: String) {
+        self.display.set_title(title);
+    }
+
+    fn window_size(&self) -> Option<(usize, usize)> {
+        self.display.window_size()
+    }
+
+    fn close(self) {
+        self.display.close();
+    }
+}
+
+impl<D, I: Input, B> Input for Split<D, I, B> {
+    fn get_keys(&self) -> Option<Vec<Key>> {
+        self.input.get_keys()
+    }
+
+    fn get_keys_pressed(&mut self) -> Option<HashSet<Key>> {
+        self.input.get_keys_pressed()
+    }
+
+    fn take_dropped_file(&mut self) -> Option<PathBuf> {
+        self.input.take_dropped_file()
+    }
+
+    fn get_pointers(&self) -> Vec<(usize, usize)> {
+        self.input.get_pointers()
+    }
+}
+
+impl<D, I, B: Beeper> Beeper for Split<D, I, B> {
+    fn set_beeping(&mut self, beeping: bool) {
+        self.beeper.set_beeping(beeping);
+    }
+
+    fn set_audio_pattern(&mut self, pattern: Option<AudioPattern>) {
+        self.beeper.set_audio_pattern(pattern);
+    }
+}
+
+/// Input with no keys ever pressed
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoInput;
+
+impl Input for NoInput {
+    fn get_keys(&self) -> Option<Vec<Key>> {
+        None
+    }
+
+    fn get_keys_pressed(&mut self) -> Option<HashSet<Key>> {
+        None
+    }
+}
+
+/// Beeper that makes no sound
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoBeeper;
+
+impl Beeper for NoBeeper {}
+
+/// Frontend with no window or audio device
+///
+/// Collects presented frames and buzzer transitions, and feeds scripted key
+/// input to the emulator. Time is counted in emulator steps (calls to `update`).
+#[derive(Debug, Default)]
+pub struct HeadlessFrontend {
+    /// Frames presented, in order
+    pub frames: VecDeque<Vec<u32>>,
+
+    /// Maximum number of frames to keep (oldest are dropped first)
+    pub frame_limit: Option<usize>,
+
+    /// Buzzer transitions as (step, on)
+    pub beeps: Vec<(u64, bool)>,
+
+    pub title: String,
+
+    /// Number of steps after which the frontend reports closing
+    pub close_at: Option<u64>,
+
+    tick: u64,
+    held: BTreeMap<u64, Vec<(Key, u64)>>,
+}
+
+impl HeadlessFrontend {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Hold `key` down from step `start` until (not including) step `end`
+    pub fn hold_key(&mut self, key: Key, start: u64, end: u64) {
+        self.held.entry(start).or_default().push((key, end));
+    }
+}
+
+impl Display for HeadlessFrontend {
+    fn update(&mut self) {
+        self.tick += 1;
+    }
+
+    fn is_closing(&self) -> bool {
+        matches!(self.close_at, Some(n) if self.tick >= n)
+    }
+
+    fn present(&mut self, buffer: &[u32], _width: usize, _height: usize) {
+        if let Some(limit) = self.frame_limit {
+            while self.frames.len() >= limit.max(1) {
+                self.frames.pop_front();
+            }
+        }
+
+        self.frames.push_back(buffer.to_vec());
+    }
+
+    fn set_title(&mut self, title: String) {
+        self.title = title;
+    }
+
+    fn close(self) {}
+}
+
+impl Input for HeadlessFrontend {
+    fn get_keys(&self) -> Option<Vec<Key>> {
+        let keys: Vec<Key> = self
+            .held
+            .range(..=self.tick)
+            .flat_map(|(_, keys)| keys.iter())
+            .filter(|(_, end)| self.tick < *end)
+            .map(|(key, _)| *key)
+            .collect();
+
+        if keys.is_empty() {
+            None
+        } else {
+            Some(keys)
+        }
+    }
+
+    fn get_keys_pressed(&mut self) -> Option<HashSet<Key>> {
+        None
+    }
+}
+
+impl Beeper for HeadlessFrontend {
+    fn set_beeping(&mut self, beeping: bool) {
+        self.beeps.push((self.tick, beeping));
+    }
+}
@@ -0,0 +1,76 @@
+use alloc::{format, string::String};
+
+use crate::chip8::{Chip8, Chip8Panic, Quirks, XO_RAM_SIZE};
+
+/// Run `data` as opcodes on a fresh `Chip8`, panicking if an invariant breaks
+///
+/// For use as a fuzz target, e.g. with cargo-fuzz:
+///
+/// ```ignore
+/// libfuzzer_sys::fuzz_target!(|data: &[u8]| chip8::fuzz::run(data));
+/// ```
+pub fn run(data: &[u8]) {
+    if let Err(e) = check(data) {
+        panic!("{}", e);
+    }
+}
+
+/// Run `data` as opcodes on a fresh `Chip8` and check the machine after each
+///
+/// The first byte selects the machine: bits 0 to 3 turn on quirks in the order
+/// of the boolean `Quirks` fields, bits 4 to 6 SCHIP, XO-CHIP RAM, and hi-res,
+/// and bit 7 holds down the key in bits 0 to 3. Every following pair of bytes
+/// is an opcode, executed directly whatever PC is, so any instruction can
+/// follow any machine state. A run ends at the first opcode the interpreter
+/// rejects with a `Chip8Panic`, which is expected behavior rather than a
+/// failure.
+pub fn check(data: &[u8]) -> Result<(), String> {
+    let (flags, opcodes) = match data.split_first() {
+        Some((flags, opcodes)) => (*flags, opcodes),
+        None => return Ok(()),
+    };
+
+    let mut cpu = Chip8::new();
+    cpu.quirks = Quirks {
+        shift_vy: flags & 1 != 0,
+        load_store_increment_i: flags & 2 != 0,
+        jump_vx: flags & 4 != 0,
+        logic_vf_reset: flags & 8 != 0,
+        ..Quirks::default()
+    };
+    cpu.set_schip(flags & 0x10 != 0);
+    if flags & 0x20 != 0 {
+        cpu.set_ram_size(XO_RAM_SIZE);
+    }
+    cpu.set_hires(flags & 0x40 != 0);
+    // Let FX0A and the key skips take both branches
+    cpu.keys[usize::from(flags & 0xF)] = flags & 0x80 != 0;
+
+    for pair in opcodes.chunks_exact(2) {
+        let opcode = u16::from_be_bytes([pair[0], pair[1]]);
+        let result: Result<(), Chip8Panic> = cpu.execute_opcode(opcode);
+
+        check_invariants(&cpu).map_err(|e| format!("after {:04X}: {}", opcode, e))?;
+
+        if result.is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that `cpu` is in a state the interpreter can always continue from
+///
+/// PC must address RAM and SP must index the stack.
+pub fn check_invariants(cpu: &Chip8) -> Result<(), String> {
+    if usize::from(cpu.pc) >= cpu.ram.len() {
+        return Err(format!("PC {:04X} is outside of RAM", cpu.pc));
+    }
+
+    if usize::from(cpu.sp) >= cpu.stack.len() {
+        return Err(format!("SP {} is outside of the stack", cpu.sp));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use gilrs::{Button, EventType, Gilrs};
+
+/// Gamepad buttons that can be bound, by name
+pub const BUTTONS: [(&str, Button); 19] = [
+    ("South", Button::South),
+    ("East", Button::East),
+    ("North", Button::North),
+    ("West", Button::West),
+    ("C", Button::C),
+    ("Z", Button::Z),
+    ("LeftTrigger", Button::LeftTrigger),
+    ("LeftTrigger2", Button::LeftTrigger2),
+    ("RightTrigger", Button::RightTrigger),
+    ("RightTrigger2", Button::RightTrigger2),
+    ("Select", Button::Select),
+    ("Start", Button::Start),
+    ("Mode", Button::Mode),
+    ("LeftThumb", Button::LeftThumb),
+    ("RightThumb", Button::RightThumb),
+    ("DPadUp", Button::DPadUp),
+    ("DPadDown", Button::DPadDown),
+    ("DPadLeft", Button::DPadLeft),
+    ("DPadRight", Button::DPadRight),
+];
+
+/// Gamepad input mapped to the hex keypad
+pub struct Gamepad {
+    gilrs: Gilrs,
+    pub button_map: HashMap<Button, u8>,
+    held: HashSet<Button>,
+}
+
+impl Gamepad {
+    pub fn new() -> anyhow::Result<Self> {
+        let gilrs = Gilrs::new().map_err(|e| anyhow!("failed to open gamepad input: {}", e))?;
+
+        Ok(Gamepad {
+            gilrs,
+            button_map: default_button_map(),
+            held: HashSet::new(),
+        })
+    }
+
+    /// Process pending gamepad events, returning the keypad keys currently held
+    pub fn poll(&mut self) -> Vec<u8> {
+        while let Some(event) = self.gilrs.next_event() {
+            match event.event {
+                EventType::ButtonPressed(button, _) => {
+                    self.held.insert(button);
+                }
+                EventType::ButtonReleased(button, _) => {
+                    self.held.remove(&button);
+                }
+                EventType::Disconnected => {
+                    self.held.clear();
+                }
+                _ => {}
+            }
+        }
+
+        self.held
+            .iter()
+            .filter_map(|button| self.button_map.get(button).copied())
+            .collect()
+    }
+}
+
+pub fn parse_button(name: &str) -> Option<Button> {
+    BUTTONS
+        .iter()
+        .find(|(button_name, _)| button_name.eq_ignore_ascii_case(name.trim()))
+        .map(|(_, button)| *button)
+}
+
+/// D-pad on 2/4/6/8 (the usual CHIP-8 directions), face buttons on 5, 0, A, B
+fn default_button_map() -> HashMap<Button, u8> {
+    let mut button_map = HashMap::new();
+
+    button_map.insert(Button::DPadUp, 0x2);
+    button_map.insert(Button::DPadLeft, 0x4);
+    button_map.insert(Button::DPadRight, 0x6);
+    button_map.insert(Button::DPadDown, 0x8);
+    button_map.insert(Button::South, 0x5);
+    button_map.insert(Button::East, 0x0);
+    button_map.insert(Button::North, 0xA);
+    button_map.insert(Button::West, 0xB);
+    button_map.insert(Button::Start, 0xF);
+
+    button_map
+}
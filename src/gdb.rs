@@ -0,0 +1,341 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use anyhow::anyhow;
+
+use crate::{
+    emu::Emulator,
+    frontend::Frontend,
+    watch::{Access, Watchpoint},
+};
+
+/// Stop reply for SIGTRAP (breakpoint, watchpoint, or single step)
+const STOP_TRAP: &str = "S05";
+/// Stop reply for SIGINT (interrupted by the client)
+const STOP_INTERRUPT: &str = "S02";
+
+/// GDB remote serial protocol server
+///
+/// Accepts one client at a time. Registers are exposed in this order, all
+/// little-endian: V0..VF (1 byte each), I (2), PC (2), SP (1), DT (1), ST (1).
+///
+/// Supported packets: `?`, `g`, `G`, `m`, `M`, `c`, `s`, `Z0`/`z0` and `Z1`/`z1`
+/// (breakpoints), `Z2`/`Z3`/`Z4` and `z2`/`z3`/`z4` (write/read/access
+/// watchpoints), `k`, `D`, and `qSupported`/`qAttached`. Other packets get an
+/// empty (unsupported) reply.
+pub struct GdbServer {
+    listener: TcpListener,
+    client: Option<TcpStream>,
+    buffer: Vec<u8>,
+    /// Whether the client is waiting for a stop reply
+    running: bool,
+}
+
+impl GdbServer {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(GdbServer {
+            listener,
+            client: None,
+            buffer: Vec::new(),
+            running: false,
+        })
+    }
+
+    pub fn local_addr(&self) -> anyhow::Result<std::net::SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Accept a client, handle pending packets, and report stops
+    ///
+    /// Call after every `Emulator::step`.
+    pub fn update<F: Frontend>(&mut self, emu: &mut Emulator<F>) -> anyhow::Result<()> {
+        if self.client.is_none() {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    self.client = Some(stream);
+                    self.buffer.clear();
+                    self.running = false;
+                    emu.pause();
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        if !self.receive()? {
+            self.disconnect(emu);
+            return Ok(());
+        }
+
+        while let Some(packet) = self.next_packet() {
+            match packet {
+                Packet::Interrupt => {
+                    emu.pause();
+                    self.running = false;
+                    self.send(STOP_INTERRUPT)?;
+                }
+                Packet::Data(data) => {
+                    self.write(b"+")?;
+
+                    if let Some(reply) = self.handle(&data, emu)? {
+                        self.send(&reply)?;
+                    }
+
+                    if self.client.is_none() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        if self.running && emu.paused && emu.step == 0 {
+            self.running = false;
+            self.send(STOP_TRAP)?;
+        }
+
+        Ok(())
+    }
+
+    /// Handle one packet, returning the reply or `None` if it comes later as a stop reply
+    fn handle<F: Frontend>(
+        &mut self,
+        data: &str,
+        emu: &mut Emulator<F>,
+    ) -> anyhow::Result<Option<String>> {
+        let cpu = &mut emu.cpu;
+        let (cmd, args) = data.split_at(data.chars().next().map_or(0, char::len_utf8));
+
+        let reply = match cmd {
+            "?" => STOP_TRAP.to_string(),
+            "g" => {
+                let mut regs = cpu.v.to_vec();
+                regs.extend_from_slice(&cpu.i.to_le_bytes());
+                regs.extend_from_slice(&cpu.pc.to_le_bytes());
+                regs.extend_from_slice(&[cpu.sp, cpu.dt, cpu.st]);
+                to_hex(&regs)
+            }
+            "G" => match from_hex(args) {
+                Some(regs) if regs.len() == 23 && usize::from(regs[20]) < cpu.stack.len() => {
+                    cpu.v.copy_from_slice(&regs[..16]);
+                    cpu.i = u16::from_le_bytes([regs[16], regs[17]]);
+                    cpu.pc = u16::from_le_bytes([regs[18], regs[19]]);
+                    cpu.sp = regs[20];
+                    cpu.dt = regs[21];
+                    cpu.st = regs[22];
+                    "OK".into()
+                }
+                _ => "E01".into(),
+            },
+            "m" => match parse_addr_len(args) {
+                Some((addr, len)) => {
+                    let bytes: Vec<u8> = (addr..addr + len)
+                        .map(|a| cpu.ram[a % cpu.ram.len()])
+                        .collect();
+                    to_hex(&bytes)
+                }
+                None => "E01".into(),
+            },
+            "M" => {
+                let parsed = args
+                    .split_once(':')
+                    .and_then(|(range, data)| Some((parse_addr_len(range)?, from_hex(data)?)));
+
+                match parsed {
+                    Some(((addr, len), bytes)) if bytes.len() == len => {
+                        for (offset, b) in bytes.iter().enumerate() {
+                            let a = (addr + offset) % cpu.ram.len();
+                            cpu.ram[a] = *b;
+                        }
+                        cpu.display_dirty = true;
+                        "OK".into()
+                    }
+                    _ => "E01".into(),
+                }
+            }
+            "c" => {
+                emu.unpause();
+                self.running = true;
+                return Ok(None);
+            }
+            "s" => {
+                if !emu.paused {
+                    emu.pause();
+                }
+                emu.step = 1;
+                self.running = true;
+                return Ok(None);
+            }
+            "Z" | "z" => match self.set_point(cmd == "Z", args, emu) {
+                Ok(true) => "OK".into(),
+                Ok(false) => String::new(),
+                Err(_) => "E01".into(),
+            },
+            "k" => {
+                emu.quit();
+                self.client = None;
+                return Ok(None);
+            }
+            "D" => {
+                self.send("OK")?;
+                self.disconnect(emu);
+                return Ok(None);
+            }
+            "H" => "OK".into(),
+            "q" if args.starts_with("Supported") => "PacketSize=1000".into(),
+            "q" if args.starts_with("Attached") => "1".into(),
+            _ => String::new(),
+        };
+
+        Ok(Some(reply))
+    }
+
+    /// Insert or remove a breakpoint/watchpoint, returning false if the type is unsupported
+    fn set_point<F: Frontend>(
+        &mut self,
+        insert: bool,
+        args: &str,
+        emu: &mut Emulator<F>,
+    ) -> anyhow::Result<bool> {
+        let mut fields = args.split(',');
+        let kind = fields.next().unwrap_or_default();
+        let addr = u16::from_str_radix(fields.next().unwrap_or_default(), 16)?;
+        let len = u16::from_str_radix(fields.next().unwrap_or("1"), 16)?.max(1);
+
+        let access: &[Access] = match kind {
+            "0" | "1" => {
+                if insert {
+                    emu.cpu.breakpoints.insert(addr);
+                } else {
+                    emu.cpu.breakpoints.remove(&addr);
+                }
+                return Ok(true);
+            }
+            "2" => &[Access::Write],
+            "3" => &[Access::Read],
+            "4" => &[Access::Read, Access::Write],
+            _ => return Ok(false),
+        };
+
+        let watchpoint = Watchpoint::new(addr, u32::from(addr) + u32::from(len), access)
+            .ok_or_else(|| anyhow!("watchpoint past the end of memory"))?;
+
+        if insert {
+            emu.cpu.watchpoints.add(watchpoint);
+        } else {
+            let index = emu
+                .cpu
+                .watchpoints
+                .list()
+                .iter()
+                .position(|w| *w == watchpoint)
+                .ok_or_else(|| anyhow!("no such watchpoint"))?;
+            emu.cpu.watchpoints.remove(index);
+        }
+
+        Ok(true)
+    }
+
+    /// Read available bytes from the client, returning false if it disconnected
+    fn receive(&mut self) -> anyhow::Result<bool> {
+        let client = match &mut self.client {
+            Some(client) => client,
+            None => return Ok(true),
+        };
+
+        let mut chunk = [0; 1024];
+
+        loop {
+            match client.read(&mut chunk) {
+                Ok(0) => return Ok(false),
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(true),
+                Err(e) if e.kind() == io::ErrorKind::ConnectionReset => return Ok(false),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn next_packet(&mut self) -> Option<Packet> {
+        loop {
+            match self.buffer.first()? {
+                b'+' | b'-' => {
+                    self.buffer.remove(0);
+                }
+                0x03 => {
+                    self.buffer.remove(0);
+                    return Some(Packet::Interrupt);
+                }
+                b'$' => {
+                    let end = self.buffer.iter().position(|b| *b == b'#')?;
+
+                    // Wait for the two checksum digits
+                    if self.buffer.len() < end + 3 {
+                        return None;
+                    }
+
+                    let packet: Vec<u8> = self.buffer.drain(..end + 3).collect();
+                    return Some(Packet::Data(
+                        String::from_utf8_lossy(&packet[1..end]).into_owned(),
+                    ));
+                }
+                _ => {
+                    self.buffer.remove(0);
+                }
+            }
+        }
+    }
+
+    fn send(&mut self, data: &str) -> anyhow::Result<()> {
+        let checksum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        self.write(format!("${}#{:02x}", data, checksum).as_bytes())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        if let Some(client) = &mut self.client {
+            client.write_all(bytes)?;
+        }
+
+        Ok(())
+    }
+
+    fn disconnect<F: Frontend>(&mut self, emu: &mut Emulator<F>) {
+        self.client = None;
+        self.running = false;
+        emu.unpause();
+    }
+}
+
+enum Packet {
+    Data(String),
+    Interrupt,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn parse_addr_len(s: &str) -> Option<(usize, usize)> {
+    let (addr, len) = s.split_once(',')?;
+    Some((
+        usize::from_str_radix(addr, 16).ok()?,
+        usize::from_str_radix(len, 16).ok()?,
+    ))
+}
@@ -0,0 +1,416 @@
+use std::{
+    fs,
+    io::{self, Stdout, Write},
+    path::PathBuf,
+};
+
+use anyhow::anyhow;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Print, PrintStyledContent, StyledContent, Stylize},
+    terminal::{self, ClearType},
+};
+
+use chip8::{
+    disasm::{self, MarkKind, Marks},
+    locale::Locale,
+    rom::{self, RomSource},
+};
+
+const HEX_COLUMNS: usize = 8;
+
+const DISASM_COLUMN: u16 = 31;
+const DISASM_WIDTH: usize = 28;
+const SPRITE_COLUMN: u16 = 61;
+
+/// Sprite rows previewed from the cursor, the most DRW draws
+const SPRITE_ROWS: usize = 15;
+
+/// Rows below the panes for the legend, status, and help
+const FOOTER_ROWS: u16 = 3;
+
+/// Terminal ROM viewer with hex, disassembly, and sprite panes that follow
+/// one cursor, for marking regions as code or data
+///
+/// Marks are saved in the `disasm::Marks` text form, which `--disassemble
+/// --marks` reads. Puts the terminal in raw mode on an alternate screen until
+/// dropped.
+pub struct HexView {
+    out: Stdout,
+    rom: Vec<u8>,
+    /// Address the ROM is loaded at
+    offset: u16,
+    marks: Marks,
+    marks_path: PathBuf,
+    /// ROM index of the cursor
+    cursor: usize,
+    /// Other end of the selection, if one is being made
+    anchor: Option<usize>,
+    /// First hex row shown
+    top_row: usize,
+    /// Marks changed since they were saved
+    modified: bool,
+    /// Quitting was refused once because of unsaved marks
+    quit_warned: bool,
+    message: String,
+    closing: bool,
+    locale: Locale,
+}
+
+impl HexView {
+    /// View the ROM from `source`, with marks from `marks`, or else the .marks
+    /// file next to it
+    pub fn open(
+        source: &dyn RomSource,
+        marks: Option<PathBuf>,
+        locale: Locale,
+    ) -> anyhow::Result<Self> {
+        let (program, _) = rom::load_with_metadata(source)?;
+
+        let marks_path = marks
+            .or_else(|| source.local_path().map(|path| path.with_extension("marks")))
+            .ok_or_else(|| {
+                anyhow!(locale.format("hexview.needs_marks", &[("rom", &source.name())]))
+            })?;
+
+        Self::new(program, 0x200, marks_path, locale)
+    }
+
+    /// View `rom`, loading marks from `marks_path` if it exists
+    pub fn new(
+        rom: Vec<u8>,
+        offset: u16,
+        marks_path: PathBuf,
+        locale: Locale,
+    ) -> anyhow::Result<Self> {
+        if rom.is_empty() {
+            return Err(anyhow!(locale.get("hexview.empty_rom").to_string()));
+        }
+
+        let marks = if marks_path.exists() {
+            let text = fs::read_to_string(&marks_path)?;
+            Marks::parse(&text).map_err(|e| anyhow!("{}: {}", marks_path.display(), e))?
+        } else {
+            Marks::default()
+        };
+
+        let mut out = io::stdout();
+
+        terminal::enable_raw_mode()?;
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        Ok(HexView {
+            out,
+            rom,
+            offset,
+            marks,
+            message: locale.format("hexview.marks_file", &[("path", &marks_path.display())]),
+            marks_path,
+            cursor: 0,
+            anchor: None,
+            top_row: 0,
+            modified: false,
+            quit_warned: false,
+            closing: false,
+            locale,
+        })
+    }
+
+    /// Draw and handle keys until the user quits
+    pub fn run(&mut self) -> anyhow::Result<()> {
+        while !self.closing {
+            self.draw()?;
+
+            if let Event::Key(key) = event::read()? {
+                self.handle_key(key);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind == KeyEventKind::Release {
+            return;
+        }
+
+        let page = self.pane_rows() * HEX_COLUMNS;
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.closing = true
+            }
+            KeyCode::Left => self.move_cursor(-1),
+            KeyCode::Right => self.move_cursor(1),
+            KeyCode::Up => self.move_cursor(-(HEX_COLUMNS as isize)),
+            KeyCode::Down => self.move_cursor(HEX_COLUMNS as isize),
+            KeyCode::PageUp => self.move_cursor(-(page as isize)),
+            KeyCode::PageDown => self.move_cursor(page as isize),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.rom.len() - 1,
+            KeyCode::Char(' ') => {
+                self.anchor = match self.anchor {
+                    Some(_) => None,
+                    None => Some(self.cursor),
+                }
+            }
+            KeyCode::Char('c') => self.mark(Some(MarkKind::Code)),
+            KeyCode::Char('d') => self.mark(Some(MarkKind::Data)),
+            KeyCode::Char('u') => self.mark(None),
+            KeyCode::Char('s') => {
+                self.message = match self.save() {
+                    Ok(()) => self.locale.format(
+                        "hexview.saved_marks",
+                        &[("path", &self.marks_path.display())],
+                    ),
+                    Err(e) => self
+                        .locale
+                        .format("error.prefix", &[("message", &format!("{:#}", e))]),
+                }
+            }
+            KeyCode::Char('q') | KeyCode::Esc => {
+                if self.modified && !self.quit_warned {
+                    self.message = self.locale.get("hexview.unsaved").to_string();
+                    self.quit_warned = true;
+                } else {
+                    self.closing = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn move_cursor(&mut self, by: isize) {
+        self.cursor = self
+            .cursor
+            .saturating_add_signed(by)
+            .min(self.rom.len() - 1);
+    }
+
+    /// ROM indexes selected: from the anchor to the cursor, or the
+    /// instruction or byte under the cursor
+    fn selection(&self, kind: Option<MarkKind>) -> (usize, usize) {
+        match (self.anchor, kind) {
+            (Some(anchor), _) => (anchor.min(self.cursor), anchor.max(self.cursor) + 1),
+            (None, Some(MarkKind::Code)) => (self.cursor, (self.cursor + 2).min(self.rom.len())),
+            (None, _) => (self.cursor, self.cursor + 1),
+        }
+    }
+
+    fn mark(&mut self, kind: Option<MarkKind>) {
+        let (start, end) = self.selection(kind);
+        let (start, end) = (self.addr(start), self.addr(end));
+
+        self.marks.set(start, end, kind);
+        self.anchor = None;
+        self.modified = true;
+        self.message = self.locale.format(
+            "hexview.marked",
+            &[
+                ("start", &format!("{:04X}", start)),
+                ("end", &format!("{:04X}", end)),
+                ("kind", &self.kind_name(kind)),
+            ],
+        );
+    }
+
+    /// Name of a mark kind in the legend and messages
+    fn kind_name(&self, kind: Option<MarkKind>) -> String {
+        let key = match kind {
+            Some(MarkKind::Code) => "hexview.code",
+            Some(MarkKind::Data) => "hexview.data",
+            None => "hexview.unmarked",
+        };
+
+        self.locale.get(key).to_string()
+    }
+
+    fn save(&mut self) -> anyhow::Result<()> {
+        let header = "# Code and data marks for chip8 --disassemble, written by chip8 hexview\n";
+        fs::write(
+            &self.marks_path,
+            format!("{}{}", header, self.marks.to_text()),
+        )?;
+        self.modified = false;
+
+        Ok(())
+    }
+
+    fn addr(&self, index: usize) -> u16 {
+        self.offset.wrapping_add(index as u16)
+    }
+
+    fn pane_rows(&self) -> usize {
+        let (_, height) = terminal::size().unwrap_or((80, 24));
+        usize::from(height.saturating_sub(FOOTER_ROWS + 1)).max(1)
+    }
+
+    fn draw(&mut self) -> anyhow::Result<()> {
+        let rows = self.pane_rows();
+
+        // Keep the cursor row in view
+        let cursor_row = self.cursor / HEX_COLUMNS;
+        if cursor_row < self.top_row {
+            self.top_row = cursor_row;
+        } else if cursor_row >= self.top_row + rows {
+            self.top_row = cursor_row + 1 - rows;
+        }
+
+        let (sel_start, sel_end) = match self.anchor {
+            Some(_) => self.selection(None),
+            None => (0, 0),
+        };
+
+        queue!(self.out, terminal::Clear(ClearType::All))?;
+
+        // Hex
+        for r in 0..rows {
+            let start = (self.top_row + r) * HEX_COLUMNS;
+            if start >= self.rom.len() {
+                break;
+            }
+
+            let addr = self.addr(start);
+            queue!(
+                self.out,
+                cursor::MoveTo(0, r as u16),
+                Print(format!("{:04X}:", addr))
+            )?;
+
+            for i in start..(start + HEX_COLUMNS).min(self.rom.len()) {
+                let mut text = self.marked(format!("{:02X}", self.rom[i]), self.addr(i));
+
+                if (sel_start..sel_end).contains(&i) {
+                    text = text.on_dark_grey();
+                }
+                if i == self.cursor {
+                    text = text.reverse();
+                }
+
+                queue!(self.out, Print(" "), PrintStyledContent(text))?;
+            }
+        }
+
+        // Disassembly, centered on the instruction under the cursor
+        let instructions = disasm::instructions_marked(&self.rom, self.offset, &self.marks);
+        let cursor_addr = self.addr(self.cursor);
+        let current = instructions
+            .iter()
+            .position(|ins| {
+                let len = ins.bytes.len() as u16;
+                (ins.addr..ins.addr.wrapping_add(len)).contains(&cursor_addr)
+            })
+            .unwrap_or(0);
+        let first = current
+            .saturating_sub(rows / 2)
+            .min(instructions.len().saturating_sub(rows));
+
+        for (r, ins) in instructions.iter().skip(first).take(rows).enumerate() {
+            let marker = if first + r == current { ">" } else { " " };
+            let hex: Vec<String> = ins.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+            let mut line = format!(
+                "{}{:04X} {} {}",
+                marker,
+                ins.addr,
+                hex.join(""),
+                if ins.opcode.is_some() {
+                    format!("{} {}", ins.mnemonic, ins.operands.join(", "))
+                } else {
+                    "DB".into()
+                }
+            );
+            line.truncate(DISASM_WIDTH);
+
+            let line = self.marked(line, ins.addr);
+            queue!(
+                self.out,
+                cursor::MoveTo(DISASM_COLUMN, r as u16),
+                PrintStyledContent(line)
+            )?;
+        }
+
+        // Sprite rows starting at the cursor
+        for (r, byte) in self.rom[self.cursor..]
+            .iter()
+            .take(SPRITE_ROWS.min(rows))
+            .enumerate()
+        {
+            let pixels: String = (0..8)
+                .map(|bit| {
+                    if byte & (0x80 >> bit) != 0 {
+                        '█'
+                    } else {
+                        '·'
+                    }
+                })
+                .collect();
+
+            queue!(
+                self.out,
+                cursor::MoveTo(SPRITE_COLUMN, r as u16),
+                Print(format!("{:02X} {}", byte, pixels))
+            )?;
+        }
+
+        let footer = rows as u16 + 1;
+        let selection = match self.anchor {
+            Some(_) => format!(
+                "  {}",
+                self.locale.format(
+                    "hexview.selecting",
+                    &[
+                        ("start", &format!("{:04X}", self.addr(sel_start))),
+                        ("end", &format!("{:04X}", self.addr(sel_end))),
+                    ]
+                )
+            ),
+            None => String::new(),
+        };
+        let modified = if self.modified {
+            format!("  {}", self.locale.get("hexview.modified"))
+        } else {
+            String::new()
+        };
+        let code = self.kind_name(Some(MarkKind::Code));
+        let data = self.kind_name(Some(MarkKind::Data));
+        let kind = self.kind_name(self.marks.kind(cursor_addr));
+
+        queue!(
+            self.out,
+            cursor::MoveTo(0, footer),
+            PrintStyledContent(code.blue()),
+            Print(" "),
+            PrintStyledContent(data.yellow()),
+            Print(format!(
+                "  {:04X}: {}{}{}",
+                cursor_addr, kind, selection, modified
+            )),
+            cursor::MoveTo(0, footer + 1),
+            Print(&self.message),
+            cursor::MoveTo(0, footer + 2),
+            Print(self.locale.get("hexview.help")),
+        )?;
+
+        self.out.flush()?;
+
+        Ok(())
+    }
+
+    /// `text` colored by how `addr` is marked
+    fn marked(&self, text: String, addr: u16) -> StyledContent<String> {
+        match self.marks.kind(addr) {
+            Some(MarkKind::Code) => text.blue(),
+            Some(MarkKind::Data) => text.yellow(),
+            None => text.stylize(),
+        }
+    }
+}
+
+impl Drop for HexView {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
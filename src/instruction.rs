@@ -0,0 +1,400 @@
+use core::fmt;
+
+/// An opcode decoded into its operation and operands
+///
+/// Decoding only looks at the opcode, so extension instructions decode the
+/// same whatever mode the machine is in; `Chip8` decides whether it runs
+/// them. Register operands are indices 0 to F.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// 00E0: clear the selected planes
+    Cls,
+    /// 0230: clear the display in hi-res mode
+    HiresCls,
+    /// 00EE: return from a subroutine
+    Ret,
+    /// 00Cn: scroll down n pixels (SCHIP)
+    Scd(u8),
+    /// 00Dn: scroll up n pixels (XO-CHIP)
+    Scu(u8),
+    /// 00FB: scroll right 4 pixels (SCHIP)
+    Scr,
+    /// 00FC: scroll left 4 pixels (SCHIP)
+    Scl,
+    /// 00FD: end the program (SCHIP)
+    Exit,
+    /// 00FE: low-resolution mode (SCHIP)
+    Low,
+    /// 00FF: high-resolution mode (SCHIP)
+    High,
+    /// 0nnn: machine code routine
+    Sys(u16),
+    /// 1nnn
+    Jp(u16),
+    /// 2nnn
+    Call(u16),
+    /// 3xkk: skip if Vx == kk
+    SeByte(u8, u8),
+    /// 4xkk: skip if Vx != kk
+    SneByte(u8, u8),
+    /// 5xy0: skip if Vx == Vy
+    SeReg(u8, u8),
+    /// 5xy2: store Vx to Vy at I (XO-CHIP)
+    SaveRange(u8, u8),
+    /// 5xy3: load Vx to Vy from I (XO-CHIP)
+    LoadRange(u8, u8),
+    /// 6xkk
+    LdByte(u8, u8),
+    /// 7xkk
+    AddByte(u8, u8),
+    /// 8xy0
+    LdReg(u8, u8),
+    /// 8xy1
+    Or(u8, u8),
+    /// 8xy2
+    And(u8, u8),
+    /// 8xy3
+    Xor(u8, u8),
+    /// 8xy4
+    AddReg(u8, u8),
+    /// 8xy5
+    Sub(u8, u8),
+    /// 8xy6
+    Shr(u8, u8),
+    /// 8xy7
+    Subn(u8, u8),
+    /// 8xyE
+    Shl(u8, u8),
+    /// 9xy0: skip if Vx != Vy
+    SneReg(u8, u8),
+    /// Annn
+    LdI(u16),
+    /// Bnnn, with x for the jump_vx quirk
+    JpV0(u8, u16),
+    /// Cxkk
+    Rnd(u8, u8),
+    /// Dxyn
+    Drw(u8, u8, u8),
+    /// Ex9E
+    Skp(u8),
+    /// ExA1
+    Sknp(u8),
+    /// F000 nnnn: I = the address in the next two bytes (XO-CHIP)
+    LdILong,
+    /// Fn01 (XO-CHIP)
+    Plane(u8),
+    /// F002 (XO-CHIP)
+    Audio,
+    /// Fx07
+    LdVxDt(u8),
+    /// Fx0A
+    LdVxK(u8),
+    /// Fx15
+    LdDtVx(u8),
+    /// Fx18
+    LdStVx(u8),
+    /// Fx1E
+    AddI(u8),
+    /// Fx29
+    LdF(u8),
+    /// Fx30: big digit sprite (SCHIP)
+    LdHf(u8),
+    /// Fx33
+    LdB(u8),
+    /// Fx3A (XO-CHIP)
+    Pitch(u8),
+    /// Fx55
+    Store(u8),
+    /// Fx65
+    Load(u8),
+    /// Fx75: store registers in the RPL flags (SCHIP)
+    StoreRpl(u8),
+    /// Fx85: load registers from the RPL flags (SCHIP)
+    LoadRpl(u8),
+    /// No instruction has this opcode
+    Unknown(u16),
+}
+
+impl Instruction {
+    /// The address a JP, CALL, `JP V0`, or `LD I` refers to
+    pub fn target(self) -> Option<u16> {
+        match self {
+            Instruction::Jp(nnn)
+            | Instruction::Call(nnn)
+            | Instruction::JpV0(_, nnn)
+            | Instruction::LdI(nnn) => Some(nnn),
+            _ => None,
+        }
+    }
+
+    /// The extension the instruction belongs to, or `None` for base CHIP-8
+    ///
+    /// DRW is base CHIP-8 even with n = 0: CHIP-8 draws nothing where SCHIP
+    /// draws a 16x16 sprite, but both are valid.
+    ///
+    /// ```
+    /// use chip8::instruction::{decode, Extension};
+    ///
+    /// let op = decode(0xF03A).extension_op().unwrap();
+    /// assert_eq!((op.extension, op.mnemonic), (Extension::XoChip, "PITCH Vx"));
+    /// assert_eq!(decode(0xD120).extension_op(), None);
+    /// ```
+    pub fn extension_op(self) -> Option<ExtensionOp> {
+        let extension = match self {
+            Instruction::Scd(_)
+            | Instruction::Scr
+            | Instruction::Scl
+            | Instruction::Exit
+            | Instruction::Low
+            | Instruction::High
+            | Instruction::LdHf(_)
+            | Instruction::StoreRpl(_)
+            | Instruction::LoadRpl(_) => Extension::Schip,
+            Instruction::Scu(_)
+            | Instruction::SaveRange(..)
+            | Instruction::LoadRange(..)
+            | Instruction::LdILong
+            | Instruction::Plane(_)
+            | Instruction::Audio
+            | Instruction::Pitch(_) => Extension::XoChip,
+            _ => return None,
+        };
+
+        Some(ExtensionOp {
+            extension,
+            mnemonic: self.family().1,
+        })
+    }
+
+    /// The opcode pattern and mnemonic, with operands in their generic form,
+    /// shared by every instruction of this kind
+    ///
+    /// ```
+    /// use chip8::instruction::decode;
+    ///
+    /// assert_eq!(decode(0x3A07).family(), ("3xkk", "SE Vx, byte"));
+    /// assert_eq!(decode(0x3B00).family(), decode(0x3A07).family());
+    /// ```
+    pub fn family(self) -> (&'static str, &'static str) {
+        match self {
+            Instruction::Cls => ("00E0", "CLS"),
+            Instruction::HiresCls => ("0230", "CLS (hi-res)"),
+            Instruction::Ret => ("00EE", "RET"),
+            Instruction::Scd(_) => ("00Cn", "SCD n"),
+            Instruction::Scu(_) => ("00Dn", "SCU n"),
+            Instruction::Scr => ("00FB", "SCR"),
+            Instruction::Scl => ("00FC", "SCL"),
+            Instruction::Exit => ("00FD", "EXIT"),
+            Instruction::Low => ("00FE", "LOW"),
+            Instruction::High => ("00FF", "HIGH"),
+            Instruction::Sys(_) => ("0nnn", "SYS addr"),
+            Instruction::Jp(_) => ("1nnn", "JP addr"),
+            Instruction::Call(_) => ("2nnn", "CALL addr"),
+            Instruction::SeByte(..) => ("3xkk", "SE Vx, byte"),
+            Instruction::SneByte(..) => ("4xkk", "SNE Vx, byte"),
+            Instruction::SeReg(..) => ("5xy0", "SE Vx, Vy"),
+            Instruction::SaveRange(..) => ("5xy2", "SAVE Vx - Vy"),
+            Instruction::LoadRange(..) => ("5xy3", "LOAD Vx - Vy"),
+            Instruction::LdByte(..) => ("6xkk", "LD Vx, byte"),
+            Instruction::AddByte(..) => ("7xkk", "ADD Vx, byte"),
+            Instruction::LdReg(..) => ("8xy0", "LD Vx, Vy"),
+            Instruction::Or(..) => ("8xy1", "OR Vx, Vy"),
+            Instruction::And(..) => ("8xy2", "AND Vx, Vy"),
+            Instruction::Xor(..) => ("8xy3", "XOR Vx, Vy"),
+            Instruction::AddReg(..) => ("8xy4", "ADD Vx, Vy"),
+            Instruction::Sub(..) => ("8xy5", "SUB Vx, Vy"),
+            Instruction::Shr(..) => ("8xy6", "SHR Vx"),
+            Instruction::Subn(..) => ("8xy7", "SUBN Vx, Vy"),
+            Instruction::Shl(..) => ("8xyE", "SHL Vx"),
+            Instruction::SneReg(..) => ("9xy0", "SNE Vx, Vy"),
+            Instruction::LdI(_) => ("Annn", "LD I, addr"),
+            Instruction::JpV0(..) => ("Bnnn", "JP V0, addr"),
+            Instruction::Rnd(..) => ("Cxkk", "RND Vx, byte"),
+            Instruction::Drw(..) => ("Dxyn", "DRW Vx, Vy, n"),
+            Instruction::Skp(_) => ("Ex9E", "SKP Vx"),
+            Instruction::Sknp(_) => ("ExA1", "SKNP Vx"),
+            Instruction::LdILong => ("F000", "LD I, nnnn"),
+            Instruction::Plane(_) => ("Fn01", "PLANE n"),
+            Instruction::Audio => ("F002", "AUDIO"),
+            Instruction::LdVxDt(_) => ("Fx07", "LD Vx, DT"),
+            Instruction::LdVxK(_) => ("Fx0A", "LD Vx, K"),
+            Instruction::LdDtVx(_) => ("Fx15", "LD DT, Vx"),
+            Instruction::LdStVx(_) => ("Fx18", "LD ST, Vx"),
+            Instruction::AddI(_) => ("Fx1E", "ADD I, Vx"),
+            Instruction::LdF(_) => ("Fx29", "LD F, Vx"),
+            Instruction::LdHf(_) => ("Fx30", "LD HF, Vx"),
+            Instruction::LdB(_) => ("Fx33", "LD B, Vx"),
+            Instruction::Pitch(_) => ("Fx3A", "PITCH Vx"),
+            Instruction::Store(_) => ("Fx55", "LD [I], Vx"),
+            Instruction::Load(_) => ("Fx65", "LD Vx, [I]"),
+            Instruction::StoreRpl(_) => ("Fx75", "LD R, Vx"),
+            Instruction::LoadRpl(_) => ("Fx85", "LD Vx, R"),
+            Instruction::Unknown(_) => ("????", "unknown"),
+        }
+    }
+
+    /// Whether the instruction skips the next one on a condition
+    pub fn is_skip(self) -> bool {
+        matches!(
+            self,
+            Instruction::SeByte(..)
+                | Instruction::SneByte(..)
+                | Instruction::SeReg(..)
+                | Instruction::SneReg(..)
+                | Instruction::Skp(_)
+                | Instruction::Sknp(_)
+        )
+    }
+}
+
+/// Instruction set extending CHIP-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extension {
+    /// SUPER-CHIP 1.1: high resolution, scrolling, and RPL flags
+    Schip,
+    /// XO-CHIP: extra memory, bit planes, and audio patterns
+    XoChip,
+}
+
+impl Extension {
+    /// Command-line flag that enables the extension's instructions
+    pub fn flag(self) -> &'static str {
+        match self {
+            Extension::Schip => "--schip",
+            Extension::XoChip => "--xo-ram",
+        }
+    }
+}
+
+impl fmt::Display for Extension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Extension::Schip => "SCHIP",
+            Extension::XoChip => "XO-CHIP",
+        })
+    }
+}
+
+/// Extension instruction, as named in error messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtensionOp {
+    pub extension: Extension,
+    /// Conventional mnemonic, with operands in their generic form
+    pub mnemonic: &'static str,
+}
+
+/// Decoders by the opcode's first nibble
+const DECODERS: [fn(u16) -> Instruction; 16] = [
+    decode_0,
+    |op| Instruction::Jp(nnn(op)),
+    |op| Instruction::Call(nnn(op)),
+    |op| Instruction::SeByte(x(op), kk(op)),
+    |op| Instruction::SneByte(x(op), kk(op)),
+    decode_5,
+    |op| Instruction::LdByte(x(op), kk(op)),
+    |op| Instruction::AddByte(x(op), kk(op)),
+    decode_8,
+    |op| match op & 0xF {
+        0x0 => Instruction::SneReg(x(op), y(op)),
+        _ => Instruction::Unknown(op),
+    },
+    |op| Instruction::LdI(nnn(op)),
+    |op| Instruction::JpV0(x(op), nnn(op)),
+    |op| Instruction::Rnd(x(op), kk(op)),
+    |op| Instruction::Drw(x(op), y(op), (op & 0xF) as u8),
+    |op| match kk(op) {
+        0x9E => Instruction::Skp(x(op)),
+        0xA1 => Instruction::Sknp(x(op)),
+        _ => Instruction::Unknown(op),
+    },
+    decode_f,
+];
+
+/// Decode `opcode`, dispatching on its first nibble
+pub fn decode(opcode: u16) -> Instruction {
+    DECODERS[usize::from(opcode >> 12)](opcode)
+}
+
+fn decode_0(op: u16) -> Instruction {
+    match op {
+        0x00E0 => Instruction::Cls,
+        0x0230 => Instruction::HiresCls,
+        0x00EE => Instruction::Ret,
+        0x00FB => Instruction::Scr,
+        0x00FC => Instruction::Scl,
+        0x00FD => Instruction::Exit,
+        0x00FE => Instruction::Low,
+        0x00FF => Instruction::High,
+        _ => match op & 0xFFF0 {
+            0x00C0 => Instruction::Scd((op & 0xF) as u8),
+            0x00D0 => Instruction::Scu((op & 0xF) as u8),
+            _ => Instruction::Sys(nnn(op)),
+        },
+    }
+}
+
+fn decode_5(op: u16) -> Instruction {
+    match op & 0xF {
+        0x0 => Instruction::SeReg(x(op), y(op)),
+        0x2 => Instruction::SaveRange(x(op), y(op)),
+        0x3 => Instruction::LoadRange(x(op), y(op)),
+        _ => Instruction::Unknown(op),
+    }
+}
+
+fn decode_8(op: u16) -> Instruction {
+    let (x, y) = (x(op), y(op));
+
+    match op & 0xF {
+        0x0 => Instruction::LdReg(x, y),
+        0x1 => Instruction::Or(x, y),
+        0x2 => Instruction::And(x, y),
+        0x3 => Instruction::Xor(x, y),
+        0x4 => Instruction::AddReg(x, y),
+        0x5 => Instruction::Sub(x, y),
+        0x6 => Instruction::Shr(x, y),
+        0x7 => Instruction::Subn(x, y),
+        0xE => Instruction::Shl(x, y),
+        _ => Instruction::Unknown(op),
+    }
+}
+
+fn decode_f(op: u16) -> Instruction {
+    let x = x(op);
+
+    match kk(op) {
+        0x00 if x == 0 => Instruction::LdILong,
+        0x01 => Instruction::Plane(x),
+        0x02 if x == 0 => Instruction::Audio,
+        0x07 => Instruction::LdVxDt(x),
+        0x0A => Instruction::LdVxK(x),
+        0x15 => Instruction::LdDtVx(x),
+        0x18 => Instruction::LdStVx(x),
+        0x1E => Instruction::AddI(x),
+        0x29 => Instruction::LdF(x),
+        0x30 => Instruction::LdHf(x),
+        0x33 => Instruction::LdB(x),
+        0x3A => Instruction::Pitch(x),
+        0x55 => Instruction::Store(x),
+        0x65 => Instruction::Load(x),
+        0x75 => Instruction::StoreRpl(x),
+        0x85 => Instruction::LoadRpl(x),
+        _ => Instruction::Unknown(op),
+    }
+}
+
+fn nnn(op: u16) -> u16 {
+    op & 0x0FFF
+}
+
+fn kk(op: u16) -> u8 {
+    op as u8
+}
+
+fn x(op: u16) -> u8 {
+    ((op >> 8) & 0xF) as u8
+}
+
+fn y(op: u16) -> u8 {
+    ((op >> 4) & 0xF) as u8
+}
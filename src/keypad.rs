@@ -0,0 +1,125 @@
+use crate::{
+    filter::Frame,
+    overlay::{self, GLYPH_HEIGHT, GLYPH_WIDTH},
+};
+
+/// Keys as laid out on the COSMAC VIP's hex keypad, top row first
+pub const LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+const GRID_COLOR: u32 = 0x3F3F3F;
+
+/// Hex keypad drawn below a frame, for playing with the mouse
+///
+/// The frame is enlarged as by the register overlay, then a keypad half as
+/// tall as it is wide is added below it, with the keys in `held` (bit n =
+/// key n) lit.
+pub fn draw(frame: &Frame, held: u16) -> Frame {
+    let display = overlay::enlarge(frame);
+    let (width, panel_height) = (display.width, display.width / 2);
+    let height = display.height + panel_height;
+    let (off, on) = frame.palette;
+
+    let mut pixels = display.pixels;
+    pixels.resize(width * height, off);
+
+    let mut out = Frame {
+        width,
+        height,
+        pixels,
+        palette: frame.palette,
+        time: frame.time,
+    };
+
+    let (cell_width, cell_height) = (width / 4, panel_height / 4);
+    let top = display.height;
+
+    for (row, keys) in LAYOUT.iter().enumerate() {
+        for (col, key) in keys.iter().enumerate() {
+            let (x, y) = (col * cell_width, top + row * cell_height);
+            let lit = held & (1 << key) != 0;
+
+            for py in y..y + cell_height {
+                for px in x..x + cell_width {
+                    let edge =
+                        px == x || py == y || px == x + cell_width - 1 || py == y + cell_height - 1;
+                    out.pixels[py * width + px] = match (edge, lit) {
+                        (true, _) => GRID_COLOR,
+                        (false, true) => on,
+                        (false, false) => off,
+                    };
+                }
+            }
+
+            let label = if lit { off } else { on };
+            draw_label(&mut out, (x, y), (cell_width, cell_height), *key, label);
+        }
+    }
+
+    out
+}
+
+/// The key under `(x, y)` in a `width` x `height` frame made by `draw`
+///
+/// ```
+/// use chip8::{filter::Frame, keypad};
+///
+/// let display = Frame {
+///     width: 64,
+///     height: 32,
+///     pixels: vec![0; 64 * 32],
+///     ..Frame::default()
+/// };
+/// let frame = keypad::draw(&display, 0);
+/// assert_eq!((frame.width, frame.height), (256, 256));
+///
+/// let key_at = |x, y| keypad::key_at(frame.width, frame.height, x, y);
+/// assert_eq!(key_at(0, 128), Some(0x1));
+/// assert_eq!(key_at(255, 255), Some(0xF));
+/// assert_eq!(key_at(0, 0), None, "the display is not part of the keypad");
+/// ```
+pub fn key_at(width: usize, height: usize, x: usize, y: usize) -> Option<u8> {
+    let panel_height = width / 2;
+    let top = height.checked_sub(panel_height)?;
+
+    if x >= width || y < top || y >= height {
+        return None;
+    }
+
+    let col = x / (width / 4).max(1);
+    let row = (y - top) / (panel_height / 4).max(1);
+
+    LAYOUT.get(row)?.get(col).copied()
+}
+
+/// Write `key` as a hex digit centered in a cell, as large as fits
+fn draw_label(frame: &mut Frame, at: (usize, usize), size: (usize, usize), key: u8, color: u32) {
+    let scale = (size.0 / (GLYPH_WIDTH * 3))
+        .min(size.1 / (GLYPH_HEIGHT * 2))
+        .max(1);
+    let x = at.0 + size.0.saturating_sub(GLYPH_WIDTH * scale) / 2;
+    let y = at.1 + size.1.saturating_sub(GLYPH_HEIGHT * scale) / 2;
+    let digit = char::from_digit(u32::from(key), 16).unwrap_or('?');
+
+    for (dy, bits) in overlay::glyph(digit).iter().enumerate() {
+        for dx in 0..GLYPH_WIDTH {
+            if bits & (0b100 >> dx) == 0 {
+                continue;
+            }
+
+            for sy in 0..scale {
+                for sx in 0..scale {
+                    let (px, py) = (x + dx * scale + sx, y + dy * scale + sy);
+
+                    if px < frame.width && py < frame.height {
+                        frame.pixels[py * frame.width + px] = color;
+                    }
+                }
+            }
+        }
+    }
+}
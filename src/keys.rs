@@ -0,0 +1,368 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::anyhow;
+use minifb::Key;
+
+/// Every nameable host key
+pub const ALL_KEYS: [Key; 106] = [
+    Key::Key0,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F13,
+    Key::F14,
+    Key::F15,
+    Key::Down,
+    Key::Left,
+    Key::Right,
+    Key::Up,
+    Key::Apostrophe,
+    Key::Backquote,
+    Key::Backslash,
+    Key::Comma,
+    Key::Equal,
+    Key::LeftBracket,
+    Key::Minus,
+    Key::Period,
+    Key::RightBracket,
+    Key::Semicolon,
+    Key::Slash,
+    Key::Backspace,
+    Key::Delete,
+    Key::End,
+    Key::Enter,
+    Key::Escape,
+    Key::Home,
+    Key::Insert,
+    Key::Menu,
+    Key::PageDown,
+    Key::PageUp,
+    Key::Pause,
+    Key::Space,
+    Key::Tab,
+    Key::NumLock,
+    Key::CapsLock,
+    Key::ScrollLock,
+    Key::LeftShift,
+    Key::RightShift,
+    Key::LeftCtrl,
+    Key::RightCtrl,
+    Key::NumPad0,
+    Key::NumPad1,
+    Key::NumPad2,
+    Key::NumPad3,
+    Key::NumPad4,
+    Key::NumPad5,
+    Key::NumPad6,
+    Key::NumPad7,
+    Key::NumPad8,
+    Key::NumPad9,
+    Key::NumPadDot,
+    Key::NumPadSlash,
+    Key::NumPadAsterisk,
+    Key::NumPadMinus,
+    Key::NumPadPlus,
+    Key::NumPadEnter,
+    Key::LeftAlt,
+    Key::RightAlt,
+    Key::LeftSuper,
+    Key::RightSuper,
+];
+
+/// Parse a host key name, case-insensitively
+///
+/// Names match the `minifb::Key` variants (`Q`, `F5`, `NumPad0`, `Backspace`), and the
+/// digit keys may also be written without the `Key` prefix (`1`).
+pub fn parse_key(name: &str) -> Option<Key> {
+    let name = name.trim();
+
+    ALL_KEYS.iter().copied().find(|key| {
+        let key_name = key_name(*key);
+
+        key_name.eq_ignore_ascii_case(name) || key_name.strip_prefix("Key") == Some(name)
+    })
+}
+
+pub fn key_name(key: Key) -> String {
+    format!("{:?}", key)
+}
+
+/// Modifier keys held down for a hotkey chord, either side counting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    /// Modifiers among the keys held down
+    pub fn held(keys: &[Key]) -> Self {
+        let any = |left: Key, right: Key| keys.iter().any(|k| *k == left || *k == right);
+
+        Modifiers {
+            ctrl: any(Key::LeftCtrl, Key::RightCtrl),
+            shift: any(Key::LeftShift, Key::RightShift),
+            alt: any(Key::LeftAlt, Key::RightAlt),
+        }
+    }
+
+    pub fn is_empty(self) -> bool {
+        self == Modifiers::default()
+    }
+}
+
+/// Host key with optional modifiers, e.g. `F5` or `Ctrl+Shift+R`
+///
+/// Matches only when exactly these modifiers are held, so `Ctrl+R` and `R`
+/// can be bound separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hotkey {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl Hotkey {
+    /// `key` with no modifiers
+    pub fn plain(key: Key) -> Self {
+        Hotkey {
+            modifiers: Modifiers::default(),
+            key,
+        }
+    }
+}
+
+impl From<Key> for Hotkey {
+    fn from(key: Key) -> Self {
+        Hotkey::plain(key)
+    }
+}
+
+impl FromStr for Hotkey {
+    type Err = anyhow::Error;
+
+    /// `[Ctrl+][Shift+][Alt+]KEY`, modifiers in any order and case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key_name = parts.pop().unwrap_or("");
+        let mut modifiers = Modifiers::default();
+
+        for part in parts {
+            let flag = match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => &mut modifiers.ctrl,
+                "shift" => &mut modifiers.shift,
+                "alt" => &mut modifiers.alt,
+                _ => return Err(anyhow!("unknown modifier '{}' in '{}'", part, s)),
+            };
+            *flag = true;
+        }
+
+        Ok(Hotkey {
+            modifiers,
+            key: parse_key(key_name).ok_or_else(|| anyhow!("unknown key '{}'", key_name))?,
+        })
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.modifiers.ctrl, "Ctrl+"),
+            (self.modifiers.shift, "Shift+"),
+            (self.modifiers.alt, "Alt+"),
+        ] {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+
+        f.write_str(&key_name(self.key))
+    }
+}
+
+/// Emulator control bound to a host key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Reset,
+    ToggleDebug,
+    /// Registers and the current instruction drawn over the display
+    ToggleOverlay,
+    CyclePalette,
+    SaveState,
+    LoadState,
+    SetCheckpoint,
+    Pause,
+    Step,
+    /// Held rather than pressed
+    Rewind,
+    Screenshot,
+    /// Held: run as fast as possible, with timers kept in step
+    Turbo,
+    ToggleCheats,
+    /// Frames and instructions per second shown in the window title
+    ToggleRates,
+    /// Pause with the menu shown over the display
+    ToggleMenu,
+}
+
+impl Action {
+    pub const ALL: [Action; 16] = [
+        Action::Quit,
+        Action::Reset,
+        Action::ToggleDebug,
+        Action::ToggleOverlay,
+        Action::CyclePalette,
+        Action::SaveState,
+        Action::LoadState,
+        Action::SetCheckpoint,
+        Action::Pause,
+        Action::Step,
+        Action::Rewind,
+        Action::Screenshot,
+        Action::Turbo,
+        Action::ToggleCheats,
+        Action::ToggleRates,
+        Action::ToggleMenu,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Reset => "reset",
+            Action::ToggleDebug => "debug",
+            Action::ToggleOverlay => "overlay",
+            Action::CyclePalette => "palette",
+            Action::SaveState => "save",
+            Action::LoadState => "load",
+            Action::SetCheckpoint => "checkpoint",
+            Action::Pause => "pause",
+            Action::Step => "step",
+            Action::Rewind => "rewind",
+            Action::Screenshot => "screenshot",
+            Action::Turbo => "turbo",
+            Action::ToggleCheats => "cheats",
+            Action::ToggleRates => "rates",
+            Action::ToggleMenu => "menu",
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| anyhow!("unknown action '{}'", s))
+    }
+}
+
+/// What a host key is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Keypad(u8),
+    Action(Action),
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Keypad(code) => write!(f, "keypad key {:#X}", code),
+            Binding::Action(action) => write!(f, "action '{}'", action.name()),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        match u8::from_str_radix(s.trim_start_matches("0x"), 16) {
+            Ok(code) if code <= 0xf => Ok(Binding::Keypad(code)),
+            Ok(code) => Err(anyhow!("keypad key {:#X} out of range 0x0 ~ 0xF", code)),
+            Err(_) => Ok(Binding::Action(s.parse()?)),
+        }
+    }
+}
+
+/// A `KEY=BINDING` pair, e.g. `q=4`, `p=pause`, or `ctrl+r=reset`
+///
+/// Only actions can be bound to chords; keypad keys take a plain host key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyMapping {
+    pub key: Hotkey,
+    pub binding: Binding,
+}
+
+impl FromStr for KeyMapping {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, binding) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected KEY=BINDING, got '{}'", s))?;
+
+        let key: Hotkey = key.parse()?;
+        let binding = binding.parse()?;
+
+        if let Binding::Keypad(_) = binding {
+            if !key.modifiers.is_empty() {
+                return Err(anyhow!(
+                    "keypad keys cannot be bound to a chord like '{}'",
+                    key
+                ));
+            }
+        }
+
+        Ok(KeyMapping { key, binding })
+    }
+}
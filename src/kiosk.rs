@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant};
+
+/// Default chord that quits in kiosk mode, where the other controls are off
+pub const DEFAULT_EXIT_CHORD: &str = "Ctrl+Shift+Q";
+
+/// Default time without keypad input before a kiosk resets the ROM
+pub const DEFAULT_IDLE_RESET: Duration = Duration::from_secs(120);
+
+/// Unattended play: reset when nobody is playing, restart after crashes
+#[derive(Debug, Clone)]
+pub struct Kiosk {
+    /// Reset the ROM after this long without keypad input
+    pub idle_reset: Option<Duration>,
+    /// Times the ROM was restarted, after going idle or crashing
+    pub restarts: u64,
+    last_input: Instant,
+}
+
+impl Kiosk {
+    pub fn new(idle_reset: Option<Duration>) -> Self {
+        Kiosk {
+            idle_reset,
+            restarts: 0,
+            last_input: Instant::now(),
+        }
+    }
+
+    /// Note that a keypad key is held
+    pub fn input(&mut self) {
+        self.last_input = Instant::now();
+    }
+
+    /// Whether nobody has played for `idle_reset`; the idle time starts over
+    /// after this returns true
+    pub fn check_idle(&mut self) -> bool {
+        match self.idle_reset {
+            Some(limit) if self.last_input.elapsed() >= limit => {
+                self.restarted();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Count a restart and give the new run the full idle time
+    pub fn restarted(&mut self) {
+        self.restarts += 1;
+        self.last_input = Instant::now();
+    }
+}
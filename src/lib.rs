@@ -0,0 +1,214 @@
+//! CHIP-8 interpreter and emulator driver
+//!
+//! [`Chip8`] is the interpreter core: load a ROM, call [`Chip8::step`] once per
+//! instruction and [`Chip8::timer_tick`] at 60 Hz, and read the `display`
+//! buffer. It has no dependencies on windowing, audio, or wall-clock time.
+//!
+//! [`Emulator`] drives a `Chip8` in real time against a [`Frontend`], which
+//! supplies input and presents frames and sound. It adds key mapping, display
+//! filters, rewind, save states, input recording, and debugging hooks. Use
+//! [`Emulator::new`] for the built-in minifb window, or
+//! [`Emulator::with_frontend`] to embed the emulator in another application.
+//! A frontend is any type implementing the [`frontend::Display`],
+//! [`frontend::Input`], and [`frontend::Beeper`] traits;
+//! [`Emulator::with_parts`] combines separate implementations of each.
+//!
+//! ```
+//! use chip8::{Chip8, Emulator, HeadlessFrontend};
+//!
+//! // LD V0, 05; JP 202
+//! let rom = [0x60, 0x05, 0x12, 0x02];
+//!
+//! let mut cpu = Chip8::new();
+//! cpu.load_rom(&rom)?;
+//! cpu.step()?;
+//! assert_eq!(cpu.v[0], 5);
+//!
+//! let mut frontend = HeadlessFrontend::new();
+//! frontend.close_at = Some(100);
+//!
+//! let mut emu = Emulator::with_frontend(Chip8::new(), frontend)?;
+//! emu.clock_period = None;
+//! emu.rom = rom.to_vec();
+//! emu.reset()?;
+//!
+//! while !emu.closing {
+//!     emu.step()?;
+//! }
+//!
+//! assert!(!emu.frontend.frames.is_empty());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The interpreter core (`chip8`, `agent`, `asm`, `octo`, `disasm`, `fuzz`,
+//! `instruction`, `metadata`, `rng`, and `watch`) is `no_std` and only needs
+//! `alloc`. Everything else needs the default `std` feature, except the `wasm`
+//! feature's browser bindings and the `ffi` feature's C interface, which build
+//! on the core alone.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+// C callers need an allocator and panic handler even without the `std` feature
+#[cfg(all(feature = "ffi", not(feature = "std")))]
+extern crate std;
+
+/// Headless environment for bots and reinforcement learning
+pub mod agent;
+/// Assembler for the mnemonic syntax `disasm` emits
+pub mod asm;
+/// Interpreter throughput workloads shared by `chip8 bench` and the criterion benches
+#[cfg(feature = "std")]
+pub mod bench;
+/// Finding the input-independent tail of a recorded crash
+#[cfg(feature = "std")]
+pub mod bisect;
+/// Message bus running the emulator and the UI on separate threads
+#[cfg(feature = "std")]
+pub mod bus;
+/// Cheat files holding memory at chosen values
+#[cfg(feature = "std")]
+pub mod cheat;
+/// Interpreter core
+pub mod chip8;
+/// C interface for embedding the interpreter; see `include/chip8.h`
+#[cfg(feature = "ffi")]
+pub mod chip8_ffi;
+/// Instruction mnemonics and ROM disassembly
+pub mod disasm;
+/// Real-time emulator driver
+#[cfg(feature = "std")]
+pub mod emu;
+/// Display post-processing pipeline
+#[cfg(feature = "std")]
+pub mod filter;
+/// Per-change display dumps and comparing two runs of them
+#[cfg(feature = "std")]
+pub mod frames;
+/// Display, input, and sound backend traits, and a headless implementation
+#[cfg(feature = "std")]
+pub mod frontend;
+/// Opcode execution checks for fuzzing the interpreter
+pub mod fuzz;
+/// Gamepad input via gilrs
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+/// GDB remote serial protocol server
+#[cfg(feature = "std")]
+pub mod gdb;
+/// Opcodes decoded into instructions, shared by the interpreter and disassembler
+pub mod instruction;
+/// Clickable hex keypad drawn below the display
+#[cfg(feature = "std")]
+pub mod keypad;
+/// Host key bindings and emulator actions
+#[cfg(feature = "std")]
+pub mod keys;
+/// Unattended mode resetting idle and crashed ROMs
+#[cfg(feature = "std")]
+pub mod kiosk;
+/// Translated window titles and messages
+#[cfg(feature = "std")]
+pub mod locale;
+/// Pause menu drawn over the display
+#[cfg(feature = "std")]
+pub mod menu;
+/// Title, author, and settings stored at the end of a ROM file
+pub mod metadata;
+/// Input movie recording and replay
+#[cfg(feature = "std")]
+pub mod movie;
+/// Sharing a session over TCP for guests to mirror
+#[cfg(feature = "std")]
+pub mod netplay;
+/// Assembler for Octo source
+pub mod octo;
+/// Registers and the current instruction drawn over the display
+#[cfg(feature = "std")]
+pub mod overlay;
+/// Built-in color palettes
+#[cfg(feature = "std")]
+pub mod palette;
+/// Native plugins adding display filters, audio sinks, and step hooks
+#[cfg(feature = "plugins")]
+pub mod plugin;
+/// PNG image encoding for display dumps, and decoding for comparing them
+#[cfg(feature = "std")]
+pub mod png;
+/// Practice mode checkpoints and loss conditions
+#[cfg(feature = "std")]
+pub mod practice;
+/// Per-subroutine profiler
+#[cfg(feature = "std")]
+pub mod profile;
+/// Watching the ROM file for changes
+#[cfg(feature = "std")]
+pub mod reload;
+/// Rewind history
+#[cfg(feature = "std")]
+pub mod rewind;
+/// Random number source for RND
+pub mod rng;
+/// ROM sources: files, zip archives, URLs, stdin, the built-in demo, and ROM database entries
+#[cfg(feature = "std")]
+pub mod rom;
+/// Known ROMs and their recommended settings
+#[cfg(feature = "std")]
+pub mod romdb;
+/// Save state files
+#[cfg(feature = "std")]
+pub mod savestate;
+/// Keypad input scheduled in emulated time
+#[cfg(feature = "std")]
+pub mod schedule;
+/// Rhai scripts hooked to emulator events
+#[cfg(feature = "scripting")]
+pub mod script;
+/// SDL2 window frontend with sound and controllers
+#[cfg(feature = "sdl")]
+pub mod sdl;
+/// Execution statistics
+#[cfg(feature = "std")]
+pub mod stats;
+/// Data directory for save states and other per-ROM files
+#[cfg(feature = "std")]
+pub mod storage;
+/// Names for program addresses, from `.sym` files
+#[cfg(feature = "std")]
+pub mod symbols;
+/// Terminal frontend using half-block characters
+#[cfg(feature = "std")]
+pub mod term;
+/// Running ROMs and asserting on the display, for tests
+#[cfg(feature = "std")]
+pub mod testing;
+/// Sampling and buffering for per-step instrumentation
+#[cfg(feature = "std")]
+pub mod throttle;
+/// ROM title-screen thumbnails
+#[cfg(feature = "std")]
+pub mod thumbnail;
+/// `chip8` subcommands other than running a ROM
+#[cfg(feature = "std")]
+pub mod tools;
+/// Per-ROM conditions for achievements and automated checks
+#[cfg(feature = "std")]
+pub mod trigger;
+/// JavaScript bindings for the browser frontend
+#[cfg(feature = "wasm")]
+pub mod wasm;
+/// Memory watchpoints
+pub mod watch;
+/// minifb window frontend
+#[cfg(feature = "std")]
+pub mod window;
+
+pub use crate::{
+    chip8::{Chip8, Chip8Panic, OutOfMemory, Quirks, StepOutcome, SysPolicy},
+    rng::Chip8Rng,
+};
+#[cfg(feature = "std")]
+pub use crate::{
+    emu::Emulator,
+    frontend::{Frontend, HeadlessFrontend},
+};
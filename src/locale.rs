@@ -0,0 +1,135 @@
+use std::{collections::HashMap, fmt, fs, path::Path};
+
+use anyhow::{anyhow, Context};
+use toml::Value;
+
+/// Language files built into the binary, by code
+pub const LANGUAGES: &[(&str, &str)] = &[
+    ("en", include_str!("../lang/en.toml")),
+    ("es", include_str!("../lang/es.toml")),
+];
+
+/// Messages the emulator shows, in one language
+///
+/// A language file is TOML with one table per area (`[window]`, `[status]`,
+/// `[error]`, `[menu]`, and `[tools]`, `[debugger]`, `[hexview]`, `[picker]`
+/// for the terminal tools) of message strings. Messages are looked up as
+/// `table.key`, and `{name}` placeholders are filled in by `format`. Anything
+/// a language file leaves out falls back to English; see `lang/en.toml` for
+/// every key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    messages: HashMap<String, String>,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::english()
+    }
+}
+
+impl Locale {
+    pub fn english() -> Self {
+        let messages = flatten(LANGUAGES[0].1).expect("built-in English messages are valid");
+        Locale { messages }
+    }
+
+    /// A built-in language by code, e.g. `es`
+    pub fn builtin(code: &str) -> Option<Self> {
+        LANGUAGES
+            .iter()
+            .find(|(c, _)| c.eq_ignore_ascii_case(code))
+            .map(|(_, text)| Self::parse(text).expect("built-in messages are valid"))
+    }
+
+    /// Messages from language file text, over the English ones
+    ///
+    /// Keys English does not have are rejected, so typos do not go unnoticed.
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut locale = Self::english();
+        let mut unknown = Vec::new();
+
+        for (key, message) in flatten(text)? {
+            match locale.messages.get_mut(&key) {
+                Some(english) => *english = message,
+                None => unknown.push(key),
+            }
+        }
+
+        if unknown.is_empty() {
+            Ok(locale)
+        } else {
+            unknown.sort();
+            Err(anyhow!("unknown message keys: {}", unknown.join(", ")))
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read language file {}", path.display()))?;
+
+        Self::parse(&text).with_context(|| format!("in language file {}", path.display()))
+    }
+
+    /// A built-in language code, or else the path of a language file
+    pub fn find(name: &str) -> anyhow::Result<Self> {
+        match Self::builtin(name) {
+            Some(locale) => Ok(locale),
+            None if is_path(name) => Self::load(Path::new(name)),
+            None => Err(anyhow!(
+                "unknown language '{}', expected {} or a path to a language file",
+                name,
+                codes()
+            )),
+        }
+    }
+
+    /// The message for `key`, or the key itself if there is none
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.messages.get(key).map(String::as_str).unwrap_or(key)
+    }
+
+    /// The message for `key` with each `{name}` replaced by its value in `args`
+    pub fn format(&self, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+        let mut message = self.get(key).to_string();
+
+        for (name, value) in args {
+            message = message.replace(&format!("{{{}}}", name), &value.to_string());
+        }
+
+        message
+    }
+}
+
+/// Built-in language codes, for messages: "en, es"
+pub fn codes() -> String {
+    let codes: Vec<&str> = LANGUAGES.iter().map(|(code, _)| *code).collect();
+    codes.join(", ")
+}
+
+/// Whether a `language` setting names a file rather than a built-in code
+pub fn is_path(name: &str) -> bool {
+    name.contains(['/', '\\', '.'])
+}
+
+/// Language file text as `table.key` -> message
+fn flatten(text: &str) -> anyhow::Result<HashMap<String, String>> {
+    let tables: HashMap<String, Value> = toml::from_str(text)?;
+    let mut messages = HashMap::new();
+
+    for (table, entries) in tables {
+        let entries = entries
+            .as_table()
+            .ok_or_else(|| anyhow!("'{}' must be a table of messages", table))?;
+
+        for (key, message) in entries {
+            let message = message
+                .as_str()
+                .ok_or_else(|| anyhow!("{}.{} must be a string", table, key))?;
+
+            messages.insert(format!("{}.{}", table, key), message.to_string());
+        }
+    }
+
+    Ok(messages)
+}
@@ -1,69 +1,761 @@
-mod chip8;
-mod disasm;
-mod emu;
-mod palette;
-mod window;
+mod app;
+mod config;
+mod debugger;
+mod hexview;
+mod monitor;
+mod picker;
 
-use std::{fs::File, io::Read, path::PathBuf, time::Duration};
+use anyhow::anyhow;
+use std::{path::PathBuf, str::FromStr};
 use structopt::StructOpt;
 
-use emu::Emulator;
+use chip8::{
+    emu::PausedTimers,
+    filter::FilterSpec,
+    keys::{Hotkey, KeyMapping},
+    locale::Locale,
+    palette,
+    practice::LossCondition,
+    rom,
+    storage::Storage,
+    throttle::SampleSpec,
+    tools,
+    watch::Watchpoint,
+};
+use config::Config;
+use hexview::HexView;
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    #[structopt(about = "Assemble a program written in disassembler mnemonics or Octo into a ROM")]
+    Asm {
+        #[structopt(parse(from_os_str), help = "Assembly source")]
+        input: PathBuf,
+
+        #[structopt(long, help = "Read Octo syntax (the default for .8o files)")]
+        octo: bool,
+
+        #[structopt(
+            short,
+            long,
+            parse(from_os_str),
+            help = "Output ROM (default: input with a .ch8 extension)"
+        )]
+        out: Option<PathBuf>,
+    },
+
+    #[structopt(about = "Run interpreter benchmarks")]
+    Bench {
+        #[structopt(long, help = "Run the built-in benchmark suite")]
+        suite: bool,
+
+        #[structopt(long, default_value = "1000000", help = "Iterations per benchmark")]
+        iterations: u64,
+
+        #[structopt(help = "ROM to benchmark (same forms as the main ROM argument)")]
+        file: Option<String>,
+    },
+
+    #[structopt(about = "List ROMs in a directory with title-screen thumbnails")]
+    Browse {
+        #[structopt(parse(from_os_str), default_value = ".", help = "ROM directory")]
+        dir: PathBuf,
+    },
+
+    #[structopt(
+        about = "Find the earliest state after which a recorded crash no longer depends on input"
+    )]
+    Bisect {
+        #[structopt(help = "ROM (same forms as the main ROM argument)")]
+        rom: String,
+
+        #[structopt(
+            parse(from_os_str),
+            help = "Movie recorded with --record, ending in a crash"
+        )]
+        movie: PathBuf,
+
+        #[structopt(
+            long,
+            parse(from_os_str),
+            help = "Output path; writes a .c8s save state and a .c8m movie (default: <movie>.repro)"
+        )]
+        out: Option<PathBuf>,
+    },
+
+    #[structopt(about = "Check or create a config file")]
+    Config(ConfigCommand),
+
+    #[structopt(
+        about = "Compare two --dump-frames directories and show the first differing frame"
+    )]
+    CompareFrames {
+        #[structopt(parse(from_os_str))]
+        first: PathBuf,
+
+        #[structopt(parse(from_os_str))]
+        second: PathBuf,
+
+        #[structopt(
+            short,
+            long,
+            parse(from_os_str),
+            default_value = "frame-diff.png",
+            help = "XOR image of the first differing frame, white where pixels differ"
+        )]
+        out: PathBuf,
+    },
+
+    #[structopt(
+        about = "Browse a ROM in hex, disassembly, and sprite panes, marking code and data"
+    )]
+    Hexview {
+        #[structopt(help = "ROM (same forms as the main ROM argument)")]
+        rom: String,
+
+        #[structopt(
+            short,
+            long,
+            parse(from_os_str),
+            help = "Marks file to load and save (default: <rom>.marks)"
+        )]
+        marks: Option<PathBuf>,
+    },
+
+    #[structopt(about = "Show a ROM's size, data folder, and metadata")]
+    Info {
+        #[structopt(help = "ROM (same forms as the main ROM argument)")]
+        rom: String,
+    },
+
+    #[structopt(about = "Write title, author, tickrate, and quirks metadata to the end of a ROM")]
+    Tag {
+        #[structopt(help = "ROM file, or another ROM source with --out")]
+        rom: String,
+
+        #[structopt(long)]
+        title: Option<String>,
+
+        #[structopt(long)]
+        author: Option<String>,
+
+        #[structopt(long, help = "Instructions per 60 Hz frame")]
+        tickrate: Option<String>,
+
+        #[structopt(
+            long,
+            help = "Comma-separated quirks to enable, e.g. shift_vy,jump_vx; empty for none"
+        )]
+        quirks: Option<String>,
+
+        #[structopt(long, help = "Drop existing metadata before applying the options")]
+        clear: bool,
+
+        #[structopt(
+            short,
+            long,
+            parse(from_os_str),
+            help = "Output ROM (default: rewrite the input)"
+        )]
+        out: Option<PathBuf>,
+    },
+
+    #[structopt(about = "Run a ROM without input and check a hash of the final display")]
+    Test {
+        #[structopt(help = "ROM (same forms as the main ROM argument)")]
+        rom: String,
+
+        #[structopt(
+            long,
+            help = "Expected display hash (16 hex digits); without it, the hash is printed"
+        )]
+        expect: Option<String>,
+
+        #[structopt(long, default_value = "1000000", help = "Instructions to run")]
+        cycles: u64,
+    },
+
+    #[structopt(about = "List or delete save states and other files kept per ROM")]
+    Storage(StorageCommand),
+}
+
+#[derive(Debug, StructOpt)]
+enum ConfigCommand {
+    #[structopt(about = "Report every problem in a config file")]
+    Check {
+        #[structopt(
+            parse(from_os_str),
+            help = "Config file (default: --config, or chip8.toml)"
+        )]
+        file: Option<PathBuf>,
+    },
+
+    #[structopt(about = "Write a commented config file with the default settings")]
+    Init {
+        #[structopt(parse(from_os_str), help = "Output path (default: chip8.toml)")]
+        file: Option<PathBuf>,
+
+        #[structopt(long, help = "Overwrite an existing file")]
+        force: bool,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum StorageCommand {
+    #[structopt(about = "List the files kept for each ROM")]
+    Ls,
+
+    #[structopt(
+        about = "Delete caches and empty folders, or everything kept for a ROM or all ROMs"
+    )]
+    Clean {
+        #[structopt(
+            help = "Delete all files kept for this ROM (same forms as the main ROM argument)"
+        )]
+        rom: Option<String>,
+
+        #[structopt(
+            long,
+            conflicts_with = "rom",
+            help = "Delete the files kept for every ROM, and all caches"
+        )]
+        all: bool,
+    },
+}
 
 #[derive(Debug, StructOpt)]
 struct Opt {
-    #[structopt(parse(from_os_str), help = "Input ROM file")]
-    file: PathBuf,
+    #[structopt(subcommand)]
+    cmd: Option<Command>,
+
+    #[structopt(
+        help = "Input ROM: a file, archive.zip[:member], an http:// URL, - for stdin, or demo"
+    )]
+    file: Option<String>,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Config file (default: chip8.toml if present)"
+    )]
+    config: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Data directory for save states and other per-ROM files (default: $CHIP8_DATA_DIR, or the platform data directory)"
+    )]
+    data_dir: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        help = "Start with cleared RPL flags and do not save them (SCHIP games keep high scores there)"
+    )]
+    no_persist: bool,
 
     #[structopt(short, long, help = "Print debug messages")]
     verbose: bool,
 
+    #[structopt(
+        long = "sample",
+        number_of_values = 1,
+        help = "Run per-step instrumentation less often: trace, triggers, or practice = all, events, or every N steps, e.g. trace=1000 (repeatable)"
+    )]
+    samples: Vec<SampleSpec>,
+
+    #[structopt(
+        long,
+        help = "Keep only the last N trace lines, printed when execution pauses or stops"
+    )]
+    trace_buffer: Option<usize>,
+
+    #[structopt(
+        long = "plugin",
+        parse(from_os_str),
+        help = "Load a native plugin library (.so/.dll/.dylib); repeatable, needs the `plugins` feature"
+    )]
+    plugins: Vec<PathBuf>,
+
+    #[structopt(
+        long = "script",
+        parse(from_os_str),
+        help = "Run a Rhai script hooking steps, frames, keys, and timer ticks; repeatable, needs the `scripting` feature"
+    )]
+    scripts: Vec<PathBuf>,
+
+    #[structopt(
+        long,
+        help = "Open an interactive debugger in the terminal (registers, disassembly, memory)"
+    )]
+    debug: bool,
+
+    #[structopt(
+        long,
+        conflicts_with = "debug",
+        help = "Read monitor commands from stdin while running (peek, poke, break, regs, continue; try help)"
+    )]
+    monitor: bool,
+
+    #[structopt(
+        long = "break",
+        number_of_values = 1,
+        parse(try_from_str = parse_addr),
+        help = "Pause when PC reaches this address (hex, repeatable)"
+    )]
+    breakpoints: Vec<u16>,
+
+    #[structopt(
+        long = "watch",
+        number_of_values = 1,
+        help = "Pause on memory access in a range, e.g. 200..300:w or 2F0:rw (hex, repeatable)"
+    )]
+    watchpoints: Vec<Watchpoint>,
+
+    #[structopt(
+        long,
+        help = "Listen for a GDB remote protocol client on this localhost port"
+    )]
+    gdb: Option<u16>,
+
+    #[structopt(
+        long,
+        help = "Restart with the new ROM whenever the ROM file is written"
+    )]
+    reload: bool,
+
     #[structopt(short, long, help = "Clock speed (Hz)")]
     clock: Option<f64>,
 
-    #[structopt(short, long, help = "Color Palette")]
+    #[structopt(
+        long,
+        help = "Emulated display refresh rate (Hz), e.g. 30, 60, or 120 (default: show every change)"
+    )]
+    refresh: Option<f64>,
+
+    #[structopt(
+        long,
+        help = "Timers while paused or stopped on an error: freeze (default), or run in real time"
+    )]
+    paused_timers: Option<PausedTimers>,
+
+    #[structopt(
+        long,
+        help = "Language of window titles and messages: en, es, or a language file path"
+    )]
+    language: Option<String>,
+
+    #[structopt(
+        long = "ipt",
+        help = "Run exactly this many instructions per 60 Hz timer tick (deterministic timers)"
+    )]
+    instructions_per_tick: Option<u32>,
+
+    #[structopt(
+        long,
+        help = "Emulate the original keypad: only the lowest pressed key is reported"
+    )]
+    single_key: bool,
+
+    #[structopt(long, help = "Show a hex keypad below the display that can be clicked")]
+    keypad: bool,
+
+    #[structopt(
+        long,
+        help = "Use the 64x64 hi-res display, chosen automatically for ROMs that start with JP 260"
+    )]
+    hires: bool,
+
+    #[structopt(
+        long,
+        help = "Use XO-CHIP's 64K of RAM, chosen automatically for ROMs too large for 4K"
+    )]
+    xo_ram: bool,
+
+    #[structopt(
+        long,
+        help = "Enable the SUPER-CHIP instructions, which XO-CHIP RAM enables too"
+    )]
+    schip: bool,
+
+    #[structopt(
+        long = "map",
+        number_of_values = 1,
+        help = "Bind a host key to a keypad key or action, e.g. q=4, p=pause, or ctrl+r=reset (repeatable)"
+    )]
+    key_mappings: Vec<KeyMapping>,
+
+    #[structopt(
+        short,
+        long,
+        parse(try_from_str = parse_palette),
+        help = "Color palette: 0 ~ 9, or a name such as classic, paper, or amber"
+    )]
     palette: Option<usize>,
 
+    #[structopt(
+        long = "filter",
+        number_of_values = 1,
+        help = "Display filter stage, applied in order: palette, fade[=MS], hue[=DEG_PER_S], phosphor[=DECAY], scanlines[=BRIGHTNESS], grid[=SIZE], crt[=SIZE], rotate=DEGREES (repeatable)"
+    )]
+    filters: Vec<FilterSpec>,
+
+    #[structopt(
+        long,
+        value_name = "DECAY",
+        help = "Fade pixels out instead of switching them off, to hide flicker; DECAY is the brightness kept per frame (default: 0.75)"
+    )]
+    phosphor: Option<Option<f32>>,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Record keypad input to a movie file (implies --ipt 16 unless given)"
+    )]
+    record: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        visible_alias = "playback",
+        parse(from_os_str),
+        help = "Replay keypad input from a movie recorded with --record (sets --ipt and the RNG seed to match)"
+    )]
+    play: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        conflicts_with_all = &["record", "play"],
+        help = "Share the session on this TCP port for --join guests to mirror (implies --ipt 16 unless given)"
+    )]
+    host: Option<u16>,
+
+    #[structopt(
+        long,
+        conflicts_with_all = &["host", "record", "play"],
+        help = "Mirror the session shared with --host at HOST:PORT; run the same ROM"
+    )]
+    join: Option<String>,
+
+    #[structopt(
+        long,
+        conflicts_with_all = &["random", "play"],
+        help = "Seed RND with this number, for the same random values every run"
+    )]
+    seed: Option<u64>,
+
+    #[structopt(
+        long,
+        conflicts_with = "play",
+        help = "Seed RND from the operating system; the seed is printed so the run can be repeated"
+    )]
+    random: bool,
+
+    #[structopt(
+        long,
+        conflicts_with = "debug",
+        help = "Unattended mode: fullscreen, controls off except --kiosk-exit, reset when idle, restart after crashes"
+    )]
+    kiosk: bool,
+
+    #[structopt(
+        long,
+        requires = "kiosk",
+        help = "Key chord that quits in kiosk mode (default: Ctrl+Shift+Q)"
+    )]
+    kiosk_exit: Option<Hotkey>,
+
+    #[structopt(
+        long,
+        requires = "kiosk",
+        help = "Seconds without keypad input before kiosk mode resets the ROM, 0 for never (default: 120)"
+    )]
+    idle_reset: Option<f64>,
+
+    #[structopt(
+        long,
+        help = "Print execution statistics and an opcode histogram on exit"
+    )]
+    stats: bool,
+
+    #[structopt(
+        long,
+        help = "Print a per-subroutine profile (flat and call tree) on exit"
+    )]
+    profile: bool,
+
+    #[structopt(
+        long,
+        default_value = "minifb",
+        help = "Display backend: minifb, term (terminal), or sdl (needs the `sdl` feature)"
+    )]
+    backend: Backend,
+
+    #[structopt(
+        long,
+        help = "Run without a window, as fast as possible, until --cycles or --until-halt stops it"
+    )]
+    headless: bool,
+
+    #[structopt(long, help = "Stop after this many instructions")]
+    cycles: Option<u64>,
+
+    #[structopt(
+        long,
+        help = "With --headless, stop with exit code 3 once the program has waited this many instructions for a key with no input left"
+    )]
+    input_timeout: Option<u64>,
+
+    #[structopt(
+        long,
+        help = "Stop once the program halts: jumps to itself, or waits for a key with no input left"
+    )]
+    until_halt: bool,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Write the display on exit: a .png image, otherwise ASCII art; - for stdout (the default with --headless)"
+    )]
+    dump: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Write the display to numbered PNG files in DIR each time it changes"
+    )]
+    dump_frames: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        help = "Save a screenshot to the ROM's data folder on exit, scaled like the window"
+    )]
+    screenshot_on_exit: bool,
+
     #[structopt(short, long, help = "Disassemble program and exit")]
     disassemble: bool,
+
+    #[structopt(long, help = "Disassemble in Octo syntax")]
+    octo: bool,
+
+    #[structopt(
+        long,
+        default_value = "text",
+        help = "Disassembly format: text, or json (an array of instructions)"
+    )]
+    format: ListingFormat,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Code and data marks for --disassemble, as saved by chip8 hexview (default: <rom>.marks if present)"
+    )]
+    marks: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Address names shown by --debug, one 'ADDR NAME' per line (default: <rom>.sym if present)"
+    )]
+    symbols: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        help = "Practice mode: reload checkpoint (F6 to set) when condition is met, e.g. mem:2F0=0 or pixel:10,5=1"
+    )]
+    practice: Option<LossCondition>,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Trigger file announcing conditions as they are met (default: <rom>.triggers.toml if present)"
+    )]
+    triggers: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Cheat file of ADDR=VALUE memory patches, toggled with F8 (default: <rom>.cheats if present)"
+    )]
+    cheats: Option<PathBuf>,
 }
 
 fn main() -> anyhow::Result<()> {
     let opt = Opt::from_args();
+    let locale = locale(&opt)?;
 
-    let f = File::open(&opt.file)?;
-    let program_rom: Vec<u8> = f.bytes().filter_map(|r| r.ok()).collect();
+    match opt.cmd {
+        Some(Command::Asm {
+            ref input,
+            octo,
+            ref out,
+        }) => tools::assemble(input, octo, out.as_deref(), &locale),
+        Some(Command::Bench {
+            suite,
+            iterations,
+            ref file,
+        }) => {
+            let source = file
+                .as_deref()
+                .map(|file| rom::from_arg(file, &storage(&opt)));
+            tools::bench(suite, iterations, source.as_deref(), &locale)
+        }
+        Some(Command::Browse { ref dir }) => tools::browse(dir, storage(&opt), &locale),
+        Some(Command::Bisect {
+            ref rom,
+            ref movie,
+            ref out,
+        }) => {
+            let config = Config::find(opt.config.as_deref())?;
+            let source = rom::from_arg(rom, &storage(&opt));
+            tools::bisect(
+                source.as_ref(),
+                config.quirks,
+                movie,
+                out.as_deref(),
+                &locale,
+            )
+        }
+        Some(Command::Config(ConfigCommand::Check { ref file })) => {
+            config::check(file.as_deref().or(opt.config.as_deref()), &locale)
+        }
+        Some(Command::Config(ConfigCommand::Init { ref file, force })) => {
+            config::init(file.as_deref(), force, &locale)
+        }
+        Some(Command::CompareFrames {
+            ref first,
+            ref second,
+            ref out,
+        }) => tools::compare_frames(first, second, out, &locale),
+        Some(Command::Hexview { ref rom, ref marks }) => {
+            let source = rom::from_arg(rom, &storage(&opt));
+            HexView::open(source.as_ref(), marks.clone(), locale)?.run()
+        }
+        Some(Command::Info { ref rom }) => {
+            let source = rom::from_arg(rom, &storage(&opt));
+            tools::info(
+                source.as_ref(),
+                &storage(&opt),
+                &app::load_romdb(&opt)?,
+                &locale,
+            )
+        }
+        Some(Command::Tag {
+            ref rom,
+            ref title,
+            ref author,
+            ref tickrate,
+            ref quirks,
+            clear,
+            ref out,
+        }) => {
+            let fields = [
+                ("title", title),
+                ("author", author),
+                ("tickrate", tickrate),
+                ("quirks", quirks),
+            ];
 
-    if opt.disassemble {
-        for line in disasm::disassemble(&program_rom, 0x200) {
-            println!("{}", line);
+            let source = rom::from_arg(rom, &storage(&opt));
+            tools::tag(source.as_ref(), &fields, clear, out.as_deref(), &locale)
+        }
+        Some(Command::Test {
+            ref rom,
+            ref expect,
+            cycles,
+        }) => {
+            let source = rom::from_arg(rom, &storage(&opt));
+            tools::test(source.as_ref(), expect.as_deref(), cycles, &locale)
+        }
+        Some(Command::Storage(StorageCommand::Ls)) => tools::storage_ls(&storage(&opt), &locale),
+        Some(Command::Storage(StorageCommand::Clean { ref rom, all })) => {
+            let source = rom.as_deref().map(|rom| rom::from_arg(rom, &storage(&opt)));
+            tools::storage_clean(&storage(&opt), source.as_deref(), all, &locale)
         }
-    } else {
-        let mut emu = Emulator::new()?;
+        None => app::run(&opt, &locale),
+    }
+}
 
-        emu.debug_print = opt.verbose;
+/// Where the emulator shows its display and reads keys from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Minifb,
+    Term,
+    #[cfg(feature = "sdl")]
+    Sdl,
+}
 
-        if let Some(p) = opt.palette {
-            emu.set_palette(palette::builtin(p));
-        }
+impl FromStr for Backend {
+    type Err = anyhow::Error;
 
-        if let Some(clock) = opt.clock {
-            emu.clock_period = if clock > 0. {
-                Some(Duration::from_secs_f64(1. / clock))
-            } else {
-                // Use native clock
-                None
-            };
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "minifb" => Ok(Backend::Minifb),
+            "term" => Ok(Backend::Term),
+            #[cfg(feature = "sdl")]
+            "sdl" => Ok(Backend::Sdl),
+            #[cfg(not(feature = "sdl"))]
+            "sdl" => Err(anyhow!("the sdl backend needs the `sdl` feature")),
+            _ => Err(anyhow!(
+                "unknown backend '{}', expected minifb, term, or sdl",
+                s
+            )),
         }
+    }
+}
 
-        emu.rom = program_rom;
-        emu.reset()?;
+/// How `--disassemble` prints its listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListingFormat {
+    Text,
+    Json,
+}
 
-        while !emu.closing {
-            emu.step()?;
+impl FromStr for ListingFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(ListingFormat::Text),
+            "json" => Ok(ListingFormat::Json),
+            _ => Err(anyhow!("unknown format '{}', expected text or json", s)),
         }
+    }
+}
 
-        emu.close();
+fn parse_palette(s: &str) -> Result<usize, String> {
+    palette::find(s).ok_or_else(|| {
+        format!(
+            "unknown palette '{}', expected 0 ~ {} or one of {}",
+            s,
+            palette::BUILTIN_PALETTES.len() - 1,
+            palette::names()
+        )
+    })
+}
+
+fn parse_addr(s: &str) -> Result<u16, std::num::ParseIntError> {
+    u16::from_str_radix(s.trim_start_matches("0x").trim_start_matches("0X"), 16)
+}
+
+/// Messages in the language `--language` or the config asks for
+///
+/// A config that does not load leaves English, so `config check` can still
+/// report what is wrong with it.
+fn locale(opt: &Opt) -> anyhow::Result<Locale> {
+    let config = Config::find(opt.config.as_deref()).ok();
+
+    match opt
+        .language
+        .as_deref()
+        .or_else(|| config.as_ref()?.language())
+    {
+        Some(language) => Locale::find(language),
+        None => Ok(Locale::default()),
     }
+}
 
-    Ok(())
+fn storage(opt: &Opt) -> Storage {
+    match &opt.data_dir {
+        Some(dir) => Storage::new(dir),
+        None => Storage::open_default(),
+    }
 }
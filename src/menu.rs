@@ -0,0 +1,87 @@
+use minifb::Key;
+
+/// Entry of the pause menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    Resume,
+    Reset,
+    SaveState,
+    LoadState,
+    /// Switch to the next palette, staying in the menu
+    Palette,
+    /// Show or hide the register overlay, staying in the menu
+    Overlay,
+    Quit,
+}
+
+impl MenuItem {
+    pub const ALL: [MenuItem; 7] = [
+        MenuItem::Resume,
+        MenuItem::Reset,
+        MenuItem::SaveState,
+        MenuItem::LoadState,
+        MenuItem::Palette,
+        MenuItem::Overlay,
+        MenuItem::Quit,
+    ];
+
+    /// Locale key of the entry's label
+    pub fn label_key(self) -> &'static str {
+        match self {
+            MenuItem::Resume => "menu.resume",
+            MenuItem::Reset => "menu.reset",
+            MenuItem::SaveState => "menu.save_state",
+            MenuItem::LoadState => "menu.load_state",
+            MenuItem::Palette => "menu.palette",
+            MenuItem::Overlay => "menu.overlay",
+            MenuItem::Quit => "menu.quit",
+        }
+    }
+}
+
+/// Menu shown over the display while paused with the menu control
+///
+/// Up and Down move the selection, Enter chooses it, and Escape closes the
+/// menu. The keys are taken from the controls and keypad while the menu is
+/// open.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PauseMenu {
+    selected: usize,
+}
+
+impl PauseMenu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn selected(&self) -> MenuItem {
+        MenuItem::ALL[self.selected]
+    }
+
+    /// Handle a key press
+    pub fn press(&mut self, key: Key) -> MenuPress {
+        let count = MenuItem::ALL.len();
+
+        match key {
+            Key::Up => self.selected = (self.selected + count - 1) % count,
+            Key::Down => self.selected = (self.selected + 1) % count,
+            Key::Enter | Key::NumPadEnter => return MenuPress::Chose(self.selected()),
+            Key::Escape => return MenuPress::Closed,
+            _ => return MenuPress::Ignored,
+        }
+
+        MenuPress::Moved
+    }
+}
+
+/// What a key press did to the menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuPress {
+    /// The menu does not use the key, so it works as usual
+    Ignored,
+    /// The selection changed
+    Moved,
+    Chose(MenuItem),
+    /// Escape was pressed to close the menu
+    Closed,
+}
@@ -0,0 +1,260 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{convert::TryFrom, fmt};
+
+use crate::chip8::{Quirks, SysPolicy};
+
+/// Last bytes of a ROM carrying a metadata block
+pub const MAGIC: [u8; 4] = *b"C8MD";
+
+/// Quirk names, as the `Quirks` fields
+///
+/// `sys_ignore` and `sys_callback` select a `SysPolicy` other than the default.
+pub const QUIRK_NAMES: [&str; 6] = [
+    "shift_vy",
+    "load_store_increment_i",
+    "jump_vx",
+    "logic_vf_reset",
+    "sys_ignore",
+    "sys_callback",
+];
+
+/// Information about a ROM stored after its program
+///
+/// The block is appended to the ROM file as
+///
+/// ```text
+/// program | text | text length (u16, big-endian) | "C8MD"
+/// ```
+///
+/// where the text is UTF-8 `key = value` lines:
+///
+/// ```text
+/// title = Pong
+/// author = Paul Vervalin
+/// tickrate = 7
+/// quirks = shift_vy, logic_vf_reset
+/// ```
+///
+/// `tickrate` is instructions per 60 Hz frame, as in Octo, and `quirks` lists
+/// the quirks to enable. Interpreters unaware of the block load it as data
+/// past the end of the program, which well-behaved ROMs never read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub tickrate: Option<u32>,
+    pub quirks: Option<Quirks>,
+}
+
+/// Malformed metadata block or field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataError {
+    pub message: String,
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MetadataError {}
+
+fn error(message: String) -> MetadataError {
+    MetadataError { message }
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+
+    /// These fields, with any left unset filled in from `fallback`
+    pub fn or(self, fallback: Metadata) -> Metadata {
+        Metadata {
+            title: self.title.or(fallback.title),
+            author: self.author.or(fallback.author),
+            tickrate: self.tickrate.or(fallback.tickrate),
+            quirks: self.quirks.or(fallback.quirks),
+        }
+    }
+
+    /// Split a ROM file into its program and metadata block, if it has one
+    pub fn split(rom: &[u8]) -> Result<(&[u8], Option<Metadata>), MetadataError> {
+        let (rest, magic) = match rom.len().checked_sub(MAGIC.len()) {
+            Some(i) => rom.split_at(i),
+            None => return Ok((rom, None)),
+        };
+
+        if magic != MAGIC {
+            return Ok((rom, None));
+        }
+
+        let (rest, len) = match rest.len().checked_sub(2) {
+            Some(i) => rest.split_at(i),
+            None => return Err(error("metadata block is truncated".into())),
+        };
+        let len = usize::from(u16::from_be_bytes([len[0], len[1]]));
+
+        let start = rest
+            .len()
+            .checked_sub(len)
+            .ok_or_else(|| error("metadata block is longer than the ROM".into()))?;
+        let text = core::str::from_utf8(&rest[start..])
+            .map_err(|_| error("metadata is not UTF-8 text".into()))?;
+
+        Ok((&rest[..start], Some(text.parse()?)))
+    }
+
+    /// The ROM file for `program` with this metadata appended
+    ///
+    /// `program` must not already carry a block; see `split`.
+    pub fn append_to(&self, program: &[u8]) -> Result<Vec<u8>, MetadataError> {
+        let text = self.to_string();
+        let len = u16::try_from(text.len())
+            .map_err(|_| error(format!("metadata is too long ({} bytes)", text.len())))?;
+
+        let mut rom = program.to_vec();
+        rom.extend_from_slice(text.as_bytes());
+        rom.extend_from_slice(&len.to_be_bytes());
+        rom.extend_from_slice(&MAGIC);
+
+        Ok(rom)
+    }
+
+    /// Set one field from its name and text value
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), MetadataError> {
+        let value = value.trim();
+
+        match key.trim() {
+            "title" => self.title = Some(single_line(key, value)?),
+            "author" => self.author = Some(single_line(key, value)?),
+            "tickrate" => {
+                let tickrate =
+                    value.parse().ok().filter(|n| *n > 0).ok_or_else(|| {
+                        error(format!("tickrate must be 1 or more, got '{}'", value))
+                    })?;
+
+                self.tickrate = Some(tickrate);
+            }
+            "quirks" => self.quirks = Some(parse_quirks(value)?),
+            other => {
+                return Err(error(format!(
+                    "unknown metadata key '{}', expected title, author, tickrate, or quirks",
+                    other
+                )))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl core::str::FromStr for Metadata {
+    type Err = MetadataError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut metadata = Metadata::default();
+
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected key = value, got '{}'", line)))?;
+
+            metadata.set(key, value)?;
+        }
+
+        Ok(metadata)
+    }
+}
+
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(title) = &self.title {
+            writeln!(f, "title = {}", title)?;
+        }
+        if let Some(author) = &self.author {
+            writeln!(f, "author = {}", author)?;
+        }
+        if let Some(tickrate) = self.tickrate {
+            writeln!(f, "tickrate = {}", tickrate)?;
+        }
+        if let Some(quirks) = &self.quirks {
+            let enabled: Vec<&str> = QUIRK_NAMES
+                .iter()
+                .copied()
+                .filter(|name| quirk(quirks, name))
+                .collect();
+
+            if enabled.is_empty() {
+                writeln!(f, "quirks = none")?;
+            } else {
+                writeln!(f, "quirks = {}", enabled.join(", "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn single_line(key: &str, value: &str) -> Result<String, MetadataError> {
+    if value.contains('\n') {
+        Err(error(format!("{} must be a single line", key)))
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+/// Comma-separated quirk names, all others disabled; empty for none
+pub fn parse_quirks(text: &str) -> Result<Quirks, MetadataError> {
+    let mut quirks = Quirks::default();
+
+    for name in text
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        let flag = match name {
+            "shift_vy" => &mut quirks.shift_vy,
+            "load_store_increment_i" => &mut quirks.load_store_increment_i,
+            "jump_vx" => &mut quirks.jump_vx,
+            "logic_vf_reset" => &mut quirks.logic_vf_reset,
+            "sys_ignore" => {
+                quirks.sys = SysPolicy::Ignore;
+                continue;
+            }
+            "sys_callback" => {
+                quirks.sys = SysPolicy::Callback;
+                continue;
+            }
+            _ => {
+                return Err(error(format!(
+                    "unknown quirk '{}', expected one of: {}",
+                    name,
+                    QUIRK_NAMES.join(", ")
+                )))
+            }
+        };
+
+        *flag = true;
+    }
+
+    Ok(quirks)
+}
+
+fn quirk(quirks: &Quirks, name: &str) -> bool {
+    match name {
+        "shift_vy" => quirks.shift_vy,
+        "load_store_increment_i" => quirks.load_store_increment_i,
+        "jump_vx" => quirks.jump_vx,
+        "logic_vf_reset" => quirks.logic_vf_reset,
+        "sys_ignore" => quirks.sys == SysPolicy::Ignore,
+        "sys_callback" => quirks.sys == SysPolicy::Callback,
+        _ => false,
+    }
+}
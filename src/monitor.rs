@@ -0,0 +1,192 @@
+use std::{
+    io::{self, BufRead},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use anyhow::anyhow;
+
+use chip8::{emu::Emulator, frontend::Frontend};
+
+/// Bytes `peek` shows unless given a length
+const PEEK_DEFAULT_LEN: usize = 16;
+
+const PEEK_COLUMNS: usize = 16;
+
+const HELP: &str = "regs | peek ADDR [LEN] | poke ADDR BYTE.. | break ADDR | delete [ADDR] | step [N] | continue | pause | reset | quit";
+
+/// Line-based monitor reading commands from stdin while the emulator runs
+///
+/// Commands are run between steps and answer on stdout, one line per command
+/// or per 16 bytes of `peek`, so the monitor can be driven by a script as
+/// well as typed at. Numbers are hex, except counts.
+pub struct Monitor {
+    lines: Receiver<String>,
+    open: bool,
+    was_paused: bool,
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        let (sender, lines) = mpsc::channel();
+
+        // Reads block, so they happen on a thread of their own
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Monitor {
+            lines,
+            open: true,
+            was_paused: false,
+        }
+    }
+
+    /// Whether stdin may still send commands
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Run the commands that arrived since the last call, and report pauses
+    ///
+    /// Call after every `Emulator::step`.
+    pub fn update<F: Frontend>(&mut self, emu: &mut Emulator<F>) {
+        if emu.paused && !self.was_paused {
+            println!("{}", emu.break_reason.as_deref().unwrap_or("Paused"));
+        }
+
+        loop {
+            match self.lines.try_recv() {
+                Ok(line) => match run_command(&line, emu) {
+                    Ok(reply) if reply.is_empty() => {}
+                    Ok(reply) => println!("{}", reply),
+                    Err(e) => println!("Error: {}", e),
+                },
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.open = false;
+                    break;
+                }
+            }
+        }
+
+        self.was_paused = emu.paused;
+    }
+}
+
+fn run_command<F: Frontend>(command: &str, emu: &mut Emulator<F>) -> anyhow::Result<String> {
+    let mut words = command.split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let arg = words.next();
+
+    let addr =
+        || -> anyhow::Result<u16> { hex(arg.ok_or_else(|| anyhow!("expected an address"))?) };
+
+    match name {
+        "regs" => Ok(emu.cpu.status()),
+        "peek" => {
+            let start = usize::from(addr()?);
+            let len = words.next().map_or(Ok(PEEK_DEFAULT_LEN), str::parse)?;
+            let end = (start + len).min(emu.cpu.ram.len());
+
+            let lines: Vec<String> = (start..end)
+                .step_by(PEEK_COLUMNS)
+                .map(|row| {
+                    let bytes: Vec<String> = emu.cpu.ram[row..(row + PEEK_COLUMNS).min(end)]
+                        .iter()
+                        .map(|b| format!("{:02X}", b))
+                        .collect();
+                    format!("{:04X}: {}", row, bytes.join(" "))
+                })
+                .collect();
+
+            Ok(lines.join("\n"))
+        }
+        "poke" => {
+            let addr = addr()?;
+            let bytes = words
+                .map(|b| u8::from_str_radix(b.trim_start_matches("0x"), 16))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if bytes.is_empty() {
+                return Err(anyhow!("expected bytes to write, e.g. poke 2F0 AA"));
+            }
+
+            let end = usize::from(addr) + bytes.len();
+            if end > emu.cpu.ram.len() {
+                return Err(anyhow!(
+                    "{} bytes at {:04X} run past the end of memory",
+                    bytes.len(),
+                    addr
+                ));
+            }
+
+            emu.cpu.ram[usize::from(addr)..end].copy_from_slice(&bytes);
+
+            Ok(format!("Wrote {} byte(s) at {:04X}", bytes.len(), addr))
+        }
+        "break" => {
+            let addr = addr()?;
+
+            if emu.cpu.breakpoints.insert(addr) {
+                Ok(format!("Breakpoint set at {:04X}", addr))
+            } else {
+                emu.cpu.breakpoints.remove(&addr);
+                Ok(format!("Breakpoint removed at {:04X}", addr))
+            }
+        }
+        "delete" => {
+            if arg.is_none() {
+                emu.cpu.breakpoints.clear();
+                Ok("All breakpoints removed".into())
+            } else if emu.cpu.breakpoints.remove(&addr()?) {
+                Ok("Breakpoint removed".into())
+            } else {
+                Err(anyhow!("no breakpoint at {:04X}", addr()?))
+            }
+        }
+        "step" => {
+            let n = arg.map_or(Ok(1), str::parse)?;
+
+            if !emu.paused {
+                emu.pause();
+            }
+            emu.step += n;
+
+            Ok(format!("Stepping {} instruction(s)", n))
+        }
+        "continue" => {
+            emu.unpause();
+            Ok("Running".into())
+        }
+        "pause" => {
+            emu.pause();
+            Ok(String::new())
+        }
+        "reset" => {
+            emu.reset()?;
+            Ok("Reset".into())
+        }
+        "quit" => {
+            emu.quit();
+            Ok("Quitting".into())
+        }
+        "help" => Ok(HELP.into()),
+        "" => Ok(String::new()),
+        _ => Err(anyhow!(
+            "unknown command '{}', expected one of: {}",
+            name,
+            HELP
+        )),
+    }
+}
+
+/// Parse a hex number, with or without `0x`
+fn hex(text: &str) -> anyhow::Result<u16> {
+    let digits = text.trim_start_matches("0x").trim_start_matches("0X");
+    Ok(u16::from_str_radix(digits, 16)?)
+}
@@ -0,0 +1,329 @@
+use std::{fs, ops::Range, path::Path};
+
+use anyhow::{anyhow, Context};
+
+use crate::{
+    chip8::{Chip8, Chip8Panic, RNG_SEED},
+    schedule::KeySchedule,
+};
+
+const HEADER: &str = "chip8-movie 1";
+
+/// Keypad input recorded once per executed instruction
+///
+/// Replaying a movie assumes deterministic timing: the delay and sound timers
+/// tick once every `instructions_per_tick` instructions, counted from
+/// `start_step`, and RND draws from an RNG seeded with `seed` at reset.
+///
+/// Stored as text, run-length encoded:
+///
+/// ```text
+/// chip8-movie 1
+/// ipt 16
+/// start 0
+/// seed BAD5EED5...
+/// buzzer 1210 on
+/// buzzer 1402 off
+/// 1200 0000
+/// 35 0020
+/// ```
+///
+/// Each run is a repeat count followed by the held keys as a 16-bit mask (bit n = key n).
+/// The seed is 32 bytes of hex; movies without it used `RNG_SEED`. `buzzer`
+/// lines log when the sound came on and off during recording, so a replay's
+/// sound can be checked against them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub instructions_per_tick: u32,
+    /// Instruction count (since reset) at which the movie starts
+    pub start_step: u64,
+    /// RNG seed at reset
+    pub seed: [u8; 32],
+    /// Keypad mask for each instruction
+    pub inputs: Vec<u16>,
+    /// Buzzer transitions while recording, in order
+    pub buzzer: Vec<BuzzerEvent>,
+}
+
+/// The buzzer turning on or off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuzzerEvent {
+    /// Instructions executed since reset when it changed
+    pub cycle: u64,
+    pub on: bool,
+}
+
+/// Cycles the buzzer was on for, from its transitions; a beep still going
+/// at the last event runs until `end`
+///
+/// At a fixed `instructions_per_tick`, a beep's length in 60 Hz frames is its
+/// cycle count divided by it.
+pub fn beeps(events: &[BuzzerEvent], end: u64) -> Vec<Range<u64>> {
+    let mut beeps = Vec::new();
+    let mut start = None;
+
+    for event in events {
+        match (event.on, start) {
+            (true, None) => start = Some(event.cycle),
+            (false, Some(on)) => {
+                beeps.push(on..event.cycle);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(on) = start {
+        beeps.push(on..end.max(on));
+    }
+
+    beeps
+}
+
+impl Default for Movie {
+    fn default() -> Self {
+        Movie::new(0)
+    }
+}
+
+/// Error hit while replaying a movie
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crash {
+    /// Instruction count (since reset) of the failing instruction
+    pub step: u64,
+    pub pc: u16,
+    pub error: Chip8Panic,
+}
+
+impl Movie {
+    pub fn new(instructions_per_tick: u32) -> Self {
+        Movie {
+            instructions_per_tick,
+            start_step: 0,
+            seed: RNG_SEED,
+            inputs: Vec::new(),
+            buzzer: Vec::new(),
+        }
+    }
+
+    /// Record the keys currently held on `cpu` for the next instruction
+    pub fn record(&mut self, cpu: &Chip8) {
+        self.inputs.push(key_mask(cpu));
+    }
+
+    pub fn end_step(&self) -> u64 {
+        self.start_step + self.inputs.len() as u64
+    }
+
+    /// Inputs for instructions `start..end` (counted since reset)
+    pub fn slice(&self, start: u64, end: u64) -> Movie {
+        let index =
+            |step: u64| (step.saturating_sub(self.start_step) as usize).min(self.inputs.len());
+        let (start, end) = (index(start), index(end).max(index(start)));
+        let cycles = self.start_step + start as u64..self.start_step + end as u64;
+
+        Movie {
+            instructions_per_tick: self.instructions_per_tick,
+            start_step: cycles.start,
+            seed: self.seed,
+            inputs: self.inputs[start..end].to_vec(),
+            buzzer: self
+                .buzzer
+                .iter()
+                .filter(|event| cycles.contains(&event.cycle))
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// Key holds equivalent to the recorded input, in cycles since reset
+    pub fn schedule(&self) -> KeySchedule {
+        let mut schedule = KeySchedule::new();
+
+        for key in 0..0x10 {
+            let held = |i: &usize| self.inputs[*i] & (1 << key) != 0;
+            let mut i = 0;
+
+            while i < self.inputs.len() {
+                if held(&i) {
+                    let end = (i..self.inputs.len())
+                        .find(|j| !held(j))
+                        .unwrap_or(self.inputs.len());
+                    schedule.schedule(
+                        key,
+                        self.start_step + i as u64,
+                        self.start_step + end as u64,
+                    );
+                    i = end;
+                } else {
+                    i += 1;
+                }
+            }
+        }
+
+        schedule
+    }
+
+    /// Replay instructions `from..to` (counted since reset) on `cpu`, stopping at the first error
+    ///
+    /// When `idle` is set, no keys are pressed instead of the recorded input.
+    pub fn replay(&self, cpu: &mut Chip8, from: u64, to: u64, idle: bool) -> Option<Crash> {
+        let ipt = u64::from(self.instructions_per_tick.max(1));
+
+        let mut schedule = if idle {
+            KeySchedule::new()
+        } else {
+            self.schedule()
+        };
+
+        for step in from..to {
+            if step > 0 && step % ipt == 0 {
+                cpu.timer_tick();
+            }
+
+            schedule.apply(cpu, step);
+
+            if let Err(error) = cpu.step() {
+                return Some(Crash {
+                    step,
+                    pc: cpu.pc,
+                    error,
+                });
+            }
+        }
+
+        None
+    }
+
+    pub fn to_text(&self) -> String {
+        let seed: String = self.seed.iter().map(|b| format!("{:02X}", b)).collect();
+        let mut out = format!(
+            "{}\nipt {}\nstart {}\nseed {}\n",
+            HEADER, self.instructions_per_tick, self.start_step, seed
+        );
+
+        for event in &self.buzzer {
+            let state = if event.on { "on" } else { "off" };
+            out.push_str(&format!("buzzer {} {}\n", event.cycle, state));
+        }
+
+        let mut i = 0;
+        while i < self.inputs.len() {
+            let mask = self.inputs[i];
+            let run = self.inputs[i..].iter().take_while(|m| **m == mask).count();
+
+            out.push_str(&format!("{} {:04X}\n", run, mask));
+            i += run;
+        }
+
+        out
+    }
+
+    pub fn from_text(text: &str) -> anyhow::Result<Self> {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .peekable();
+
+        if lines.next() != Some(HEADER) {
+            return Err(anyhow!("not a movie file"));
+        }
+
+        let mut field = |name: &str| -> anyhow::Result<u64> {
+            lines
+                .next()
+                .and_then(|line| line.strip_prefix(name))
+                .ok_or_else(|| anyhow!("missing '{}' line", name))?
+                .trim()
+                .parse()
+                .with_context(|| format!("invalid '{}' line", name))
+        };
+
+        let instructions_per_tick = field("ipt")? as u32;
+        let start_step = field("start")?;
+
+        let seed = match lines.peek().and_then(|line| line.strip_prefix("seed")) {
+            Some(hex) => {
+                lines.next();
+                parse_seed(hex.trim())?
+            }
+            None => RNG_SEED,
+        };
+
+        let mut inputs = Vec::new();
+        let mut buzzer = Vec::new();
+
+        for line in lines {
+            if let Some(event) = line.strip_prefix("buzzer ") {
+                let (cycle, state) = event
+                    .split_once(' ')
+                    .ok_or_else(|| anyhow!("invalid buzzer line '{}'", line))?;
+                let on = match state.trim() {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(anyhow!("invalid buzzer line '{}'", line)),
+                };
+
+                buzzer.push(BuzzerEvent {
+                    cycle: cycle.parse()?,
+                    on,
+                });
+                continue;
+            }
+
+            let (run, mask) = line
+                .split_once(' ')
+                .ok_or_else(|| anyhow!("invalid input line '{}'", line))?;
+
+            let run: usize = run.parse()?;
+            let mask = u16::from_str_radix(mask.trim(), 16)?;
+
+            inputs.extend(std::iter::repeat_n(mask, run));
+        }
+
+        Ok(Movie {
+            instructions_per_tick,
+            start_step,
+            seed,
+            inputs,
+            buzzer,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, self.to_text())
+            .with_context(|| format!("failed to write movie {}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read movie {}", path.display()))?;
+
+        Self::from_text(&text).with_context(|| format!("invalid movie {}", path.display()))
+    }
+}
+
+/// 32 bytes as 64 hex digits
+pub fn parse_seed(hex: &str) -> anyhow::Result<[u8; 32]> {
+    let mut seed = [0; 32];
+
+    if hex.len() != seed.len() * 2 || !hex.is_ascii() {
+        return Err(anyhow!("seed must be 64 hex digits, got '{}'", hex));
+    }
+
+    for (i, byte) in seed.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .with_context(|| format!("invalid seed '{}'", hex))?;
+    }
+
+    Ok(seed)
+}
+
+fn key_mask(cpu: &Chip8) -> u16 {
+    cpu.keys
+        .iter()
+        .enumerate()
+        .filter(|(_, held)| **held)
+        .fold(0, |mask, (key, _)| mask | (1 << key))
+}
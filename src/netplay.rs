@@ -0,0 +1,304 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use anyhow::{anyhow, Context};
+
+use crate::{
+    emu::{self, Emulator},
+    frontend::Frontend,
+    movie::Movie,
+    romdb, savestate,
+};
+
+const HEADER: &str = "chip8-netplay 1";
+
+/// Shares a running session with guests over TCP, who mirror it
+///
+/// Guests get the machine state when they join, then the keypad input of
+/// every instruction, so they run the same session in lockstep, the way a
+/// movie replays. Sent as text lines:
+///
+/// ```text
+/// chip8-netplay 1
+/// ipt 16
+/// rom 3b5c...
+/// state 1200 4 4338535301...
+/// 16 0000
+/// 16 0020
+/// ```
+///
+/// `state` gives the instruction count, the instructions since the last timer
+/// tick, and a save state in hex. Input lines are runs as in a movie: a repeat
+/// count and a keypad mask. A new `state` follows anything input cannot
+/// replay: a reset, a loaded state, or rewinding.
+pub struct NetHost {
+    listener: TcpListener,
+    guests: Vec<TcpStream>,
+}
+
+impl NetHost {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(NetHost {
+            listener,
+            guests: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> anyhow::Result<std::net::SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Send new input, resynchronize guests if needed, and accept new ones
+    ///
+    /// Call after every `Emulator::step`. Input is taken from the emulator's
+    /// recording, which the host owns: it is started here and cleared as it
+    /// is sent.
+    pub fn update<F: Frontend>(&mut self, emu: &mut Emulator<F>) -> anyhow::Result<()> {
+        let ipt = emu
+            .instructions_per_tick
+            .unwrap_or(emu::DEFAULT_INSTRUCTIONS_PER_TICK);
+
+        // The recording ends, or stops matching the instruction count, when
+        // the machine is changed some other way than by running
+        let in_step = emu
+            .recording
+            .as_ref()
+            .is_some_and(|movie| movie.end_step() == emu.cycle);
+
+        if !in_step {
+            let mut movie = Movie::new(ipt);
+            movie.start_step = emu.cycle;
+            emu.recording = Some(movie);
+
+            let state = state_line(emu);
+            self.broadcast(&state);
+        } else if emu.paused
+            || emu
+                .recording
+                .as_ref()
+                .is_some_and(|movie| movie.inputs.len() >= ipt as usize)
+        {
+            // Sent a frame at a time, or all of it once paused
+            self.flush(emu);
+        }
+
+        loop {
+            match self.listener.accept() {
+                Ok((mut stream, addr)) => {
+                    stream.set_nonblocking(false)?;
+                    stream.set_nodelay(true)?;
+
+                    // Guests already connected get the input up to the state
+                    self.flush(emu);
+
+                    let hello = format!(
+                        "{}\nipt {}\nrom {}\n{}",
+                        HEADER,
+                        ipt,
+                        romdb::sha1_hex(&emu.rom),
+                        state_line(emu)
+                    );
+
+                    if stream.write_all(hello.as_bytes()).is_ok() {
+                        println!("Guest joined from {}", addr);
+                        self.guests.push(stream);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send the input not sent yet, as the session ends
+    pub fn finish<F: Frontend>(mut self, emu: &mut Emulator<F>) {
+        self.flush(emu);
+    }
+
+    /// Send the recorded input and clear it
+    fn flush<F: Frontend>(&mut self, emu: &mut Emulator<F>) {
+        let movie = match &mut emu.recording {
+            Some(movie) if !movie.inputs.is_empty() => movie,
+            _ => return,
+        };
+
+        let mut lines = String::new();
+        let mut i = 0;
+
+        while i < movie.inputs.len() {
+            let mask = movie.inputs[i];
+            let run = movie.inputs[i..].iter().take_while(|m| **m == mask).count();
+
+            lines.push_str(&format!("{} {:04X}\n", run, mask));
+            i += run;
+        }
+
+        movie.start_step += movie.inputs.len() as u64;
+        movie.inputs.clear();
+
+        self.broadcast(&lines);
+    }
+
+    fn broadcast(&mut self, text: &str) {
+        self.guests.retain_mut(|guest| {
+            let sent = guest.write_all(text.as_bytes()).is_ok();
+
+            if !sent {
+                println!("Guest left");
+            }
+
+            sent
+        });
+    }
+}
+
+fn state_line<F: Frontend>(emu: &Emulator<F>) -> String {
+    let state: String = savestate::serialize(&emu.cpu)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect();
+
+    format!("state {} {} {}\n", emu.cycle, emu.tick_instructions, state)
+}
+
+/// Mirrors a session shared by a `NetHost`
+///
+/// The emulator only runs the instructions whose input has arrived, so it
+/// waits when it gets ahead of the host. Local keypad input should be turned
+/// off, as it would make the session diverge.
+pub struct NetGuest {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    greeted: bool,
+    /// The host closed the connection
+    closed: bool,
+    connected: bool,
+}
+
+impl NetGuest {
+    pub fn connect(addr: &str) -> anyhow::Result<Self> {
+        let stream =
+            TcpStream::connect(addr).with_context(|| format!("failed to join {}", addr))?;
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+
+        Ok(NetGuest {
+            stream,
+            buffer: Vec::new(),
+            greeted: false,
+            closed: false,
+            connected: true,
+        })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Apply what the host sent since the last call
+    ///
+    /// Call after every `Emulator::step`. When the host goes away, the
+    /// emulator runs the input already sent, then is paused and left to run
+    /// on its own.
+    pub fn update<F: Frontend>(&mut self, emu: &mut Emulator<F>) -> anyhow::Result<()> {
+        if !self.connected {
+            return Ok(());
+        }
+
+        let mut chunk = [0; 4096];
+        while !self.closed {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => self.closed = true,
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => self.closed = true,
+            }
+        }
+
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+
+            self.handle(line.trim(), emu)
+                .context("invalid data from the netplay host")?;
+        }
+
+        if self.closed && emu.input_limit.is_none_or(|limit| emu.cycle >= limit) {
+            self.disconnect(emu);
+        }
+
+        Ok(())
+    }
+
+    fn handle<F: Frontend>(&mut self, line: &str, emu: &mut Emulator<F>) -> anyhow::Result<()> {
+        if !self.greeted {
+            if line != HEADER {
+                return Err(anyhow!("not a chip8 netplay host"));
+            }
+
+            self.greeted = true;
+            return Ok(());
+        }
+
+        let (name, rest) = line.split_once(' ').unwrap_or((line, ""));
+
+        match name {
+            "ipt" => emu.instructions_per_tick = Some(rest.parse()?),
+            "rom" => {
+                if rest != romdb::sha1_hex(&emu.rom) {
+                    println!("Warning: the host is running a different ROM");
+                }
+            }
+            "state" => {
+                let mut fields = rest.splitn(3, ' ');
+                let mut field = || fields.next().ok_or_else(|| anyhow!("short state line"));
+
+                let cycle = field()?.parse()?;
+                let tick_instructions = field()?.parse()?;
+                let hex = field()?;
+
+                let state = (0..hex.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(hex.get(i..i + 2).unwrap_or_default(), 16))
+                    .collect::<Result<Vec<u8>, _>>()?;
+
+                savestate::deserialize(&mut emu.cpu, &state)?;
+                emu.cycle = cycle;
+                emu.tick_instructions = tick_instructions;
+                emu.key_schedule.clear();
+                emu.input_limit = Some(cycle);
+            }
+            _ => {
+                let run: u64 = name.parse()?;
+                let mask = u16::from_str_radix(rest, 16)?;
+                let start = emu.input_limit.unwrap_or(emu.cycle);
+
+                for key in 0..0x10 {
+                    if mask & (1 << key) != 0 {
+                        emu.key_schedule.schedule(key, start, start + run);
+                    }
+                }
+
+                emu.input_limit = Some(start + run);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn disconnect<F: Frontend>(&mut self, emu: &mut Emulator<F>) {
+        self.connected = false;
+        emu.input_limit = None;
+        emu.break_reason = Some("The netplay host left".into());
+        emu.pause();
+        println!("The netplay host left");
+    }
+}
@@ -0,0 +1,639 @@
+use alloc::{collections::BTreeMap, format, string::String, vec, vec::Vec};
+
+use crate::asm::AsmError;
+
+/// Address the first assembled byte is loaded at
+const ORIGIN: u16 = 0x200;
+
+/// Assemble Octo source into a ROM loaded at 200
+///
+/// Covers the CHIP-8 subset of Octo: `: label` definitions, `:const`,
+/// `:alias`, `:org`, `:byte`, and `:call`; register statements such as
+/// `v0 := 5`, `v1 += v2`, `i := hex v0`, and `sprite v0 v1 5`; `if ... then`
+/// and `if ... begin ... else ... end`; `loop ... while ... again`; and the
+/// `<`, `>`, `<=`, `>=` comparisons, which use `vf`. A bare label calls it,
+/// and a bare number emits a byte. Numbers are decimal, `0x` hex, or `0b`
+/// binary. `#` starts a comment.
+///
+/// When the program defines `main` somewhere other than the start, a jump to
+/// it is placed at 200, as Octo does.
+///
+/// Stops at the first error.
+pub fn assemble(source: &str) -> Result<Vec<u8>, Vec<AsmError>> {
+    let tokens = tokenize(source);
+
+    let mut assembler = Assembler {
+        tokens: &tokens,
+        pos: 0,
+        rom: Vec::new(),
+        here: 0,
+        labels: BTreeMap::new(),
+        constants: BTreeMap::new(),
+        aliases: BTreeMap::new(),
+        fixups: Vec::new(),
+        blocks: Vec::new(),
+    };
+
+    assembler.run().map_err(|e| vec![e])?;
+
+    Ok(assembler.rom)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+fn tokenize(source: &str) -> Vec<Token<'_>> {
+    source
+        .lines()
+        .enumerate()
+        .flat_map(|(i, line)| {
+            line.split('#')
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .map(move |text| Token { text, line: i + 1 })
+        })
+        .collect()
+}
+
+/// Label reference to fill in once every label is known
+struct Fixup<'a> {
+    /// Offset of the opcode in the ROM
+    offset: usize,
+    label: &'a str,
+    line: usize,
+}
+
+/// Open `if ... begin` or `loop` block
+enum Block {
+    /// Offset of the jump over the `if` body, patched at `else` or `end`
+    If { jump: usize },
+    /// Loop start address, and offsets of `while` jumps patched at `again`
+    Loop { start: u16, exits: Vec<usize> },
+}
+
+/// Comparison between a register and a register or number
+enum Operand {
+    Register(u8),
+    Number(u8),
+}
+
+struct Assembler<'a> {
+    tokens: &'a [Token<'a>],
+    pos: usize,
+    rom: Vec<u8>,
+    /// Write offset into `rom`
+    here: usize,
+    labels: BTreeMap<&'a str, u16>,
+    constants: BTreeMap<&'a str, i32>,
+    aliases: BTreeMap<&'a str, u8>,
+    fixups: Vec<Fixup<'a>>,
+    blocks: Vec<Block>,
+}
+
+impl<'a> Assembler<'a> {
+    fn run(&mut self) -> Result<(), AsmError> {
+        let main_first = matches!(
+            self.tokens,
+            [Token { text: ":", .. }, Token { text: "main", .. }, ..]
+        );
+        let has_main = self
+            .tokens
+            .windows(2)
+            .any(|pair| pair[0].text == ":" && pair[1].text == "main");
+
+        if has_main && !main_first {
+            self.emit_address(0x1000, "main", 1);
+        }
+
+        while self.pos < self.tokens.len() {
+            self.statement()?;
+        }
+
+        if let Some(block) = self.blocks.last() {
+            let line = self.tokens.last().map_or(1, |t| t.line);
+            let message = match block {
+                Block::If { .. } => "'begin' without 'end'",
+                Block::Loop { .. } => "'loop' without 'again'",
+            };
+            return Err(AsmError {
+                line,
+                message: message.into(),
+            });
+        }
+
+        if usize::from(ORIGIN) + self.rom.len() > 0x1000 {
+            return Err(AsmError {
+                line: self.tokens.last().map_or(1, |t| t.line),
+                message: "program does not fit in memory".into(),
+            });
+        }
+
+        for fixup in self.fixups.iter() {
+            let addr = *self.labels.get(fixup.label).ok_or_else(|| AsmError {
+                line: fixup.line,
+                message: format!("undefined label '{}'", fixup.label),
+            })?;
+
+            self.rom[fixup.offset] |= (addr >> 8) as u8 & 0x0F;
+            self.rom[fixup.offset + 1] = addr as u8;
+        }
+
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), AsmError> {
+        let token = self.next()?;
+
+        match token.text {
+            ":" => {
+                let name = self.name()?;
+                self.define_label(name, token.line)?;
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.number()?;
+                self.constants.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let reg = self.register()?;
+                self.aliases.insert(name, reg);
+            }
+            ":org" => {
+                let addr = self.address_number()?;
+                if addr < ORIGIN {
+                    return Err(self.error(format!(":org {:X} is below {:X}", addr, ORIGIN)));
+                }
+                self.here = usize::from(addr - ORIGIN);
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit_bytes(&[byte]);
+            }
+            ":call" => {
+                let target = self.next()?;
+                self.emit_target(0x2000, target)?;
+            }
+            ":breakpoint" => {
+                self.name()?;
+            }
+            "clear" => self.emit(0x00E0),
+            "return" | ";" => self.emit(0x00EE),
+            "jump" => {
+                let target = self.next()?;
+                self.emit_target(0x1000, target)?;
+            }
+            "jump0" => {
+                let target = self.next()?;
+                self.emit_target(0xB000, target)?;
+            }
+            "save" => {
+                let x = self.register()?;
+                self.emit(0xF055 | u16::from(x) << 8);
+            }
+            "load" => {
+                let x = self.register()?;
+                self.emit(0xF065 | u16::from(x) << 8);
+            }
+            "bcd" => {
+                let x = self.register()?;
+                self.emit(0xF033 | u16::from(x) << 8);
+            }
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.number_in(0, 15)? as u16;
+                self.emit(0xD000 | u16::from(x) << 8 | u16::from(y) << 4 | n);
+            }
+            "delay" | "buzzer" => {
+                self.expect(":=")?;
+                let x = self.register()?;
+                let kk = if token.text == "delay" { 0x15 } else { 0x18 };
+                self.emit(0xF000 | u16::from(x) << 8 | kk);
+            }
+            "i" => self.i_statement()?,
+            "if" => self.if_statement()?,
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump }) => {
+                    let skip_else = self.here;
+                    self.emit(0x1000);
+                    self.patch_jump(jump);
+                    self.blocks.push(Block::If { jump: skip_else });
+                }
+                _ => return Err(self.error("'else' without 'if ... begin'".into())),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump }) => self.patch_jump(jump),
+                _ => return Err(self.error("'end' without 'if ... begin'".into())),
+            },
+            "loop" => {
+                let start = self.addr();
+                self.blocks.push(Block::Loop {
+                    start,
+                    exits: Vec::new(),
+                });
+            }
+            "while" => {
+                let (prelude, skip) = self.condition()?;
+                for opcode in prelude {
+                    self.emit(opcode);
+                }
+                self.emit(skip.1);
+
+                let exit = self.here;
+                self.emit(0x1000);
+
+                match self.blocks.iter_mut().rev().find_map(|block| match block {
+                    Block::Loop { exits, .. } => Some(exits),
+                    Block::If { .. } => None,
+                }) {
+                    Some(exits) => exits.push(exit),
+                    None => return Err(self.error("'while' outside of 'loop'".into())),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, exits }) => {
+                    self.emit(0x1000 | start);
+                    for exit in exits {
+                        self.patch_jump(exit);
+                    }
+                }
+                _ => return Err(self.error("'again' without 'loop'".into())),
+            },
+            text => {
+                if let Some(x) = self.try_register(text) {
+                    self.register_statement(x)?;
+                } else if let Some(value) = self.try_number(text) {
+                    let byte = self.byte_value(value)?;
+                    self.emit_bytes(&[byte]);
+                } else if text.starts_with(':') {
+                    return Err(self.error(format!("unsupported directive '{}'", text)));
+                } else if is_name(text) {
+                    self.emit_target(0x2000, token)?;
+                } else {
+                    return Err(self.error(format!("unexpected '{}'", text)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `vx := ...`, `vx += ...`, and the other register operators
+    fn register_statement(&mut self, x: u8) -> Result<(), AsmError> {
+        let op = self.next()?.text;
+        let rhs = self.next()?;
+        let x8 = u16::from(x) << 8;
+
+        let alu = |n: u16| -> Option<u16> { Some(0x8000 | x8 | n) };
+
+        if let Some(y) = self.try_register(rhs.text) {
+            let y4 = u16::from(y) << 4;
+            let opcode = match op {
+                ":=" => alu(0x0),
+                "|=" => alu(0x1),
+                "&=" => alu(0x2),
+                "^=" => alu(0x3),
+                "+=" => alu(0x4),
+                "-=" => alu(0x5),
+                ">>=" => alu(0x6),
+                "=-" => alu(0x7),
+                "<<=" => alu(0xE),
+                _ => None,
+            };
+
+            return match opcode {
+                Some(opcode) => {
+                    self.emit(opcode | y4);
+                    Ok(())
+                }
+                None => Err(self.error(format!("unknown operator '{}'", op))),
+            };
+        }
+
+        match (op, rhs.text) {
+            (":=", "delay") => self.emit(0xF007 | x8),
+            (":=", "key") => self.emit(0xF00A | x8),
+            (":=", "random") => {
+                let kk = self.byte()?;
+                self.emit(0xC000 | x8 | u16::from(kk));
+            }
+            (":=", _) | ("+=", _) | ("-=", _) => {
+                let value = self.value(rhs.text)?;
+                let kk = self.byte_value(if op == "-=" { -value } else { value })?;
+                let base = if op == ":=" { 0x6000 } else { 0x7000 };
+                self.emit(base | x8 | u16::from(kk));
+            }
+            _ => return Err(self.error(format!("unknown operator '{}'", op))),
+        }
+
+        Ok(())
+    }
+
+    /// `i := addr`, `i := hex vx`, `i += vx`
+    fn i_statement(&mut self) -> Result<(), AsmError> {
+        let op = self.next()?.text;
+        let rhs = self.next()?;
+
+        match (op, rhs.text) {
+            (":=", "hex") => {
+                let x = self.register()?;
+                self.emit(0xF029 | u16::from(x) << 8);
+            }
+            (":=", _) => self.emit_target(0xA000, rhs)?,
+            ("+=", _) => match self.try_register(rhs.text) {
+                Some(x) => self.emit(0xF01E | u16::from(x) << 8),
+                None => return Err(self.error("expected a register after 'i +='".into())),
+            },
+            _ => return Err(self.error(format!("unknown operator '{}' for i", op))),
+        }
+
+        Ok(())
+    }
+
+    /// `if cond then` guards the next statement; `if cond begin` opens a block
+    fn if_statement(&mut self) -> Result<(), AsmError> {
+        let (prelude, (then_skip, begin_skip)) = self.condition()?;
+
+        for opcode in prelude {
+            self.emit(opcode);
+        }
+
+        match self.next()?.text {
+            "then" => self.emit(then_skip),
+            "begin" => {
+                self.emit(begin_skip);
+                let jump = self.here;
+                self.emit(0x1000);
+                self.blocks.push(Block::If { jump });
+            }
+            other => {
+                return Err(self.error(format!("expected 'then' or 'begin', found '{}'", other)))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Instructions that set up a condition, and the skips that test it
+    ///
+    /// The first skip passes over the next instruction when the condition is
+    /// false (for `then`), the second when it is true (for `begin`/`while`).
+    fn condition(&mut self) -> Result<(Vec<u16>, (u16, u16)), AsmError> {
+        let x = self.register()?;
+        let x8 = u16::from(x) << 8;
+        let op = self.next()?.text;
+
+        match op {
+            "key" => return Ok((Vec::new(), (0xE0A1 | x8, 0xE09E | x8))),
+            "-key" => return Ok((Vec::new(), (0xE09E | x8, 0xE0A1 | x8))),
+            _ => {}
+        }
+
+        let rhs = self.next()?;
+        let rhs = match self.try_register(rhs.text) {
+            Some(y) => Operand::Register(y),
+            None => Operand::Number(self.byte_value(self.value(rhs.text)?)?),
+        };
+
+        let equal = |rhs: &Operand| match *rhs {
+            Operand::Register(y) => (
+                0x9000 | x8 | u16::from(y) << 4,
+                0x5000 | x8 | u16::from(y) << 4,
+            ),
+            Operand::Number(kk) => (0x4000 | x8 | u16::from(kk), 0x3000 | x8 | u16::from(kk)),
+        };
+
+        match op {
+            "==" => Ok((Vec::new(), equal(&rhs))),
+            "!=" => {
+                let (then_skip, begin_skip) = equal(&rhs);
+                Ok((Vec::new(), (begin_skip, then_skip)))
+            }
+            "<" | ">" | "<=" | ">=" => {
+                if x == 0xF {
+                    return Err(self.error(format!("vf cannot be compared with '{}'", op)));
+                }
+
+                // Load the right side into vf and subtract, leaving the
+                // no-borrow flag: vf =- vx sets vf = 1 when vx >= rhs, and
+                // vf -= vx when rhs >= vx
+                let load = match rhs {
+                    Operand::Register(y) => 0x8F00 | u16::from(y) << 4,
+                    Operand::Number(kk) => 0x6F00 | u16::from(kk),
+                };
+                let (subtract, flag) = match op {
+                    ">=" => (0x8F07, 1),
+                    "<" => (0x8F07, 0),
+                    "<=" => (0x8F05, 1),
+                    _ => (0x8F05, 0),
+                };
+                let vf = 0x0F00;
+
+                Ok((
+                    vec![load, subtract | u16::from(x) << 4],
+                    (0x4000 | vf | flag, 0x3000 | vf | flag),
+                ))
+            }
+            _ => Err(self.error(format!("unknown comparison '{}'", op))),
+        }
+    }
+
+    fn define_label(&mut self, name: &'a str, line: usize) -> Result<(), AsmError> {
+        if self.labels.insert(name, self.addr()).is_some() {
+            return Err(AsmError {
+                line,
+                message: format!("label '{}' is already defined", name),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Emit `base | target`, where `target` is a number, constant, or label
+    fn emit_target(&mut self, base: u16, target: Token<'a>) -> Result<(), AsmError> {
+        if let Some(value) = self.try_number(target.text) {
+            let addr = self.address_value(value)?;
+            self.emit(base | addr);
+        } else if let Some(addr) = self.labels.get(target.text) {
+            self.emit(base | addr);
+        } else if is_name(target.text) {
+            self.emit_address(base, target.text, target.line);
+        } else {
+            return Err(self.error(format!("expected an address, found '{}'", target.text)));
+        }
+
+        Ok(())
+    }
+
+    fn emit_address(&mut self, base: u16, label: &'a str, line: usize) {
+        self.fixups.push(Fixup {
+            offset: self.here,
+            label,
+            line,
+        });
+        self.emit(base);
+    }
+
+    fn emit(&mut self, opcode: u16) {
+        self.emit_bytes(&opcode.to_be_bytes());
+    }
+
+    fn emit_bytes(&mut self, bytes: &[u8]) {
+        let end = self.here + bytes.len();
+        if self.rom.len() < end {
+            self.rom.resize(end, 0);
+        }
+
+        self.rom[self.here..end].copy_from_slice(bytes);
+        self.here = end;
+    }
+
+    /// Point the placeholder jump at `offset` to the current address
+    fn patch_jump(&mut self, offset: usize) {
+        let addr = self.addr();
+        self.rom[offset] = 0x10 | (addr >> 8) as u8 & 0x0F;
+        self.rom[offset + 1] = addr as u8;
+    }
+
+    fn addr(&self) -> u16 {
+        ORIGIN + self.here as u16
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, AsmError> {
+        let token = self.tokens.get(self.pos).copied().ok_or_else(|| AsmError {
+            line: self.tokens.last().map_or(1, |t| t.line),
+            message: "unexpected end of source".into(),
+        })?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), AsmError> {
+        let token = self.next()?;
+        if token.text == text {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{}', found '{}'", text, token.text)))
+        }
+    }
+
+    fn name(&mut self) -> Result<&'a str, AsmError> {
+        let token = self.next()?;
+        if is_name(token.text) {
+            Ok(token.text)
+        } else {
+            Err(self.error(format!("'{}' is not a valid name", token.text)))
+        }
+    }
+
+    fn register(&mut self) -> Result<u8, AsmError> {
+        let token = self.next()?;
+        self.try_register(token.text)
+            .ok_or_else(|| self.error(format!("expected a register, found '{}'", token.text)))
+    }
+
+    fn try_register(&self, text: &str) -> Option<u8> {
+        if let Some(reg) = self.aliases.get(text) {
+            return Some(*reg);
+        }
+
+        match text.as_bytes() {
+            [b'v' | b'V', digit] => (*digit as char).to_digit(16).map(|x| x as u8),
+            _ => None,
+        }
+    }
+
+    fn number(&mut self) -> Result<i32, AsmError> {
+        let token = self.next()?;
+        self.value(token.text)
+    }
+
+    fn byte(&mut self) -> Result<u8, AsmError> {
+        let value = self.number()?;
+        self.byte_value(value)
+    }
+
+    fn number_in(&mut self, min: i32, max: i32) -> Result<i32, AsmError> {
+        let value = self.number()?;
+        if (min..=max).contains(&value) {
+            Ok(value)
+        } else {
+            Err(self.error(format!("{} is outside {}..{}", value, min, max)))
+        }
+    }
+
+    fn address_number(&mut self) -> Result<u16, AsmError> {
+        let value = self.number()?;
+        self.address_value(value)
+    }
+
+    /// A number or constant
+    fn value(&self, text: &str) -> Result<i32, AsmError> {
+        self.try_number(text)
+            .ok_or_else(|| self.error(format!("expected a number, found '{}'", text)))
+    }
+
+    fn try_number(&self, text: &str) -> Option<i32> {
+        if let Some(value) = self.constants.get(text) {
+            return Some(*value);
+        }
+
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+
+        let value = if let Some(hex) = digits.strip_prefix("0x") {
+            i32::from_str_radix(hex, 16).ok()?
+        } else if let Some(bin) = digits.strip_prefix("0b") {
+            i32::from_str_radix(bin, 2).ok()?
+        } else if digits.bytes().all(|b| b.is_ascii_digit()) && !digits.is_empty() {
+            digits.parse().ok()?
+        } else {
+            return None;
+        };
+
+        Some(if negative { -value } else { value })
+    }
+
+    /// A byte from -128..255, negative numbers in two's complement
+    fn byte_value(&self, value: i32) -> Result<u8, AsmError> {
+        if (-128..=255).contains(&value) {
+            Ok(value as u8)
+        } else {
+            Err(self.error(format!("{} does not fit in a byte", value)))
+        }
+    }
+
+    fn address_value(&self, value: i32) -> Result<u16, AsmError> {
+        if (0..=0xFFF).contains(&value) {
+            Ok(value as u16)
+        } else {
+            Err(self.error(format!("{} is not a 12-bit address", value)))
+        }
+    }
+
+    /// Error at the most recently read token
+    fn error(&self, message: String) -> AsmError {
+        let line = self
+            .tokens
+            .get(self.pos.saturating_sub(1))
+            .map_or(1, |t| t.line);
+
+        AsmError { line, message }
+    }
+}
+
+fn is_name(text: &str) -> bool {
+    text.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
@@ -0,0 +1,191 @@
+use crate::{chip8::Chip8, disasm, filter::Frame};
+
+/// Glyph size in pixels, before the one-pixel gap after each glyph and line
+pub(crate) const GLYPH_WIDTH: usize = 3;
+pub(crate) const GLYPH_HEIGHT: usize = 5;
+
+/// Frames narrower than this are enlarged so the text fits beside the display
+const MIN_WIDTH: usize = 256;
+
+const TEXT_COLOR: u32 = 0xFFFFFF;
+
+/// Margin around the text panel, in pixels
+const MARGIN: usize = 2;
+
+/// Machine state drawn over a frame in a small bitmap font
+///
+/// The frame is enlarged by a whole factor to at least `MIN_WIDTH` pixels
+/// wide, then the top-left corner is darkened and the registers and current
+/// instruction are written over it.
+pub fn draw(frame: &Frame, cpu: &Chip8) -> Frame {
+    let mut out = enlarge(frame);
+    draw_panel(&mut out, &status_lines(cpu), (MARGIN, MARGIN));
+    out
+}
+
+/// Menu of `items` drawn in the middle of a frame, enlarged as by `draw`,
+/// with `selected` marked
+pub fn draw_menu(frame: &Frame, items: &[&str], selected: usize) -> Frame {
+    let mut out = enlarge(frame);
+
+    let lines: Vec<String> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| format!("{} {}", if i == selected { '>' } else { ' ' }, item))
+        .collect();
+
+    let (width, height) = panel_size(&lines);
+    let x = out.width.saturating_sub(width) / 2;
+    let y = out.height.saturating_sub(height) / 2;
+    draw_panel(&mut out, &lines, (x, y));
+
+    out
+}
+
+/// `frame` scaled up by a whole factor to at least `MIN_WIDTH` pixels wide
+pub(crate) fn enlarge(frame: &Frame) -> Frame {
+    let factor = MIN_WIDTH.div_ceil(frame.width.max(1)).max(1);
+    let (width, height) = (frame.width * factor, frame.height * factor);
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in frame.pixels.chunks(frame.width.max(1)) {
+        let line: Vec<u32> = row
+            .iter()
+            .flat_map(|&px| std::iter::repeat_n(px, factor))
+            .collect();
+
+        for _ in 0..factor {
+            pixels.extend_from_slice(&line);
+        }
+    }
+
+    Frame {
+        width,
+        height,
+        pixels,
+        palette: frame.palette,
+        time: frame.time,
+    }
+}
+
+/// Size of a panel holding `lines`, margins included
+fn panel_size(lines: &[String]) -> (usize, usize) {
+    let columns = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+
+    (
+        columns * (GLYPH_WIDTH + 1) + MARGIN * 2,
+        lines.len() * (GLYPH_HEIGHT + 1) + MARGIN * 2,
+    )
+}
+
+/// Darken a panel with its top-left corner at `at`, inset by the margin,
+/// and write `lines` on it
+fn draw_panel(frame: &mut Frame, lines: &[String], at: (usize, usize)) {
+    let (left, top) = (at.0.saturating_sub(MARGIN), at.1.saturating_sub(MARGIN));
+    let (panel_width, panel_height) = panel_size(lines);
+
+    for y in top..(top + panel_height).min(frame.height) {
+        for x in left..(left + panel_width).min(frame.width) {
+            let px = &mut frame.pixels[y * frame.width + x];
+            *px = (*px >> 2) & 0x3F3F3F;
+        }
+    }
+
+    for (row, line) in lines.iter().enumerate() {
+        let y = at.1 + row * (GLYPH_HEIGHT + 1);
+
+        for (col, c) in line.chars().enumerate() {
+            draw_glyph(frame, at.0 + col * (GLYPH_WIDTH + 1), y, c);
+        }
+    }
+}
+
+fn status_lines(cpu: &Chip8) -> Vec<String> {
+    let mut lines = vec![format!(
+        "PC {:04X}  I {:04X}  SP {:X}",
+        cpu.pc, cpu.i, cpu.sp
+    )];
+
+    lines.extend(cpu.v.chunks(4).enumerate().map(|(row, regs)| {
+        let values: Vec<String> = regs.iter().map(|v| format!("{:02X}", v)).collect();
+        format!("V{:X}  {}", row * 4, values.join(" "))
+    }));
+
+    lines.push(format!("DT {:02X}  ST {:02X}", cpu.dt, cpu.st));
+
+    let pc = usize::from(cpu.pc);
+    if let (Some(&hi), Some(&lo)) = (cpu.ram.get(pc), cpu.ram.get(pc + 1)) {
+        lines.push(format!("{:02X}{:02X} {}", hi, lo, disasm::mnemonic(hi, lo)));
+    }
+
+    lines
+}
+
+fn draw_glyph(frame: &mut Frame, x: usize, y: usize, c: char) {
+    for (dy, bits) in glyph(c).iter().enumerate() {
+        for dx in 0..GLYPH_WIDTH {
+            let on = bits & (0b100 >> dx) != 0;
+            let (px, py) = (x + dx, y + dy);
+
+            if on && px < frame.width && py < frame.height {
+                frame.pixels[py * frame.width + px] = TEXT_COLOR;
+            }
+        }
+    }
+}
+
+/// Rows of a 3x5 glyph, most significant bit leftmost; unknown characters are a block
+pub(crate) fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ' ' => [0; GLYPH_HEIGHT],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '[' => [0b110, 0b100, 0b100, 0b100, 0b110],
+        ']' => [0b011, 0b001, 0b001, 0b001, 0b011],
+        '{' => [0b011, 0b010, 0b110, 0b010, 0b011],
+        '}' => [0b110, 0b010, 0b011, 0b010, 0b110],
+        '?' => [0b110, 0b001, 0b010, 0b000, 0b010],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        _ => [0b111; GLYPH_HEIGHT],
+    }
+}
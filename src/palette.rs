@@ -1,21 +1,96 @@
-pub const BUILTIN_PALETTES: [(&str, &str); 10] = [
-    ("000000", "ffffff"),
-    ("f0f6f0", "222323"),
-    ("280e0b", "ffecc9"),
-    ("363636", "ececec"),
-    ("10368f", "ff8e42"),
-    ("210009", "00ffae"),
-    ("40318e", "88d7de"),
-    ("040612", "d400ff"),
-    ("3f291e", "fdca55"),
-    ("2b0000", "cc0e13"),
+use std::{fs, path::Path};
+
+use anyhow::Context;
+
+use crate::chip8::plane_color;
+
+/// Built-in palettes as (name, off, on)
+pub const BUILTIN_PALETTES: [(&str, &str, &str); 10] = [
+    ("classic", "000000", "ffffff"),
+    ("paper", "f0f6f0", "222323"),
+    ("cocoa", "280e0b", "ffecc9"),
+    ("gray", "363636", "ececec"),
+    ("ocean", "10368f", "ff8e42"),
+    ("mint", "210009", "00ffae"),
+    ("frost", "40318e", "88d7de"),
+    ("neon", "040612", "d400ff"),
+    ("amber", "3f291e", "fdca55"),
+    ("crimson", "2b0000", "cc0e13"),
 ];
 
 pub fn builtin(index: usize) -> (u32, u32) {
-    let (off, on) = BUILTIN_PALETTES[index % BUILTIN_PALETTES.len()];
+    let (_, off, on) = BUILTIN_PALETTES[index % BUILTIN_PALETTES.len()];
     (from_hex(off), from_hex(on))
 }
 
+/// Index of a built-in palette given by index or name
+pub fn find(name: &str) -> Option<usize> {
+    let name = name.trim();
+
+    match name.parse::<usize>() {
+        Ok(index) => Some(index).filter(|&i| i < BUILTIN_PALETTES.len()),
+        Err(_) => BUILTIN_PALETTES
+            .iter()
+            .position(|(n, _, _)| n.eq_ignore_ascii_case(name)),
+    }
+}
+
+/// Built-in palette names, for messages: "classic, paper, ..."
+pub fn names() -> String {
+    let names: Vec<&str> = BUILTIN_PALETTES.iter().map(|(name, _, _)| *name).collect();
+    names.join(", ")
+}
+
+/// Parse an `RRGGBB` color, with or without a leading `#`
+pub fn parse_hex(hex: &str) -> Option<u32> {
+    let hex = hex.trim().trim_start_matches('#');
+
+    if hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        None
+    }
+}
+
+/// Set the top-level `palette` key of the TOML config at `path` to `index`
+///
+/// The rest of the file, comments included, is kept as it is. Without a
+/// `palette` line, one is added after the other top-level keys.
+pub fn save_to_config(path: &Path, index: usize) -> anyhow::Result<()> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read config {}", path.display()))?;
+
+    let setting = format!("palette = {}", index);
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+
+    let first_table = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .unwrap_or(lines.len());
+    let existing = lines[..first_table].iter().position(|line| {
+        line.split('=')
+            .next()
+            .is_some_and(|key| key.trim() == "palette" && line.contains('='))
+    });
+
+    match existing {
+        Some(i) => lines[i] = setting,
+        None => {
+            // After the last top-level line, not the blank lines before a table
+            let mut end = first_table;
+            while end > 0 && lines[end - 1].trim().is_empty() {
+                end -= 1;
+            }
+            lines.insert(end, setting);
+        }
+    }
+
+    let mut text = lines.join("\n");
+    text.push('\n');
+
+    fs::write(path, text).with_context(|| format!("failed to write config {}", path.display()))
+}
+
 pub fn from_hex(hex: &str) -> u32 {
     let hex = format!("{:06}", hex.trim().trim_matches('#'));
 
@@ -30,3 +105,9 @@ pub fn from_u8_rgb(r: u8, g: u8, b: u8) -> u32 {
     let (r, g, b) = (r as u32, g as u32, b as u32);
     (r << 16) | (g << 8) | b
 }
+
+pub fn convert_display(display: &[u8], buffer: &mut [u32], colors: (u32, u32)) {
+    for (b, px) in buffer.iter_mut().zip(display.iter()) {
+        *b = plane_color(*px, colors);
+    }
+}
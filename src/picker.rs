@@ -0,0 +1,292 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Stdout, Write},
+    path::{Path, PathBuf},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Print, PrintStyledContent, Stylize},
+    terminal::{self, ClearType},
+};
+
+use chip8::{
+    locale::Locale,
+    storage::Storage,
+    thumbnail::{self, Thumbnail},
+};
+
+/// Width of the list pane, left of the thumbnail
+const LIST_WIDTH: usize = 44;
+const THUMBNAIL_COLUMN: u16 = 46;
+
+/// Rows below the list for the folder, status, and help
+const FOOTER_ROWS: u16 = 3;
+
+/// One line of the picker
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Entry {
+    Recent(PathBuf),
+    Parent,
+    Folder(PathBuf),
+    Rom(PathBuf),
+}
+
+/// Terminal menu for choosing a ROM when none is given: the recently opened
+/// ROMs first, then the folders and ROMs of a directory to browse
+///
+/// The selected ROM's title screen is previewed with its cached thumbnail.
+/// Puts the terminal in raw mode on an alternate screen until dropped.
+pub struct Picker {
+    out: Stdout,
+    storage: Storage,
+    recent: Vec<PathBuf>,
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    cursor: usize,
+    /// First entry shown
+    top: usize,
+    thumbnails: HashMap<PathBuf, Option<Thumbnail>>,
+    message: String,
+    picked: Option<PathBuf>,
+    closing: bool,
+    locale: Locale,
+}
+
+impl Picker {
+    /// Browse from `dir`, listing the recent ROMs kept in `storage`
+    pub fn new(storage: Storage, dir: PathBuf, locale: Locale) -> anyhow::Result<Self> {
+        let recent = storage.recent_roms();
+
+        let mut out = io::stdout();
+
+        terminal::enable_raw_mode()?;
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        let mut picker = Picker {
+            out,
+            storage,
+            recent,
+            dir: PathBuf::new(),
+            entries: Vec::new(),
+            cursor: 0,
+            top: 0,
+            thumbnails: HashMap::new(),
+            message: String::new(),
+            picked: None,
+            closing: false,
+            locale,
+        };
+        picker.open_dir(dir);
+
+        Ok(picker)
+    }
+
+    /// Draw and handle keys until a ROM is picked, returning it, or the user
+    /// quits
+    pub fn run(mut self) -> anyhow::Result<Option<PathBuf>> {
+        while !self.closing {
+            self.draw()?;
+
+            if let Event::Key(key) = event::read()? {
+                self.handle_key(key);
+            }
+        }
+
+        Ok(self.picked.take())
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind == KeyEventKind::Release {
+            return;
+        }
+
+        let page = self.list_rows();
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.closing = true
+            }
+            KeyCode::Up => self.move_cursor(-1),
+            KeyCode::Down => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-(page as isize)),
+            KeyCode::PageDown => self.move_cursor(page as isize),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.entries.len().saturating_sub(1),
+            KeyCode::Enter | KeyCode::Right => self.open(),
+            KeyCode::Backspace | KeyCode::Left => {
+                if let Some(parent) = self.parent() {
+                    self.open_dir(parent);
+                }
+            }
+            KeyCode::Char('q') | KeyCode::Esc => self.closing = true,
+            _ => {}
+        }
+    }
+
+    fn move_cursor(&mut self, by: isize) {
+        self.cursor = self
+            .cursor
+            .saturating_add_signed(by)
+            .min(self.entries.len().saturating_sub(1));
+    }
+
+    fn open(&mut self) {
+        match self.entries.get(self.cursor).cloned() {
+            Some(Entry::Recent(path)) | Some(Entry::Rom(path)) => {
+                self.picked = Some(path);
+                self.closing = true;
+            }
+            Some(Entry::Parent) => {
+                if let Some(parent) = self.parent() {
+                    self.open_dir(parent);
+                }
+            }
+            Some(Entry::Folder(dir)) => self.open_dir(dir),
+            None => {}
+        }
+    }
+
+    /// List `dir`, keeping the current listing if it cannot be read
+    fn open_dir(&mut self, dir: PathBuf) {
+        let dir = dir.canonicalize().unwrap_or(dir);
+        let mut folders = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.is_dir())
+                .collect::<Vec<_>>(),
+            Err(e) => {
+                let message = format!("{}: {}", dir.display(), e);
+                self.message = self.locale.format("error.prefix", &[("message", &message)]);
+                return;
+            }
+        };
+        folders.sort();
+
+        let roms = thumbnail::list_roms(&dir).unwrap_or_default();
+
+        self.entries = self.recent.iter().cloned().map(Entry::Recent).collect();
+        self.entries.push(Entry::Parent);
+        self.entries.extend(folders.into_iter().map(Entry::Folder));
+        self.entries.extend(roms.into_iter().map(Entry::Rom));
+
+        // Start on the newest recent ROM, or on the folder's first entry
+        // when there are none or another folder is opened
+        let first_time = self.dir.as_os_str().is_empty();
+        self.cursor = if first_time && !self.recent.is_empty() {
+            0
+        } else {
+            (self.recent.len() + 1).min(self.entries.len() - 1)
+        };
+        self.top = 0;
+        self.message.clear();
+        self.dir = dir;
+    }
+
+    fn parent(&self) -> Option<PathBuf> {
+        self.dir.parent().map(Path::to_path_buf)
+    }
+
+    fn list_rows(&self) -> usize {
+        let (_, height) = terminal::size().unwrap_or((80, 24));
+        usize::from(height.saturating_sub(FOOTER_ROWS + 1)).max(1)
+    }
+
+    /// Thumbnail of the ROM under the cursor, rendered once per ROM
+    fn thumbnail(&mut self) -> Option<&Thumbnail> {
+        let path = match self.entries.get(self.cursor)? {
+            Entry::Recent(path) | Entry::Rom(path) => path.clone(),
+            _ => return None,
+        };
+
+        let storage = &self.storage;
+        self.thumbnails
+            .entry(path)
+            .or_insert_with_key(|path| Thumbnail::cached(path, storage).ok())
+            .as_ref()
+    }
+
+    fn draw(&mut self) -> anyhow::Result<()> {
+        let rows = self.list_rows();
+
+        // Keep the cursor in view
+        if self.cursor < self.top {
+            self.top = self.cursor;
+        } else if self.cursor >= self.top + rows {
+            self.top = self.cursor + 1 - rows;
+        }
+
+        queue!(self.out, terminal::Clear(ClearType::All))?;
+
+        for (r, entry) in self.entries.iter().enumerate().skip(self.top).take(rows) {
+            let file_name = |path: &Path| {
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            };
+
+            let mut line = match entry {
+                Entry::Recent(path) => format!("* {}", file_name(path)),
+                Entry::Parent => "  ../".into(),
+                Entry::Folder(path) => format!("  {}/", file_name(path)),
+                Entry::Rom(path) => format!("  {}", file_name(path)),
+            };
+            line = line.chars().take(LIST_WIDTH).collect();
+
+            let text = if r == self.cursor {
+                line.reverse()
+            } else if matches!(entry, Entry::Folder(_) | Entry::Parent) {
+                line.blue()
+            } else {
+                line.stylize()
+            };
+
+            queue!(
+                self.out,
+                cursor::MoveTo(0, (r - self.top) as u16),
+                PrintStyledContent(text)
+            )?;
+        }
+
+        if let Some(lines) = self.thumbnail().map(Thumbnail::to_text) {
+            for (r, line) in lines.iter().take(rows).enumerate() {
+                queue!(
+                    self.out,
+                    cursor::MoveTo(THUMBNAIL_COLUMN, r as u16),
+                    Print(format!("|{}|", line))
+                )?;
+            }
+        }
+
+        let footer = rows as u16 + 1;
+        let location = match self.entries.get(self.cursor) {
+            Some(Entry::Recent(path)) => path.display().to_string(),
+            _ => self.dir.display().to_string(),
+        };
+
+        queue!(
+            self.out,
+            cursor::MoveTo(0, footer),
+            Print(location),
+            cursor::MoveTo(0, footer + 1),
+            Print(&self.message),
+            cursor::MoveTo(0, footer + 2),
+            Print(self.locale.get("picker.help")),
+        )?;
+
+        self.out.flush()?;
+
+        Ok(())
+    }
+}
+
+impl Drop for Picker {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    ffi::{c_void, CStr},
+    os::raw::c_char,
+    path::Path,
+    rc::Rc,
+};
+
+use anyhow::anyhow;
+use libloading::{Library, Symbol};
+
+use crate::{
+    chip8::Chip8,
+    filter::{DisplayFilter, Frame},
+};
+
+/// Version of `Chip8Plugin` this build understands; bumped on any layout change
+pub const ABI_VERSION: u32 = 1;
+
+/// Symbol every plugin exports, `bool chip8_plugin_init(Chip8Plugin *plugin)`
+const INIT_SYMBOL: &[u8] = b"chip8_plugin_init\0";
+
+/// Machine state passed to a plugin's step hook
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Chip8PluginState {
+    /// Instructions executed since reset
+    pub cycle: u64,
+    pub pc: u16,
+    pub i: u16,
+    /// Opcode just executed
+    pub opcode: u16,
+    pub v: [u8; 16],
+    pub dt: u8,
+    pub st: u8,
+    pub sp: u8,
+}
+
+/// Table a plugin fills in from `chip8_plugin_init`, declared in
+/// `include/chip8_plugin.h`
+///
+/// `abi_version` must be set to `ABI_VERSION`. Every callback is optional and
+/// receives `user` first.
+#[repr(C)]
+pub struct Chip8Plugin {
+    pub abi_version: u32,
+    /// Name shown in messages, a NUL-terminated UTF-8 string
+    pub name: *const c_char,
+    pub user: *mut c_void,
+    /// Display filter: change `width * height` 0RGB pixels in place
+    pub filter: Option<unsafe extern "C" fn(*mut c_void, *mut u32, usize, usize)>,
+    /// Audio sink: the buzzer turned on or off
+    pub set_beeping: Option<unsafe extern "C" fn(*mut c_void, bool)>,
+    /// Step hook: called after every executed instruction
+    pub on_step: Option<unsafe extern "C" fn(*mut c_void, *const Chip8PluginState)>,
+    /// Called once before the library is unloaded
+    pub destroy: Option<unsafe extern "C" fn(*mut c_void)>,
+}
+
+/// A loaded plugin library
+///
+/// Plugins run native code in the emulator's process with no sandboxing, so
+/// only load libraries you trust.
+pub struct Plugin {
+    pub name: String,
+    vtable: Chip8Plugin,
+    // Dropped last, after `destroy` has run
+    _library: Library,
+}
+
+impl Plugin {
+    /// Load the shared library at `path` and initialize it
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        // Safety: loading a library runs its initializers; the user asked
+        // for this plugin by path.
+        let library = unsafe { Library::new(path) }
+            .map_err(|e| anyhow!("failed to load plugin {}: {}", path.display(), e))?;
+
+        let mut vtable = Chip8Plugin {
+            abi_version: 0,
+            name: std::ptr::null(),
+            user: std::ptr::null_mut(),
+            filter: None,
+            set_beeping: None,
+            on_step: None,
+            destroy: None,
+        };
+
+        // Safety: the symbol is declared with this signature in the header.
+        let ok = unsafe {
+            let init: Symbol<unsafe extern "C" fn(*mut Chip8Plugin) -> bool> =
+                library.get(INIT_SYMBOL).map_err(|e| {
+                    anyhow!(
+                        "{} is not a chip8 plugin (no chip8_plugin_init): {}",
+                        path.display(),
+                        e
+                    )
+                })?;
+
+            init(&mut vtable)
+        };
+
+        if !ok {
+            return Err(anyhow!("plugin {} failed to initialize", path.display()));
+        }
+
+        if vtable.abi_version != ABI_VERSION {
+            return Err(anyhow!(
+                "plugin {} uses ABI version {}, expected {}",
+                path.display(),
+                vtable.abi_version,
+                ABI_VERSION
+            ));
+        }
+
+        let name = if vtable.name.is_null() {
+            path.display().to_string()
+        } else {
+            // Safety: the header requires a NUL-terminated string that lives
+            // as long as the plugin.
+            unsafe { CStr::from_ptr(vtable.name) }
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        Ok(Plugin {
+            name,
+            vtable,
+            _library: library,
+        })
+    }
+
+    pub fn has_filter(&self) -> bool {
+        self.vtable.filter.is_some()
+    }
+
+    pub fn set_beeping(&self, beeping: bool) {
+        if let Some(set_beeping) = self.vtable.set_beeping {
+            // Safety: callbacks take the plugin's own `user` pointer.
+            unsafe { set_beeping(self.vtable.user, beeping) }
+        }
+    }
+
+    /// Call the step hook, if any, after `opcode` ran on `cpu`
+    pub fn on_step(&self, cpu: &Chip8, opcode: u16, cycle: u64) {
+        if let Some(on_step) = self.vtable.on_step {
+            let state = Chip8PluginState {
+                cycle,
+                pc: cpu.pc,
+                i: cpu.i,
+                opcode,
+                v: cpu.v,
+                dt: cpu.dt,
+                st: cpu.st,
+                sp: cpu.sp,
+            };
+
+            // Safety: `state` outlives the call.
+            unsafe { on_step(self.vtable.user, &state) }
+        }
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        if let Some(destroy) = self.vtable.destroy {
+            // Safety: called once, while the library is still loaded.
+            unsafe { destroy(self.vtable.user) }
+        }
+    }
+}
+
+/// Display filter stage running a plugin's `filter` callback
+pub struct PluginFilter(pub Rc<Plugin>);
+
+impl DisplayFilter for PluginFilter {
+    fn apply(&mut self, frame: &mut Frame) {
+        if let Some(filter) = self.0.vtable.filter {
+            // Safety: the buffer holds exactly `width * height` pixels.
+            unsafe {
+                filter(
+                    self.0.vtable.user,
+                    frame.pixels.as_mut_ptr(),
+                    frame.width,
+                    frame.height,
+                )
+            }
+        }
+    }
+}
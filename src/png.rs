@@ -0,0 +1,183 @@
+use std::{
+    convert::TryInto,
+    fs,
+    io::{Read, Write},
+    path::Path,
+};
+
+use anyhow::anyhow;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression, Crc};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// Encode 0RGB pixels as an 8-bit RGB PNG image
+pub fn encode(pixels: &[u32], width: usize, height: usize) -> anyhow::Result<Vec<u8>> {
+    assert_eq!(pixels.len(), width * height, "pixel buffer size mismatch");
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, RGB, deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    // Each scanline starts with its filter type; 0 is no filtering
+    let mut raw = Vec::with_capacity(height * (1 + width * 3));
+    for row in pixels.chunks(width.max(1)) {
+        raw.push(0);
+        for px in row {
+            raw.extend_from_slice(&px.to_be_bytes()[1..]);
+        }
+    }
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&raw)?;
+    let data = encoder.finish()?;
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &data);
+    write_chunk(&mut png, b"IEND", &[]);
+
+    Ok(png)
+}
+
+/// Write 0RGB pixels to a PNG file
+pub fn save(path: &Path, pixels: &[u32], width: usize, height: usize) -> anyhow::Result<()> {
+    fs::write(path, encode(pixels, width, height)?)?;
+    Ok(())
+}
+
+/// Decode an 8-bit grayscale or RGB PNG image, with or without alpha, to 0RGB
+/// pixels, returning them with the width and height
+///
+/// Alpha is dropped. Interlaced and palette images are not supported.
+pub fn decode(png: &[u8]) -> anyhow::Result<(Vec<u32>, usize, usize)> {
+    let mut rest = png
+        .strip_prefix(&SIGNATURE[..])
+        .ok_or_else(|| anyhow!("not a PNG image"))?;
+
+    let mut header = None;
+    let mut data = Vec::new();
+
+    while rest.len() >= 12 {
+        let len = u32::from_be_bytes(rest[..4].try_into()?) as usize;
+        let kind = &rest[4..8];
+        let chunk = rest
+            .get(8..8 + len)
+            .ok_or_else(|| anyhow!("truncated PNG chunk"))?;
+
+        match kind {
+            b"IHDR" => header = Some(chunk.to_vec()),
+            b"IDAT" => data.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+
+        rest = rest.get(12 + len..).unwrap_or_default();
+    }
+
+    let header = header
+        .filter(|h| h.len() == 13)
+        .ok_or_else(|| anyhow!("PNG has no header"))?;
+    let width = u32::from_be_bytes(header[0..4].try_into()?) as usize;
+    let height = u32::from_be_bytes(header[4..8].try_into()?) as usize;
+
+    let channels = match (header[8], header[9], header[12]) {
+        (8, 0, 0) => 1,
+        (8, 4, 0) => 2,
+        (8, 2, 0) => 3,
+        (8, 6, 0) => 4,
+        (depth, color, interlace) => {
+            return Err(anyhow!(
+                "unsupported PNG format (bit depth {}, color type {}, interlace {})",
+                depth,
+                color,
+                interlace
+            ))
+        }
+    };
+
+    let mut raw = Vec::new();
+    ZlibDecoder::new(&data[..]).read_to_end(&mut raw)?;
+
+    let stride = width * channels;
+    if raw.len() < height * (1 + stride) {
+        return Err(anyhow!("PNG image data is truncated"));
+    }
+
+    let mut pixels = Vec::with_capacity(width * height);
+    let mut prev = vec![0u8; stride];
+
+    for line in raw.chunks(1 + stride).take(height) {
+        let mut row = line[1..].to_vec();
+        unfilter(line[0], &mut row, &prev, channels)?;
+
+        pixels.extend(row.chunks(channels).map(|px| {
+            let (r, g, b) = match channels {
+                1 | 2 => (px[0], px[0], px[0]),
+                _ => (px[0], px[1], px[2]),
+            };
+
+            u32::from_be_bytes([0, r, g, b])
+        }));
+
+        prev = row;
+    }
+
+    Ok((pixels, width, height))
+}
+
+/// Read a PNG file; see `decode`
+pub fn load(path: &Path) -> anyhow::Result<(Vec<u32>, usize, usize)> {
+    decode(&fs::read(path)?).map_err(|e| anyhow!("{}: {}", path.display(), e))
+}
+
+/// Undo a scanline's filter in place, given the previous (unfiltered) scanline
+fn unfilter(filter: u8, row: &mut [u8], prev: &[u8], bpp: usize) -> anyhow::Result<()> {
+    for i in 0..row.len() {
+        let left = if i >= bpp { row[i - bpp] } else { 0 };
+        let up = prev[i];
+        let up_left = if i >= bpp { prev[i - bpp] } else { 0 };
+
+        let predicted = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+            4 => paeth(left, up, up_left),
+            _ => return Err(anyhow!("unknown PNG filter type {}", filter)),
+        };
+
+        row[i] = row[i].wrapping_add(predicted);
+    }
+
+    Ok(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let (pa, pb, pc) = (
+        (p - i16::from(a)).abs(),
+        (p - i16::from(b)).abs(),
+        (p - i16::from(c)).abs(),
+    );
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+}
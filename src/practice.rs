@@ -0,0 +1,146 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use crate::chip8::Chip8;
+
+/// Comparison used by a memory loss condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+}
+
+impl CompareOp {
+    fn eval(self, a: u8, b: u8) -> bool {
+        match self {
+            CompareOp::Eq => a == b,
+            CompareOp::Ne => a != b,
+            CompareOp::Lt => a < b,
+            CompareOp::Gt => a > b,
+        }
+    }
+}
+
+/// Condition signalling that the player has lost
+///
+/// Parsed from strings of the form:
+/// - `mem:2F0=0`, `mem:2F0!=0`, `mem:2F0<3`, `mem:2F0>3` (address in hex, value in hex)
+/// - `pixel:10,5=1` or `pixel:10,5=0` (coordinates in decimal)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LossCondition {
+    Mem { addr: u16, op: CompareOp, value: u8 },
+    Pixel { x: usize, y: usize, on: bool },
+}
+
+impl LossCondition {
+    pub fn is_met(&self, cpu: &Chip8) -> bool {
+        match *self {
+            LossCondition::Mem { addr, op, value } => {
+                let byte = cpu.ram[usize::from(addr) % cpu.ram.len()];
+                op.eval(byte, value)
+            }
+            LossCondition::Pixel { x, y, on } => {
+                let idx =
+                    (y % cpu.display_height()) * cpu.display_width() + (x % cpu.display_width());
+                (cpu.display[idx] != 0) == on
+            }
+        }
+    }
+}
+
+impl FromStr for LossCondition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Some(rest) = s.strip_prefix("mem:") {
+            let (addr, op, value) = ["!=", "=", "<", ">"]
+                .iter()
+                .find_map(|op| {
+                    rest.find(op)
+                        .map(|i| (&rest[..i], *op, &rest[i + op.len()..]))
+                })
+                .ok_or_else(|| anyhow!("missing comparison in '{}'", s))?;
+
+            let op = match op {
+                "=" => CompareOp::Eq,
+                "!=" => CompareOp::Ne,
+                "<" => CompareOp::Lt,
+                _ => CompareOp::Gt,
+            };
+
+            Ok(LossCondition::Mem {
+                addr: u16::from_str_radix(addr.trim(), 16)?,
+                op,
+                value: u8::from_str_radix(value.trim(), 16)?,
+            })
+        } else if let Some(rest) = s.strip_prefix("pixel:") {
+            let (coord, state) = rest
+                .split_once('=')
+                .ok_or_else(|| anyhow!("missing '=' in '{}'", s))?;
+            let (x, y) = coord
+                .split_once(',')
+                .ok_or_else(|| anyhow!("expected 'x,y' in '{}'", s))?;
+
+            Ok(LossCondition::Pixel {
+                x: x.trim().parse()?,
+                y: y.trim().parse()?,
+                on: state.trim() != "0",
+            })
+        } else {
+            Err(anyhow!("unknown practice condition '{}'", s))
+        }
+    }
+}
+
+/// Practice mode state
+///
+/// Holds a checkpoint snapshot which is restored whenever the loss condition
+/// becomes true.
+#[derive(Debug, Clone)]
+pub struct Practice {
+    pub condition: LossCondition,
+    pub checkpoint: Option<Chip8>,
+    lost: bool,
+}
+
+impl Practice {
+    pub fn new(condition: LossCondition) -> Self {
+        Practice {
+            condition,
+            checkpoint: None,
+            lost: false,
+        }
+    }
+
+    pub fn set_checkpoint(&mut self, cpu: &Chip8) {
+        self.checkpoint = Some(cpu.clone());
+        self.lost = false;
+    }
+
+    /// Check the loss condition, restoring the checkpoint into `cpu` on a rising edge.
+    ///
+    /// Returns true if the checkpoint was restored.
+    pub fn check(&mut self, cpu: &mut Chip8) -> bool {
+        let lost = self.condition.is_met(cpu);
+        let rising_edge = lost && !self.lost;
+        self.lost = lost;
+
+        if !rising_edge {
+            return false;
+        }
+
+        match &self.checkpoint {
+            Some(checkpoint) => {
+                cpu.restore(checkpoint);
+                self.lost = self.condition.is_met(cpu);
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{
+    chip8::{Chip8Panic, StepOutcome},
+    instruction::{decode, Instruction},
+};
+
+/// Instruction counts grouped by subroutine (CALL target)
+///
+/// Follows CALL and RET to keep a shadow call stack, attributing every
+/// instruction to the innermost active routine. Code outside any call is
+/// attributed to the entry point.
+#[derive(Debug, Clone)]
+pub struct CallProfile {
+    nodes: Vec<Node>,
+    /// Active frames as (node, instruction count on entry)
+    stack: Vec<(usize, u64)>,
+    instructions: u64,
+}
+
+/// Call tree node: one routine reached through one particular call path
+#[derive(Debug, Clone)]
+struct Node {
+    routine: u16,
+    parent: Option<usize>,
+    children: BTreeMap<u16, usize>,
+    calls: u64,
+    /// Instructions executed in this routine itself
+    exclusive: u64,
+    /// Instructions executed in this routine and its callees, for completed calls
+    inclusive: u64,
+}
+
+/// Per-routine totals for the flat view
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RoutineStats {
+    pub calls: u64,
+    pub inclusive: u64,
+    pub exclusive: u64,
+}
+
+impl CallProfile {
+    pub fn new(entry: u16) -> Self {
+        let mut root = Node::new(entry, None);
+        root.calls = 1;
+
+        CallProfile {
+            nodes: vec![root],
+            stack: vec![(0, 0)],
+            instructions: 0,
+        }
+    }
+
+    /// Record a step of `opcode` at `pc`, where `next_pc` is PC after the step
+    pub fn record_step(
+        &mut self,
+        pc: u16,
+        opcode: u16,
+        next_pc: u16,
+        result: &Result<StepOutcome, Chip8Panic>,
+    ) {
+        if !matches!(
+            result,
+            Ok(StepOutcome::Executed) | Ok(StepOutcome::Watchpoint(_))
+        ) {
+            return;
+        }
+
+        let current = self.current();
+        self.nodes[current].exclusive += 1;
+        self.instructions += 1;
+
+        match decode(opcode) {
+            Instruction::Call(target) => {
+                let child = match self.nodes[current].children.get(&target) {
+                    Some(child) => *child,
+                    None => {
+                        self.nodes.push(Node::new(target, Some(current)));
+                        let child = self.nodes.len() - 1;
+                        self.nodes[current].children.insert(target, child);
+                        child
+                    }
+                };
+
+                self.nodes[child].calls += 1;
+                self.stack.push((child, self.instructions));
+            }
+            Instruction::Ret if next_pc != pc && self.stack.len() > 1 => {
+                if let Some((node, entry)) = self.stack.pop() {
+                    self.nodes[node].inclusive += self.instructions - entry;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Totals per routine, counting recursive calls' inclusive time once
+    pub fn routines(&self) -> BTreeMap<u16, RoutineStats> {
+        let mut routines: BTreeMap<u16, RoutineStats> = BTreeMap::new();
+
+        for (id, node) in self.nodes.iter().enumerate() {
+            let stats = routines.entry(node.routine).or_default();
+            stats.calls += node.calls;
+            stats.exclusive += node.exclusive;
+
+            if !self.has_ancestor(id, node.routine) {
+                stats.inclusive += self.inclusive(id);
+            }
+        }
+
+        routines
+    }
+
+    pub fn summary(&self) -> String {
+        let total = self.instructions.max(1) as f64;
+        let percent = |n: u64| n as f64 * 100. / total;
+
+        let mut routines: Vec<(u16, RoutineStats)> = self.routines().into_iter().collect();
+        routines.sort_by(|a, b| b.1.inclusive.cmp(&a.1.inclusive).then(a.0.cmp(&b.0)));
+
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "Subroutines (flat):");
+        let _ = writeln!(
+            out,
+            "  Routine        Calls     Inclusive            Exclusive"
+        );
+
+        for (routine, stats) in routines {
+            let _ = writeln!(
+                out,
+                "  {:04X}    {:>12}  {:>12} {:5.1}%  {:>12} {:5.1}%",
+                routine,
+                stats.calls,
+                stats.inclusive,
+                percent(stats.inclusive),
+                stats.exclusive,
+                percent(stats.exclusive)
+            );
+        }
+
+        let _ = writeln!(out, "Call tree:");
+        self.write_tree(&mut out, 0, 1, &percent);
+
+        out
+    }
+
+    fn write_tree(&self, out: &mut String, id: usize, depth: usize, percent: &dyn Fn(u64) -> f64) {
+        let node = &self.nodes[id];
+
+        let _ = writeln!(
+            out,
+            "{:indent$}{:04X}  calls {}  incl {:.1}%  excl {:.1}%",
+            "",
+            node.routine,
+            node.calls,
+            percent(self.inclusive(id)),
+            percent(node.exclusive),
+            indent = depth * 2
+        );
+
+        for child in node.children.values() {
+            self.write_tree(out, *child, depth + 1, percent);
+        }
+    }
+
+    fn current(&self) -> usize {
+        self.stack.last().map_or(0, |(node, _)| *node)
+    }
+
+    /// Inclusive count of a node, including calls still in progress
+    fn inclusive(&self, id: usize) -> u64 {
+        let active: u64 = self
+            .stack
+            .iter()
+            .filter(|(node, _)| *node == id)
+            .map(|(_, entry)| self.instructions - entry)
+            .sum();
+
+        self.nodes[id].inclusive + active
+    }
+
+    fn has_ancestor(&self, id: usize, routine: u16) -> bool {
+        let mut parent = self.nodes[id].parent;
+
+        while let Some(p) = parent {
+            if self.nodes[p].routine == routine {
+                return true;
+            }
+            parent = self.nodes[p].parent;
+        }
+
+        false
+    }
+}
+
+impl Node {
+    fn new(routine: u16, parent: Option<usize>) -> Self {
+        Node {
+            routine,
+            parent,
+            children: BTreeMap::new(),
+            calls: 0,
+            exclusive: 0,
+            inclusive: 0,
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Quiet time after the last write before the file counts as changed, so a
+/// file still being written is not read
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// Notices when a ROM file is written, for reloading it
+///
+/// Watches the file's folder rather than the file, since many editors and
+/// build tools save by writing a new file and renaming it over the old one.
+pub struct RomWatcher {
+    file_name: OsString,
+    events: Receiver<notify::Result<notify::Event>>,
+    /// Time of the last write not yet reported
+    last_write: Option<Instant>,
+    // Stops watching when dropped
+    _watcher: RecommendedWatcher,
+}
+
+impl RomWatcher {
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("{} is not a file", path.display()))?
+            .to_os_string();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("failed to watch {}", dir.display()))?;
+
+        Ok(RomWatcher {
+            file_name,
+            events,
+            last_write: None,
+            _watcher: watcher,
+        })
+    }
+
+    /// Whether the file was created or written since the last call, and has
+    /// settled
+    ///
+    /// One save usually raises several events; they are reported as one.
+    pub fn changed(&mut self) -> bool {
+        for event in self.events.try_iter().flatten() {
+            if (event.kind.is_create() || event.kind.is_modify())
+                && event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name() == Some(&self.file_name))
+            {
+                self.last_write = Some(Instant::now());
+            }
+        }
+
+        match self.last_write {
+            Some(time) if time.elapsed() >= SETTLE_TIME => {
+                self.last_write = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::VecDeque;
+
+use crate::chip8::Chip8;
+
+/// Ring buffer of recent machine snapshots
+///
+/// A snapshot is recorded every `interval` steps. Stepping backward restores
+/// one snapshot every `interval` calls so rewinding plays at the same rate as
+/// recording.
+#[derive(Debug, Clone)]
+pub struct Rewind {
+    pub capacity: usize,
+    pub interval: u32,
+    buffer: VecDeque<Chip8>,
+    counter: u32,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize, interval: u32) -> Self {
+        Rewind {
+            capacity,
+            interval: interval.max(1),
+            buffer: VecDeque::with_capacity(capacity),
+            counter: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.counter = 0;
+    }
+
+    /// Call after every executed step
+    pub fn record(&mut self, cpu: &Chip8) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.counter += 1;
+
+        if self.counter >= self.interval {
+            self.counter = 0;
+
+            if self.buffer.len() >= self.capacity {
+                self.buffer.pop_front();
+            }
+
+            self.buffer.push_back(cpu.clone());
+        }
+    }
+
+    /// Call once per step while rewinding; returns a snapshot to restore when due
+    pub fn step_back(&mut self) -> Option<Chip8> {
+        self.counter += 1;
+
+        if self.counter >= self.interval {
+            self.counter = 0;
+            self.buffer.pop_back()
+        } else {
+            None
+        }
+    }
+}
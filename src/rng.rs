@@ -0,0 +1,33 @@
+use core::fmt::Debug;
+
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+/// Random number source for RND
+///
+/// Seeded on every reset so runs are reproducible. `ChaCha12Rng` is the
+/// default; small targets can supply a cheaper generator.
+pub trait Chip8Rng: Clone + Debug {
+    fn from_seed(seed: [u8; 32]) -> Self;
+
+    fn next_byte(&mut self) -> u8;
+}
+
+impl Chip8Rng for ChaCha12Rng {
+    fn from_seed(seed: [u8; 32]) -> Self {
+        SeedableRng::from_seed(seed)
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        (self.next_u32() & 0xff) as u8
+    }
+}
+
+/// Expand a 64-bit seed into a full RNG seed, the same way for every run
+///
+/// Lets seeds be given as one number, e.g. `--seed 42`.
+pub fn seed_from_u64(state: u64) -> [u8; 32] {
+    let mut seed = [0; 32];
+    <ChaCha12Rng as SeedableRng>::seed_from_u64(state).fill_bytes(&mut seed);
+    seed
+}
@@ -0,0 +1,493 @@
+use std::{
+    fmt, fs,
+    io::{self, Read, Write},
+    iter,
+    net::TcpStream,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context};
+use flate2::{read::DeflateDecoder, Crc};
+
+use crate::{
+    chip8::Chip8,
+    metadata::Metadata,
+    romdb::{self, RomDb},
+    storage::Storage,
+    thumbnail::ROM_EXTENSIONS,
+};
+
+/// Largest download or archive member accepted, well above any CHIP-8 ROM
+const MAX_FETCH_SIZE: u64 = 0x10000;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Built-in demo: draws the hex font, 0 ~ 7 on the top row and 8 ~ F below
+pub const DEMO_ROM: [u8; 30] = [
+    0x00, 0xE0, // 200: CLS
+    0x60, 0x00, // 202: LD V0, 00     ; digit
+    0x61, 0x01, // 204: LD V1, 01     ; x
+    0x62, 0x01, // 206: LD V2, 01     ; y
+    0xF0, 0x29, // 208: LD F, V0
+    0xD1, 0x25, // 20A: DRW V1, V2, 5
+    0x70, 0x01, // 20C: ADD V0, 01
+    0x71, 0x08, // 20E: ADD V1, 08
+    0x31, 0x41, // 210: SE V1, 41     ; end of row
+    0x12, 0x18, // 212: JP 218
+    0x61, 0x01, // 214: LD V1, 01
+    0x72, 0x08, // 216: ADD V2, 08
+    0x30, 0x10, // 218: SE V0, 10     ; all digits drawn
+    0x12, 0x08, // 21A: JP 208
+    0x12, 0x1C, // 21C: JP 21C
+];
+
+/// Somewhere a ROM image can be read from
+///
+/// Every ROM the emulator and tools run goes through `load`, which reads the
+/// source and checks the image fits in memory.
+pub trait RomSource: fmt::Debug + Send + Sync {
+    /// Short description for titles and messages
+    fn name(&self) -> String;
+
+    /// Path of the ROM on disk, if it lives there; per-ROM files the user
+    /// writes, such as triggers, are looked for next to it
+    fn local_path(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Read the raw ROM image
+    fn read(&self) -> anyhow::Result<Vec<u8>>;
+}
+
+/// Read `source` and check it is a loadable ROM, without any metadata block
+pub fn load(source: &dyn RomSource) -> anyhow::Result<Vec<u8>> {
+    Ok(load_with_metadata(source)?.0)
+}
+
+/// Read `source` and check it is a loadable ROM, splitting off the metadata
+/// block at its end, if any
+pub fn load_with_metadata(source: &dyn RomSource) -> anyhow::Result<(Vec<u8>, Option<Metadata>)> {
+    let rom = source
+        .read()
+        .with_context(|| format!("failed to read ROM {}", source.name()))?;
+
+    let (program, metadata) = Metadata::split(&rom)
+        .with_context(|| format!("invalid metadata in ROM {}", source.name()))?;
+
+    if program.is_empty() {
+        return Err(anyhow!("ROM {} is empty", source.name()));
+    }
+
+    Chip8::new()
+        .load_rom(program)
+        .with_context(|| format!("ROM {} is too large", source.name()))?;
+
+    Ok((program.to_vec(), metadata))
+}
+
+/// Pick a source from a command-line argument
+///
+/// - `-`: standard input
+/// - `demo`: the built-in demo, unless a file by that name exists
+/// - `http://host/path`: download
+/// - `db:title` or `db:sha1`: the ROM of a ROM database entry, looked up in
+///   `storage`
+/// - `archive.zip:member` or `archive.zip`: a member of a zip archive (the
+///   first ROM in it, if not named)
+/// - anything else: a file
+pub fn from_arg(arg: &str, storage: &Storage) -> Box<dyn RomSource> {
+    if arg == "-" {
+        return Box::new(StdinRom);
+    }
+
+    if let Some(query) = arg.strip_prefix("db:") {
+        return Box::new(RomDbRom {
+            query: query.to_string(),
+            storage: storage.clone(),
+        });
+    }
+
+    if arg.eq_ignore_ascii_case("demo") && !Path::new(arg).exists() {
+        return Box::new(DemoRom);
+    }
+
+    if arg.starts_with("http://") || arg.starts_with("https://") {
+        return Box::new(UrlRom(arg.to_string()));
+    }
+
+    let lower = arg.to_ascii_lowercase();
+
+    if let Some(i) = lower.find(".zip:") {
+        return Box::new(ZipRom {
+            archive: PathBuf::from(&arg[..i + 4]),
+            member: Some(arg[i + 5..].to_string()),
+        });
+    }
+
+    if lower.ends_with(".zip") {
+        return Box::new(ZipRom {
+            archive: PathBuf::from(arg),
+            member: None,
+        });
+    }
+
+    Box::new(FileRom(PathBuf::from(arg)))
+}
+
+#[derive(Debug, Clone)]
+pub struct FileRom(pub PathBuf);
+
+impl RomSource for FileRom {
+    fn name(&self) -> String {
+        self.0.display().to_string()
+    }
+
+    fn local_path(&self) -> Option<PathBuf> {
+        Some(self.0.clone())
+    }
+
+    fn read(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(fs::read(&self.0)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StdinRom;
+
+impl RomSource for StdinRom {
+    fn name(&self) -> String {
+        "<stdin>".into()
+    }
+
+    fn read(&self) -> anyhow::Result<Vec<u8>> {
+        let mut rom = Vec::new();
+        io::stdin()
+            .lock()
+            .take(MAX_FETCH_SIZE)
+            .read_to_end(&mut rom)?;
+        Ok(rom)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DemoRom;
+
+impl RomSource for DemoRom {
+    fn name(&self) -> String {
+        "demo".into()
+    }
+
+    fn read(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(DEMO_ROM.to_vec())
+    }
+}
+
+/// Placeholder for starting without a ROM, until one is dropped onto the window
+#[derive(Debug, Clone, Copy)]
+pub struct NoRom;
+
+impl RomSource for NoRom {
+    fn name(&self) -> String {
+        "no ROM".into()
+    }
+
+    fn read(&self) -> anyhow::Result<Vec<u8>> {
+        Err(anyhow!("no ROM file given"))
+    }
+}
+
+/// ROM downloaded over plain HTTP
+///
+/// HTTPS is not supported; download the file first.
+#[derive(Debug, Clone)]
+pub struct UrlRom(pub String);
+
+impl RomSource for UrlRom {
+    fn name(&self) -> String {
+        self.0.clone()
+    }
+
+    fn read(&self) -> anyhow::Result<Vec<u8>> {
+        if self.0.starts_with("https://") {
+            return Err(anyhow!(
+                "https is not supported; download the ROM and open the file"
+            ));
+        }
+
+        let rest = self.0.trim_start_matches("http://");
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let addr = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{}:80", host)
+        };
+
+        let mut stream = TcpStream::connect(&addr)?;
+        stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+        stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+
+        // HTTP/1.0 so the body is never chunked and ends when the connection closes
+        write!(
+            stream,
+            "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: chip8\r\n\r\n",
+            path, host
+        )?;
+
+        let mut response = Vec::new();
+        stream
+            .take(MAX_FETCH_SIZE + 0x1000)
+            .read_to_end(&mut response)?;
+
+        let header_end = response
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or_else(|| anyhow!("malformed HTTP response"))?;
+        let header = String::from_utf8_lossy(&response[..header_end]);
+        let status = header.lines().next().unwrap_or_default();
+
+        match status.split_whitespace().nth(1) {
+            Some("200") => {}
+            _ => return Err(anyhow!("server responded '{}'", status)),
+        }
+
+        Ok(response[header_end + 4..].to_vec())
+    }
+}
+
+/// ROM of a ROM database entry, named by its title or SHA-1
+///
+/// The database only describes ROMs, so the image is whichever of the
+/// built-in demo and the recently opened ROMs has the entry's program.
+#[derive(Debug, Clone)]
+pub struct RomDbRom {
+    pub query: String,
+
+    /// Where the user's database entries and recent ROMs are kept
+    pub storage: Storage,
+}
+
+impl RomDbRom {
+    /// File the ROM was found in, if not built in, and its image
+    fn find(&self) -> anyhow::Result<(Option<PathBuf>, Vec<u8>)> {
+        let mut db = RomDb::builtin();
+        db.extend(RomDb::load(&self.storage.romdb_path())?);
+
+        let hashes: Vec<&str> = db.find(&self.query).collect();
+
+        if hashes.is_empty() {
+            return Err(anyhow!("no ROM database entry for '{}'", self.query));
+        }
+
+        let builtin = (None, DEMO_ROM.to_vec());
+        let recent = self
+            .storage
+            .recent_roms()
+            .into_iter()
+            .filter_map(|path| Some((Some(path.clone()), fs::read(path).ok()?)));
+
+        iter::once(builtin)
+            .chain(recent)
+            .find(|(_, rom)| {
+                Metadata::split(rom)
+                    .is_ok_and(|(program, _)| hashes.contains(&romdb::sha1_hex(program).as_str()))
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "'{}' is in the ROM database, but no recent ROM matches it; open its file once",
+                    self.query
+                )
+            })
+    }
+}
+
+impl RomSource for RomDbRom {
+    fn name(&self) -> String {
+        format!("db:{}", self.query)
+    }
+
+    fn local_path(&self) -> Option<PathBuf> {
+        self.find().ok()?.0
+    }
+
+    fn read(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(self.find()?.1)
+    }
+}
+
+/// ROM stored in a zip archive
+#[derive(Debug, Clone)]
+pub struct ZipRom {
+    pub archive: PathBuf,
+
+    /// Member name; the first member with a ROM extension if `None`
+    pub member: Option<String>,
+}
+
+impl RomSource for ZipRom {
+    fn name(&self) -> String {
+        match &self.member {
+            Some(member) => format!("{}:{}", self.archive.display(), member),
+            None => self.archive.display().to_string(),
+        }
+    }
+
+    /// The member's file name next to the archive, or the archive itself
+    fn local_path(&self) -> Option<PathBuf> {
+        match &self.member {
+            Some(member) => Some(self.archive.with_file_name(Path::new(member).file_name()?)),
+            None => Some(self.archive.clone()),
+        }
+    }
+
+    fn read(&self) -> anyhow::Result<Vec<u8>> {
+        let data = fs::read(&self.archive)?;
+        let entries = zip_entries(&data)?;
+
+        let entry = match &self.member {
+            Some(member) => entries
+                .iter()
+                .find(|e| &e.name == member)
+                .ok_or_else(|| anyhow!("no member '{}' in archive", member))?,
+            None => entries
+                .iter()
+                .find(|e| has_rom_extension(&e.name))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "no ROM in archive (looked for .{})",
+                        ROM_EXTENSIONS.join(", .")
+                    )
+                })?,
+        };
+
+        zip_extract(&data, entry)
+    }
+}
+
+fn has_rom_extension(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ROM_EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+}
+
+/// Central directory record of a zip archive member
+#[derive(Debug, Clone)]
+struct ZipEntry {
+    name: String,
+    flags: u16,
+    method: u16,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    header_offset: u32,
+}
+
+fn le16(data: &[u8], offset: usize) -> anyhow::Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| anyhow!("truncated zip archive"))
+}
+
+fn le32(data: &[u8], offset: usize) -> anyhow::Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| anyhow!("truncated zip archive"))
+}
+
+/// Read the central directory, found through the end-of-central-directory record
+fn zip_entries(data: &[u8]) -> anyhow::Result<Vec<ZipEntry>> {
+    const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+    const ENTRY_SIGNATURE: u32 = 0x0201_4b50;
+
+    // The record is 22 bytes plus a comment of up to 64 KiB
+    let eocd = (0..=data.len().saturating_sub(22))
+        .rev()
+        .take(0x10000)
+        .find(|&i| le32(data, i).ok() == Some(EOCD_SIGNATURE))
+        .ok_or_else(|| anyhow!("not a zip archive"))?;
+
+    let count = le16(data, eocd + 10)?;
+    let mut offset = le32(data, eocd + 16)? as usize;
+    let mut entries = Vec::with_capacity(count.into());
+
+    for _ in 0..count {
+        if le32(data, offset)? != ENTRY_SIGNATURE {
+            return Err(anyhow!("corrupt zip central directory"));
+        }
+
+        let name_len = usize::from(le16(data, offset + 28)?);
+        let extra_len = usize::from(le16(data, offset + 30)?);
+        let comment_len = usize::from(le16(data, offset + 32)?);
+        let name = data
+            .get(offset + 46..offset + 46 + name_len)
+            .ok_or_else(|| anyhow!("truncated zip archive"))?;
+
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            flags: le16(data, offset + 8)?,
+            method: le16(data, offset + 10)?,
+            crc: le32(data, offset + 16)?,
+            compressed_size: le32(data, offset + 20)?,
+            size: le32(data, offset + 24)?,
+            header_offset: le32(data, offset + 42)?,
+        });
+
+        offset += 46 + name_len + extra_len + comment_len;
+    }
+
+    Ok(entries)
+}
+
+fn zip_extract(data: &[u8], entry: &ZipEntry) -> anyhow::Result<Vec<u8>> {
+    const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+
+    if entry.flags & 1 != 0 {
+        return Err(anyhow!("'{}' is encrypted", entry.name));
+    }
+
+    if u64::from(entry.size) > MAX_FETCH_SIZE {
+        return Err(anyhow!("'{}' is too large to be a ROM", entry.name));
+    }
+
+    let header = entry.header_offset as usize;
+    if le32(data, header)? != LOCAL_SIGNATURE {
+        return Err(anyhow!("corrupt zip local header for '{}'", entry.name));
+    }
+
+    let start =
+        header + 30 + usize::from(le16(data, header + 26)?) + usize::from(le16(data, header + 28)?);
+    let compressed = data
+        .get(start..start + entry.compressed_size as usize)
+        .ok_or_else(|| anyhow!("truncated zip archive"))?;
+
+    let contents = match entry.method {
+        0 => compressed.to_vec(),
+        8 => {
+            let mut contents = Vec::with_capacity(entry.size as usize);
+            DeflateDecoder::new(compressed)
+                .take(MAX_FETCH_SIZE)
+                .read_to_end(&mut contents)?;
+            contents
+        }
+        method => {
+            return Err(anyhow!(
+                "'{}' uses unsupported compression method {}",
+                entry.name,
+                method
+            ))
+        }
+    };
+
+    let mut crc = Crc::new();
+    crc.update(&contents);
+
+    if crc.sum() != entry.crc || contents.len() != entry.size as usize {
+        return Err(anyhow!("'{}' is corrupt (checksum mismatch)", entry.name));
+    }
+
+    Ok(contents)
+}
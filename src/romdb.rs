@@ -0,0 +1,114 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{anyhow, Context};
+
+use crate::metadata::Metadata;
+
+/// Entries shipped with the emulator
+const BUILTIN: &str = include_str!("../romdb/roms.txt");
+
+/// Known ROMs, keyed by the SHA-1 of their program, with recommended settings
+///
+/// Read from text where each entry is a bracketed hash followed by metadata
+/// lines, in the form `Metadata` parses:
+///
+/// ```text
+/// # comment
+/// [ef636b850ed8434309478f73314f8b5a3fbf84b4]
+/// title = Hex font demo
+/// tickrate = 15
+/// quirks = shift_vy
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RomDb {
+    entries: HashMap<String, Metadata>,
+}
+
+impl RomDb {
+    /// The database shipped with the emulator
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN).expect("built-in ROM database is valid")
+    }
+
+    /// Load `path`, or an empty database if it does not exist
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read ROM database {}", path.display()))?;
+
+        Self::parse(&text).with_context(|| format!("invalid ROM database {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut entries = HashMap::new();
+        let mut current: Option<(String, Metadata)> = None;
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(hash) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let hash = hash.trim().to_ascii_lowercase();
+
+                if hash.len() != 40 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(anyhow!("line {}: '{}' is not a SHA-1 hash", i + 1, hash));
+                }
+
+                entries.extend(current.replace((hash, Metadata::default())));
+                continue;
+            }
+
+            let (_, metadata) = current
+                .as_mut()
+                .ok_or_else(|| anyhow!("line {}: expected [SHA-1] before '{}'", i + 1, line))?;
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected key = value, got '{}'", i + 1, line))?;
+
+            metadata
+                .set(key, value)
+                .map_err(|e| anyhow!("line {}: {}", i + 1, e))?;
+        }
+
+        entries.extend(current);
+
+        Ok(RomDb { entries })
+    }
+
+    /// Add the entries of `other`, replacing these for the same ROMs
+    pub fn extend(&mut self, other: RomDb) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Hashes of the entries matching `query`: a SHA-1, or a title, ignoring case
+    pub fn find<'a>(&'a self, query: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let query = query.trim();
+
+        self.entries
+            .iter()
+            .filter(move |(hash, metadata)| {
+                hash.eq_ignore_ascii_case(query)
+                    || metadata
+                        .title
+                        .as_deref()
+                        .is_some_and(|title| title.eq_ignore_ascii_case(query))
+            })
+            .map(|(hash, _)| hash.as_str())
+    }
+
+    /// Entry for the ROM with program `program`
+    pub fn get(&self, program: &[u8]) -> Option<&Metadata> {
+        self.entries.get(&sha1_hex(program))
+    }
+}
+
+/// SHA-1 of `data` in lowercase hex, the key of database entries
+pub fn sha1_hex(data: &[u8]) -> String {
+    sha1_smol::Sha1::from(data).digest().to_string()
+}
@@ -0,0 +1,219 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Context};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+use crate::{
+    chip8::{Chip8, XO_RAM_SIZE},
+    storage,
+};
+
+const MAGIC: &[u8; 4] = b"C8SS";
+const VERSION: u8 = 3;
+
+/// Serialize the full machine state
+///
+/// Layout (version 3, integers little-endian):
+///
+/// | Field         | Size        |
+/// |---------------|-------------|
+/// | magic "C8SS"  | 4           |
+/// | version       | 1           |
+/// | RAM size      | 4           |
+/// | RNG seed      | 32          |
+/// | RNG word pos  | 16          |
+/// | V0..VF        | 16          |
+/// | I             | 2           |
+/// | DT, ST        | 1, 1        |
+/// | PC            | 2           |
+/// | SP            | 1           |
+/// | stack         | 16 x 2      |
+/// | RAM           | RAM size    |
+/// | hi-res, SCHIP high resolution | 1, 1 |
+/// | display       | 1 per pixel (plane mask) |
+/// | keys          | 16          |
+/// | plane mask    | 1           |
+/// | audio pattern | 1 (0 if none, else 1 followed by 16) |
+/// | pitch         | 1           |
+///
+/// Earlier versions, without the RAM size, display modes, and XO-CHIP sound,
+/// are rejected rather than restored without them.
+pub fn serialize(cpu: &Chip8) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.extend_from_slice(&(cpu.ram.len() as u32).to_le_bytes());
+
+    out.extend_from_slice(&cpu.rng_seed);
+    out.extend_from_slice(&cpu.rng.get_word_pos().to_le_bytes());
+
+    out.extend_from_slice(&cpu.v);
+    out.extend_from_slice(&cpu.i.to_le_bytes());
+    out.push(cpu.dt);
+    out.push(cpu.st);
+    out.extend_from_slice(&cpu.pc.to_le_bytes());
+    out.push(cpu.sp);
+
+    for addr in cpu.stack.iter() {
+        out.extend_from_slice(&addr.to_le_bytes());
+    }
+
+    out.extend_from_slice(&cpu.ram);
+    out.push(cpu.hires as u8);
+    out.push(cpu.schip_hires as u8);
+    out.extend_from_slice(&cpu.display);
+    out.extend(cpu.keys.iter().map(|k| *k as u8));
+
+    out.push(cpu.plane);
+    match cpu.audio_bits {
+        Some(bits) => {
+            out.push(1);
+            out.extend_from_slice(&bits);
+        }
+        None => out.push(0),
+    }
+    out.push(cpu.pitch);
+
+    out
+}
+
+/// Restore machine state previously written by `serialize` into `cpu`
+pub fn deserialize(cpu: &mut Chip8, data: &[u8]) -> anyhow::Result<()> {
+    let mut r = Reader { data, pos: 0 };
+
+    if r.take(MAGIC.len())? != MAGIC {
+        return Err(anyhow!("not a save state file"));
+    }
+
+    let version = r.u8()?;
+    if version != VERSION {
+        return Err(anyhow!("unsupported save state version {}", version));
+    }
+
+    let ram_size = r.u32()? as usize;
+    if ram_size != cpu.ram.len() {
+        return Err(anyhow!(
+            "save state has {} bytes of RAM but this machine has {}; run with{} --xo-ram to load it",
+            ram_size,
+            cpu.ram.len(),
+            if ram_size == XO_RAM_SIZE { "" } else { "out" }
+        ));
+    }
+
+    let mut seed = [0; 32];
+    seed.copy_from_slice(r.take(32)?);
+    let mut word_pos = [0; 16];
+    word_pos.copy_from_slice(r.take(16)?);
+
+    let mut state = cpu.clone();
+
+    state.rng_seed = seed;
+    state.rng = ChaCha12Rng::from_seed(seed);
+    state.rng.set_word_pos(u128::from_le_bytes(word_pos));
+
+    state.v.copy_from_slice(r.take(cpu.v.len())?);
+    state.i = r.u16()?;
+    state.dt = r.u8()?;
+    state.st = r.u8()?;
+    state.pc = r.u16()?;
+    state.sp = r.u8()?;
+    if usize::from(state.sp) >= state.stack.len() {
+        return Err(anyhow!(
+            "stack pointer {} in save state is past the stack",
+            state.sp
+        ));
+    }
+
+    for addr in state.stack.iter_mut() {
+        *addr = r.u16()?;
+    }
+
+    state.ram.copy_from_slice(r.take(cpu.ram.len())?);
+
+    // The display is the size of the saved modes
+    state.hires = r.bool()?;
+    state.schip_hires = r.bool()?;
+    state.resize_display();
+    let display_len = state.display.len();
+    state.display.copy_from_slice(r.take(display_len)?);
+
+    for (k, b) in state.keys.iter_mut().zip(r.take(cpu.keys.len())?) {
+        *k = *b != 0;
+    }
+
+    state.plane = r.u8()?;
+    state.audio_bits = if r.bool()? {
+        let mut bits = [0; 16];
+        bits.copy_from_slice(r.take(16)?);
+        Some(bits)
+    } else {
+        None
+    };
+    state.pitch = r.u8()?;
+
+    if r.pos != data.len() {
+        return Err(anyhow!("trailing data in save state"));
+    }
+
+    state.redraw();
+    *cpu = state;
+
+    Ok(())
+}
+
+pub fn save(path: &Path, cpu: &Chip8) -> anyhow::Result<()> {
+    storage::create_parent(path)?;
+    fs::write(path, serialize(cpu))
+        .with_context(|| format!("failed to write save state {}", path.display()))
+}
+
+pub fn load(path: &Path, cpu: &mut Chip8) -> anyhow::Result<()> {
+    let data =
+        fs::read(path).with_context(|| format!("failed to read save state {}", path.display()))?;
+
+    deserialize(cpu, &data)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self.pos + n;
+
+        if end > self.data.len() {
+            return Err(anyhow!("save state is truncated"));
+        }
+
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> anyhow::Result<bool> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(anyhow!("invalid flag {} in save state", b)),
+        }
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
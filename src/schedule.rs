@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+
+use crate::chip8::Chip8;
+
+/// Keypad presses scheduled in emulated time
+///
+/// Times are cycles: instructions executed since reset. A scheduled key is
+/// held for cycles `press_at..release_at`. Holds are dropped once released,
+/// so cycles passed to `apply` must not go backwards.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeySchedule {
+    /// Holds by press cycle, as (key, release cycle)
+    holds: BTreeMap<u64, Vec<(u8, u64)>>,
+}
+
+impl KeySchedule {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Hold keypad `key` from cycle `press_at` until (not including) cycle `release_at`
+    pub fn schedule(&mut self, key: u8, press_at: u64, release_at: u64) {
+        if release_at > press_at {
+            self.holds
+                .entry(press_at)
+                .or_default()
+                .push((key & 0xf, release_at));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.holds.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.holds.clear();
+    }
+
+    /// Cycle after the last scheduled release, or 0 if nothing is scheduled
+    pub fn end(&self) -> u64 {
+        self.holds
+            .values()
+            .flatten()
+            .map(|(_, release)| *release)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Press the keys held at `cycle` on `cpu`, dropping holds that have ended
+    pub fn apply(&mut self, cpu: &mut Chip8, cycle: u64) {
+        let mut finished = Vec::new();
+
+        for (press_at, holds) in self.holds.range_mut(..=cycle) {
+            holds.retain(|(_, release_at)| *release_at > cycle);
+
+            for (key, _) in holds.iter() {
+                cpu.set_key(*key);
+            }
+
+            if holds.is_empty() {
+                finished.push(*press_at);
+            }
+        }
+
+        for press_at in finished {
+            self.holds.remove(&press_at);
+        }
+    }
+}
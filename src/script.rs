@@ -0,0 +1,291 @@
+use std::{mem, path::Path};
+
+use anyhow::anyhow;
+use rhai::{Blob, CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+
+use crate::chip8::Chip8;
+
+/// Events a script can hook, each by defining a function of that name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// `before_step(opcode)`: the instruction at PC is about to run
+    BeforeStep,
+    /// `on_draw()`: a frame was drawn
+    Draw,
+    /// `on_key(key, pressed)`: a keypad key was pressed or released
+    Key,
+    /// `on_tick()`: the timers ticked, 60 times a second
+    Tick,
+}
+
+impl Hook {
+    const ALL: [Hook; 4] = [Hook::BeforeStep, Hook::Draw, Hook::Key, Hook::Tick];
+
+    fn name(self) -> &'static str {
+        match self {
+            Hook::BeforeStep => "before_step",
+            Hook::Draw => "on_draw",
+            Hook::Key => "on_key",
+            Hook::Tick => "on_tick",
+        }
+    }
+
+    fn params(self) -> usize {
+        match self {
+            Hook::BeforeStep => 1,
+            Hook::Key => 2,
+            Hook::Draw | Hook::Tick => 0,
+        }
+    }
+}
+
+/// What a script asked the emulator to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptRequest {
+    Pause(String),
+    Quit,
+}
+
+/// The machine as a script sees it, bound to `this` in hooks
+///
+/// `this.pc`, `this.i`, `this.dt`, `this.st`, and `this.v[x]` read and write
+/// registers, `this.peek(addr)` and `this.poke(addr, byte)` memory, and
+/// `this.vars` is a map kept between calls for the script's own state.
+#[derive(Debug, Clone, Default)]
+struct Machine {
+    pc: u16,
+    i: u16,
+    v: Blob,
+    dt: u8,
+    st: u8,
+    sp: u8,
+    cycle: u64,
+    ram: Vec<u8>,
+    vars: Map,
+    request: Option<ScriptRequest>,
+}
+
+impl Machine {
+    fn addr(&self, addr: i64) -> usize {
+        addr.rem_euclid(self.ram.len().max(1) as i64) as usize
+    }
+}
+
+/// A Rhai script with event hooks, loaded with `--script`
+///
+/// The script's top level runs once when loaded. Hooks are functions named
+/// after a `Hook`; those not defined cost nothing:
+///
+/// ```text
+/// fn on_tick() {
+///     if this.peek(0x2F0) == 3 && !("split" in this.vars) {
+///         this.vars.split = this.cycle;
+///         print(`Level 3 at instruction ${this.cycle}`);
+///     }
+/// }
+///
+/// fn on_key(key, pressed) {
+///     if key == 0xF && pressed { this.pause("F pressed"); }
+/// }
+/// ```
+pub struct Script {
+    pub name: String,
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    hooks: Vec<Hook>,
+    /// Script state kept between hooks
+    vars: Map,
+    /// Keys as last reported to `on_key`
+    keys: [bool; 0x10],
+}
+
+impl Script {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let name = path.display().to_string();
+        let engine = new_engine();
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| anyhow!("failed to load script {}: {}", name, e))?;
+
+        let mut hooks = Vec::new();
+
+        for f in ast.iter_functions() {
+            if let Some(hook) = Hook::ALL.iter().find(|hook| hook.name() == f.name) {
+                if f.params.len() != hook.params() {
+                    return Err(anyhow!(
+                        "script {}: {} takes {} parameter(s)",
+                        name,
+                        hook.name(),
+                        hook.params()
+                    ));
+                }
+
+                hooks.push(*hook);
+            }
+        }
+
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| anyhow!("script {}: {}", name, e))?;
+
+        Ok(Script {
+            name,
+            engine,
+            ast,
+            scope,
+            hooks,
+            vars: Map::new(),
+            keys: [false; 0x10],
+        })
+    }
+
+    pub fn has_hook(&self, hook: Hook) -> bool {
+        self.hooks.contains(&hook)
+    }
+
+    /// Run the script's `hook`, if defined, on `cpu`
+    ///
+    /// `Hook::Key` runs `on_key` once for each key that changed since the
+    /// last call.
+    pub fn run(
+        &mut self,
+        hook: Hook,
+        cpu: &mut Chip8,
+        cycle: u64,
+    ) -> anyhow::Result<Option<ScriptRequest>> {
+        if !self.has_hook(hook) {
+            return Ok(None);
+        }
+
+        match hook {
+            Hook::BeforeStep => {
+                let opcode = i64::from(cpu.mem_read_opcode(cpu.pc));
+                self.call(hook, cpu, cycle, (opcode,))
+            }
+            Hook::Key => {
+                let mut request = None;
+
+                for key in 0..0x10 {
+                    let pressed = cpu.keys[key];
+
+                    if pressed != self.keys[key] {
+                        self.keys[key] = pressed;
+                        let args = (key as i64, pressed);
+                        request = self.call(hook, cpu, cycle, args)?.or(request);
+                    }
+                }
+
+                Ok(request)
+            }
+            Hook::Draw | Hook::Tick => self.call(hook, cpu, cycle, ()),
+        }
+    }
+
+    fn call(
+        &mut self,
+        hook: Hook,
+        cpu: &mut Chip8,
+        cycle: u64,
+        args: impl rhai::FuncArgs,
+    ) -> anyhow::Result<Option<ScriptRequest>> {
+        // Memory is moved in and out rather than copied, since hooks can run
+        // on every step
+        let mut this = Dynamic::from(Machine {
+            pc: cpu.pc,
+            i: cpu.i,
+            v: cpu.v.to_vec(),
+            dt: cpu.dt,
+            st: cpu.st,
+            sp: cpu.sp,
+            cycle,
+            ram: mem::take(&mut cpu.ram),
+            vars: mem::take(&mut self.vars),
+            request: None,
+        });
+
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut this);
+        // Any value, as a hook ending in an expression returns it
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut self.scope,
+            &self.ast,
+            hook.name(),
+            args,
+        );
+
+        let machine = this.cast::<Machine>();
+        cpu.ram = machine.ram;
+        cpu.pc = machine.pc;
+        cpu.i = machine.i;
+        cpu.dt = machine.dt;
+        cpu.st = machine.st;
+        for (v, value) in cpu.v.iter_mut().zip(machine.v) {
+            *v = value;
+        }
+        self.vars = machine.vars;
+
+        if let Err(e) = result {
+            return Err(anyhow!("script {}: {}: {}", self.name, hook.name(), e));
+        }
+
+        Ok(machine.request)
+    }
+}
+
+fn new_engine() -> Engine {
+    let mut engine = Engine::new();
+
+    engine
+        .register_type_with_name::<Machine>("Machine")
+        .register_get_set(
+            "pc",
+            |m: &mut Machine| i64::from(m.pc),
+            |m: &mut Machine, pc: i64| m.pc = m.addr(pc) as u16,
+        )
+        .register_get_set(
+            "i",
+            |m: &mut Machine| i64::from(m.i),
+            |m: &mut Machine, i: i64| m.i = i as u16,
+        )
+        .register_get_set(
+            "v",
+            |m: &mut Machine| m.v.clone(),
+            |m: &mut Machine, v: Blob| m.v = v,
+        )
+        .register_get_set(
+            "dt",
+            |m: &mut Machine| i64::from(m.dt),
+            |m: &mut Machine, dt: i64| m.dt = dt as u8,
+        )
+        .register_get_set(
+            "st",
+            |m: &mut Machine| i64::from(m.st),
+            |m: &mut Machine, st: i64| m.st = st as u8,
+        )
+        .register_get_set(
+            "vars",
+            |m: &mut Machine| m.vars.clone(),
+            |m: &mut Machine, vars: Map| m.vars = vars,
+        )
+        .register_get("sp", |m: &mut Machine| i64::from(m.sp))
+        .register_get("cycle", |m: &mut Machine| m.cycle as i64)
+        .register_fn("peek", |m: &mut Machine, addr: i64| {
+            i64::from(m.ram[m.addr(addr)])
+        })
+        .register_fn("poke", |m: &mut Machine, addr: i64, byte: i64| {
+            let addr = m.addr(addr);
+            m.ram[addr] = byte as u8;
+        })
+        .register_fn("pause", |m: &mut Machine, reason: &str| {
+            m.request = Some(ScriptRequest::Pause(reason.to_string()));
+        })
+        .register_fn("quit", |m: &mut Machine| {
+            m.request = Some(ScriptRequest::Quit);
+        });
+
+    engine
+}
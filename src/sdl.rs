@@ -0,0 +1,394 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+};
+
+use anyhow::anyhow;
+use minifb::{Key, Scale};
+use sdl2::{
+    audio::{AudioCallback, AudioDevice, AudioSpecDesired},
+    controller::{Button, GameController},
+    event::Event,
+    keyboard::Keycode,
+    pixels::PixelFormatEnum,
+    render::{Canvas, TextureCreator},
+    video::{Window, WindowContext},
+    EventPump, GameControllerSubsystem, Sdl,
+};
+
+use crate::{
+    chip8::AudioPattern,
+    frontend::{self, Beeper, Display, Input},
+    keys,
+};
+
+const BEEP_FREQUENCY_HZ: f32 = 440.;
+const BEEP_VOLUME: f32 = 0.1;
+
+/// Samples in an XO-CHIP audio pattern
+const PATTERN_BITS: usize = 128;
+
+/// SDL2 window with a square-wave buzzer and game controller input
+///
+/// Runs on the calling thread: events are pumped in `update`, once per
+/// emulator step. Frames are scaled to the window with letterboxing, and
+/// controllers act as the keyboard keys of the default layout: D-pad as
+/// W/A/S/D, A as E, B as Q, X as 1, Y as 2, and Start as Space (pause). Files
+/// dropped onto the window are passed on to be loaded as ROMs. The mouse and
+/// every finger on a touch screen are reported as pointers.
+pub struct SdlFrontend {
+    _sdl: Sdl,
+    canvas: Canvas<Window>,
+    texture_creator: TextureCreator<WindowContext>,
+    event_pump: EventPump,
+    controller_subsystem: GameControllerSubsystem,
+    controllers: Vec<GameController>,
+    audio: Option<AudioDevice<SquareWave>>,
+    frame_size: (usize, usize),
+    held: HashSet<Key>,
+    pressed: HashSet<Key>,
+    dropped_file: Option<PathBuf>,
+    /// Fingers on the screen by ID, at positions normalized to 0.0 ~ 1.0
+    /// across the window
+    touches: BTreeMap<i64, (f32, f32)>,
+    closing: bool,
+}
+
+impl SdlFrontend {
+    pub fn new(title: &str, width: usize, height: usize, scale: Scale) -> anyhow::Result<Self> {
+        let sdl = sdl2::init().map_err(|e| anyhow!("SDL init failed: {}", e))?;
+        let video = sdl.video().map_err(|e| anyhow!(e))?;
+        let controller_subsystem = sdl.game_controller().map_err(|e| anyhow!(e))?;
+
+        // Nearest-neighbor scaling keeps pixels square and sharp
+        sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "0");
+
+        let factor = scale_factor(scale);
+        let mut builder = video.window(title, (width as u32) * factor, (height as u32) * factor);
+        builder.position_centered().resizable();
+
+        if let Scale::FitScreen = scale {
+            builder.fullscreen_desktop();
+        }
+
+        let window = builder.build()?;
+
+        let mut canvas = window.into_canvas().present_vsync().build()?;
+        canvas.set_logical_size(width as u32, height as u32)?;
+        let texture_creator = canvas.texture_creator();
+
+        // Sound is optional: keep running without it
+        let audio = sdl
+            .audio()
+            .and_then(|audio| {
+                let spec = AudioSpecDesired {
+                    freq: Some(44100),
+                    channels: Some(1),
+                    samples: None,
+                };
+
+                audio.open_playback(None, &spec, |spec| SquareWave {
+                    phase: 0.,
+                    phase_inc: BEEP_FREQUENCY_HZ / spec.freq as f32,
+                    freq: spec.freq as f32,
+                    pattern: None,
+                })
+            })
+            .map_err(|e| eprintln!("Sound disabled: {}", e))
+            .ok();
+
+        let event_pump = sdl.event_pump().map_err(|e| anyhow!(e))?;
+
+        Ok(SdlFrontend {
+            _sdl: sdl,
+            canvas,
+            texture_creator,
+            event_pump,
+            controller_subsystem,
+            controllers: Vec::new(),
+            audio,
+            frame_size: (width, height),
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            dropped_file: None,
+            touches: BTreeMap::new(),
+            closing: false,
+        })
+    }
+
+    fn key_down(&mut self, key: Key) {
+        if self.held.insert(key) {
+            self.pressed.insert(key);
+        }
+    }
+}
+
+impl Display for SdlFrontend {
+    fn update(&mut self) {
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+
+        for event in events {
+            match event {
+                Event::Quit { .. } => self.closing = true,
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat,
+                    ..
+                } => {
+                    if let Some(key) = host_key(keycode) {
+                        // Repeats count as presses, like minifb's KeyRepeat::Yes
+                        if repeat {
+                            self.pressed.insert(key);
+                        } else {
+                            self.key_down(key);
+                        }
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    if let Some(key) = host_key(keycode) {
+                        self.held.remove(&key);
+                    }
+                }
+                Event::FingerDown {
+                    finger_id, x, y, ..
+                }
+                | Event::FingerMotion {
+                    finger_id, x, y, ..
+                } => {
+                    self.touches.insert(finger_id, (x, y));
+                }
+                Event::FingerUp { finger_id, .. } => {
+                    self.touches.remove(&finger_id);
+                }
+                Event::DropFile { filename, .. } => {
+                    self.dropped_file = Some(PathBuf::from(filename));
+                }
+                Event::ControllerDeviceAdded { which, .. } => {
+                    match self.controller_subsystem.open(which) {
+                        Ok(controller) => self.controllers.push(controller),
+                        Err(e) => eprintln!("Failed to open controller {}: {}", which, e),
+                    }
+                }
+                Event::ControllerButtonDown { button, .. } => {
+                    if let Some(key) = controller_key(button) {
+                        self.key_down(key);
+                    }
+                }
+                Event::ControllerButtonUp { button, .. } => {
+                    if let Some(key) = controller_key(button) {
+                        self.held.remove(&key);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn is_closing(&self) -> bool {
+        self.closing
+    }
+
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) {
+        if self.frame_size != (width, height) {
+            self.frame_size = (width, height);
+            let _ = self.canvas.set_logical_size(width as u32, height as u32);
+        }
+
+        // RGB888 is 32 bits per pixel, 0RGB in native byte order
+        let mut texture = match self.texture_creator.create_texture_streaming(
+            PixelFormatEnum::RGB888,
+            width as u32,
+            height as u32,
+        ) {
+            Ok(texture) => texture,
+            Err(e) => {
+                eprintln!("Failed to create texture: {}", e);
+                return;
+            }
+        };
+
+        let bytes: Vec<u8> = buffer.iter().flat_map(|px| px.to_ne_bytes()).collect();
+
+        if texture.update(None, &bytes, width * 4).is_ok() {
+            self.canvas.clear();
+            let _ = self.canvas.copy(&texture, None, None);
+            self.canvas.present();
+        }
+    }
+
+    fn set_title(&mut self, title: String) {
+        let _ = self.canvas.window_mut().set_title(&title);
+    }
+
+    fn window_size(&self) -> Option<(usize, usize)> {
+        let (width, height) = self.canvas.window().size();
+        Some((width as usize, height as usize))
+    }
+
+    fn close(self) {}
+}
+
+impl Input for SdlFrontend {
+    fn get_keys(&self) -> Option<Vec<Key>> {
+        if self.held.is_empty() {
+            None
+        } else {
+            Some(self.held.iter().copied().collect())
+        }
+    }
+
+    fn get_keys_pressed(&mut self) -> Option<HashSet<Key>> {
+        if self.pressed.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.pressed))
+        }
+    }
+
+    fn take_dropped_file(&mut self) -> Option<PathBuf> {
+        self.dropped_file.take()
+    }
+
+    fn get_pointers(&self) -> Vec<(usize, usize)> {
+        let (width, height) = self.canvas.window().size();
+        let window = (width as f32, height as f32);
+        let mouse = self.event_pump.mouse_state();
+
+        // Touches also move the mouse, which only adds a point already held
+        let mouse = mouse.left().then(|| (mouse.x() as f32, mouse.y() as f32));
+        let touches = self
+            .touches
+            .values()
+            .map(|(x, y)| (x * window.0, y * window.1));
+
+        mouse
+            .into_iter()
+            .chain(touches)
+            .filter_map(|pos| frontend::letterboxed(self.frame_size, window, pos))
+            .collect()
+    }
+}
+
+impl Beeper for SdlFrontend {
+    fn set_beeping(&mut self, beeping: bool) {
+        if let Some(audio) = &self.audio {
+            if beeping {
+                audio.resume();
+            } else {
+                audio.pause();
+            }
+        }
+    }
+
+    fn set_audio_pattern(&mut self, pattern: Option<AudioPattern>) {
+        if let Some(audio) = &mut self.audio {
+            let mut wave = audio.lock();
+            wave.pattern = pattern;
+            wave.phase = 0.;
+        }
+    }
+}
+
+/// The buzzer tone, or an XO-CHIP audio pattern once one is set
+struct SquareWave {
+    /// Position in the current period (tone) or pattern (0.0 ~ 1.0)
+    phase: f32,
+    phase_inc: f32,
+    /// Output sample rate
+    freq: f32,
+    pattern: Option<AudioPattern>,
+}
+
+impl AudioCallback for SquareWave {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        let phase_inc = match &self.pattern {
+            Some(pattern) => pattern.rate() / PATTERN_BITS as f32 / self.freq,
+            None => self.phase_inc,
+        };
+
+        for sample in out.iter_mut() {
+            let high = match &self.pattern {
+                Some(pattern) => {
+                    let bit = (self.phase * PATTERN_BITS as f32) as usize % PATTERN_BITS;
+                    pattern.bits[bit / 8] & (0x80 >> (bit % 8)) != 0
+                }
+                None => self.phase < 0.5,
+            };
+
+            *sample = if high { BEEP_VOLUME } else { -BEEP_VOLUME };
+            self.phase = (self.phase + phase_inc) % 1.;
+        }
+    }
+}
+
+fn scale_factor(scale: Scale) -> u32 {
+    match scale {
+        Scale::X1 => 1,
+        Scale::X2 => 2,
+        Scale::X4 => 4,
+        Scale::X8 => 8,
+        Scale::X16 | Scale::FitScreen => 16,
+        Scale::X32 => 32,
+    }
+}
+
+/// The minifb key for an SDL keycode, so key maps and config work unchanged
+fn host_key(keycode: Keycode) -> Option<Key> {
+    let key = match keycode {
+        Keycode::Return => Key::Enter,
+        Keycode::Kp0 => Key::NumPad0,
+        Keycode::Kp1 => Key::NumPad1,
+        Keycode::Kp2 => Key::NumPad2,
+        Keycode::Kp3 => Key::NumPad3,
+        Keycode::Kp4 => Key::NumPad4,
+        Keycode::Kp5 => Key::NumPad5,
+        Keycode::Kp6 => Key::NumPad6,
+        Keycode::Kp7 => Key::NumPad7,
+        Keycode::Kp8 => Key::NumPad8,
+        Keycode::Kp9 => Key::NumPad9,
+        Keycode::KpPeriod => Key::NumPadDot,
+        Keycode::KpDivide => Key::NumPadSlash,
+        Keycode::KpMultiply => Key::NumPadAsterisk,
+        Keycode::KpMinus => Key::NumPadMinus,
+        Keycode::KpPlus => Key::NumPadPlus,
+        Keycode::KpEnter => Key::NumPadEnter,
+        Keycode::LShift => Key::LeftShift,
+        Keycode::RShift => Key::RightShift,
+        Keycode::LCtrl => Key::LeftCtrl,
+        Keycode::RCtrl => Key::RightCtrl,
+        Keycode::LAlt => Key::LeftAlt,
+        Keycode::RAlt => Key::RightAlt,
+        Keycode::LGui => Key::LeftSuper,
+        Keycode::RGui => Key::RightSuper,
+        Keycode::Equals => Key::Equal,
+        Keycode::Quote => Key::Apostrophe,
+        Keycode::CapsLock => Key::CapsLock,
+        Keycode::ScrollLock => Key::ScrollLock,
+        Keycode::NumLockClear => Key::NumLock,
+        Keycode::Application => Key::Menu,
+        // Letters, digits, F-keys, arrows, and the rest share minifb's names
+        _ => return keys::parse_key(&keycode.name().replace(' ', "")),
+    };
+
+    Some(key)
+}
+
+fn controller_key(button: Button) -> Option<Key> {
+    match button {
+        Button::DPadUp => Some(Key::W),
+        Button::DPadLeft => Some(Key::A),
+        Button::DPadDown => Some(Key::S),
+        Button::DPadRight => Some(Key::D),
+        Button::A => Some(Key::E),
+        Button::B => Some(Key::Q),
+        Button::X => Some(Key::Key1),
+        Button::Y => Some(Key::Key2),
+        Button::Start => Some(Key::Space),
+        _ => None,
+    }
+}
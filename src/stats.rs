@@ -0,0 +1,237 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    chip8::{Chip8Panic, StepOutcome},
+    instruction::{decode, Instruction},
+};
+
+/// Number of addresses listed in the summary
+const TOP_ADDRESSES: usize = 10;
+
+/// Width of the longest bar in the opcode histogram
+const HISTOGRAM_WIDTH: usize = 30;
+
+/// Execution statistics collected over an emulator session
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub start: Instant,
+    pub instructions: u64,
+    pub frames: u64,
+    /// DRW instructions executed
+    pub draws: u64,
+    pub unknown_opcodes: u64,
+    /// Wall-clock time spent blocked on LD Vx, K (FX0A)
+    pub key_wait: Duration,
+    exec_counts: Vec<u64>,
+    /// Executions per `Instruction::family`
+    family_counts: BTreeMap<(&'static str, &'static str), u64>,
+    wait_since: Option<Instant>,
+}
+
+impl Stats {
+    pub fn new(ram_size: usize) -> Self {
+        Stats {
+            start: Instant::now(),
+            instructions: 0,
+            frames: 0,
+            draws: 0,
+            unknown_opcodes: 0,
+            key_wait: Duration::from_secs(0),
+            exec_counts: vec![0; ram_size],
+            family_counts: BTreeMap::new(),
+            wait_since: None,
+        }
+    }
+
+    /// Record a step of `opcode` at `pc`, where `next_pc` is PC after the step
+    pub fn record_step(
+        &mut self,
+        pc: u16,
+        opcode: u16,
+        next_pc: u16,
+        result: &Result<StepOutcome, Chip8Panic>,
+    ) {
+        match result {
+            Ok(StepOutcome::Breakpoint(_)) => return,
+            Err(Chip8Panic::UnknownOpCode | Chip8Panic::ExtensionOpCode(_)) => {
+                self.unknown_opcodes += 1
+            }
+            _ => {}
+        }
+
+        self.instructions += 1;
+
+        if let Some(count) = self.exec_counts.get_mut(usize::from(pc)) {
+            *count += 1;
+        }
+
+        let instruction = decode(opcode);
+
+        if let Instruction::Drw(..) = instruction {
+            self.draws += 1;
+        }
+
+        *self.family_counts.entry(instruction.family()).or_insert(0) += 1;
+
+        let waiting = matches!(instruction, Instruction::LdVxK(_)) && next_pc == pc;
+
+        match (waiting, self.wait_since) {
+            (true, None) => self.wait_since = Some(Instant::now()),
+            (false, Some(since)) => {
+                self.key_wait += since.elapsed();
+                self.wait_since = None;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn record_frame(&mut self) {
+        self.frames += 1;
+    }
+
+    /// Most-executed addresses as (address, count), busiest first
+    pub fn hot_addresses(&self, n: usize) -> Vec<(u16, u64)> {
+        let mut counts: Vec<(u16, u64)> = self
+            .exec_counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(addr, count)| (addr as u16, *count))
+            .collect();
+
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+
+    /// Executions of each opcode family as (pattern, mnemonic, count),
+    /// busiest first, leaving out those never run
+    pub fn opcode_families(&self) -> Vec<(&'static str, &'static str, u64)> {
+        let mut counts: Vec<_> = self
+            .family_counts
+            .iter()
+            .map(|(&(pattern, mnemonic), count)| (pattern, mnemonic, *count))
+            .collect();
+
+        counts.sort_by_key(|(_, _, count)| std::cmp::Reverse(*count));
+        counts
+    }
+
+    pub fn summary(&self) -> String {
+        let elapsed = self.start.elapsed();
+        let key_wait = self.key_wait
+            + self
+                .wait_since
+                .map_or(Duration::from_secs(0), |t| t.elapsed());
+
+        let secs = elapsed.as_secs_f64();
+        let ips = if secs > 0. {
+            self.instructions as f64 / secs
+        } else {
+            0.
+        };
+
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "Run time:         {:.3} s", secs);
+        let _ = writeln!(out, "Instructions:     {}", self.instructions);
+        let _ = writeln!(out, "Average IPS:      {:.0}", ips);
+        let _ = writeln!(out, "Frames:           {}", self.frames);
+        let _ = writeln!(out, "Draws (DRW):      {}", self.draws);
+        let _ = writeln!(out, "Unknown opcodes:  {}", self.unknown_opcodes);
+        let _ = writeln!(out, "Key wait (FX0A):  {:.3} s", key_wait.as_secs_f64());
+        let _ = writeln!(out, "Most executed:");
+
+        for (addr, count) in self.hot_addresses(TOP_ADDRESSES) {
+            let share = count as f64 * 100. / self.instructions.max(1) as f64;
+            let _ = writeln!(out, "  {:04X}  {:>12}  {:5.1}%", addr, count, share);
+        }
+
+        let families = self.opcode_families();
+        let busiest = families.first().map_or(1, |(_, _, count)| *count);
+        let _ = writeln!(out, "Opcode families:");
+
+        for (pattern, mnemonic, count) in families {
+            let share = count as f64 * 100. / self.instructions.max(1) as f64;
+            let bar = (count * HISTOGRAM_WIDTH as u64).div_ceil(busiest) as usize;
+            let _ = writeln!(
+                out,
+                "  {}  {:<14} {:>12}  {:5.1}%  {}",
+                pattern,
+                mnemonic,
+                count,
+                share,
+                "#".repeat(bar)
+            );
+        }
+
+        out
+    }
+}
+
+/// Frames presented and instructions executed per wall-clock second
+///
+/// Counts are collected for a second at a time, so the rates lag by up to a
+/// second but do not jitter from step to step.
+#[derive(Debug, Clone)]
+pub struct RateMeter {
+    since: Instant,
+    frames: u64,
+    instructions: u64,
+    /// (frames, instructions) per second over the last full second
+    rates: Option<(f64, f64)>,
+}
+
+impl Default for RateMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateMeter {
+    pub fn new() -> Self {
+        RateMeter {
+            since: Instant::now(),
+            frames: 0,
+            instructions: 0,
+            rates: None,
+        }
+    }
+
+    pub fn record_frame(&mut self) {
+        self.frames += 1;
+    }
+
+    pub fn record_instructions(&mut self, count: u64) {
+        self.instructions += count;
+    }
+
+    /// Work out the rates once a second has passed since they last were,
+    /// returning whether they were
+    pub fn update(&mut self) -> bool {
+        let elapsed = self.since.elapsed().as_secs_f64();
+        if elapsed < 1. {
+            return false;
+        }
+
+        self.rates = Some((
+            self.frames as f64 / elapsed,
+            self.instructions as f64 / elapsed,
+        ));
+        self.since = Instant::now();
+        self.frames = 0;
+        self.instructions = 0;
+
+        true
+    }
+
+    /// (frames, instructions) per second, once a second has been measured
+    pub fn rates(&self) -> Option<(f64, f64)> {
+        self.rates
+    }
+}
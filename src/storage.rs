@@ -0,0 +1,306 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+/// Environment variable overriding the data directory
+pub const DATA_DIR_ENV: &str = "CHIP8_DATA_DIR";
+
+const ROMS_DIR: &str = "roms";
+const THUMBNAILS_DIR: &str = "thumbnails";
+const PROFILES_FILE: &str = "profiles.toml";
+const RECENT_FILE: &str = "recent.txt";
+const ROMDB_FILE: &str = "romdb.txt";
+
+/// Most ROMs kept in the recent list
+pub const RECENT_LIMIT: usize = 10;
+
+/// Data directory holding everything the emulator writes for itself
+///
+/// ```text
+/// <root>/
+///   roms/<name>-<hash>/     one per ROM, see `RomStorage`
+///   thumbnails/             `chip8 browse` cache
+///   profiles.toml           per-ROM settings, written by hand
+///   recent.txt              paths of ROMs opened lately, newest first
+///   romdb.txt               ROM database entries, added to the built-in ones
+/// ```
+///
+/// ROM folders are keyed by a hash of the ROM contents, so files follow a ROM
+/// when it is moved or renamed, and edited ROMs get a fresh folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Storage {
+    pub root: PathBuf,
+}
+
+/// Files kept for one ROM
+///
+/// ```text
+/// state.c8s       save state (F5/F7)
+/// flags.rpl       SCHIP RPL user flags
+/// cheats.toml     cheat codes
+/// screenshots/
+/// recordings/
+/// ```
+///
+/// Paths are only named here; the folder is created when something is saved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomStorage {
+    pub dir: PathBuf,
+}
+
+/// Summary of one ROM folder, from `Storage::list`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomEntry {
+    pub dir: PathBuf,
+    /// Files below `dir`, relative to it, with their sizes in bytes
+    pub files: Vec<(PathBuf, u64)>,
+}
+
+impl RomEntry {
+    pub fn size(&self) -> u64 {
+        self.files.iter().map(|(_, size)| size).sum()
+    }
+}
+
+impl Storage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Storage { root: root.into() }
+    }
+
+    /// `$CHIP8_DATA_DIR`, else the platform's per-user data directory
+    ///
+    /// That is `$XDG_DATA_HOME/chip8` or `~/.local/share/chip8` on Linux,
+    /// `~/Library/Application Support/chip8` on macOS, and `%APPDATA%\chip8` on
+    /// Windows. Falls back to `.chip8` in the working directory.
+    pub fn open_default() -> Self {
+        Self::new(default_root().unwrap_or_else(|| PathBuf::from(".chip8")))
+    }
+
+    /// Storage for the ROM named `name` (usually `RomSource::name`) with contents `rom`
+    pub fn rom(&self, name: &str, rom: &[u8]) -> RomStorage {
+        RomStorage {
+            dir: self.root.join(ROMS_DIR).join(rom_key(name, rom)),
+        }
+    }
+
+    pub fn thumbnail_dir(&self) -> PathBuf {
+        self.root.join(THUMBNAILS_DIR)
+    }
+
+    /// Settings for single ROMs, keyed by `rom_hash`
+    pub fn profiles_path(&self) -> PathBuf {
+        self.root.join(PROFILES_FILE)
+    }
+
+    /// ROM database entries of the user's own, see `romdb::RomDb`
+    pub fn romdb_path(&self) -> PathBuf {
+        self.root.join(ROMDB_FILE)
+    }
+
+    /// ROM files opened lately, newest first, leaving out any since removed
+    pub fn recent_roms(&self) -> Vec<PathBuf> {
+        let text = fs::read_to_string(self.root.join(RECENT_FILE)).unwrap_or_default();
+
+        text.lines()
+            .map(PathBuf::from)
+            .filter(|path| path.is_file())
+            .collect()
+    }
+
+    /// Put `path` at the top of the recent ROMs, dropping the oldest past `RECENT_LIMIT`
+    pub fn add_recent(&self, path: &Path) -> anyhow::Result<()> {
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("failed to find {}", path.display()))?;
+
+        let mut recent = self.recent_roms();
+        recent.retain(|other| *other != path);
+        recent.insert(0, path);
+        recent.truncate(RECENT_LIMIT);
+
+        let lines: Vec<String> = recent
+            .iter()
+            .map(|path| format!("{}\n", path.display()))
+            .collect();
+
+        let recent_path = self.root.join(RECENT_FILE);
+        create_parent(&recent_path)?;
+        fs::write(&recent_path, lines.concat())
+            .with_context(|| format!("failed to write {}", recent_path.display()))
+    }
+
+    /// Every ROM folder with its files, sorted by folder name
+    pub fn list(&self) -> anyhow::Result<Vec<RomEntry>> {
+        let roms_dir = self.root.join(ROMS_DIR);
+
+        if !roms_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+
+        for entry in fs::read_dir(&roms_dir)
+            .with_context(|| format!("failed to read {}", roms_dir.display()))?
+        {
+            let dir = entry?.path();
+
+            if dir.is_dir() {
+                let mut files = Vec::new();
+                list_files(&dir, Path::new(""), &mut files)?;
+                files.sort();
+
+                entries.push(RomEntry { dir, files });
+            }
+        }
+
+        entries.sort_by(|a, b| a.dir.cmp(&b.dir));
+
+        Ok(entries)
+    }
+
+    /// Delete caches and empty ROM folders, returning the paths removed
+    pub fn clean(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut removed = Vec::new();
+
+        let thumbnails = self.thumbnail_dir();
+        if thumbnails.exists() {
+            fs::remove_dir_all(&thumbnails)?;
+            removed.push(thumbnails);
+        }
+
+        for entry in self.list()? {
+            if entry.files.is_empty() {
+                fs::remove_dir_all(&entry.dir)?;
+                removed.push(entry.dir);
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Delete every ROM folder and cache, leaving anything else in `root`
+    pub fn remove_all(&self) -> anyhow::Result<()> {
+        for dir in [self.root.join(ROMS_DIR), self.thumbnail_dir()].iter() {
+            if dir.exists() {
+                fs::remove_dir_all(dir)
+                    .with_context(|| format!("failed to remove {}", dir.display()))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl RomStorage {
+    pub fn state_path(&self) -> PathBuf {
+        self.dir.join("state.c8s")
+    }
+
+    pub fn flags_path(&self) -> PathBuf {
+        self.dir.join("flags.rpl")
+    }
+
+    pub fn cheats_path(&self) -> PathBuf {
+        self.dir.join("cheats.toml")
+    }
+
+    pub fn screenshot_dir(&self) -> PathBuf {
+        self.dir.join("screenshots")
+    }
+
+    pub fn recording_dir(&self) -> PathBuf {
+        self.dir.join("recordings")
+    }
+
+    /// Delete everything stored for this ROM
+    pub fn remove(&self) -> anyhow::Result<()> {
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir)
+                .with_context(|| format!("failed to remove {}", self.dir.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Create the parent directory of `path` if needed
+pub fn create_parent(path: &Path) -> anyhow::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => {
+            fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// FNV-1a hash, stable across runs and platforms
+pub fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Short hash of a ROM's contents, as used in its folder name
+pub fn rom_hash(rom: &[u8]) -> String {
+    format!("{:08x}", fnv1a(rom) as u32)
+}
+
+/// Folder name for a ROM: its file stem, for people, and a content hash
+fn rom_key(name: &str, rom: &[u8]) -> String {
+    // Last path, archive member, or URL component, without its extension
+    let file = name.rsplit(['/', '\\', ':']).next().unwrap_or(name);
+    let stem = match file.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => file,
+    };
+
+    let stem: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    format!("{}-{}", stem.trim_matches('_'), rom_hash(rom))
+}
+
+fn default_root() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os(DATA_DIR_ENV) {
+        return Some(PathBuf::from(dir));
+    }
+
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+
+    base.map(|base| base.join("chip8"))
+}
+
+fn list_files(dir: &Path, prefix: &Path, files: &mut Vec<(PathBuf, u64)>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let relative = prefix.join(entry.file_name());
+        let metadata = entry.metadata()?;
+
+        if metadata.is_dir() {
+            list_files(&entry.path(), &relative, files)?;
+        } else {
+            files.push((relative, metadata.len()));
+        }
+    }
+
+    Ok(())
+}
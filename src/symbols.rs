@@ -0,0 +1,55 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{anyhow, Context};
+
+/// Names for program addresses, read from a `.sym` file
+///
+/// Each line is a hex address and a name; blank lines and lines starting with
+/// `#` are skipped:
+///
+/// ```text
+/// # main loop and its subroutines
+/// 0200 main
+/// 0x02A4 draw_player
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Symbols {
+    names: BTreeMap<u16, String>,
+}
+
+impl Symbols {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read symbols {}", path.display()))?;
+
+        Self::parse(&text).with_context(|| format!("invalid symbols {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut names = BTreeMap::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (addr, name) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| anyhow!("line {}: expected an address and a name", i + 1))?;
+            let digits = addr.trim_start_matches("0x").trim_start_matches("0X");
+            let addr = u16::from_str_radix(digits, 16)
+                .map_err(|_| anyhow!("line {}: '{}' is not a hex address", i + 1, addr))?;
+
+            names.insert(addr, name.trim().to_string());
+        }
+
+        Ok(Symbols { names })
+    }
+
+    /// Name of `addr`, if it has one
+    pub fn get(&self, addr: u16) -> Option<&str> {
+        self.names.get(&addr).map(String::as_str)
+    }
+}
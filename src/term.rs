@@ -0,0 +1,284 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Stdout, Write},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor,
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{self, ClearType},
+};
+use minifb::Key;
+
+use crate::{
+    frontend::{Beeper, Display, Input},
+    keys,
+};
+
+/// How long a key counts as held after a press when the terminal cannot
+/// report releases
+const KEY_HOLD: Duration = Duration::from_millis(250);
+
+/// Renders the display in the terminal with half-block characters and reads
+/// keys from it
+///
+/// Each character cell shows two pixels: the top one in the foreground color
+/// of `▀` and the bottom one in the background color. Frames larger than the
+/// terminal are downsampled to fit.
+///
+/// Most terminals only report key presses, so a key stays held for
+/// `KEY_HOLD` after its last press or auto-repeat. Terminals supporting the
+/// kitty keyboard protocol also report releases, which are used instead.
+/// Ctrl+C closes the emulator. The buzzer rings the terminal bell.
+///
+/// Puts the terminal in raw mode on an alternate screen until closed or dropped.
+pub struct TermFrontend {
+    out: Stdout,
+    reports_release: bool,
+    /// Held keys and when their hold expires (`None` if waiting for a release)
+    held: HashMap<Key, Option<Instant>>,
+    pressed: HashSet<Key>,
+    frame: Vec<u32>,
+    frame_size: (usize, usize),
+    closing: bool,
+}
+
+impl TermFrontend {
+    pub fn new() -> anyhow::Result<Self> {
+        let mut out = io::stdout();
+
+        terminal::enable_raw_mode()?;
+        execute!(
+            out,
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            terminal::Clear(ClearType::All)
+        )?;
+
+        let reports_release = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if reports_release {
+            execute!(
+                out,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        Ok(TermFrontend {
+            out,
+            reports_release,
+            held: HashMap::new(),
+            pressed: HashSet::new(),
+            frame: Vec::new(),
+            frame_size: (0, 0),
+            closing: false,
+        })
+    }
+
+    fn handle_key(&mut self, event: KeyEvent) {
+        if event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL) {
+            self.closing = true;
+            return;
+        }
+
+        let key = match host_key(event.code) {
+            Some(key) => key,
+            None => return,
+        };
+
+        if event.kind != KeyEventKind::Release {
+            // Terminals report modifiers along with a key rather than as keys
+            // of their own, so hold them briefly for hotkey chords
+            for (flag, modifier) in [
+                (KeyModifiers::CONTROL, Key::LeftCtrl),
+                (KeyModifiers::SHIFT, Key::LeftShift),
+                (KeyModifiers::ALT, Key::LeftAlt),
+            ] {
+                if event.modifiers.contains(flag) {
+                    self.held.insert(modifier, Some(Instant::now() + KEY_HOLD));
+                }
+            }
+        }
+
+        match event.kind {
+            KeyEventKind::Press | KeyEventKind::Repeat => {
+                let expires = if self.reports_release {
+                    None
+                } else {
+                    Some(Instant::now() + KEY_HOLD)
+                };
+
+                if self.held.insert(key, expires).is_none() || event.kind == KeyEventKind::Repeat {
+                    self.pressed.insert(key);
+                }
+            }
+            KeyEventKind::Release => {
+                self.held.remove(&key);
+            }
+        }
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let (width, height) = self.frame_size;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        let (columns, rows) = terminal::size()?;
+        let (columns, rows) = (usize::from(columns).max(1), usize::from(rows).max(1));
+
+        // Integer downsampling keeps pixels evenly sized
+        let step = width.div_ceil(columns).max(height.div_ceil(rows * 2));
+        let (out_width, out_height) = (width / step, height / step);
+
+        let (frame, out) = (&self.frame, &mut self.out);
+
+        let pixel = |x: usize, y: usize| {
+            let px = frame[(y * step) * width + x * step];
+            Color::Rgb {
+                r: (px >> 16) as u8,
+                g: (px >> 8) as u8,
+                b: px as u8,
+            }
+        };
+
+        let mut colors = None;
+
+        for row in 0..out_height.div_ceil(2) {
+            queue!(out, cursor::MoveTo(0, row as u16))?;
+
+            for x in 0..out_width {
+                let top = pixel(x, row * 2);
+                let bottom = if row * 2 + 1 < out_height {
+                    pixel(x, row * 2 + 1)
+                } else {
+                    Color::Reset
+                };
+
+                if colors != Some((top, bottom)) {
+                    colors = Some((top, bottom));
+                    queue!(out, SetForegroundColor(top), SetBackgroundColor(bottom))?;
+                }
+
+                queue!(out, Print('▀'))?;
+            }
+        }
+
+        queue!(out, ResetColor)?;
+        out.flush()
+    }
+}
+
+impl Display for TermFrontend {
+    fn update(&mut self) {
+        let mut resized = false;
+
+        while event::poll(Duration::from_secs(0)).unwrap_or(false) {
+            match event::read() {
+                Ok(Event::Key(key)) => self.handle_key(key),
+                Ok(Event::Resize(_, _)) => resized = true,
+                Ok(_) => {}
+                Err(_) => self.closing = true,
+            }
+        }
+
+        let now = Instant::now();
+        self.held
+            .retain(|_, expires| expires.is_none_or(|expires| now < expires));
+
+        if resized {
+            let _ = queue!(self.out, ResetColor, terminal::Clear(ClearType::All));
+            let _ = self.draw();
+        }
+    }
+
+    fn is_closing(&self) -> bool {
+        self.closing
+    }
+
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) {
+        self.frame.clear();
+        self.frame.extend_from_slice(buffer);
+        self.frame_size = (width, height);
+
+        let _ = self.draw();
+    }
+
+    fn set_title(&mut self, title: String) {
+        let _ = execute!(self.out, terminal::SetTitle(title));
+    }
+
+    fn close(self) {}
+}
+
+impl Drop for TermFrontend {
+    fn drop(&mut self) {
+        if self.reports_release {
+            let _ = execute!(self.out, PopKeyboardEnhancementFlags);
+        }
+
+        let _ = execute!(
+            self.out,
+            ResetColor,
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl Input for TermFrontend {
+    fn get_keys(&self) -> Option<Vec<Key>> {
+        if self.held.is_empty() {
+            None
+        } else {
+            Some(self.held.keys().copied().collect())
+        }
+    }
+
+    fn get_keys_pressed(&mut self) -> Option<HashSet<Key>> {
+        if self.pressed.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.pressed))
+        }
+    }
+}
+
+/// Rings the terminal bell when the buzzer starts
+impl Beeper for TermFrontend {
+    fn set_beeping(&mut self, beeping: bool) {
+        if beeping {
+            let _ = execute!(self.out, Print('\x07'));
+        }
+    }
+}
+
+/// The minifb key for a terminal key, so key maps and config work unchanged
+fn host_key(code: KeyCode) -> Option<Key> {
+    match code {
+        KeyCode::Char(' ') => Some(Key::Space),
+        KeyCode::Char(c) => keys::parse_key(&c.to_string()),
+        KeyCode::F(n) => keys::parse_key(&format!("F{}", n)),
+        KeyCode::Enter => Some(Key::Enter),
+        KeyCode::Esc => Some(Key::Escape),
+        KeyCode::Backspace => Some(Key::Backspace),
+        KeyCode::Tab => Some(Key::Tab),
+        KeyCode::Up => Some(Key::Up),
+        KeyCode::Down => Some(Key::Down),
+        KeyCode::Left => Some(Key::Left),
+        KeyCode::Right => Some(Key::Right),
+        KeyCode::Home => Some(Key::Home),
+        KeyCode::End => Some(Key::End),
+        KeyCode::PageUp => Some(Key::PageUp),
+        KeyCode::PageDown => Some(Key::PageDown),
+        KeyCode::Insert => Some(Key::Insert),
+        KeyCode::Delete => Some(Key::Delete),
+        _ => None,
+    }
+}
@@ -0,0 +1,211 @@
+use std::{fmt::Write, fs, path::Path};
+
+use anyhow::anyhow;
+
+use crate::chip8::{Chip8, Quirks};
+
+/// Instructions per 60 Hz timer tick in `run_rom`, as in headless runs
+const INSTRUCTIONS_PER_TICK: u64 = 16;
+
+/// Assert that a `Chip8` display matches an ASCII-art pattern
+///
+/// See `check_display` for the pattern format. Panics with a side-by-side
+/// diff on mismatch. Use `file = PATH` to load the pattern from a file.
+///
+/// ```ignore
+/// assert_display!(cpu, "
+///     ####
+///     #..#
+/// ");
+/// assert_display!(cpu, file = "tests/expected/logo.txt");
+/// ```
+#[macro_export]
+macro_rules! assert_display {
+    ($chip8:expr, file = $path:expr) => {
+        match $crate::testing::load_pattern($path) {
+            Ok(pattern) => $crate::assert_display!($chip8, &pattern),
+            Err(e) => panic!("{}", e),
+        }
+    };
+    ($chip8:expr, $expected:expr) => {
+        if let Err(diff) = $crate::testing::check_display(&$chip8, $expected) {
+            panic!("display does not match expected pattern\n{}", diff);
+        }
+    };
+}
+
+/// Load `rom` and run it for `cycles` instructions with no input
+///
+/// Timers tick every 16 instructions, as in a headless run, so the result is
+/// the same every time. Stops with an error if the program crashes.
+///
+/// ```
+/// use chip8::{assert_display, chip8::Quirks, testing};
+///
+/// // LD V0, 0; LD F, V0; DRW V0, V0, 5; JP 206
+/// let rom = [0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x06];
+/// let cpu = testing::run_rom(&rom, Quirks::default(), 100)?;
+///
+/// assert_display!(cpu, "
+///     XXXX
+///     X..X
+///     X..X
+///     X..X
+///     XXXX
+/// ");
+///
+/// // The program has halted, so running it longer gives the same display
+/// let longer = testing::run_rom(&rom, Quirks::default(), 200)?;
+/// assert_eq!(cpu.display_hash(), longer.display_hash());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn run_rom(rom: &[u8], quirks: Quirks, cycles: u64) -> anyhow::Result<Chip8> {
+    let mut cpu = Chip8::new();
+    cpu.quirks = quirks;
+    cpu.load_rom(rom)?;
+
+    for cycle in 0..cycles {
+        if cycle > 0 && cycle % INSTRUCTIONS_PER_TICK == 0 {
+            cpu.timer_tick();
+        }
+
+        let pc = cpu.pc;
+        cpu.step()
+            .map_err(|e| anyhow!("{} at {:04X} after {} instructions", e, pc, cycle))?;
+    }
+
+    Ok(cpu)
+}
+
+/// Parse an ASCII-art pattern into rows of pixels
+///
+/// `#`, `X`, `x`, `1` and `█` are lit pixels; `.`, `-` and `0` are unlit.
+/// Whitespace around each row and blank lines before and after the pattern
+/// are ignored, so patterns can be indented inside raw strings.
+pub fn parse_pattern(pattern: &str) -> anyhow::Result<Vec<Vec<bool>>> {
+    let mut rows: Vec<&str> = pattern.lines().map(str::trim).collect();
+
+    while rows.first().is_some_and(|row| row.is_empty()) {
+        rows.remove(0);
+    }
+    while rows.last().is_some_and(|row| row.is_empty()) {
+        rows.pop();
+    }
+
+    rows.iter()
+        .enumerate()
+        .map(|(y, row)| {
+            row.chars()
+                .enumerate()
+                .map(|(x, c)| match c {
+                    '#' | 'X' | 'x' | '1' | '█' => Ok(true),
+                    '.' | '-' | '0' => Ok(false),
+                    _ => Err(anyhow!(
+                        "invalid character '{}' at row {}, column {} of display pattern",
+                        c,
+                        y,
+                        x
+                    )),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Load an ASCII-art display pattern from a file
+pub fn load_pattern<P: AsRef<Path>>(path: P) -> anyhow::Result<String> {
+    let path = path.as_ref();
+    fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read display pattern {}: {}", path.display(), e))
+}
+
+/// Compare the display against an ASCII-art pattern
+///
+/// The pattern is anchored at the top-left corner. Pixels outside of it must
+/// be unlit, so a small pattern can check a single sprite. On mismatch,
+/// returns a readable diff of expected and actual rows, marking pixels that
+/// are unexpectedly lit with `+` and missing with `-`.
+pub fn check_display(cpu: &Chip8, expected: &str) -> Result<(), String> {
+    let pattern = parse_pattern(expected).map_err(|e| e.to_string())?;
+
+    let (width, height) = (cpu.display_width(), cpu.display_height());
+
+    if pattern.len() > height || pattern.iter().any(|row| row.len() > width) {
+        return Err(format!(
+            "display pattern is larger than the {}x{} display",
+            width, height
+        ));
+    }
+
+    let actual = |x: usize, y: usize| cpu.display[y * width + x] != 0;
+    let expected = |x: usize, y: usize| {
+        pattern
+            .get(y)
+            .and_then(|row| row.get(x))
+            .copied()
+            .unwrap_or(false)
+    };
+
+    let mismatches: Vec<(usize, usize)> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| actual(x, y) != expected(x, y))
+        .collect();
+
+    let (first_x, first_y) = match mismatches.first() {
+        Some(first) => *first,
+        None => return Ok(()),
+    };
+
+    // Show the smallest region covering the pattern and all lit pixels
+    let lit = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| actual(x, y));
+    let (mut columns, mut rows) = (
+        pattern.iter().map(Vec::len).max().unwrap_or(0),
+        pattern.len(),
+    );
+    for (x, y) in lit {
+        columns = columns.max(x + 1);
+        rows = rows.max(y + 1);
+    }
+
+    let render = |y: usize, px: &dyn Fn(usize, usize) -> char| -> String {
+        (0..columns).map(|x| px(x, y)).collect()
+    };
+    let ascii = |on: bool| if on { '#' } else { '.' };
+    let w = columns.max("expected".len());
+
+    let mut out = String::new();
+
+    // Writing to a String cannot fail
+    let _ = writeln!(
+        out,
+        "{} pixel(s) differ, first at ({}, {})",
+        mismatches.len(),
+        first_x,
+        first_y
+    );
+    let _ = writeln!(out, "    {:<w$}  {:<w$}  diff", "expected", "actual", w = w);
+
+    for y in 0..rows {
+        let diff = render(y, &|x, y| match (expected(x, y), actual(x, y)) {
+            (false, true) => '+',
+            (true, false) => '-',
+            _ => '.',
+        });
+        let marker = if diff.contains(['+', '-']) { '<' } else { ' ' };
+
+        let _ = writeln!(
+            out,
+            "{:>2}  {:<w$}  {:<w$}  {} {}",
+            y,
+            render(y, &|x, y| ascii(expected(x, y))),
+            render(y, &|x, y| ascii(actual(x, y))),
+            diff,
+            marker,
+            w = w
+        );
+    }
+
+    Err(out)
+}
@@ -0,0 +1,217 @@
+use std::{collections::VecDeque, fmt, str::FromStr};
+
+use anyhow::anyhow;
+
+use crate::instruction::{decode, Instruction};
+
+/// Which steps a per-step subscriber runs on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rate {
+    /// Every step
+    #[default]
+    All,
+    /// Every Nth step
+    Every(u32),
+    /// Steps that clear or draw the display, read keys, or set a timer
+    Events,
+}
+
+impl FromStr for Rate {
+    type Err = anyhow::Error;
+
+    /// `all`, `events`, or a step count N
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "all" | "1" => Ok(Rate::All),
+            "events" => Ok(Rate::Events),
+            n => match n.parse::<u32>() {
+                Ok(0) | Err(_) => Err(anyhow!(
+                    "expected all, events, or a step count of 1 or more, got '{}'",
+                    s
+                )),
+                Ok(n) => Ok(Rate::Every(n)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rate::All => write!(f, "all"),
+            Rate::Every(n) => write!(f, "{}", n),
+            Rate::Events => write!(f, "events"),
+        }
+    }
+}
+
+/// Decides which steps a subscriber sees, so costly instrumentation can stay
+/// on at full speed
+#[derive(Debug, Clone, Default)]
+pub struct Throttle {
+    pub rate: Rate,
+    count: u32,
+}
+
+impl Throttle {
+    pub fn new(rate: Rate) -> Self {
+        Throttle { rate, count: 0 }
+    }
+
+    /// Whether to run for the step about to execute `opcode`
+    pub fn sample(&mut self, opcode: u16) -> bool {
+        match self.rate {
+            Rate::All => true,
+            Rate::Every(n) => {
+                self.count += 1;
+
+                if self.count >= n {
+                    self.count = 0;
+                    true
+                } else {
+                    false
+                }
+            }
+            Rate::Events => is_event(opcode),
+        }
+    }
+}
+
+/// Whether `opcode` interacts with the display, keypad, or timers
+pub fn is_event(opcode: u16) -> bool {
+    matches!(
+        decode(opcode),
+        Instruction::Cls
+            | Instruction::Drw(..)
+            | Instruction::Skp(_)
+            | Instruction::Sknp(_)
+            | Instruction::LdVxK(_)
+            | Instruction::LdDtVx(_)
+            | Instruction::LdStVx(_)
+    )
+}
+
+/// Per-step instrumentation that can be throttled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subscriber {
+    /// `--verbose` instruction trace
+    Trace,
+    /// Trigger conditions
+    Triggers,
+    /// Practice mode loss condition
+    Practice,
+}
+
+impl Subscriber {
+    pub const ALL: [Subscriber; 3] = [
+        Subscriber::Trace,
+        Subscriber::Triggers,
+        Subscriber::Practice,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Subscriber::Trace => "trace",
+            Subscriber::Triggers => "triggers",
+            Subscriber::Practice => "practice",
+        }
+    }
+}
+
+/// Throttle for each subscriber, all running every step by default
+#[derive(Debug, Clone, Default)]
+pub struct Throttles {
+    pub trace: Throttle,
+    pub triggers: Throttle,
+    pub practice: Throttle,
+}
+
+impl Throttles {
+    pub fn set(&mut self, spec: SampleSpec) {
+        let throttle = match spec.subscriber {
+            Subscriber::Trace => &mut self.trace,
+            Subscriber::Triggers => &mut self.triggers,
+            Subscriber::Practice => &mut self.practice,
+        };
+
+        *throttle = Throttle::new(spec.rate);
+    }
+}
+
+/// A `SUBSCRIBER=RATE` pair, e.g. `trace=1000` or `triggers=events`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleSpec {
+    pub subscriber: Subscriber,
+    pub rate: Rate,
+}
+
+impl FromStr for SampleSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, rate) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected SUBSCRIBER=RATE, got '{}'", s))?;
+
+        let subscriber = Subscriber::ALL
+            .iter()
+            .copied()
+            .find(|sub| sub.name().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| {
+                let names: Vec<&str> = Subscriber::ALL.iter().map(|sub| sub.name()).collect();
+                anyhow!(
+                    "unknown subscriber '{}', expected one of: {}",
+                    name,
+                    names.join(", ")
+                )
+            })?;
+
+        Ok(SampleSpec {
+            subscriber,
+            rate: rate.parse()?,
+        })
+    }
+}
+
+/// The most recent lines of output, held back and written together
+///
+/// Keeps a trace cheap during normal play: nothing is printed until
+/// execution stops, and then only the lines leading up to it.
+#[derive(Debug, Clone)]
+pub struct RingBuffer {
+    lines: VecDeque<String>,
+    capacity: usize,
+    /// Lines pushed out by newer ones since the last flush
+    dropped: u64,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        RingBuffer {
+            lines: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, line: String) {
+        if self.lines.len() >= self.capacity {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+
+        self.lines.push_back(line);
+    }
+
+    /// Print the held lines to stdout, oldest first, and empty the buffer
+    pub fn flush(&mut self) {
+        if self.dropped > 0 {
+            println!("... {} earlier lines dropped", self.dropped);
+            self.dropped = 0;
+        }
+
+        for line in self.lines.drain(..) {
+            println!("{}", line);
+        }
+    }
+}
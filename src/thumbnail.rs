@@ -0,0 +1,163 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use crate::{
+    chip8::Chip8,
+    emu::DEFAULT_INSTRUCTIONS_PER_TICK,
+    rom::{self, FileRom},
+    storage::{self, Storage},
+};
+
+/// Steps to run before capturing: ~2 s at the default 1 kHz clock
+pub const THUMBNAIL_STEPS: u64 = 2000;
+
+/// File extensions treated as ROMs when scanning a directory
+pub const ROM_EXTENSIONS: [&str; 3] = ["ch8", "c8", "rom"];
+
+/// Captured display of a ROM after running it headless for a short time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<bool>,
+}
+
+impl Thumbnail {
+    /// Run `rom` headless for `THUMBNAIL_STEPS` steps and capture the display
+    ///
+    /// Execution stops early on a CPU error, keeping whatever was drawn so far.
+    pub fn render(rom: &[u8]) -> anyhow::Result<Self> {
+        let mut cpu = Chip8::new();
+        cpu.load_rom(rom)?;
+
+        for step in 0..THUMBNAIL_STEPS {
+            if step % u64::from(DEFAULT_INSTRUCTIONS_PER_TICK) == 0 {
+                cpu.timer_tick();
+            }
+
+            if cpu.step().is_err() {
+                break;
+            }
+        }
+
+        Ok(Thumbnail {
+            width: cpu.display_width(),
+            height: cpu.display_height(),
+            pixels: cpu.display.iter().map(|&px| px != 0).collect(),
+        })
+    }
+
+    /// Load the thumbnail for `rom_path` from the storage cache, rendering and caching it if needed
+    pub fn cached(rom_path: &Path, storage: &Storage) -> anyhow::Result<Self> {
+        let rom = rom::load(&FileRom(rom_path.to_path_buf()))?;
+        let cache_path = storage
+            .thumbnail_dir()
+            .join(format!("{:016x}.thumb", storage::fnv1a(&rom)));
+
+        if let Ok(data) = fs::read(&cache_path) {
+            if let Some(thumbnail) = Self::from_bytes(&data) {
+                return Ok(thumbnail);
+            }
+        }
+
+        let thumbnail = Self::render(&rom)?;
+
+        storage::create_parent(&cache_path)?;
+        fs::write(&cache_path, thumbnail.to_bytes())?;
+
+        Ok(thumbnail)
+    }
+
+    /// Render as text, two pixels per character cell, downscaled by 2 horizontally
+    pub fn to_text(&self) -> Vec<String> {
+        let px = |x: usize, y: usize| {
+            y < self.height && (x..x + 2).any(|x| x < self.width && self.pixels[y * self.width + x])
+        };
+
+        (0..self.height)
+            .step_by(2)
+            .map(|y| {
+                (0..self.width)
+                    .step_by(2)
+                    .map(|x| match (px(x, y), px(x, y + 1)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![self.width as u8, self.height as u8];
+        out.extend(self.pixels.chunks(8).map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |acc, (i, px)| acc | ((*px as u8) << (7 - i)))
+        }));
+        out
+    }
+
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        let (width, height) = (usize::from(*data.first()?), usize::from(*data.get(1)?));
+        let packed = data.get(2..)?;
+
+        if packed.len() * 8 < width * height {
+            return None;
+        }
+
+        let pixels = (0..width * height)
+            .map(|i| (packed[i / 8] >> (7 - i % 8)) & 1 == 1)
+            .collect();
+
+        Some(Thumbnail {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+/// List ROM files in `dir`, sorted by name
+pub fn list_roms(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut roms: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ROM_EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+                .unwrap_or(false)
+        })
+        .collect();
+
+    roms.sort();
+
+    Ok(roms)
+}
+
+/// Generate thumbnails for `roms` on a background thread, sending each as it completes
+pub fn spawn_scan(
+    roms: Vec<PathBuf>,
+    storage: Storage,
+) -> Receiver<(PathBuf, anyhow::Result<Thumbnail>)> {
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        for path in roms {
+            let thumbnail = Thumbnail::cached(&path, &storage);
+
+            if tx.send((path, thumbnail)).is_err() {
+                break;
+            }
+        }
+    });
+
+    rx
+}
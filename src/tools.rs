@@ -0,0 +1,481 @@
+use std::{fs, path::Path};
+
+use anyhow::anyhow;
+
+use crate::{
+    asm, bench, bisect,
+    chip8::Quirks,
+    frames::{self, Difference},
+    locale::Locale,
+    metadata::Metadata,
+    movie::Movie,
+    octo, png,
+    rom::{self, RomSource},
+    romdb::{self, RomDb},
+    savestate,
+    storage::{self, Storage},
+    testing, thumbnail,
+};
+
+/// Assemble `input` into `out`, or the same name with a .ch8 extension
+///
+/// Octo syntax is read if `octo_syntax` is set or `input` is a .8o file.
+pub fn assemble(
+    input: &Path,
+    octo_syntax: bool,
+    out: Option<&Path>,
+    locale: &Locale,
+) -> anyhow::Result<()> {
+    let source = fs::read_to_string(input)
+        .map_err(|e| anyhow!("failed to read {}: {}", input.display(), e))?;
+
+    let octo_syntax = octo_syntax || input.extension().is_some_and(|ext| ext == "8o");
+    let assembled = if octo_syntax {
+        octo::assemble(&source)
+    } else {
+        asm::assemble(&source)
+    };
+
+    let rom = match assembled {
+        Ok(rom) => rom,
+        Err(errors) => {
+            for e in errors.iter() {
+                eprintln!("{}:{}", input.display(), e);
+            }
+
+            return Err(anyhow!(locale.format(
+                "tools.assembly_errors",
+                &[("count", &errors.len()), ("path", &input.display())]
+            )));
+        }
+    };
+
+    let out = out
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| input.with_extension("ch8"));
+
+    fs::write(&out, &rom)?;
+    println!(
+        "{}",
+        locale.format(
+            "tools.wrote_rom",
+            &[("path", &out.display()), ("size", &rom.len())]
+        )
+    );
+
+    Ok(())
+}
+
+/// Print the size, hashes, data folder, metadata, and ROM database entry of
+/// the ROM from `source`
+pub fn info(
+    source: &dyn RomSource,
+    storage: &Storage,
+    romdb: &RomDb,
+    locale: &Locale,
+) -> anyhow::Result<()> {
+    let (program, metadata) = rom::load_with_metadata(source)?;
+
+    let range = format!("200-{:03X}", 0x200 + program.len() - 1);
+    let data = storage.rom(&source.name(), &program).dir;
+
+    let lines = [
+        locale.format("tools.info_rom", &[("rom", &source.name())]),
+        locale.format(
+            "tools.info_size",
+            &[("size", &program.len()), ("range", &range)],
+        ),
+        locale.format("tools.info_data", &[("path", &data.display())]),
+        locale.format("tools.info_hash", &[("hash", &storage::rom_hash(&program))]),
+        locale.format("tools.info_sha1", &[("hash", &romdb::sha1_hex(&program))]),
+    ];
+
+    for line in lines.iter() {
+        println!("{}", line);
+    }
+
+    match metadata {
+        Some(metadata) => {
+            println!("{}", locale.get("tools.info_metadata"));
+            for line in metadata.to_string().lines() {
+                println!("  {}", line);
+            }
+        }
+        None => println!("{}", locale.get("tools.no_metadata")),
+    }
+
+    match romdb.get(&program) {
+        Some(known) => {
+            println!("{}", locale.get("tools.info_romdb"));
+            for line in known.to_string().lines() {
+                println!("  {}", line);
+            }
+        }
+        None => println!("{}", locale.get("tools.not_in_romdb")),
+    }
+
+    Ok(())
+}
+
+/// Set the metadata `fields` of the ROM from `source`, given as (key, value)
+/// pairs, dropping its existing metadata first if `clear`
+///
+/// The ROM is written to `out`, or back to its file.
+pub fn tag(
+    source: &dyn RomSource,
+    fields: &[(&str, &Option<String>)],
+    clear: bool,
+    out: Option<&Path>,
+    locale: &Locale,
+) -> anyhow::Result<()> {
+    let out = out
+        .map(Path::to_path_buf)
+        .or_else(|| source.local_path())
+        .ok_or_else(|| anyhow!(locale.format("tools.tag_needs_out", &[("rom", &source.name())])))?;
+
+    let (program, existing) = rom::load_with_metadata(source)?;
+
+    let mut metadata = if clear {
+        Metadata::default()
+    } else {
+        existing.unwrap_or_default()
+    };
+
+    for (key, value) in fields.iter() {
+        if let Some(value) = value {
+            metadata.set(key, value)?;
+        }
+    }
+
+    let tagged = if metadata.is_empty() {
+        program.to_vec()
+    } else {
+        metadata.append_to(&program)?
+    };
+
+    fs::write(&out, &tagged)?;
+
+    if metadata.is_empty() {
+        println!(
+            "{}",
+            locale.format("tools.wrote_untagged", &[("path", &out.display())])
+        );
+    } else {
+        println!(
+            "{}",
+            locale.format("tools.wrote_tagged", &[("path", &out.display())])
+        );
+        print!("{}", metadata);
+    }
+
+    Ok(())
+}
+
+/// Run the ROM from `source` for `cycles` instructions and print its display,
+/// failing if its hash is not `expect` (16 hex digits), when given
+pub fn test(
+    source: &dyn RomSource,
+    expect: Option<&str>,
+    cycles: u64,
+    locale: &Locale,
+) -> anyhow::Result<()> {
+    let expect = expect
+        .map(|hash| {
+            u64::from_str_radix(hash.trim(), 16)
+                .map_err(|_| anyhow!(locale.format("tools.bad_hash", &[("hash", &hash)])))
+        })
+        .transpose()?;
+
+    let (program, metadata) = rom::load_with_metadata(source)?;
+    let quirks = metadata.and_then(|m| m.quirks).unwrap_or_default();
+
+    let cpu = testing::run_rom(&program, quirks, cycles)?;
+    let hash = cpu.display_hash();
+
+    match expect {
+        Some(expected) if expected == hash => {
+            println!(
+                "{}",
+                locale.format(
+                    "tools.test_ok",
+                    &[("rom", &source.name()), ("cycles", &cycles)]
+                )
+            );
+            Ok(())
+        }
+        Some(expected) => {
+            println!("{}", cpu.display_to_ascii());
+            Err(anyhow!(locale.format(
+                "tools.hash_mismatch",
+                &[
+                    ("hash", &format!("{:016x}", hash)),
+                    ("expected", &format!("{:016x}", expected))
+                ]
+            )))
+        }
+        None => {
+            println!("{}", cpu.display_to_ascii());
+            println!(
+                "{}",
+                locale.format("tools.display_hash", &[("hash", &format!("{:016x}", hash))])
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Time `iterations` of the built-in workloads if `suite` is set, and of the
+/// ROM from `source`, if given
+pub fn bench(
+    suite: bool,
+    iterations: u64,
+    source: Option<&dyn RomSource>,
+    locale: &Locale,
+) -> anyhow::Result<()> {
+    if !suite && source.is_none() {
+        return Err(anyhow!(locale.get("tools.bench_needs_rom").to_string()));
+    }
+
+    if suite {
+        for workload in bench::SUITE.iter() {
+            let elapsed = workload.run(iterations)?;
+            bench::report(workload.name(), iterations, elapsed);
+        }
+    }
+
+    if let Some(source) = source {
+        let rom = rom::load(source)?;
+        let elapsed = bench::run_rom(&rom, iterations)?;
+        bench::report(&source.name(), iterations, elapsed);
+    }
+
+    Ok(())
+}
+
+/// Print a thumbnail of every ROM in `dir`
+pub fn browse(dir: &Path, storage: Storage, locale: &Locale) -> anyhow::Result<()> {
+    let roms = thumbnail::list_roms(dir)?;
+
+    if roms.is_empty() {
+        println!(
+            "{}",
+            locale.format("tools.no_roms", &[("dir", &dir.display())])
+        );
+    }
+
+    for (path, thumbnail) in thumbnail::spawn_scan(roms, storage) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        match thumbnail {
+            Ok(thumbnail) => {
+                println!("{}", name);
+                for line in thumbnail.to_text() {
+                    println!("  |{}|", line);
+                }
+            }
+            Err(e) => println!(
+                "{}",
+                locale.format(
+                    "tools.thumbnail_error",
+                    &[("name", &name), ("message", &format!("{:#}", e))]
+                )
+            ),
+        }
+
+        println!();
+    }
+
+    Ok(())
+}
+
+/// Print the ROM folders in `storage` and the files in each
+pub fn storage_ls(storage: &Storage, locale: &Locale) -> anyhow::Result<()> {
+    let entries = storage.list()?;
+
+    if entries.is_empty() {
+        println!(
+            "{}",
+            locale.format("tools.nothing_stored", &[("dir", &storage.root.display())])
+        );
+    }
+
+    for entry in entries.iter() {
+        let name = entry.dir.file_name().unwrap_or_default().to_string_lossy();
+        println!(
+            "{}",
+            locale.format(
+                "tools.stored_rom",
+                &[("name", &name), ("size", &entry.size())]
+            )
+        );
+
+        for (path, size) in entry.files.iter() {
+            println!("  {:<32} {:>8}", path.display(), size);
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove every ROM folder and cache in `storage` if `all`, else the folder
+/// of the ROM from `rom`, else the caches and empty ROM folders
+pub fn storage_clean(
+    storage: &Storage,
+    rom: Option<&dyn RomSource>,
+    all: bool,
+    locale: &Locale,
+) -> anyhow::Result<()> {
+    if all {
+        storage.remove_all()?;
+        println!(
+            "{}",
+            locale.format("tools.removed_all", &[("dir", &storage.root.display())])
+        );
+    } else if let Some(source) = rom {
+        let rom_storage = storage.rom(&source.name(), &rom::load(source)?);
+
+        rom_storage.remove()?;
+        println!(
+            "{}",
+            locale.format("tools.removed", &[("path", &rom_storage.dir.display())])
+        );
+    } else {
+        for path in storage.clean()? {
+            println!(
+                "{}",
+                locale.format("tools.removed", &[("path", &path.display())])
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Compare the frame dumps of two runs, failing at the first difference and
+/// writing the pixels that differ to `out` as a PNG image
+pub fn compare_frames(
+    first: &Path,
+    second: &Path,
+    out: &Path,
+    locale: &Locale,
+) -> anyhow::Result<()> {
+    let comparison = frames::compare(first, second)?;
+
+    let message = match comparison.difference {
+        None => {
+            println!(
+                "{}",
+                locale.format("tools.frames_match", &[("count", &comparison.matching)])
+            );
+            return Ok(());
+        }
+        Some(Difference::Missing { name, in_first }) => {
+            let (has, lacks) = if in_first {
+                (first, second)
+            } else {
+                (second, first)
+            };
+
+            locale.format(
+                "tools.frame_missing",
+                &[
+                    ("name", &name),
+                    ("has", &has.display()),
+                    ("lacks", &lacks.display()),
+                ],
+            )
+        }
+        Some(Difference::Size {
+            name,
+            first: (w1, h1),
+            second: (w2, h2),
+        }) => locale.format(
+            "tools.frame_size",
+            &[
+                ("name", &name),
+                ("first", &format!("{}x{}", w1, h1)),
+                ("second", &format!("{}x{}", w2, h2)),
+            ],
+        ),
+        Some(Difference::Pixels {
+            name,
+            count,
+            xor,
+            width,
+            height,
+        }) => {
+            png::save(out, &xor, width, height)?;
+            locale.format(
+                "tools.frame_pixels",
+                &[("name", &name), ("count", &count), ("path", &out.display())],
+            )
+        }
+    };
+
+    println!(
+        "{}",
+        locale.format(
+            "tools.frames_match_before",
+            &[("count", &comparison.matching)]
+        )
+    );
+    Err(anyhow!(message))
+}
+
+/// Cut the recorded crash in `movie` of the ROM from `source` down to a save
+/// state and the input after it, written next to `out`, or to
+/// `<movie>.repro.c8s` and `<movie>.repro.c8m`
+pub fn bisect(
+    source: &dyn RomSource,
+    quirks: Quirks,
+    movie: &Path,
+    out: Option<&Path>,
+    locale: &Locale,
+) -> anyhow::Result<()> {
+    let program_rom = rom::load(source)?;
+    let movie_data = Movie::load(movie)?;
+
+    let repro = bisect::bisect(&program_rom, quirks, &movie_data)?;
+
+    println!(
+        "{}",
+        locale.format(
+            "tools.crash",
+            &[
+                ("cycle", &repro.crash.step),
+                ("message", &repro.crash.error),
+                ("pc", &format!("{:04X}", repro.crash.pc)),
+            ]
+        )
+    );
+    println!(
+        "{}",
+        locale.format(
+            "tools.input_ends",
+            &[("cycle", &repro.snapshot_step), ("replays", &repro.replays)]
+        )
+    );
+
+    let out = out
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| movie.with_extension("repro.c8m"));
+    let state_path = out.with_extension("c8s");
+    let movie_path = out.with_extension("c8m");
+
+    savestate::save(&state_path, &repro.snapshot)?;
+    repro.inputs.save(&movie_path)?;
+
+    println!(
+        "{}",
+        locale.format(
+            "tools.wrote_repro",
+            &[
+                ("state", &state_path.display()),
+                ("movie", &movie_path.display()),
+                ("count", &repro.inputs.inputs.len()),
+            ]
+        )
+    );
+
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use std::{fs, path::Path, str::FromStr};
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+
+use crate::{chip8::Chip8, practice::LossCondition};
+
+/// Memory and pixel tests combined with `and` and `or`
+///
+/// Tests use the same syntax as practice conditions (`mem:2F0=3`,
+/// `pixel:10,5=1`) and contain no spaces. `and` binds tighter than `or`, and
+/// parentheses group:
+///
+/// ```text
+/// mem:2F0=3 and (mem:2F1>0 or pixel:10,5=1)
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    Test(LossCondition),
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+}
+
+impl Condition {
+    pub fn is_met(&self, cpu: &Chip8) -> bool {
+        match self {
+            Condition::Test(test) => test.is_met(cpu),
+            Condition::And(all) => all.iter().all(|c| c.is_met(cpu)),
+            Condition::Or(any) => any.iter().any(|c| c.is_met(cpu)),
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spaced = s.replace('(', " ( ").replace(')', " ) ");
+        let tokens: Vec<&str> = spaced.split_whitespace().collect();
+
+        let mut pos = 0;
+        let condition = parse_or(&tokens, &mut pos)?;
+
+        match tokens.get(pos) {
+            None => Ok(condition),
+            Some(token) => Err(anyhow!("unexpected '{}' in condition '{}'", token, s)),
+        }
+    }
+}
+
+fn parse_or(tokens: &[&str], pos: &mut usize) -> anyhow::Result<Condition> {
+    let mut terms = vec![parse_and(tokens, pos)?];
+
+    while tokens
+        .get(*pos)
+        .is_some_and(|t| t.eq_ignore_ascii_case("or"))
+    {
+        *pos += 1;
+        terms.push(parse_and(tokens, pos)?);
+    }
+
+    Ok(if terms.len() == 1 {
+        terms.remove(0)
+    } else {
+        Condition::Or(terms)
+    })
+}
+
+fn parse_and(tokens: &[&str], pos: &mut usize) -> anyhow::Result<Condition> {
+    let mut terms = vec![parse_term(tokens, pos)?];
+
+    while tokens
+        .get(*pos)
+        .is_some_and(|t| t.eq_ignore_ascii_case("and"))
+    {
+        *pos += 1;
+        terms.push(parse_term(tokens, pos)?);
+    }
+
+    Ok(if terms.len() == 1 {
+        terms.remove(0)
+    } else {
+        Condition::And(terms)
+    })
+}
+
+fn parse_term(tokens: &[&str], pos: &mut usize) -> anyhow::Result<Condition> {
+    let token = *tokens
+        .get(*pos)
+        .ok_or_else(|| anyhow!("condition ends early"))?;
+    *pos += 1;
+
+    if token == "(" {
+        let inner = parse_or(tokens, pos)?;
+
+        if tokens.get(*pos) != Some(&")") {
+            return Err(anyhow!("missing ')'"));
+        }
+        *pos += 1;
+
+        Ok(inner)
+    } else {
+        Ok(Condition::Test(token.parse()?))
+    }
+}
+
+/// Condition that fires a message when it becomes true
+#[derive(Debug, Clone)]
+pub struct Trigger {
+    pub name: String,
+    pub condition: Condition,
+    /// Shown when the trigger fires; defaults to the name
+    pub message: String,
+    /// Fire only the first time, like an achievement
+    pub once: bool,
+    /// Stop the emulator when fired
+    pub quit: bool,
+    /// Number of times fired
+    pub fired: u32,
+    met: bool,
+}
+
+/// Trigger that fired during `Triggers::check`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fired {
+    pub name: String,
+    pub message: String,
+    pub quit: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerFile {
+    #[serde(default)]
+    trigger: Vec<TriggerEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerEntry {
+    name: String,
+    when: String,
+    message: Option<String>,
+    #[serde(default)]
+    once: bool,
+    #[serde(default)]
+    quit: bool,
+}
+
+/// Per-ROM set of triggers, for achievements, tutorial prompts, or checking
+/// that a game reached some state
+///
+/// Loaded from TOML, conventionally `<rom>.triggers.toml` next to the ROM:
+///
+/// ```toml
+/// [[trigger]]
+/// name = "Level 2"
+/// when = "mem:2F0=2 and mem:2F1>0"
+/// message = "Reached level 2"
+/// once = true
+/// ```
+///
+/// Triggers are edge-triggered: one fires when its condition goes from false
+/// to true, and must become false again before firing again.
+#[derive(Debug, Clone, Default)]
+pub struct Triggers {
+    pub triggers: Vec<Trigger>,
+}
+
+impl Triggers {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read triggers {}", path.display()))?;
+
+        Self::parse(&text).with_context(|| format!("invalid triggers in {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let file: TriggerFile = toml::from_str(text)?;
+
+        let triggers = file
+            .trigger
+            .into_iter()
+            .map(|entry| {
+                let condition = entry
+                    .when
+                    .parse()
+                    .with_context(|| format!("trigger '{}'", entry.name))?;
+
+                let name = entry.name;
+
+                Ok(Trigger {
+                    message: entry.message.unwrap_or_else(|| name.clone()),
+                    name,
+                    condition,
+                    once: entry.once,
+                    quit: entry.quit,
+                    fired: 0,
+                    met: false,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Triggers { triggers })
+    }
+
+    /// Evaluate every trigger against `cpu`, returning those that fired
+    pub fn check(&mut self, cpu: &Chip8) -> Vec<Fired> {
+        let mut fired = Vec::new();
+
+        for trigger in self.triggers.iter_mut() {
+            if trigger.once && trigger.fired > 0 {
+                continue;
+            }
+
+            let met = trigger.condition.is_met(cpu);
+
+            if met && !trigger.met {
+                trigger.fired += 1;
+                fired.push(Fired {
+                    name: trigger.name.clone(),
+                    message: trigger.message.clone(),
+                    quit: trigger.quit,
+                });
+            }
+
+            trigger.met = met;
+        }
+
+        fired
+    }
+
+    /// Forget condition states, e.g. after a reset, keeping fire counts
+    pub fn rearm(&mut self) {
+        for trigger in self.triggers.iter_mut() {
+            trigger.met = false;
+        }
+    }
+}
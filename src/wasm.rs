@@ -0,0 +1,126 @@
+use alloc::{string::ToString, vec::Vec};
+
+use wasm_bindgen::prelude::*;
+
+use crate::chip8::{plane_color, Chip8};
+
+const DEFAULT_COLOR_ON: u32 = 0xff_ffff;
+const DEFAULT_COLOR_OFF: u32 = 0;
+
+/// Interpreter handle for JavaScript, driven from a `requestAnimationFrame` loop
+///
+/// The page calls `step` and `timer_tick` at its own pace, forwards key
+/// events with `key_down`/`key_up`, and copies `framebuffer` (RGBA, one byte
+/// per channel) into a canvas `ImageData`.
+#[wasm_bindgen]
+pub struct WasmChip8 {
+    cpu: Chip8,
+    rom: Vec<u8>,
+    /// Keypad keys currently held, applied before every instruction
+    held: [bool; 0x10],
+    color_on: u32,
+    color_off: u32,
+}
+
+impl Default for WasmChip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl WasmChip8 {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        WasmChip8 {
+            cpu: Chip8::new(),
+            rom: Vec::new(),
+            held: [false; 0x10],
+            color_on: DEFAULT_COLOR_ON,
+            color_off: DEFAULT_COLOR_OFF,
+        }
+    }
+
+    /// Reset the machine and load `rom` at 0x200
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), JsValue> {
+        self.rom = rom.to_vec();
+        self.reset()
+    }
+
+    pub fn reset(&mut self) -> Result<(), JsValue> {
+        self.cpu.reset();
+        self.cpu
+            .load_rom(&self.rom)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Run up to `count` instructions, failing with a message on a CPU error
+    pub fn step(&mut self, count: u32) -> Result<(), JsValue> {
+        for _ in 0..count {
+            for (key, _) in self.held.iter().enumerate().filter(|(_, held)| **held) {
+                self.cpu.set_key(key as u8);
+            }
+
+            self.cpu
+                .step()
+                .map_err(|e| JsValue::from_str(&alloc::format!("{} at {:04X}", e, self.cpu.pc)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Decrement the delay and sound timers; call at 60 Hz
+    pub fn timer_tick(&mut self) {
+        self.cpu.timer_tick();
+    }
+
+    pub fn key_down(&mut self, key: u8) {
+        self.held[usize::from(key & 0xf)] = true;
+    }
+
+    pub fn key_up(&mut self, key: u8) {
+        self.held[usize::from(key & 0xf)] = false;
+    }
+
+    /// Whether the buzzer should sound
+    pub fn beeping(&self) -> bool {
+        self.cpu.st > 0
+    }
+
+    pub fn width(&self) -> usize {
+        self.cpu.display_width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.cpu.display_height()
+    }
+
+    /// Set the off and on colors as 0xRRGGBB
+    pub fn set_colors(&mut self, off: u32, on: u32) {
+        self.color_off = off;
+        self.color_on = on;
+        self.cpu.redraw();
+    }
+
+    /// Whether the display changed since the last call
+    pub fn take_dirty(&mut self) -> bool {
+        core::mem::take(&mut self.cpu.display_dirty)
+    }
+
+    /// Rows of the display changed since the last call, bit n set for row n
+    pub fn take_changed_rows(&mut self) -> u64 {
+        self.cpu.take_changed_rows()
+    }
+
+    /// Display as RGBA bytes, row by row
+    pub fn framebuffer(&self) -> Vec<u8> {
+        self.cpu
+            .display
+            .iter()
+            .flat_map(|px| {
+                let color = plane_color(*px, (self.color_off, self.color_on));
+                [(color >> 16) as u8, (color >> 8) as u8, color as u8, 0xff]
+            })
+            .collect()
+    }
+}
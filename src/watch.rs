@@ -0,0 +1,220 @@
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::{convert::TryFrom, str::FromStr};
+
+#[cfg(feature = "std")]
+use anyhow::anyhow;
+
+/// Watched pages are 256 bytes
+const PAGE_SHIFT: u32 = 8;
+
+/// End of the 16-bit address space, the furthest a watchpoint can reach
+const ADDR_END: u32 = 0x10000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+impl Access {
+    pub const ALL: [Access; 3] = [Access::Read, Access::Write, Access::Execute];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+
+    fn letter(self) -> char {
+        match self {
+            Access::Read => 'r',
+            Access::Write => 'w',
+            Access::Execute => 'x',
+        }
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::Execute => "execute",
+        })
+    }
+}
+
+/// Break on memory accesses of the given kinds within `start..end`
+///
+/// Parsed from `ADDR[..END][:rwx]` (hex, end exclusive), e.g. `200..300:w`
+/// or `2F0:rw`. Without an access filter, reads and writes are watched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    start: u16,
+    /// Exclusive, so up to 10000 to take in the last byte of 64K
+    end: u32,
+    /// Bit set of `Access` kinds
+    access: u8,
+}
+
+impl Watchpoint {
+    /// Watch `start..end` for `access`, or `None` if the range is empty or
+    /// runs past the 16-bit address space
+    ///
+    /// ```
+    /// use chip8::watch::{Access, Watchpoint};
+    ///
+    /// assert!(Watchpoint::new(0xFFFF, 0x10000, &[Access::Write]).is_some());
+    /// assert!(Watchpoint::new(0x300, 0x200, &[Access::Write]).is_none());
+    /// ```
+    pub fn new(start: u16, end: u32, access: &[Access]) -> Option<Self> {
+        if u32::from(start) >= end || end > ADDR_END {
+            return None;
+        }
+
+        Some(Watchpoint {
+            start,
+            end,
+            access: access.iter().fold(0, |bits, a| bits | a.bit()),
+        })
+    }
+
+    pub fn matches(&self, addr: u16, access: Access) -> bool {
+        self.access & access.bit() != 0
+            && (u32::from(self.start)..self.end).contains(&u32::from(addr))
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let access: String = Access::ALL
+            .iter()
+            .filter(|a| self.access & a.bit() != 0)
+            .map(|a| a.letter())
+            .collect();
+
+        write!(f, "{:04X}..{:04X}:{}", self.start, self.end, access)
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for Watchpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_addr = |a: &str| {
+            u32::from_str_radix(a.trim().trim_start_matches("0x"), 16)
+                .map_err(|_| anyhow!("invalid address '{}' in watchpoint '{}'", a, s))
+        };
+
+        let (range, access) = match s.trim().split_once(':') {
+            Some((range, access)) => (range, access),
+            None => (s.trim(), "rw"),
+        };
+
+        let (start, end) = match range.split_once("..") {
+            Some((start, end)) => (parse_addr(start)?, parse_addr(end)?),
+            None => {
+                let addr = parse_addr(range)?;
+                (addr, addr.saturating_add(1))
+            }
+        };
+
+        let start = u16::try_from(start)
+            .ok()
+            .filter(|_| end <= ADDR_END)
+            .ok_or_else(|| anyhow!("watchpoint '{}' runs past FFFF", s))?;
+
+        let access = access
+            .chars()
+            .map(|c| match c.to_ascii_lowercase() {
+                'r' => Ok(Access::Read),
+                'w' => Ok(Access::Write),
+                'x' => Ok(Access::Execute),
+                _ => Err(anyhow!("unknown access '{}' in watchpoint '{}'", c, s)),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Watchpoint::new(start, end, &access)
+            .ok_or_else(|| anyhow!("empty watchpoint range '{}'", s))
+    }
+}
+
+/// Memory access that triggered a watchpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub addr: u16,
+    pub access: Access,
+    /// Address of the instruction making the access
+    pub pc: u16,
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {:04X} by {:04X}", self.access, self.addr, self.pc)
+    }
+}
+
+/// Set of watchpoints with a per-page mask of watched access kinds
+///
+/// Accesses to pages without a matching watchpoint cost a single bit test,
+/// so the interpreter only scans the list when a watched page is touched.
+#[derive(Debug, Clone, Default)]
+pub struct Watchpoints {
+    list: Vec<Watchpoint>,
+    pages: Vec<u8>,
+}
+
+impl Watchpoints {
+    pub fn add(&mut self, watchpoint: Watchpoint) {
+        self.list.push(watchpoint);
+        self.rebuild();
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Watchpoint> {
+        if index < self.list.len() {
+            let watchpoint = self.list.remove(index);
+            self.rebuild();
+            Some(watchpoint)
+        } else {
+            None
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+        self.pages.clear();
+    }
+
+    pub fn list(&self) -> &[Watchpoint] {
+        &self.list
+    }
+
+    /// Whether `access` at `addr` triggers a watchpoint
+    pub fn check(&self, addr: u16, access: Access) -> bool {
+        match self.pages.get(usize::from(addr >> PAGE_SHIFT)) {
+            Some(mask) if mask & access.bit() != 0 => {
+                self.list.iter().any(|w| w.matches(addr, access))
+            }
+            _ => false,
+        }
+    }
+
+    fn rebuild(&mut self) {
+        self.pages.clear();
+
+        for w in self.list.iter() {
+            let first = usize::from(w.start >> PAGE_SHIFT);
+            let last = ((w.end - 1) >> PAGE_SHIFT) as usize;
+
+            if self.pages.len() <= last {
+                self.pages.resize(last + 1, 0);
+            }
+
+            for mask in self.pages[first..=last].iter_mut() {
+                *mask |= w.access;
+            }
+        }
+    }
+}
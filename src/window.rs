@@ -1,140 +1,111 @@
-use std::{
-    collections::HashSet,
-    sync::{Arc, Mutex, MutexGuard},
-    thread::{self, JoinHandle},
-    time::Duration,
-};
+use std::{collections::HashSet, time::Duration};
+
+use minifb::{Key, MouseButton, MouseMode, Scale, Window, WindowOptions};
 
-use minifb::{Key, Window, WindowOptions};
+use crate::{
+    bus::{self, RemoteFrontend},
+    frontend::{self, Beeper, Display, Input},
+};
 
 const REFRESH_PERIOD_S: f64 = 1. / 240.; // 240 Hz
 
-pub struct WindowHandle {
-    join_handle: JoinHandle<()>,
-    display_buffer: Arc<Mutex<Vec<u32>>>,
-    display_dirty: Arc<Mutex<bool>>,
-    keys: Arc<Mutex<Option<Vec<Key>>>>,
-    keys_pressed: Arc<Mutex<Option<HashSet<Key>>>>,
-    title_update: Arc<Mutex<Option<String>>>,
-    closing: Arc<Mutex<bool>>,
-}
+/// minifb window running on its own UI thread, connected to the emulator by the bus
+pub type WindowHandle = RemoteFrontend;
 
-impl WindowHandle {
-    pub fn is_closing(&self) -> bool {
-        *self.closing.lock().unwrap()
-    }
+/// minifb window, driven by the UI thread
+struct MinifbWindow {
+    window: Window,
+    buffer: Vec<u32>,
+    size: (usize, usize),
+    dirty: bool,
+}
 
-    pub fn get_keys(&self) -> Option<Vec<Key>> {
-        self.keys.lock().unwrap().clone()
+impl Display for MinifbWindow {
+    fn update(&mut self) {
+        if self.dirty {
+            let (width, height) = self.size;
+            self.window
+                .update_with_buffer(&self.buffer, width, height)
+                .unwrap();
+            self.dirty = false;
+        } else {
+            self.window.update();
+        }
     }
 
-    pub fn get_keys_pressed(&self) -> Option<HashSet<Key>> {
-        self.keys_pressed.lock().unwrap().take()
+    fn is_closing(&self) -> bool {
+        !self.window.is_open()
     }
 
-    pub fn get_display_buffer_mut(&mut self) -> MutexGuard<Vec<u32>> {
-        let display_buffer_guard = self.display_buffer.lock().unwrap();
-
-        *self.display_dirty.lock().unwrap() = true;
-
-        display_buffer_guard
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) {
+        self.buffer.clear();
+        self.buffer.extend_from_slice(buffer);
+        self.size = (width, height);
+        self.dirty = true;
     }
 
-    pub fn set_title(&mut self, title: String) {
-        self.title_update.lock().unwrap().replace(title);
+    fn set_title(&mut self, title: String) {
+        self.window.set_title(&title);
     }
 
-    pub fn close(self) {
-        *self.closing.lock().unwrap() = true;
-        self.join_handle.join().unwrap();
+    fn window_size(&self) -> Option<(usize, usize)> {
+        Some(self.window.get_size())
     }
-}
 
-struct WindowSharedData {
-    display_buffer: Arc<Mutex<Vec<u32>>>,
-    display_dirty: Arc<Mutex<bool>>,
-    keys: Arc<Mutex<Option<Vec<Key>>>>,
-    keys_pressed: Arc<Mutex<Option<HashSet<Key>>>>,
-    title_update: Arc<Mutex<Option<String>>>,
-    closing: Arc<Mutex<bool>>,
+    fn close(self) {}
 }
 
-pub fn spawn(title: String, width: usize, height: usize) -> WindowHandle {
-    let display_buffer = Arc::new(Mutex::new((0..width * height).map(|_| 0).collect()));
-    let display_dirty = Arc::new(Mutex::new(true));
-    let keys = Arc::new(Mutex::new(None));
-    let keys_pressed = Arc::new(Mutex::new(None));
-    let title_update = Arc::new(Mutex::new(None));
-    let closing = Arc::new(Mutex::new(false));
-
-    let shared_data = WindowSharedData {
-        display_buffer: display_buffer.clone(),
-        display_dirty: display_dirty.clone(),
-        keys: keys.clone(),
-        keys_pressed: keys_pressed.clone(),
-        title_update: title_update.clone(),
-        closing: closing.clone(),
-    };
-
-    let join_handle = thread::spawn(move || {
-        let opts = WindowOptions {
-            resize: true,
-            scale: minifb::Scale::X16,
-            scale_mode: minifb::ScaleMode::AspectRatioStretch,
-            ..Default::default()
-        };
-
-        let mut window = Window::new(&title, width, height, opts).unwrap();
-
-        window.limit_update_rate(Some(Duration::from_secs_f64(REFRESH_PERIOD_S)));
-
-        while !*shared_data.closing.lock().unwrap() && window.is_open() {
-            if *shared_data.display_dirty.lock().unwrap() {
-                let buffer = shared_data.display_buffer.lock().unwrap().clone();
-                window.update_with_buffer(&buffer, width, height).unwrap();
-            } else {
-                window.update();
-            }
-
-            {
-                let mut keys = shared_data.keys.lock().unwrap();
-
-                if let Some(new_keys) = window.get_keys() {
-                    (*keys).replace(new_keys);
-                } else {
-                    (*keys).take();
-                }
-            }
+impl Input for MinifbWindow {
+    fn get_keys(&self) -> Option<Vec<Key>> {
+        self.window.get_keys()
+    }
 
-            {
-                if let Some(new_keys) = window.get_keys_pressed(minifb::KeyRepeat::Yes) {
-                    let mut keys_pressed_guard = shared_data.keys_pressed.lock().unwrap();
+    fn get_keys_pressed(&mut self) -> Option<HashSet<Key>> {
+        self.window
+            .get_keys_pressed(minifb::KeyRepeat::Yes)
+            .map(|keys| keys.into_iter().collect())
+    }
 
-                    let mut keys_pressed = keys_pressed_guard.take().unwrap_or_default();
+    fn get_pointers(&self) -> Vec<(usize, usize)> {
+        if !self.window.get_mouse_down(MouseButton::Left) {
+            return Vec::new();
+        }
 
-                    for key in new_keys {
-                        keys_pressed.insert(key);
-                    }
+        // The window stretches the frame to fit, keeping its aspect ratio
+        let (width, height) = self.window.get_size();
 
-                    (*keys_pressed_guard).replace(keys_pressed);
-                }
-            }
+        self.window
+            .get_unscaled_mouse_pos(MouseMode::Discard)
+            .and_then(|pos| frontend::letterboxed(self.size, (width as f32, height as f32), pos))
+            .into_iter()
+            .collect()
+    }
+}
 
-            if let Some(new_title) = shared_data.title_update.lock().unwrap().take() {
-                window.set_title(&new_title);
+/// minifb has no audio output
+impl Beeper for MinifbWindow {}
+
+pub fn spawn(title: String, width: usize, height: usize, scale: Scale) -> WindowHandle {
+    bus::spawn_ui(
+        move || {
+            // Fitting the screen covers it with a borderless window on top
+            let fullscreen = matches!(scale, Scale::FitScreen);
+            let opts = WindowOptions {
+                resize: true,
+                scale,
+                scale_mode: minifb::ScaleMode::AspectRatioStretch,
+                borderless: fullscreen,
+                topmost: fullscreen,
+                ..Default::default()
+            };
+
+            MinifbWindow {
+                window: Window::new(&title, width, height, opts).unwrap(),
+                buffer: vec![0; width * height],
+                size: (width, height),
+                dirty: true,
             }
-        }
-
-        *shared_data.closing.lock().unwrap() = true;
-    });
-
-    WindowHandle {
-        join_handle,
-        display_buffer,
-        display_dirty,
-        keys,
-        keys_pressed,
-        title_update,
-        closing,
-    }
+        },
+        Duration::from_secs_f64(REFRESH_PERIOD_S),
+    )
 }